@@ -4,7 +4,11 @@ use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures;
 
 use mdanceio::base_application_service::BaseApplicationService;
+use mdanceio::error::MdanceioError;
+use mdanceio::event_publisher::{Event, MotionTarget};
 use mdanceio::injector::Injector;
+use mdanceio::project::{EnvironmentSettings, PostProcessSettings};
+use mdanceio::project_file::AssetLoader;
 
 pub struct CanvasSize<T> {
     pub width: T,
@@ -17,6 +21,18 @@ impl<T> CanvasSize<T> {
     }
 }
 
+/// Resolves project assets with a synchronous JS function `(path) => Uint8Array`.
+struct JsAssetLoader(js_sys::Function);
+
+impl AssetLoader for JsAssetLoader {
+    fn load(&mut self, path: &str) -> Result<Vec<u8>, MdanceioError> {
+        self.0
+            .call1(&JsValue::NULL, &JsValue::from_str(path))
+            .map(|value| js_sys::Uint8Array::new(&value).to_vec())
+            .map_err(|err| MdanceioError::asset_not_loaded(path, &format!("{:?}", err)))
+    }
+}
+
 #[wasm_bindgen]
 pub struct WasmClient {
     instance: wgpu::Instance,
@@ -175,6 +191,612 @@ impl WasmClient {
             .map_err(|e| e.to_string().into())
     }
 
+    pub fn remove_model(&mut self, handle: u32) -> Result<(), JsValue> {
+        self.service
+            .remove_model(handle)
+            .map_err(|e| e.to_string().into())
+    }
+
+    pub fn remove_model_motion(&mut self, handle: Option<u32>) {
+        self.service.remove_model_motion(handle);
+    }
+
+    pub fn remove_camera_motion(&mut self) {
+        self.service.remove_camera_motion();
+    }
+
+    pub fn remove_light_motion(&mut self) {
+        self.service.remove_light_motion();
+    }
+
+    pub fn resample_model_motion(&mut self, handle: u32, from_fps: f32) -> Result<(), JsValue> {
+        self.service
+            .resample_motion(MotionTarget::Model(handle), from_fps)
+            .map_err(|e| e.to_string().into())
+    }
+
+    pub fn resample_camera_motion(&mut self, from_fps: f32) -> Result<(), JsValue> {
+        self.service
+            .resample_motion(MotionTarget::Camera, from_fps)
+            .map_err(|e| e.to_string().into())
+    }
+
+    pub fn resample_light_motion(&mut self, from_fps: f32) -> Result<(), JsValue> {
+        self.service
+            .resample_motion(MotionTarget::Light, from_fps)
+            .map_err(|e| e.to_string().into())
+    }
+
+    pub fn set_preferred_motion_fps(&mut self, value: u32) {
+        self.service.set_preferred_motion_fps(value);
+    }
+
+    pub fn bone_keyframe_interpolation(
+        &self,
+        handle: u32,
+        bone_name: &str,
+        frame_index: u32,
+        channel: u32,
+    ) -> Option<Vec<u8>> {
+        self.service
+            .bone_keyframe_interpolation(handle, bone_name, frame_index, channel)
+            .map(|parameters| parameters.to_vec())
+    }
+
+    pub fn set_bone_keyframe_interpolation(
+        &mut self,
+        handle: u32,
+        bone_name: &str,
+        frame_index: u32,
+        channel: u32,
+        parameters: &[u8],
+    ) -> Result<(), JsValue> {
+        let parameters = to_interpolation_parameters(parameters)?;
+        self.service
+            .set_bone_keyframe_interpolation(handle, bone_name, frame_index, channel, parameters)
+            .map_err(|e| e.to_string().into())
+    }
+
+    pub fn split_bone_keyframe_interval(
+        &mut self,
+        handle: u32,
+        bone_name: &str,
+        frame_index: u32,
+    ) -> Result<(), JsValue> {
+        self.service
+            .split_bone_keyframe_interval(handle, bone_name, frame_index)
+            .map_err(|e| e.to_string().into())
+    }
+
+    pub fn camera_keyframe_interpolation(&self, frame_index: u32, channel: u32) -> Option<Vec<u8>> {
+        self.service
+            .camera_keyframe_interpolation(frame_index, channel)
+            .map(|parameters| parameters.to_vec())
+    }
+
+    pub fn set_camera_keyframe_interpolation(
+        &mut self,
+        frame_index: u32,
+        channel: u32,
+        parameters: &[u8],
+    ) -> Result<(), JsValue> {
+        let parameters = to_interpolation_parameters(parameters)?;
+        self.service
+            .set_camera_keyframe_interpolation(frame_index, channel, parameters)
+            .map_err(|e| e.to_string().into())
+    }
+
+    pub fn split_camera_keyframe_interval(&mut self, frame_index: u32) -> Result<(), JsValue> {
+        self.service
+            .split_camera_keyframe_interval(frame_index)
+            .map_err(|e| e.to_string().into())
+    }
+
+    pub fn set_camera_perspective(&mut self, value: bool) {
+        self.service.set_camera_perspective(value);
+    }
+
+    pub fn camera_names(&self) -> Box<[JsValue]> {
+        self.service
+            .camera_names()
+            .iter()
+            .map(|name| name.into())
+            .collect()
+    }
+
+    pub fn active_camera_name(&self) -> String {
+        self.service.active_camera_name()
+    }
+
+    pub fn add_camera(&mut self, name: &str) -> Result<(), JsValue> {
+        self.service
+            .add_camera(name)
+            .map_err(|e| e.to_string().into())
+    }
+
+    pub fn remove_camera(&mut self, name: &str) -> Result<(), JsValue> {
+        self.service
+            .remove_camera(name)
+            .map_err(|e| e.to_string().into())
+    }
+
+    pub fn rename_camera(&mut self, name: &str, new_name: &str) -> Result<(), JsValue> {
+        self.service
+            .rename_camera(name, new_name)
+            .map_err(|e| e.to_string().into())
+    }
+
+    pub fn set_active_camera(&mut self, name: &str) -> Result<(), JsValue> {
+        self.service
+            .set_active_camera(name)
+            .map_err(|e| e.to_string().into())
+    }
+
+    pub fn camera_keyframe_follow_model(&self, frame_index: u32) -> Option<u32> {
+        self.service
+            .camera_keyframe_follow_target(frame_index)
+            .map(|(handle, _)| handle)
+    }
+
+    pub fn camera_keyframe_follow_bone(&self, frame_index: u32) -> Option<String> {
+        self.service
+            .camera_keyframe_follow_target(frame_index)
+            .map(|(_, bone_name)| bone_name)
+    }
+
+    pub fn set_camera_keyframe_follow_target(
+        &mut self,
+        frame_index: u32,
+        handle: u32,
+        bone_name: &str,
+    ) -> Result<(), JsValue> {
+        self.service
+            .set_camera_keyframe_follow_target(frame_index, handle, bone_name)
+            .map_err(|e| e.to_string().into())
+    }
+
+    pub fn clear_camera_keyframe_follow_target(&mut self, frame_index: u32) -> Result<(), JsValue> {
+        self.service
+            .clear_camera_keyframe_follow_target(frame_index)
+            .map_err(|e| e.to_string().into())
+    }
+
+    pub fn set_camera_follow_rotation(&mut self, value: bool) {
+        self.service.set_camera_follow_rotation(value);
+    }
+
+    pub fn set_shadow_map_resolution(&mut self, texture_size: u32, cascade_count: u32) {
+        self.service
+            .set_shadow_map_resolution(texture_size, cascade_count, &self.device);
+    }
+
+    pub fn set_shadow_map_filter_radius(&mut self, value: u32) {
+        self.service.set_shadow_map_filter_radius(value);
+    }
+
+    pub fn set_shadow_map_split_lambda(&mut self, value: f32) {
+        self.service.set_shadow_map_split_lambda(value);
+    }
+
+    pub fn set_shadow_map_depth_bias(&mut self, value: f32) {
+        self.service.set_shadow_map_depth_bias(value);
+    }
+
+    /// MSAA sample count is `1 << value`.
+    pub fn set_sample_level(&mut self, value: u32) -> Result<(), JsValue> {
+        self.service
+            .set_sample_level(value)
+            .map_err(|e| e.to_string().into())
+    }
+
+    pub fn set_post_process_enabled(&mut self, value: bool) {
+        self.service.set_post_process_settings(PostProcessSettings {
+            enabled: value,
+            ..self.service.post_process_settings()
+        });
+    }
+
+    pub fn set_bloom(&mut self, enabled: bool, threshold: f32, intensity: f32) {
+        self.service.set_post_process_settings(PostProcessSettings {
+            bloom: enabled,
+            bloom_threshold: threshold,
+            bloom_intensity: intensity,
+            ..self.service.post_process_settings()
+        });
+    }
+
+    pub fn set_tone_mapping(&mut self, enabled: bool, exposure: f32) {
+        self.service.set_post_process_settings(PostProcessSettings {
+            tone_mapping: enabled,
+            exposure,
+            ..self.service.post_process_settings()
+        });
+    }
+
+    pub fn set_tone_mapping_operator(&mut self, operator: u32) -> Result<(), JsValue> {
+        self.service
+            .set_tone_mapping_operator(operator)
+            .map_err(|e| e.to_string().into())
+    }
+
+    pub fn set_hdr_enabled(&mut self, value: bool) {
+        self.service.set_post_process_settings(PostProcessSettings {
+            hdr: value,
+            ..self.service.post_process_settings()
+        });
+    }
+
+    pub fn set_color_grading(&mut self, enabled: bool, intensity: f32) {
+        self.service.set_post_process_settings(PostProcessSettings {
+            color_grading: enabled,
+            color_grading_intensity: intensity,
+            ..self.service.post_process_settings()
+        });
+    }
+
+    pub fn load_color_grading_lut(&mut self, data: &[u8]) -> Result<(), JsValue> {
+        self.service
+            .load_color_grading_lut(data, &self.device, &self.queue)
+            .map_err(|e| e.to_string().into())
+    }
+
+    pub fn set_vignette(&mut self, enabled: bool, intensity: f32, smoothness: f32) {
+        self.service.set_post_process_settings(PostProcessSettings {
+            vignette: enabled,
+            vignette_intensity: intensity,
+            vignette_smoothness: smoothness,
+            ..self.service.post_process_settings()
+        });
+    }
+
+    pub fn set_depth_of_field(
+        &mut self,
+        enabled: bool,
+        focus_distance: f32,
+        focus_range: f32,
+        max_blur_radius: f32,
+    ) {
+        self.service.set_post_process_settings(PostProcessSettings {
+            depth_of_field: enabled,
+            focus_distance,
+            focus_range,
+            max_blur_radius,
+            ..self.service.post_process_settings()
+        });
+    }
+
+    pub fn set_fxaa(&mut self, enabled: bool) {
+        self.service.set_post_process_settings(PostProcessSettings {
+            fxaa: enabled,
+            ..self.service.post_process_settings()
+        });
+    }
+
+    pub fn load_environment_image(&mut self, data: &[u8]) -> Result<(), JsValue> {
+        self.service
+            .load_environment_image(data, &self.device, &self.queue)
+            .map_err(|e| e.to_string().into())
+    }
+
+    pub fn clear_environment_image(&mut self) {
+        self.service.clear_environment_image();
+    }
+
+    pub fn set_environment_skybox(&mut self, enabled: bool, intensity: f32) {
+        self.service.set_environment_settings(EnvironmentSettings {
+            skybox: enabled,
+            skybox_intensity: intensity,
+            ..self.service.environment_settings()
+        });
+    }
+
+    pub fn set_environment_lighting(
+        &mut self,
+        enabled: bool,
+        diffuse_intensity: f32,
+        specular_intensity: f32,
+    ) {
+        self.service.set_environment_settings(EnvironmentSettings {
+            lighting: enabled,
+            diffuse_intensity,
+            specular_intensity,
+            ..self.service.environment_settings()
+        });
+    }
+
+    /// Rotates the environment image around the Y axis by `degrees`.
+    pub fn set_environment_rotation(&mut self, degrees: f32) {
+        self.service.set_environment_settings(EnvironmentSettings {
+            rotation: degrees,
+            ..self.service.environment_settings()
+        });
+    }
+
+    pub fn set_ambient_occlusion(&mut self, enabled: bool, radius: f32, intensity: f32, bias: f32) {
+        self.service.set_post_process_settings(PostProcessSettings {
+            ambient_occlusion: enabled,
+            ambient_occlusion_radius: radius,
+            ambient_occlusion_intensity: intensity,
+            ambient_occlusion_bias: bias,
+            ..self.service.post_process_settings()
+        });
+    }
+
+    pub fn set_outline(
+        &mut self,
+        enabled: bool,
+        width: f32,
+        color: &[f32],
+        depth_threshold: f32,
+        normal_threshold: f32,
+    ) -> Result<(), JsValue> {
+        self.service.set_post_process_settings(PostProcessSettings {
+            outline: enabled,
+            outline_width: width,
+            outline_color: to_vector4(color)?,
+            outline_depth_threshold: depth_threshold,
+            outline_normal_threshold: normal_threshold,
+            ..self.service.post_process_settings()
+        });
+        Ok(())
+    }
+
+    pub fn local_light_count(&self) -> usize {
+        self.service.local_light_count()
+    }
+
+    pub fn add_point_light(
+        &mut self,
+        position: &[f32],
+        color: &[f32],
+        range: f32,
+    ) -> Result<usize, JsValue> {
+        self.service
+            .add_point_light(to_vector3(position)?, to_vector3(color)?, range)
+            .map_err(|e| e.to_string().into())
+    }
+
+    pub fn add_spot_light(
+        &mut self,
+        position: &[f32],
+        direction: &[f32],
+        color: &[f32],
+        range: f32,
+        inner_angle: f32,
+        outer_angle: f32,
+    ) -> Result<usize, JsValue> {
+        self.service
+            .add_spot_light(
+                to_vector3(position)?,
+                to_vector3(direction)?,
+                to_vector3(color)?,
+                range,
+                inner_angle,
+                outer_angle,
+            )
+            .map_err(|e| e.to_string().into())
+    }
+
+    pub fn remove_local_light(&mut self, index: usize) -> Result<(), JsValue> {
+        self.service
+            .remove_local_light(index)
+            .map_err(|e| e.to_string().into())
+    }
+
+    pub fn set_local_light_position(&mut self, index: usize, value: &[f32]) -> Result<(), JsValue> {
+        self.service
+            .set_local_light_position(index, to_vector3(value)?)
+            .map_err(|e| e.to_string().into())
+    }
+
+    pub fn set_local_light_direction(
+        &mut self,
+        index: usize,
+        value: &[f32],
+    ) -> Result<(), JsValue> {
+        self.service
+            .set_local_light_direction(index, to_vector3(value)?)
+            .map_err(|e| e.to_string().into())
+    }
+
+    pub fn set_local_light_color(&mut self, index: usize, value: &[f32]) -> Result<(), JsValue> {
+        self.service
+            .set_local_light_color(index, to_vector3(value)?)
+            .map_err(|e| e.to_string().into())
+    }
+
+    pub fn set_local_light_range(&mut self, index: usize, value: f32) -> Result<(), JsValue> {
+        self.service
+            .set_local_light_range(index, value)
+            .map_err(|e| e.to_string().into())
+    }
+
+    pub fn set_local_light_cone_angles(
+        &mut self,
+        index: usize,
+        inner_angle: f32,
+        outer_angle: f32,
+    ) -> Result<(), JsValue> {
+        self.service
+            .set_local_light_cone_angles(index, inner_angle, outer_angle)
+            .map_err(|e| e.to_string().into())
+    }
+
+    pub fn set_local_light_shadow_enabled(
+        &mut self,
+        index: usize,
+        value: bool,
+    ) -> Result<(), JsValue> {
+        self.service
+            .set_local_light_shadow_enabled(index, value)
+            .map_err(|e| e.to_string().into())
+    }
+
+    pub fn interpolation_preset(preset: u32) -> Option<Vec<u8>> {
+        BaseApplicationService::interpolation_preset(preset).map(|parameters| parameters.to_vec())
+    }
+
+    /// Sampled points of the curve flattened as `[x0, y0, x1, y1, ...]`
+    pub fn sample_interpolation_curve(
+        parameters: &[u8],
+        count: usize,
+    ) -> Result<Vec<f32>, JsValue> {
+        let parameters = to_interpolation_parameters(parameters)?;
+        Ok(
+            BaseApplicationService::sample_interpolation_curve(parameters, count)
+                .into_iter()
+                .flatten()
+                .collect(),
+        )
+    }
+
+    pub fn load_model_motion_layer(
+        &mut self,
+        handle: u32,
+        name: &str,
+        data: &[u8],
+    ) -> Result<usize, JsValue> {
+        self.service
+            .load_model_motion_layer(handle, name, data)
+            .map_err(|e| e.to_string().into())
+    }
+
+    pub fn remove_model_motion_layer(&mut self, handle: u32, index: usize) {
+        self.service.remove_model_motion_layer(handle, index);
+    }
+
+    pub fn set_motion_layer_weight(&mut self, handle: u32, index: usize, value: f32) {
+        self.service.set_motion_layer_weight(handle, index, value);
+    }
+
+    pub fn set_motion_layer_additive(&mut self, handle: u32, index: usize, value: bool) {
+        self.service.set_motion_layer_additive(handle, index, value);
+    }
+
+    pub fn set_motion_layer_frame_offset(&mut self, handle: u32, index: usize, value: i32) {
+        self.service
+            .set_motion_layer_frame_offset(handle, index, value);
+    }
+
+    pub fn set_motion_layer_enabled(&mut self, handle: u32, index: usize, value: bool) {
+        self.service.set_motion_layer_enabled(handle, index, value);
+    }
+
+    pub fn set_outside_parent(
+        &mut self,
+        handle: u32,
+        bone_name: &str,
+        target_model: Option<String>,
+        target_bone: Option<String>,
+    ) -> Result<(), JsValue> {
+        let target = target_model.as_deref().zip(target_bone.as_deref());
+        self.service
+            .set_outside_parent(handle, bone_name, target)
+            .map_err(|e| e.to_string().into())
+    }
+
+    pub fn save_project(&self) -> String {
+        self.service.save_project()
+    }
+
+    pub fn load_project(&mut self, text: &str, loader: js_sys::Function) -> Result<(), JsValue> {
+        self.service
+            .load_project(
+                text,
+                &mut JsAssetLoader(loader),
+                &self.device,
+                &self.queue,
+            )
+            .map_err(|e| e.to_string().into())
+    }
+
+    pub fn load_effect(
+        &mut self,
+        manifest: &str,
+        loader: js_sys::Function,
+    ) -> Result<String, JsValue> {
+        self.service
+            .load_effect(
+                manifest,
+                &mut JsAssetLoader(loader),
+                &self.device,
+                &self.queue,
+            )
+            .map_err(|e| e.to_string().into())
+    }
+
+    pub fn load_fx_effect(
+        &mut self,
+        path: &str,
+        loader: js_sys::Function,
+    ) -> Result<String, JsValue> {
+        self.service
+            .load_fx_effect(
+                path,
+                &mut JsAssetLoader(loader),
+                &self.device,
+                &self.queue,
+            )
+            .map_err(|e| e.to_string().into())
+    }
+
+    pub fn effect_names(&self) -> Box<[JsValue]> {
+        self.service
+            .effect_names()
+            .iter()
+            .map(|name| name.into())
+            .collect()
+    }
+
+    pub fn remove_effect(&mut self, name: &str) -> Result<(), JsValue> {
+        self.service
+            .remove_effect(name, &self.device)
+            .map_err(|e| e.to_string().into())
+    }
+
+    pub fn set_model_effect(&mut self, handle: u32, name: Option<String>) -> Result<(), JsValue> {
+        self.service
+            .set_model_effect(handle, name.as_deref(), &self.device)
+            .map_err(|e| e.to_string().into())
+    }
+
+    pub fn set_material_effect(
+        &mut self,
+        handle: u32,
+        material_idx: usize,
+        name: Option<String>,
+    ) -> Result<(), JsValue> {
+        self.service
+            .set_material_effect(handle, material_idx, name.as_deref(), &self.device)
+            .map_err(|e| e.to_string().into())
+    }
+
+    pub fn set_material_ambient_occlusion_excluded(
+        &mut self,
+        handle: u32,
+        material_idx: usize,
+        value: bool,
+    ) -> Result<(), JsValue> {
+        self.service
+            .set_material_ambient_occlusion_excluded(handle, material_idx, value)
+            .map_err(|e| e.to_string().into())
+    }
+
+    pub fn set_material_outline_excluded(
+        &mut self,
+        handle: u32,
+        material_idx: usize,
+        value: bool,
+    ) -> Result<(), JsValue> {
+        self.service
+            .set_material_outline_excluded(handle, material_idx, value)
+            .map_err(|e| e.to_string().into())
+    }
+
+    pub fn set_effect_plugin_enabled(&mut self, value: bool) {
+        self.service.set_effect_plugin_enabled(value, &self.device);
+    }
+
     pub fn get_texture_names(&self) -> Box<[JsValue]> {
         self.service
             .get_model_texture_paths(1)
@@ -214,6 +836,10 @@ impl WasmClient {
         self.service.seek(frame_index);
     }
 
+    pub fn seek_precisely(&mut self, frame_index: u32, amount: f32) {
+        self.service.seek_precisely(frame_index, amount);
+    }
+
     pub fn update(&mut self) {
         self.service
             .update_current_project(&self.device, &self.queue);
@@ -222,4 +848,146 @@ impl WasmClient {
     pub fn play(&mut self) {
         self.service.play()
     }
+
+    pub fn pause(&mut self) {
+        self.service.pause()
+    }
+
+    pub fn resume(&mut self) {
+        self.service.resume()
+    }
+
+    pub fn stop(&mut self) {
+        self.service.stop()
+    }
+
+    /// Register a JS callback invoked as `callback(name, detail)` for each event, returns subscription id
+    pub fn subscribe(&mut self, callback: js_sys::Function) -> u32 {
+        self.service.subscribe(Box::new(move |event| {
+            let name = JsValue::from_str(event.name());
+            if let Err(err) = callback.call2(&JsValue::NULL, &name, &event_to_js(event)) {
+                log::warn!("Event callback failed: {:?}", err);
+            }
+        }))
+    }
+
+    pub fn unsubscribe(&mut self, id: u32) -> bool {
+        self.service.unsubscribe(id)
+    }
+}
+
+fn motion_target_to_js(target: &MotionTarget) -> JsValue {
+    match target {
+        MotionTarget::Model(handle) => JsValue::from(*handle),
+        MotionTarget::Camera => JsValue::from_str("camera"),
+        MotionTarget::Light => JsValue::from_str("light"),
+        MotionTarget::SelfShadow => JsValue::from_str("selfShadow"),
+    }
+}
+
+fn event_to_js(event: &Event) -> JsValue {
+    let detail = js_sys::Object::new();
+    let set = |key: &str, value: JsValue| {
+        let _ = js_sys::Reflect::set(&detail, &JsValue::from_str(key), &value);
+    };
+    match event {
+        Event::AddModel(handle) | Event::RemoveModel(handle) => set("model", (*handle).into()),
+        Event::SetActiveModel(handle) => {
+            set("model", (*handle).map_or(JsValue::NULL, JsValue::from))
+        }
+        Event::SetActiveBone { model, bone } => {
+            set("model", (*model).into());
+            set(
+                "bone",
+                bone.as_deref().map_or(JsValue::NULL, JsValue::from_str),
+            );
+        }
+        Event::SetActiveMorph { model, morph } => {
+            set("model", (*model).into());
+            set(
+                "morph",
+                morph.as_deref().map_or(JsValue::NULL, JsValue::from_str),
+            );
+        }
+        Event::SetActiveCamera(name) => set("name", JsValue::from_str(name)),
+        Event::AddMotion(target) | Event::RemoveMotion(target) => {
+            set("target", motion_target_to_js(target))
+        }
+        Event::Play {
+            frame_index,
+            duration,
+        }
+        | Event::Pause {
+            frame_index,
+            duration,
+        }
+        | Event::Resume {
+            frame_index,
+            duration,
+        }
+        | Event::Stop {
+            frame_index,
+            duration,
+        } => {
+            set("frameIndex", (*frame_index).into());
+            set("duration", (*duration).into());
+        }
+        Event::Seek {
+            frame_index,
+            last_frame_index,
+            duration,
+        } => {
+            set("frameIndex", (*frame_index).into());
+            set("lastFrameIndex", (*last_frame_index).into());
+            set("duration", (*duration).into());
+        }
+        Event::UpdateDuration {
+            duration,
+            last_duration,
+        } => {
+            set("duration", (*duration).into());
+            set("lastDuration", (*last_duration).into());
+        }
+        Event::SetPreferredMotionFps { value, unlimited } => {
+            set("value", (*value).into());
+            set("unlimited", (*unlimited).into());
+        }
+        Event::SetPhysicsSimulationMode(value)
+        | Event::SetShadowMapMode(value)
+        | Event::SetSampleLevel(value) => set("value", (*value).into()),
+        Event::SetShadowMapDistance(value) => set("value", (*value).into()),
+        Event::ToggleGridEnabled(value) => set("value", (*value).into()),
+        Event::ToggleModelVisible { model, value }
+        | Event::ToggleModelShadowMapEnabled { model, value }
+        | Event::ToggleModelShowAllBones { model, value }
+        | Event::ToggleModelShowAllVertexFaces { model, value }
+        | Event::ToggleModelShowAllVertexPoints { model, value } => {
+            set("model", (*model).into());
+            set("value", (*value).into());
+        }
+        Event::UpdateUndoAvailability { can_undo, can_redo } => {
+            set("canUndo", (*can_undo).into());
+            set("canRedo", (*can_redo).into());
+        }
+        Event::Error(err) => set("message", JsValue::from_str(&err.to_string())),
+    }
+    detail.into()
+}
+
+fn to_interpolation_parameters(value: &[u8]) -> Result<[u8; 4], JsValue> {
+    value
+        .try_into()
+        .map_err(|_| "interpolation parameters must be 4 bytes".into())
+}
+
+fn to_vector3(value: &[f32]) -> Result<[f32; 3], JsValue> {
+    value
+        .try_into()
+        .map_err(|_| "vector must have 3 components".into())
+}
+
+fn to_vector4(value: &[f32]) -> Result<[f32; 4], JsValue> {
+    value
+        .try_into()
+        .map_err(|_| "vector must have 4 components".into())
 }
@@ -0,0 +1,49 @@
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+};
+
+// x: factor, y: filter (0 is box, 1 is gaussian)
+@group(0) @binding(0) var<uniform> parameters: vec4<i32>;
+@group(0) @binding(1) var color_texture: texture_2d<f32>;
+
+@vertex
+fn vs_main(@location(0) position: vec4<f32>, @location(1) texcoord: vec4<f32>) -> VertexOutput {
+    var output: VertexOutput;
+    output.position = vec4<f32>(position.xy, 0.0, 1.0);
+    return output;
+}
+
+fn load_clamped(coord: vec2<i32>) -> vec4<f32> {
+    let size = vec2<i32>(textureDimensions(color_texture));
+    return textureLoad(color_texture, clamp(coord, vec2<i32>(0), size - vec2<i32>(1)), 0);
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    let factor = parameters.x;
+    let origin = vec2<i32>(input.position.xy) * factor;
+    var sum = vec4<f32>(0.0);
+    if (parameters.y == 0) {
+        for (var y = 0; y < factor; y++) {
+            for (var x = 0; x < factor; x++) {
+                sum += load_clamped(origin + vec2<i32>(x, y));
+            }
+        }
+        return sum / f32(factor * factor);
+    }
+    // the gaussian spans half a footprint around the texels of the output pixel
+    let radius = factor / 2;
+    let center = vec2<f32>(origin) + vec2<f32>(f32(factor) * 0.5);
+    let sigma = f32(factor) * 0.5;
+    var weight_sum = 0.0;
+    for (var y = -radius; y < factor + radius; y++) {
+        for (var x = -radius; x < factor + radius; x++) {
+            let coord = origin + vec2<i32>(x, y);
+            let distance = vec2<f32>(coord) + vec2<f32>(0.5) - center;
+            let weight = exp(-dot(distance, distance) / (2.0 * sigma * sigma));
+            sum += load_clamped(coord) * weight;
+            weight_sum += weight;
+        }
+    }
+    return sum / weight_sum;
+}
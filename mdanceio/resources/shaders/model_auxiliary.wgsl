@@ -0,0 +1,164 @@
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) texcoord0: vec2<f32>,
+    @location(3) uva1: vec4<f32>,
+    @location(4) uva2: vec4<f32>,
+    @location(5) uva3: vec4<f32>,
+    @location(6) uva4: vec4<f32>,
+    @location(7) color0: vec4<f32>,
+    @location(8) previous_position: vec3<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) normal: vec3<f32>,
+    @location(1) texcoord0: vec2<f32>,
+    @location(2) view_position: vec3<f32>,
+    @location(3) clip_position: vec4<f32>,
+    @location(4) previous_clip_position: vec4<f32>,
+};
+
+struct FragmentInput {
+    @location(0) normal: vec3<f32>,
+    @location(1) texcoord0: vec2<f32>,
+    @location(2) view_position: vec3<f32>,
+    @location(3) clip_position: vec4<f32>,
+    @location(4) previous_clip_position: vec4<f32>,
+}
+
+struct FragmentOutput {
+    // linear distance along the view direction
+    @location(0) depth: f32,
+    @location(1) normal: vec4<f32>,
+    // model handle and material index plus one, 0 is left for the background
+    @location(2) model: u32,
+    @location(3) material: u32,
+    // pixels moved since the previous frame, y points down
+    @location(4) motion: vec2<f32>,
+}
+
+struct LocalLight {
+    position: vec4<f32>,
+    direction: vec4<f32>,
+    color: vec4<f32>,
+    shadow: vec4<f32>,
+    shadow_matrix: mat4x4<f32>,
+}
+
+struct ModelUniform {
+    model_matrix: mat4x4<f32>,
+    model_view_matrix: mat4x4<f32>,
+    model_view_projection_matrix: mat4x4<f32>,
+    light_view_projection_matrix: mat4x4<f32>,
+    light_color: vec4<f32>,
+    light_direction: vec4<f32>,
+    camera_position: vec4<f32>,
+    shadow_map_size: vec4<f32>,
+    local_light_size: vec4<f32>,
+    local_lights: array<LocalLight, 4>,
+    shadow_cascade_splits: vec4<f32>,
+    shadow_filter: vec4<f32>,
+    shadow_cascade_matrices: array<mat4x4<f32>, 4>,
+    environment: vec4<f32>,
+    environment_irradiance: array<vec4<f32>, 9>,
+    environment_radiance: array<vec4<f32>, 9>,
+    previous_model_view_projection_matrix: mat4x4<f32>,
+    auxiliary_target_size: vec4<f32>,
+}
+
+struct MaterialUniform {
+    ambient: vec4<f32>,
+    diffuse: vec4<f32>,
+    specular: vec4<f32>,
+    edge_color: vec4<f32>,
+    enable_vertex_color: vec4<f32>,
+    diffuse_blend_factor: vec4<f32>,
+    sphere_blend_factor: vec4<f32>,
+    toon_blend_factor: vec4<f32>,
+    use_texture_sampler: vec4<f32>,
+    sphere_texture_type: vec4<f32>,
+    edge_size: f32,
+    // x: ambient occlusion excluded, y: outline excluded, z: model handle, w: material index
+    screen_space: vec4<f32>,
+}
+
+@group(0)
+@binding(0)
+var diffuse_texture: texture_2d<f32>;
+@group(0)
+@binding(1)
+var diffuse_texture_sampler: sampler;
+@group(0)
+@binding(2)
+var sphere_map_texture: texture_2d<f32>;
+@group(0)
+@binding(3)
+var sphere_map_texture_sampler: sampler;
+@group(0)
+@binding(4)
+var toon_texture: texture_2d<f32>;
+@group(0)
+@binding(5)
+var toon_texture_sampler: sampler;
+
+@group(1)
+@binding(0)
+var<uniform> model_uniform: ModelUniform;
+
+@group(1)
+@binding(1)
+var<uniform> material_uniform: MaterialUniform;
+
+@group(2) @binding(0)
+var shadow_texture: texture_2d_array<f32>;
+@group(2) @binding(1)
+var shadow_texture_sampler: sampler;
+
+fn has_diffuse_texture() -> bool {
+    return material_uniform.use_texture_sampler.x != 0.0;
+}
+
+const alpha_test_threshold: f32 = 0.005;
+
+fn to_pixel(position: vec4<f32>) -> vec2<f32> {
+    let ndc = position.xy / position.w;
+    return vec2<f32>(ndc.x + 1.0, 1.0 - ndc.y) * 0.5 * model_uniform.auxiliary_target_size.xy;
+}
+
+@vertex
+fn vs_main(
+    vin: VertexInput,
+) -> VertexOutput {
+    let position = vec4<f32>(vin.position, 1.0);
+    let normal = vec4<f32>(vin.normal, 0.0);
+    var vout: VertexOutput;
+    vout.position = model_uniform.model_view_projection_matrix * position;
+    vout.normal = (model_uniform.model_view_matrix * normal).xyz;
+    vout.texcoord0 = vin.texcoord0;
+    vout.view_position = (model_uniform.model_view_matrix * position).xyz;
+    vout.clip_position = vout.position;
+    vout.previous_clip_position = model_uniform.previous_model_view_projection_matrix
+        * vec4<f32>(vin.previous_position, 1.0);
+    return vout;
+}
+
+@fragment
+fn fs_main(
+    fin: FragmentInput,
+) -> FragmentOutput {
+    var alpha = material_uniform.diffuse.a;
+    if (has_diffuse_texture()) {
+        alpha *= textureSample(diffuse_texture, diffuse_texture_sampler, fin.texcoord0).a;
+    }
+    if (alpha - alpha_test_threshold < 0.0) {
+        discard;
+    }
+    var fout: FragmentOutput;
+    fout.depth = abs(fin.view_position.z);
+    fout.normal = vec4<f32>(normalize(fin.normal), 1.0);
+    fout.model = u32(material_uniform.screen_space.z) + 1u;
+    fout.material = u32(material_uniform.screen_space.w) + 1u;
+    fout.motion = to_pixel(fin.clip_position) - to_pixel(fin.previous_clip_position);
+    return fout;
+}
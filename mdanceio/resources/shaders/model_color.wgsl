@@ -28,6 +28,14 @@ struct FragmentInput {
     @location(5) shadow0: vec4<f32>,
 }
 
+struct LocalLight {
+    position: vec4<f32>,
+    direction: vec4<f32>,
+    color: vec4<f32>,
+    shadow: vec4<f32>,
+    shadow_matrix: mat4x4<f32>,
+}
+
 struct ModelUniform {
     model_matrix: mat4x4<f32>,
     model_view_matrix: mat4x4<f32>,
@@ -37,6 +45,14 @@ struct ModelUniform {
     light_direction: vec4<f32>,
     camera_position: vec4<f32>,
     shadow_map_size: vec4<f32>,
+    local_light_size: vec4<f32>,
+    local_lights: array<LocalLight, 4>,
+    shadow_cascade_splits: vec4<f32>,
+    shadow_filter: vec4<f32>,
+    shadow_cascade_matrices: array<mat4x4<f32>, 4>,
+    environment: vec4<f32>,
+    environment_irradiance: array<vec4<f32>, 9>,
+    environment_radiance: array<vec4<f32>, 9>,
 }
 
 struct MaterialUniform {
@@ -81,9 +97,11 @@ var<uniform> model_uniform: ModelUniform;
 var<uniform> material_uniform: MaterialUniform;
 
 @group(2) @binding(0)
-var shadow_texture: texture_2d<f32>;
+var shadow_texture: texture_2d_array<f32>;
 @group(2) @binding(1)
 var shadow_texture_sampler: sampler;
+@group(2) @binding(2)
+var local_shadow_texture: texture_2d_array<f32>;
 
 fn has_diffuse_texture() -> bool {
     return material_uniform.use_texture_sampler.x != 0.0;
@@ -120,9 +138,8 @@ const threshold_type2: f32 = 8000.0;
 
 const toon_factor: f32 = 3.0;
 
-fn shadow_coverage(texcoord: vec4<f32>, shadow_map_size: vec4<f32>) -> f32 {
-    let receiver_depth = texcoord.z;
-    let shadow_map_depth = textureSample(shadow_texture, shadow_texture_sampler, texcoord.xy).x;
+fn shadow_coverage_at(texcoord: vec2<f32>, layer: i32, receiver_depth: f32, shadow_map_size: vec4<f32>) -> f32 {
+    let shadow_map_depth = textureSampleLevel(shadow_texture, shadow_texture_sampler, texcoord, layer, 0.0).x;
     var component = saturate(receiver_depth - shadow_map_depth);
     let coverage_type_int: i32 = i32(shadow_map_size.w);
     if (coverage_type_int == 2) {
@@ -134,6 +151,92 @@ fn shadow_coverage(texcoord: vec4<f32>, shadow_map_size: vec4<f32>) -> f32 {
     return 1.0 - component;
 }
 
+fn shadow_coverage(texcoord: vec4<f32>, layer: i32, shadow_map_size: vec4<f32>) -> f32 {
+    let radius = i32(model_uniform.shadow_filter.y);
+    let texel_size = model_uniform.shadow_filter.w;
+    let receiver_depth = texcoord.z - model_uniform.shadow_filter.z;
+    var coverage = 0.0;
+    for (var y = -radius; y <= radius; y = y + 1) {
+        for (var x = -radius; x <= radius; x = x + 1) {
+            let offset = vec2<f32>(f32(x), f32(y)) * texel_size;
+            coverage += shadow_coverage_at(texcoord.xy + offset, layer, receiver_depth, shadow_map_size);
+        }
+    }
+    let width = f32(radius * 2 + 1);
+    return coverage / (width * width);
+}
+
+fn shadow_texcoord(cascade: i32, world_position: vec3<f32>) -> vec4<f32> {
+    let position = model_uniform.shadow_cascade_matrices[cascade] * vec4<f32>(world_position, 1.0);
+    return position / position.w;
+}
+
+fn find_shadow_cascade(world_position: vec3<f32>, distance: f32) -> i32 {
+    let cascade_count = i32(model_uniform.shadow_filter.x);
+    for (var i = 0; i < cascade_count; i = i + 1) {
+        let texcoord = shadow_texcoord(i, world_position);
+        if (distance <= model_uniform.shadow_cascade_splits[i] && all(saturate(texcoord.xy) == texcoord.xy)) {
+            return i;
+        }
+    }
+    return -1;
+}
+
+fn is_spot_light(light: LocalLight) -> bool {
+    return light.shadow.x != 0.0;
+}
+
+fn local_light_attenuation(light: LocalLight, world_position: vec3<f32>) -> f32 {
+    let to_light = light.position.xyz - world_position;
+    let distance = length(to_light);
+    let range = light.position.w;
+    if (distance >= range) {
+        return 0.0;
+    }
+    let ratio = distance / range;
+    let falloff = saturate(1.0 - ratio * ratio);
+    var attenuation = falloff * falloff;
+    if (is_spot_light(light) && distance > 0.0) {
+        let cos_angle = dot(-to_light / distance, light.direction.xyz);
+        attenuation *= smoothstep(light.direction.w, light.color.a, cos_angle);
+    }
+    return attenuation;
+}
+
+fn local_shadow_coverage(light: LocalLight, world_position: vec3<f32>) -> f32 {
+    let layer = i32(light.shadow.y);
+    if (layer < 0) {
+        return 1.0;
+    }
+    let position = light.shadow_matrix * vec4<f32>(world_position, 1.0);
+    if (position.w <= 0.0) {
+        return 1.0;
+    }
+    let texcoord = position.xyz / position.w;
+    if (any(saturate(texcoord.xy) != texcoord.xy)) {
+        return 1.0;
+    }
+    let shadow_map_depth = textureSampleLevel(local_shadow_texture, shadow_texture_sampler, texcoord.xy, layer, 0.0).x;
+    return select(0.0, 1.0, texcoord.z - light.shadow.z <= shadow_map_depth);
+}
+
+fn environment_direction(direction: vec3<f32>) -> vec3<f32> {
+    let c = model_uniform.environment.z;
+    let s = model_uniform.environment.w;
+    return vec3<f32>(c * direction.x + s * direction.z, direction.y, c * direction.z - s * direction.x);
+}
+
+// coefficients are premultiplied with the normalization of each basis
+fn evaluate_environment(coefficients: array<vec4<f32>, 9>, direction: vec3<f32>) -> vec3<f32> {
+    let d = environment_direction(direction);
+    var result = coefficients[0].rgb;
+    result += coefficients[1].rgb * d.y + coefficients[2].rgb * d.z + coefficients[3].rgb * d.x;
+    result += coefficients[4].rgb * (d.x * d.y) + coefficients[5].rgb * (d.y * d.z);
+    result += coefficients[6].rgb * (3.0 * d.z * d.z - 1.0) + coefficients[7].rgb * (d.x * d.z);
+    result += coefficients[8].rgb * (d.x * d.x - d.y * d.y);
+    return max(result, vec3<f32>(0.0));
+}
+
 @vertex
 fn vs_main(
     vin: VertexInput,
@@ -159,9 +262,11 @@ fn fs_main(
     fin: FragmentInput,
 ) -> @location(0) vec4<f32> {
     var material_color = fin.color0;
+    var albedo = material_uniform.diffuse.rgb;
     if (has_diffuse_texture()) {
         let texcoord0 = fin.texcoord0;
         let texel = textureSample(diffuse_texture, diffuse_texture_sampler, texcoord0);
+        albedo *= texel.rgb;
         material_color = vec4<f32>(material_color.rgb *(texel.rgb * material_uniform.diffuse_blend_factor.rgb) * material_uniform.diffuse_blend_factor.a, material_color.a * texel.a);
     }
     if (has_sphere_texture()) {
@@ -180,11 +285,12 @@ fn fs_main(
     }
     let light_position = -model_uniform.light_direction.xyz;
     if (has_shadow_map_texture()) {
-        let texcoord0 = fin.shadow0 / fin.shadow0.w;
-        let satu_texc = saturate(texcoord0.xy);
+        let world_position = model_uniform.camera_position.xyz - fin.eye;
+        let cascade = find_shadow_cascade(world_position, length(fin.eye));
         let toon_color = textureSample(toon_texture, toon_texture_sampler, vec2<f32>(0.0, 1.0));
-        var coverage = shadow_coverage(texcoord0, model_uniform.shadow_map_size);
-        if (satu_texc.x == texcoord0.x && satu_texc.y == texcoord0.y) {
+        if (cascade >= 0) {
+            let texcoord0 = shadow_texcoord(cascade, world_position);
+            var coverage = shadow_coverage(texcoord0, cascade, model_uniform.shadow_map_size);
             var shadow_color = material_color;
             shadow_color = vec4<f32>(shadow_color.rgb * (toon_color.rgb * material_uniform.toon_blend_factor.rgb) * material_uniform.toon_blend_factor.a, shadow_color.a * toon_color.a);
             coverage = min(saturate(dot(fin.normal, light_position) * toon_factor), coverage);
@@ -207,5 +313,36 @@ fn fs_main(
         let spec = pow(specular_angle, specular_power);
         material_color = vec4<f32>(material_color.rgb + material_uniform.specular.rgb * model_uniform.light_color.rgb * spec, material_color.a);
     }
-    return saturate(material_color);
+    let world_position = model_uniform.camera_position.xyz - fin.eye;
+    let normal = normalize(fin.normal);
+    if (model_uniform.environment.x > 0.0) {
+        let irradiance = evaluate_environment(model_uniform.environment_irradiance, normal);
+        material_color = vec4<f32>(material_color.rgb + albedo * irradiance * model_uniform.environment.x, material_color.a);
+    }
+    if (model_uniform.environment.y > 0.0 && specular_power > 0.0) {
+        let reflection = reflect(-normalize(fin.eye), normal);
+        let radiance = evaluate_environment(model_uniform.environment_radiance, reflection);
+        material_color = vec4<f32>(material_color.rgb + material_uniform.specular.rgb * radiance * model_uniform.environment.y, material_color.a);
+    }
+    var local_color = vec3<f32>(0.0, 0.0, 0.0);
+    for (var i = 0u; i < u32(model_uniform.local_light_size.x); i = i + 1u) {
+        let light = model_uniform.local_lights[i];
+        let attenuation = local_light_attenuation(light, world_position) * local_shadow_coverage(light, world_position);
+        if (attenuation > 0.0) {
+            let local_light_position = normalize(light.position.xyz - world_position);
+            let n_dot_l = dot(normal, local_light_position);
+            let y = 0.5 - n_dot_l * 0.5;
+            let toon_color = textureSampleLevel(toon_texture, toon_texture_sampler, vec2<f32>(0.0, y), 0.0).rgb;
+            let ramp = select(vec3<f32>(saturate(n_dot_l * toon_factor)), toon_color, has_toon_texture());
+            local_color += albedo * light.color.rgb * ramp * attenuation;
+            if (specular_power > 0.0) {
+                let half_vector = normalize(local_light_position + normalize(fin.eye));
+                let spec = pow(max(dot(normal, half_vector), 0.0), specular_power);
+                local_color += material_uniform.specular.rgb * light.color.rgb * spec * attenuation;
+            }
+        }
+    }
+    material_color = vec4<f32>(material_color.rgb + local_color, material_color.a);
+    // LDR targets clamp on write, HDR targets keep the range above 1 for the tone mapping
+    return vec4<f32>(max(material_color.rgb, vec3<f32>(0.0)), saturate(material_color.a));
 }
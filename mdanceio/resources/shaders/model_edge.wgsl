@@ -28,6 +28,14 @@ struct FragmentInput {
     @location(5) shadow0: vec4<f32>,
 }
 
+struct LocalLight {
+    position: vec4<f32>,
+    direction: vec4<f32>,
+    color: vec4<f32>,
+    shadow: vec4<f32>,
+    shadow_matrix: mat4x4<f32>,
+}
+
 struct ModelUniform {
     model_matrix: mat4x4<f32>,
     model_view_matrix: mat4x4<f32>,
@@ -37,6 +45,11 @@ struct ModelUniform {
     light_direction: vec4<f32>,
     camera_position: vec4<f32>,
     shadow_map_size: vec4<f32>,
+    local_light_size: vec4<f32>,
+    local_lights: array<LocalLight, 4>,
+    shadow_cascade_splits: vec4<f32>,
+    shadow_filter: vec4<f32>,
+    shadow_cascade_matrices: array<mat4x4<f32>, 4>,
 }
 
 struct MaterialUniform {
@@ -81,7 +94,7 @@ var<uniform> model_uniform: ModelUniform;
 var<uniform> material_uniform: MaterialUniform;
 
 @group(2) @binding(0)
-var shadow_texture: texture_2d<f32>;
+var shadow_texture: texture_2d_array<f32>;
 @group(2) @binding(1)
 var shadow_texture_sampler: sampler;
 
@@ -0,0 +1,34 @@
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+}
+
+struct FragmentInput {
+    @location(0) color: vec4<f32>,
+}
+
+struct OverlayUniform {
+    view_projection_matrix: mat4x4<f32>,
+}
+
+@group(0)
+@binding(0)
+var<uniform> overlay_uniform: OverlayUniform;
+
+@vertex
+fn vs_main(vin: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.position = overlay_uniform.view_projection_matrix * vec4<f32>(vin.position, 1.0);
+    out.color = vin.color;
+    return out;
+}
+
+@fragment
+fn fs_main(fin: FragmentInput) -> @location(0) vec4<f32> {
+    return fin.color;
+}
\ No newline at end of file
@@ -0,0 +1,18 @@
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+};
+
+@group(0) @binding(0) var color_texture: texture_2d<f32>;
+
+@vertex
+fn vs_main(@location(0) position: vec4<f32>, @location(1) texcoord: vec4<f32>) -> VertexOutput {
+    var output: VertexOutput;
+    output.position = vec4<f32>(position.xy, 0.0, 1.0);
+    return output;
+}
+
+// weighted by the blend constant when accumulating sub-frames
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    return textureLoad(color_texture, vec2<i32>(input.position.xy), 0);
+}
@@ -0,0 +1,363 @@
+struct PostProcessUniform {
+    inverse_projection: mat4x4<f32>,
+    // threshold, intensity, enabled
+    bloom: vec4<f32>,
+    // exposure, enabled, operator (0: ACES, 1: Reinhard, 2: filmic, 3: linear)
+    tone_mapping: vec4<f32>,
+    // intensity, lut size, enabled, linear working space
+    color_grading: vec4<f32>,
+    // intensity, smoothness, enabled
+    vignette: vec4<f32>,
+    // focus distance, focus range, max blur radius in pixels
+    depth_of_field: vec4<f32>,
+    // radius, intensity, bias
+    ambient_occlusion: vec4<f32>,
+    // width in pixels, depth threshold, normal threshold
+    outline: vec4<f32>,
+    outline_color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) texcoord: vec2<f32>,
+};
+
+@group(0) @binding(0) var<uniform> post_process: PostProcessUniform;
+@group(0) @binding(1) var color_texture: texture_2d<f32>;
+@group(0) @binding(2) var color_sampler: sampler;
+// bound as unfilterable float as GLSL cannot load texels from depth textures
+@group(1) @binding(0) var depth_texture: texture_2d<f32>;
+// rgb: view space normal, a: 1 + bit flags of exclusions, 0 for the background
+@group(1) @binding(1) var normal_texture: texture_2d<f32>;
+@group(2) @binding(0) var bloom_texture: texture_2d<f32>;
+@group(2) @binding(1) var lut_texture: texture_2d<f32>;
+@group(2) @binding(2) var occlusion_texture: texture_2d<f32>;
+
+const GOLDEN_ANGLE: f32 = 2.39996323;
+const DOF_SAMPLE_COUNT: i32 = 24;
+const FXAA_REDUCE_MIN: f32 = 0.0078125;
+const FXAA_REDUCE_MUL: f32 = 0.125;
+const FXAA_SPAN_MAX: f32 = 8.0;
+const AO_SAMPLE_COUNT: i32 = 16;
+const AO_NOISE_SIZE: i32 = 4;
+const FLAG_AMBIENT_OCCLUSION_EXCLUDED: i32 = 2;
+const FLAG_OUTLINE_EXCLUDED: i32 = 4;
+
+@vertex
+fn vs_main(@location(0) position: vec4<f32>, @location(1) texcoord: vec4<f32>) -> VertexOutput {
+    var output: VertexOutput;
+    output.position = vec4<f32>(position.xy, 0.0, 1.0);
+    output.texcoord = texcoord.xy;
+    return output;
+}
+
+fn sample_color(texcoord: vec2<f32>) -> vec4<f32> {
+    return textureSampleLevel(color_texture, color_sampler, texcoord, 0.0);
+}
+
+fn texel_size() -> vec2<f32> {
+    return 1.0 / vec2<f32>(textureDimensions(color_texture));
+}
+
+fn luminance(color: vec3<f32>) -> f32 {
+    return dot(color, vec3<f32>(0.299, 0.587, 0.114));
+}
+
+fn geometry_coord(texcoord: vec2<f32>) -> vec2<i32> {
+    let size = vec2<f32>(textureDimensions(depth_texture));
+    return vec2<i32>(clamp(texcoord * size, vec2<f32>(0.0), size - 1.0));
+}
+
+fn view_position(texcoord: vec2<f32>) -> vec3<f32> {
+    let depth = textureLoad(depth_texture, geometry_coord(texcoord), 0).r;
+    let ndc = vec4<f32>(texcoord.x * 2.0 - 1.0, 1.0 - texcoord.y * 2.0, depth, 1.0);
+    let position = post_process.inverse_projection * ndc;
+    return position.xyz / position.w;
+}
+
+fn view_distance(texcoord: vec2<f32>) -> f32 {
+    return abs(view_position(texcoord).z);
+}
+
+fn load_normal(texcoord: vec2<f32>) -> vec4<f32> {
+    return textureLoad(normal_texture, geometry_coord(texcoord), 0);
+}
+
+fn has_flag(flags: f32, flag: i32) -> bool {
+    return (i32(flags) & flag) != 0;
+}
+
+fn is_geometry(flags: f32) -> bool {
+    return flags > 0.5;
+}
+
+@fragment
+fn fs_ambient_occlusion(input: VertexOutput) -> @location(0) vec4<f32> {
+    let center = load_normal(input.texcoord);
+    if (!is_geometry(center.a) || has_flag(center.a, FLAG_AMBIENT_OCCLUSION_EXCLUDED)) {
+        return vec4<f32>(1.0);
+    }
+    let position = view_position(input.texcoord);
+    let normal = normalize(center.xyz);
+    let radius = post_process.ambient_occlusion.x;
+    // radius projected onto the screen in texcoord units, m11 of the projection is 1 / m11 of the inverse
+    let screen_radius = radius * 0.5 / (post_process.inverse_projection[1][1] * max(abs(position.z), 0.0001));
+    let aspect = vec2<f32>(textureDimensions(depth_texture));
+    let scale = vec2<f32>(screen_radius * aspect.y / aspect.x, screen_radius);
+    // rotates the spiral per pixel, the noise is removed by the blur of the apply pass
+    let pixel = vec2<i32>(input.position.xy) % AO_NOISE_SIZE;
+    let rotation = f32(pixel.x * AO_NOISE_SIZE + pixel.y) / f32(AO_NOISE_SIZE * AO_NOISE_SIZE) * 6.28318531;
+    var occlusion = 0.0;
+    for (var i = 0; i < AO_SAMPLE_COUNT; i = i + 1) {
+        let r = (f32(i) + 0.5) / f32(AO_SAMPLE_COUNT);
+        let theta = f32(i) * GOLDEN_ANGLE + rotation;
+        let texcoord = input.texcoord + vec2<f32>(cos(theta), sin(theta)) * scale * r;
+        let v = view_position(texcoord) - position;
+        let distance = length(v);
+        let angle = max(dot(v / max(distance, 0.0001), normal) - post_process.ambient_occlusion.z, 0.0);
+        let falloff = 1.0 - clamp(distance / radius, 0.0, 1.0);
+        occlusion = occlusion + angle * falloff * falloff;
+    }
+    let ao = 1.0 - occlusion / f32(AO_SAMPLE_COUNT) * post_process.ambient_occlusion.y;
+    return vec4<f32>(clamp(ao, 0.0, 1.0));
+}
+
+@fragment
+fn fs_ambient_occlusion_apply(input: VertexOutput) -> @location(0) vec4<f32> {
+    let source = sample_color(input.texcoord);
+    let size = vec2<i32>(textureDimensions(occlusion_texture));
+    let origin = vec2<i32>(input.position.xy) - AO_NOISE_SIZE / 2;
+    var ao = 0.0;
+    for (var y = 0; y < AO_NOISE_SIZE; y = y + 1) {
+        for (var x = 0; x < AO_NOISE_SIZE; x = x + 1) {
+            let coord = clamp(origin + vec2<i32>(x, y), vec2<i32>(0), size - 1);
+            ao = ao + textureLoad(occlusion_texture, coord, 0).r;
+        }
+    }
+    ao = ao / f32(AO_NOISE_SIZE * AO_NOISE_SIZE);
+    return vec4<f32>(source.rgb * ao, source.a);
+}
+
+// the nearer one of the pair owns the edge so that the outline stays on the front surface
+fn outline_edge(texcoord: vec2<f32>, offset: vec2<f32>) -> f32 {
+    let a = load_normal(texcoord);
+    let b = load_normal(texcoord + offset);
+    let distance_a = view_distance(texcoord);
+    let distance_b = view_distance(texcoord + offset);
+    var flags = b.a;
+    if (distance_a <= distance_b) {
+        flags = a.a;
+    }
+    if (!is_geometry(flags) || has_flag(flags, FLAG_OUTLINE_EXCLUDED)) {
+        return 0.0;
+    }
+    let depth_edge = abs(distance_a - distance_b) / max(min(distance_a, distance_b), 0.0001) > post_process.outline.y;
+    var normal_edge = false;
+    if (is_geometry(a.a) && is_geometry(b.a)) {
+        normal_edge = 1.0 - dot(normalize(a.xyz), normalize(b.xyz)) > post_process.outline.z;
+    }
+    return select(0.0, 1.0, depth_edge || normal_edge);
+}
+
+@fragment
+fn fs_outline(input: VertexOutput) -> @location(0) vec4<f32> {
+    let source = sample_color(input.texcoord);
+    let offset = post_process.outline.x / vec2<f32>(textureDimensions(depth_texture));
+    var edge = outline_edge(input.texcoord, vec2<f32>(offset.x, 0.0));
+    edge = max(edge, outline_edge(input.texcoord, vec2<f32>(-offset.x, 0.0)));
+    edge = max(edge, outline_edge(input.texcoord, vec2<f32>(0.0, offset.y)));
+    edge = max(edge, outline_edge(input.texcoord, vec2<f32>(0.0, -offset.y)));
+    // drawn over the premultiplied source, so the outline keeps covering transparent backgrounds
+    let coverage = edge * post_process.outline_color.a;
+    let color = mix(source.rgb, post_process.outline_color.rgb, coverage);
+    return vec4<f32>(color, mix(source.a, 1.0, coverage));
+}
+
+fn circle_of_confusion(texcoord: vec2<f32>) -> f32 {
+    let distance = view_distance(texcoord);
+    let range = max(post_process.depth_of_field.y, 0.0001);
+    let amount = clamp(abs(distance - post_process.depth_of_field.x) / range, 0.0, 1.0);
+    return amount * post_process.depth_of_field.z;
+}
+
+@fragment
+fn fs_depth_of_field(input: VertexOutput) -> @location(0) vec4<f32> {
+    let center = sample_color(input.texcoord);
+    let radius = circle_of_confusion(input.texcoord);
+    let texel = texel_size();
+    // alpha is blurred along with the premultiplied colors to keep transparent silhouettes soft
+    var color = center;
+    var weight = 1.0;
+    for (var i = 1; i < DOF_SAMPLE_COUNT; i = i + 1) {
+        let r = sqrt(f32(i) / f32(DOF_SAMPLE_COUNT)) * radius;
+        let theta = f32(i) * GOLDEN_ANGLE;
+        let offset = vec2<f32>(cos(theta), sin(theta)) * r * texel;
+        let texcoord = input.texcoord + offset;
+        // avoids sharp foreground bleeding into blurred background
+        let w = step(r, circle_of_confusion(texcoord) + 0.5);
+        color = color + sample_color(texcoord) * w;
+        weight = weight + w;
+    }
+    return color / weight;
+}
+
+@fragment
+fn fs_bloom_extract(input: VertexOutput) -> @location(0) vec4<f32> {
+    let color = sample_color(input.texcoord).rgb;
+    let brightness = max(max(color.r, color.g), color.b);
+    let contribution = max(brightness - post_process.bloom.x, 0.0) / max(brightness, 0.0001);
+    return vec4<f32>(color * contribution, 1.0);
+}
+
+fn blur(texcoord: vec2<f32>, direction: vec2<f32>) -> vec4<f32> {
+    let offset = direction * texel_size();
+    var color = sample_color(texcoord).rgb * 0.227027;
+    color = color + sample_color(texcoord + offset * 1.384615).rgb * 0.316216;
+    color = color + sample_color(texcoord - offset * 1.384615).rgb * 0.316216;
+    color = color + sample_color(texcoord + offset * 3.230769).rgb * 0.070270;
+    color = color + sample_color(texcoord - offset * 3.230769).rgb * 0.070270;
+    return vec4<f32>(color, 1.0);
+}
+
+@fragment
+fn fs_bloom_blur_horizontal(input: VertexOutput) -> @location(0) vec4<f32> {
+    return blur(input.texcoord, vec2<f32>(1.0, 0.0));
+}
+
+@fragment
+fn fs_bloom_blur_vertical(input: VertexOutput) -> @location(0) vec4<f32> {
+    return blur(input.texcoord, vec2<f32>(0.0, 1.0));
+}
+
+fn linear_to_srgb(color: vec3<f32>) -> vec3<f32> {
+    let low = color * 12.92;
+    let high = 1.055 * pow(color, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(high, low, color <= vec3<f32>(0.0031308));
+}
+
+fn srgb_to_linear(color: vec3<f32>) -> vec3<f32> {
+    let low = color / 12.92;
+    let high = pow((color + 0.055) / 1.055, vec3<f32>(2.4));
+    return select(high, low, color <= vec3<f32>(0.04045));
+}
+
+fn aces(color: vec3<f32>) -> vec3<f32> {
+    let a = color * (2.51 * color + 0.03);
+    let b = color * (2.43 * color + 0.59) + 0.14;
+    return clamp(a / b, vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+fn uncharted2(x: vec3<f32>) -> vec3<f32> {
+    let a = 0.15;
+    let b = 0.50;
+    let c = 0.10;
+    let d = 0.20;
+    let e = 0.02;
+    let f = 0.30;
+    return ((x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f)) - e / f;
+}
+
+fn tone_map(color: vec3<f32>) -> vec3<f32> {
+    let exposed = color * post_process.tone_mapping.x;
+    let mode = i32(post_process.tone_mapping.z + 0.5);
+    if (mode == 1) {
+        return exposed / (exposed + vec3<f32>(1.0));
+    } else if (mode == 2) {
+        let white = uncharted2(vec3<f32>(11.2));
+        return clamp(uncharted2(exposed * 2.0) / white, vec3<f32>(0.0), vec3<f32>(1.0));
+    } else if (mode == 3) {
+        return clamp(exposed, vec3<f32>(0.0), vec3<f32>(1.0));
+    }
+    return aces(exposed);
+}
+
+// LUT is a horizontal strip of N slices of N x N texels, blue selects the slice
+fn sample_lut(color: vec3<f32>) -> vec3<f32> {
+    let size = post_process.color_grading.y;
+    let c = clamp(color, vec3<f32>(0.0), vec3<f32>(1.0)) * (size - 1.0);
+    let slice = floor(c.b);
+    let next_slice = min(slice + 1.0, size - 1.0);
+    let x = (c.r + 0.5) / (size * size);
+    let y = (c.g + 0.5) / size;
+    let a = textureSampleLevel(lut_texture, color_sampler, vec2<f32>(x + slice / size, y), 0.0).rgb;
+    let b = textureSampleLevel(lut_texture, color_sampler, vec2<f32>(x + next_slice / size, y), 0.0).rgb;
+    return mix(a, b, c.b - slice);
+}
+
+@fragment
+fn fs_composite(input: VertexOutput) -> @location(0) vec4<f32> {
+    let source = sample_color(input.texcoord);
+    var color = source.rgb;
+    var alpha = source.a;
+    if (post_process.bloom.z > 0.5) {
+        let bloom = textureSampleLevel(bloom_texture, color_sampler, input.texcoord, 0.0).rgb
+            * post_process.bloom.y;
+        color = color + bloom;
+        // glows over transparent backgrounds cover them by their brightness
+        alpha = max(alpha, clamp(max(max(bloom.r, bloom.g), bloom.b), 0.0, 1.0));
+    }
+    // colors are premultiplied, curves are applied to the unpremultiplied ones to keep the
+    // edges of transparent frames from darkening
+    if (alpha > 0.0) {
+        color = color / alpha;
+    }
+    if (post_process.tone_mapping.y > 0.5) {
+        color = tone_map(color);
+    }
+    if (post_process.color_grading.z > 0.5) {
+        let linear = post_process.color_grading.w > 0.5;
+        var encoded = color;
+        if (linear) {
+            encoded = linear_to_srgb(clamp(color, vec3<f32>(0.0), vec3<f32>(1.0)));
+        }
+        var graded = sample_lut(encoded);
+        if (linear) {
+            graded = srgb_to_linear(graded);
+        }
+        color = mix(color, graded, post_process.color_grading.x);
+    }
+    if (post_process.vignette.z > 0.5) {
+        let distance = length(input.texcoord - vec2<f32>(0.5)) * 1.41421356;
+        let smoothness = max(post_process.vignette.y, 0.0001);
+        let falloff = smoothstep(1.0 - smoothness, 1.0 + smoothness * 0.5, distance);
+        color = color * (1.0 - falloff * post_process.vignette.x);
+    }
+    return vec4<f32>(color * alpha, alpha);
+}
+
+@fragment
+fn fs_fxaa(input: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = texel_size();
+    let center = sample_color(input.texcoord);
+    let luma_nw = luminance(sample_color(input.texcoord + vec2<f32>(-1.0, -1.0) * texel).rgb);
+    let luma_ne = luminance(sample_color(input.texcoord + vec2<f32>(1.0, -1.0) * texel).rgb);
+    let luma_sw = luminance(sample_color(input.texcoord + vec2<f32>(-1.0, 1.0) * texel).rgb);
+    let luma_se = luminance(sample_color(input.texcoord + vec2<f32>(1.0, 1.0) * texel).rgb);
+    let luma_m = luminance(center.rgb);
+    let luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    let luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+    var direction = vec2<f32>(
+        -((luma_nw + luma_ne) - (luma_sw + luma_se)),
+        (luma_nw + luma_sw) - (luma_ne + luma_se),
+    );
+    let direction_reduce = max(
+        (luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * FXAA_REDUCE_MUL,
+        FXAA_REDUCE_MIN,
+    );
+    let inverse_direction_min = 1.0 / (min(abs(direction.x), abs(direction.y)) + direction_reduce);
+    direction = clamp(
+        direction * inverse_direction_min,
+        vec2<f32>(-FXAA_SPAN_MAX),
+        vec2<f32>(FXAA_SPAN_MAX),
+    ) * texel;
+    // alpha is filtered with the premultiplied colors to smooth transparent silhouettes too
+    let color_a = 0.5 * (sample_color(input.texcoord + direction * (1.0 / 3.0 - 0.5))
+        + sample_color(input.texcoord + direction * (2.0 / 3.0 - 0.5)));
+    let color_b = color_a * 0.5 + 0.25 * (sample_color(input.texcoord - direction * 0.5)
+        + sample_color(input.texcoord + direction * 0.5));
+    let luma_b = luminance(color_b.rgb);
+    if (luma_b < luma_min || luma_b > luma_max) {
+        return color_a;
+    }
+    return color_b;
+}
@@ -0,0 +1,38 @@
+struct SkyboxUniform {
+    inverse_view_projection: mat4x4<f32>,
+    // intensity, cos and sin of the rotation around Y
+    parameters: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) ndc: vec2<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> skybox: SkyboxUniform;
+@group(0) @binding(1)
+var cube_texture: texture_cube<f32>;
+@group(0) @binding(2)
+var cube_sampler: sampler;
+
+@vertex
+fn vs_main(@location(0) position: vec4<f32>, @location(1) texcoord: vec4<f32>) -> VertexOutput {
+    var output: VertexOutput;
+    output.position = vec4<f32>(position.xy, 0.0, 1.0);
+    output.ndc = position.xy;
+    return output;
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    let near = skybox.inverse_view_projection * vec4<f32>(input.ndc, 0.0, 1.0);
+    // the far plane of the camera is at infinity, so a point in between gives the direction
+    let far = skybox.inverse_view_projection * vec4<f32>(input.ndc, 0.5, 1.0);
+    let direction = far.xyz / far.w - near.xyz / near.w;
+    let c = skybox.parameters.y;
+    let s = skybox.parameters.z;
+    let rotated = vec3<f32>(c * direction.x + s * direction.z, direction.y, c * direction.z - s * direction.x);
+    let color = textureSampleLevel(cube_texture, cube_sampler, rotated, 0.0).rgb;
+    return vec4<f32>(color * skybox.parameters.x, 1.0);
+}
@@ -1,15 +1,28 @@
-use cgmath::{Quaternion, Vector3};
-use image::GenericImageView;
+use cgmath::{Quaternion, Rad, Vector3};
 
 use crate::{
+    camera::CameraFollowMode,
     error::MdanceioError,
+    event_publisher::{Event, EventBus, EventCallback, MotionTarget, SubscriptionId},
+    graphics::AuxiliaryTargets,
     injector::Injector,
-    project::{ModelHandle, Project},
+    light::LocalLight,
+    motion::{
+        curve::{
+            sample_curve, BoneInterpolationChannel, CameraInterpolationChannel, InterpolationPreset,
+        },
+        layer::LayerBlendMode,
+    },
+    project::{
+        EnvironmentSettings, ModelHandle, MotionBlurSettings, PostProcessSettings, Project,
+        SupersamplingSettings, ToneMappingOperator, ViewportAlphaMode,
+    },
+    project_file::{AssetLoader, ProjectFile},
 };
-use std::{collections::HashMap, io::Cursor};
 
 pub struct BaseApplicationService {
     project: Project,
+    event_bus: EventBus,
 }
 
 impl BaseApplicationService {
@@ -21,9 +34,47 @@ impl BaseApplicationService {
     ) -> Self {
         Self {
             project: Project::new(adapter, device, queue, injector),
+            event_bus: EventBus::default(),
         }
     }
 
+    pub fn subscribe(&mut self, callback: EventCallback) -> SubscriptionId {
+        self.event_bus.subscribe(callback)
+    }
+
+    pub fn unsubscribe(&mut self, id: SubscriptionId) -> bool {
+        self.event_bus.unsubscribe(id)
+    }
+
+    /// Forward all events emitted by the project since last dispatch to subscribers
+    pub fn dispatch_events(&mut self) {
+        let events = self.project.take_events();
+        if !events.is_empty() {
+            self.event_bus.dispatch(&events);
+        }
+    }
+
+    fn report<T>(&mut self, result: Result<T, MdanceioError>) -> Result<T, MdanceioError> {
+        if let Err(err) = &result {
+            self.project.publish_event(Event::Error(err.clone()));
+        }
+        self.dispatch_events();
+        result
+    }
+
+    fn update_local_light(
+        &mut self,
+        index: usize,
+        updater: impl FnOnce(&mut LocalLight),
+    ) -> Result<(), MdanceioError> {
+        let result = self.project.local_light_mut(index).map(updater);
+        self.report(result)
+    }
+
+    fn resolve_model_handle(&self, model_handle: Option<ModelHandle>) -> Option<ModelHandle> {
+        model_handle.or_else(|| self.project.active_model_handle())
+    }
+
     pub fn draw_default_pass(
         &mut self,
         view: &wgpu::TextureView,
@@ -32,7 +83,9 @@ impl BaseApplicationService {
     ) {
         self.project.draw_shadow_map(device, queue);
         self.project.draw_viewport(view, device, queue);
+        self.project.draw_auxiliary_targets(device, queue);
         self.project.update(device, queue);
+        self.dispatch_events();
     }
 
     pub fn draw_from(
@@ -60,10 +113,27 @@ impl BaseApplicationService {
             queue,
         );
         self.project.update(device, queue);
+        self.dispatch_events();
+    }
+
+    /// Draws the frame at the preferred motion fps for offline rendering, see
+    /// `Project::draw_motion_blurred_frame`.
+    pub fn draw_frame(
+        &mut self,
+        frame_index: u32,
+        view: &wgpu::TextureView,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        self.project
+            .draw_motion_blurred_frame(frame_index, view, device, queue);
+        self.project.draw_auxiliary_targets(device, queue);
+        self.dispatch_events();
     }
 
     pub fn update_current_project(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
         self.project.update(device, queue);
+        self.dispatch_events();
     }
 
     pub fn load_model(
@@ -72,27 +142,323 @@ impl BaseApplicationService {
         device: &wgpu::Device,
         queue: &wgpu::Queue,
     ) -> Result<ModelHandle, MdanceioError> {
-        self.project.load_model(data, device, queue)
+        let result = self.project.load_model(data, device, queue);
+        self.report(result)
     }
 
     pub fn load_model_motion(&mut self, data: &[u8]) -> Result<(), MdanceioError> {
-        self.project.load_model_motion(data)
+        let result = self.project.load_model_motion(data);
+        self.report(result)
     }
 
     pub fn load_camera_motion(&mut self, data: &[u8]) -> Result<(), MdanceioError> {
-        self.project.load_camera_motion(data)
+        let result = self.project.load_camera_motion(data);
+        self.report(result)
     }
 
     pub fn load_light_motion(&mut self, data: &[u8]) -> Result<(), MdanceioError> {
-        self.project.load_light_motion(data)
+        let result = self.project.load_light_motion(data);
+        self.report(result)
+    }
+
+    pub fn remove_model(&mut self, model_handle: ModelHandle) -> Result<(), MdanceioError> {
+        let result = self.project.remove_model(model_handle);
+        self.report(result)
+    }
+
+    pub fn remove_model_motion(&mut self, model_handle: Option<ModelHandle>) {
+        if let Some(handle) = self.resolve_model_handle(model_handle) {
+            self.project.remove_model_motion(handle);
+        }
+        self.dispatch_events();
+    }
+
+    pub fn remove_camera_motion(&mut self) {
+        self.project.remove_camera_motion();
+        self.dispatch_events();
+    }
+
+    pub fn remove_light_motion(&mut self) {
+        self.project.remove_light_motion();
+        self.dispatch_events();
+    }
+
+    pub fn resample_motion(
+        &mut self,
+        target: MotionTarget,
+        from_fps: f32,
+    ) -> Result<(), MdanceioError> {
+        let result = self.project.resample_motion(target, from_fps);
+        self.report(result)
+    }
+
+    pub fn bone_keyframe_interpolation(
+        &self,
+        model_handle: ModelHandle,
+        bone_name: &str,
+        frame_index: u32,
+        channel: u32,
+    ) -> Option<[u8; 4]> {
+        let channel = BoneInterpolationChannel::from_index(channel)?;
+        self.project
+            .bone_keyframe_interpolation(model_handle, bone_name, frame_index, channel)
+    }
+
+    /// Sets control points of the bone keyframe curve, `channel` is one of translation X, Y, Z and orientation.
+    pub fn set_bone_keyframe_interpolation(
+        &mut self,
+        model_handle: ModelHandle,
+        bone_name: &str,
+        frame_index: u32,
+        channel: u32,
+        parameters: [u8; 4],
+    ) -> Result<(), MdanceioError> {
+        let result = BoneInterpolationChannel::from_index(channel)
+            .ok_or_else(MdanceioError::invalid_interpolation_channel)
+            .and_then(|channel| {
+                self.project.set_bone_keyframe_interpolation(
+                    model_handle,
+                    bone_name,
+                    frame_index,
+                    channel,
+                    parameters,
+                )
+            });
+        self.report(result)
+    }
+
+    pub fn split_bone_keyframe_interval(
+        &mut self,
+        model_handle: ModelHandle,
+        bone_name: &str,
+        frame_index: u32,
+    ) -> Result<(), MdanceioError> {
+        let result =
+            self.project
+                .split_bone_keyframe_interval(model_handle, bone_name, frame_index);
+        self.report(result)
+    }
+
+    pub fn camera_keyframe_interpolation(&self, frame_index: u32, channel: u32) -> Option<[u8; 4]> {
+        let channel = CameraInterpolationChannel::from_index(channel)?;
+        self.project
+            .camera_keyframe_interpolation(frame_index, channel)
+    }
+
+    /// Sets control points of the camera keyframe curve, `channel` is one of look at X, Y, Z, angle, fov and distance.
+    pub fn set_camera_keyframe_interpolation(
+        &mut self,
+        frame_index: u32,
+        channel: u32,
+        parameters: [u8; 4],
+    ) -> Result<(), MdanceioError> {
+        let result = CameraInterpolationChannel::from_index(channel)
+            .ok_or_else(MdanceioError::invalid_interpolation_channel)
+            .and_then(|channel| {
+                self.project
+                    .set_camera_keyframe_interpolation(frame_index, channel, parameters)
+            });
+        self.report(result)
+    }
+
+    pub fn split_camera_keyframe_interval(
+        &mut self,
+        frame_index: u32,
+    ) -> Result<(), MdanceioError> {
+        let result = self.project.split_camera_keyframe_interval(frame_index);
+        self.report(result)
+    }
+
+    /// Control points of a preset curve, `preset` is one of linear, ease in, ease out and ease in out.
+    pub fn interpolation_preset(preset: u32) -> Option<[u8; 4]> {
+        InterpolationPreset::from_index(preset).map(InterpolationPreset::parameters)
+    }
+
+    pub fn sample_interpolation_curve(parameters: [u8; 4], count: usize) -> Vec<[f32; 2]> {
+        sample_curve(parameters, count)
+    }
+
+    pub fn set_preferred_motion_fps(&mut self, value: u32) {
+        self.project.set_preferred_motion_fps(value);
+        self.dispatch_events();
+    }
+
+    pub fn load_model_motion_layer(
+        &mut self,
+        model_handle: ModelHandle,
+        name: &str,
+        data: &[u8],
+    ) -> Result<usize, MdanceioError> {
+        let result = self
+            .project
+            .load_model_motion_layer(model_handle, name, data);
+        self.report(result)
+    }
+
+    pub fn remove_model_motion_layer(&mut self, model_handle: ModelHandle, index: usize) {
+        self.project.remove_model_motion_layer(model_handle, index);
+        self.dispatch_events();
+    }
+
+    pub fn set_motion_layer_weight(&mut self, model_handle: ModelHandle, index: usize, value: f32) {
+        self.project
+            .update_model_motion_layer(model_handle, index, |layer| {
+                layer.weight = value.clamp(0f32, 1f32)
+            });
+        self.dispatch_events();
+    }
+
+    pub fn set_motion_layer_additive(
+        &mut self,
+        model_handle: ModelHandle,
+        index: usize,
+        value: bool,
+    ) {
+        self.project
+            .update_model_motion_layer(model_handle, index, |layer| {
+                layer.blend_mode = if value {
+                    LayerBlendMode::Additive
+                } else {
+                    LayerBlendMode::Override
+                }
+            });
+        self.dispatch_events();
+    }
+
+    pub fn set_motion_layer_frame_offset(
+        &mut self,
+        model_handle: ModelHandle,
+        index: usize,
+        value: i32,
+    ) {
+        self.project
+            .update_model_motion_layer(model_handle, index, |layer| layer.frame_offset = value);
+        self.dispatch_events();
+    }
+
+    pub fn set_motion_layer_enabled(
+        &mut self,
+        model_handle: ModelHandle,
+        index: usize,
+        value: bool,
+    ) {
+        self.project
+            .update_model_motion_layer(model_handle, index, |layer| layer.enabled = value);
+        self.dispatch_events();
+    }
+
+    /// Restricts the layer to the bones and morphs, or clears the restriction if `None`.
+    pub fn set_motion_layer_masks(
+        &mut self,
+        model_handle: ModelHandle,
+        index: usize,
+        bones: Option<&[&str]>,
+        morphs: Option<&[&str]>,
+    ) {
+        let to_set = |names: &[&str]| names.iter().map(|name| (*name).to_owned()).collect();
+        self.project
+            .update_model_motion_layer(model_handle, index, |layer| {
+                layer.bone_mask = bones.map(to_set);
+                layer.morph_mask = morphs.map(to_set);
+            });
+        self.dispatch_events();
+    }
+
+    pub fn set_outside_parent(
+        &mut self,
+        model_handle: ModelHandle,
+        bone_name: &str,
+        target: Option<(&str, &str)>,
+    ) -> Result<(), MdanceioError> {
+        let result = self
+            .project
+            .set_outside_parent(model_handle, bone_name, target);
+        self.report(result)
+    }
+
+    pub fn load_model_from(
+        &mut self,
+        path: &str,
+        loader: &mut dyn AssetLoader,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<ModelHandle, MdanceioError> {
+        let result = self.project.load_model_from(path, loader, device, queue);
+        self.report(result)
+    }
+
+    pub fn load_model_motion_from(
+        &mut self,
+        path: &str,
+        loader: &mut dyn AssetLoader,
+    ) -> Result<(), MdanceioError> {
+        let result = self.project.load_model_motion_from(path, loader);
+        self.report(result)
+    }
+
+    pub fn load_camera_motion_from(
+        &mut self,
+        path: &str,
+        loader: &mut dyn AssetLoader,
+    ) -> Result<(), MdanceioError> {
+        let result = self.project.load_camera_motion_from(path, loader);
+        self.report(result)
+    }
+
+    pub fn load_light_motion_from(
+        &mut self,
+        path: &str,
+        loader: &mut dyn AssetLoader,
+    ) -> Result<(), MdanceioError> {
+        let result = self.project.load_light_motion_from(path, loader);
+        self.report(result)
+    }
+
+    pub fn save_project(&self) -> String {
+        self.project.save().serialize()
+    }
+
+    pub fn load_project(
+        &mut self,
+        text: &str,
+        loader: &mut dyn AssetLoader,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<(), MdanceioError> {
+        let result = ProjectFile::parse(text)
+            .and_then(|file| self.project.load(&file, loader, device, queue));
+        self.report(result)
     }
 
     pub fn seek(&mut self, frame_index: u32) {
         self.project.seek(frame_index, true);
+        self.dispatch_events();
+    }
+
+    pub fn seek_precisely(&mut self, frame_index: u32, amount: f32) {
+        self.project
+            .seek_precisely(frame_index, amount.clamp(0f32, 1f32), true);
+        self.dispatch_events();
     }
 
     pub fn play(&mut self) {
         self.project.play();
+        self.dispatch_events();
+    }
+
+    pub fn pause(&mut self) {
+        self.project.pause();
+        self.dispatch_events();
+    }
+
+    pub fn resume(&mut self) {
+        self.project.resume();
+        self.dispatch_events();
+    }
+
+    pub fn stop(&mut self) {
+        self.project.stop();
+        self.dispatch_events();
     }
 
     pub fn enable_shadow_map(
@@ -100,14 +466,268 @@ impl BaseApplicationService {
         handle: ModelHandle,
         value: bool,
     ) -> Result<(), MdanceioError> {
-        if let Some(model) = self.project.model_mut(handle) {
-            model.set_shadow_map_enabled(value);
+        let result = if self.project.model(handle).is_some() {
+            self.project.set_model_shadow_map_enabled(handle, value);
             Ok(())
         } else {
             Err(MdanceioError::model_not_found())
+        };
+        self.report(result)
+    }
+
+    /// Shadow maps are square, `cascade_count` of 1 keeps the MMD compatible single map.
+    pub fn set_shadow_map_resolution(
+        &mut self,
+        texture_size: u32,
+        cascade_count: u32,
+        device: &wgpu::Device,
+    ) {
+        self.project
+            .set_shadow_map_resolution(texture_size, cascade_count as usize, device);
+    }
+
+    pub fn set_shadow_map_filter_radius(&mut self, value: u32) {
+        self.project.shadow_camera_mut().set_filter_radius(value);
+    }
+
+    pub fn set_shadow_map_split_lambda(&mut self, value: f32) {
+        self.project.shadow_camera_mut().set_split_lambda(value);
+    }
+
+    pub fn set_shadow_map_depth_bias(&mut self, value: f32) {
+        self.project.shadow_camera_mut().set_depth_bias(value);
+    }
+
+    pub fn post_process_settings(&self) -> PostProcessSettings {
+        *self.project.post_process_settings()
+    }
+
+    pub fn set_post_process_settings(&mut self, settings: PostProcessSettings) {
+        self.project.set_post_process_settings(settings);
+    }
+
+    /// Sets the tone mapping curve, `operator` is one of ACES, Reinhard, filmic and linear.
+    pub fn set_tone_mapping_operator(&mut self, operator: u32) -> Result<(), MdanceioError> {
+        let result = ToneMappingOperator::from_index(operator)
+            .ok_or_else(MdanceioError::invalid_tone_mapping_operator)
+            .map(|operator| {
+                self.project.set_post_process_settings(PostProcessSettings {
+                    tone_mapping_operator: operator,
+                    ..*self.project.post_process_settings()
+                })
+            });
+        self.report(result)
+    }
+
+    pub fn motion_blur_settings(&self) -> MotionBlurSettings {
+        *self.project.motion_blur_settings()
+    }
+
+    pub fn set_motion_blur_settings(&mut self, settings: MotionBlurSettings) {
+        self.project.set_motion_blur_settings(settings);
+    }
+
+    pub fn sample_level(&self) -> u32 {
+        self.project.sample_level()
+    }
+
+    /// Sets the MSAA sample count to `1 << value`, see `Project::supported_sample_counts`.
+    pub fn set_sample_level(&mut self, value: u32) -> Result<(), MdanceioError> {
+        let result = self.project.set_sample_level(value);
+        self.report(result)
+    }
+
+    pub fn supersampling_settings(&self) -> SupersamplingSettings {
+        *self.project.supersampling_settings()
+    }
+
+    pub fn set_supersampling_settings(&mut self, settings: SupersamplingSettings) {
+        self.project.set_supersampling_settings(settings);
+    }
+
+    pub fn viewport_alpha_mode(&self) -> ViewportAlphaMode {
+        self.project.viewport_alpha_mode()
+    }
+
+    pub fn set_viewport_alpha_mode(&mut self, value: ViewportAlphaMode) {
+        self.project.set_viewport_alpha_mode(value);
+    }
+
+    pub fn is_auxiliary_targets_enabled(&self) -> bool {
+        self.project.is_auxiliary_targets_enabled()
+    }
+
+    pub fn set_auxiliary_targets_enabled(&mut self, value: bool) {
+        self.project.set_auxiliary_targets_enabled(value);
+    }
+
+    pub fn auxiliary_targets(&self) -> Option<&AuxiliaryTargets> {
+        self.project.auxiliary_targets()
+    }
+
+    pub fn environment_settings(&self) -> EnvironmentSettings {
+        *self.project.environment_settings()
+    }
+
+    pub fn set_environment_settings(&mut self, settings: EnvironmentSettings) {
+        self.project.set_environment_settings(settings);
+    }
+
+    pub fn load_environment_image(
+        &mut self,
+        data: &[u8],
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<(), MdanceioError> {
+        let result = self.project.load_environment_image(data, device, queue);
+        self.report(result)
+    }
+
+    pub fn clear_environment_image(&mut self) {
+        self.project.clear_environment_image();
+    }
+
+    pub fn load_color_grading_lut(
+        &mut self,
+        data: &[u8],
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<(), MdanceioError> {
+        let result = self.project.load_color_grading_lut(data, device, queue);
+        self.report(result)
+    }
+
+    pub fn set_material_ambient_occlusion_excluded(
+        &mut self,
+        model_handle: ModelHandle,
+        material_idx: usize,
+        value: bool,
+    ) -> Result<(), MdanceioError> {
+        let result =
+            self.project
+                .set_material_ambient_occlusion_excluded(model_handle, material_idx, value);
+        self.report(result)
+    }
+
+    pub fn set_material_outline_excluded(
+        &mut self,
+        model_handle: ModelHandle,
+        material_idx: usize,
+        value: bool,
+    ) -> Result<(), MdanceioError> {
+        let result = self
+            .project
+            .set_material_outline_excluded(model_handle, material_idx, value);
+        self.report(result)
+    }
+
+    /// Returns the effect name used to assign it, `manifest` is the text of the effect package.
+    pub fn load_effect(
+        &mut self,
+        manifest: &str,
+        loader: &mut dyn AssetLoader,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<String, MdanceioError> {
+        let result = self.project.load_effect(manifest, loader, device, queue);
+        self.report(result)
+    }
+
+    /// Loads the effect package whose manifest is at `path`, recording it to be saved.
+    pub fn load_effect_from(
+        &mut self,
+        path: &str,
+        loader: &mut dyn AssetLoader,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<String, MdanceioError> {
+        let result = self.project.load_effect_from(path, loader, device, queue);
+        self.report(result)
+    }
+
+    /// Returns the effect name used to assign it, `path` is the `.fx` file of an MME effect.
+    pub fn load_fx_effect(
+        &mut self,
+        path: &str,
+        loader: &mut dyn AssetLoader,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<String, MdanceioError> {
+        let result = self.project.load_fx_effect(path, loader, device, queue);
+        self.report(result)
+    }
+
+    pub fn effect_names(&self) -> Vec<String> {
+        self.project.effect_names()
+    }
+
+    pub fn remove_effect(
+        &mut self,
+        name: &str,
+        device: &wgpu::Device,
+    ) -> Result<(), MdanceioError> {
+        let result = self.project.remove_effect(name, device);
+        self.report(result)
+    }
+
+    pub fn set_model_effect(
+        &mut self,
+        model_handle: ModelHandle,
+        name: Option<&str>,
+        device: &wgpu::Device,
+    ) -> Result<(), MdanceioError> {
+        let result = self.project.set_model_effect(model_handle, name, device);
+        self.report(result)
+    }
+
+    pub fn set_material_effect(
+        &mut self,
+        model_handle: ModelHandle,
+        material_idx: usize,
+        name: Option<&str>,
+        device: &wgpu::Device,
+    ) -> Result<(), MdanceioError> {
+        let result = self
+            .project
+            .set_material_effect(model_handle, material_idx, name, device);
+        self.report(result)
+    }
+
+    pub fn set_effect_plugin_enabled(&mut self, value: bool, device: &wgpu::Device) {
+        self.project.set_effect_plugin_enabled(value, device);
+    }
+
+    pub fn set_active_model(&mut self, model_handle: Option<ModelHandle>) {
+        self.project.set_active_model(model_handle);
+        self.dispatch_events();
+    }
+
+    pub fn set_active_bone(&mut self, model_handle: Option<ModelHandle>, bone_name: Option<&str>) {
+        if let Some(handle) = self.resolve_model_handle(model_handle) {
+            self.project.set_active_bone(handle, bone_name);
+            self.dispatch_events();
         }
     }
 
+    pub fn set_active_morph(&mut self, model_handle: Option<ModelHandle>, morph_name: &str) {
+        if let Some(handle) = self.resolve_model_handle(model_handle) {
+            self.project.set_active_morph(handle, morph_name);
+            self.dispatch_events();
+        }
+    }
+
+    pub fn set_model_visible(&mut self, model_handle: Option<ModelHandle>, value: bool) {
+        if let Some(handle) = self.resolve_model_handle(model_handle) {
+            self.project.set_model_visible(handle, value);
+            self.dispatch_events();
+        }
+    }
+
+    pub fn set_grid_visible(&mut self, value: bool) {
+        self.project.set_grid_visible(value);
+        self.dispatch_events();
+    }
+
     // pub fn disable_physics_simulation(&mut self) {
     //     self.project
     //         .set_physics_simulation_mode(SimulationMode::Disable)
@@ -128,26 +748,101 @@ impl BaseApplicationService {
     pub fn set_camera_angle(&mut self, value: Vector3<f32>) {
         self.project.global_camera_mut().set_angle(value);
         self.project.update_global_camera();
-        self.project.reset_all_model_edges(&HashMap::new());
+        self.project.reset_all_model_edges();
         // TODO: use undo
     }
 
     pub fn set_camera_distance(&mut self, value: f32) {
         self.project.global_camera_mut().set_distance(value);
         self.project.update_global_camera();
-        self.project.reset_all_model_edges(&HashMap::new());
+        self.project.reset_all_model_edges();
     }
 
     pub fn set_camera_fov(&mut self, value: i32) {
         self.project.global_camera_mut().set_fov(value);
         self.project.update_global_camera();
-        self.project.reset_all_model_edges(&HashMap::new());
+        self.project.reset_all_model_edges();
     }
 
     pub fn set_camera_look_at(&mut self, value: Vector3<f32>) {
         self.project.global_camera_mut().set_look_at(value);
         self.project.update_global_camera();
-        self.project.reset_all_model_edges(&HashMap::new());
+        self.project.reset_all_model_edges();
+    }
+
+    /// Switches between perspective and orthographic projection, orthographic views are zoomed by distance.
+    pub fn set_camera_perspective(&mut self, value: bool) {
+        self.project.global_camera_mut().set_perspective(value);
+        self.project.update_global_camera();
+        self.project.reset_all_model_edges();
+    }
+
+    pub fn camera_names(&self) -> Vec<String> {
+        self.project.camera_names().to_vec()
+    }
+
+    pub fn active_camera_name(&self) -> String {
+        self.project.active_camera_name().to_owned()
+    }
+
+    pub fn add_camera(&mut self, name: &str) -> Result<(), MdanceioError> {
+        let result = self.project.add_camera(name);
+        self.report(result)
+    }
+
+    pub fn remove_camera(&mut self, name: &str) -> Result<(), MdanceioError> {
+        let result = self.project.remove_camera(name);
+        self.project.reset_all_model_edges();
+        self.report(result)
+    }
+
+    pub fn rename_camera(&mut self, name: &str, new_name: &str) -> Result<(), MdanceioError> {
+        let result = self.project.rename_camera(name, new_name);
+        self.report(result)
+    }
+
+    /// Selects the camera to render with, camera motion and camera APIs apply to it afterwards.
+    pub fn set_active_camera(&mut self, name: &str) -> Result<(), MdanceioError> {
+        let result = self.project.set_active_camera(name);
+        self.project.reset_all_model_edges();
+        self.report(result)
+    }
+
+    pub fn camera_keyframe_follow_target(&self, frame_index: u32) -> Option<(ModelHandle, String)> {
+        self.project.camera_keyframe_follow_target(frame_index)
+    }
+
+    /// Makes look at of the camera keyframe relative to the bone, or to the model if `bone_name` is empty.
+    pub fn set_camera_keyframe_follow_target(
+        &mut self,
+        frame_index: u32,
+        model_handle: ModelHandle,
+        bone_name: &str,
+    ) -> Result<(), MdanceioError> {
+        let result = self
+            .project
+            .set_camera_keyframe_follow_target(frame_index, Some((model_handle, bone_name)));
+        self.report(result)
+    }
+
+    pub fn clear_camera_keyframe_follow_target(
+        &mut self,
+        frame_index: u32,
+    ) -> Result<(), MdanceioError> {
+        let result = self
+            .project
+            .set_camera_keyframe_follow_target(frame_index, None);
+        self.report(result)
+    }
+
+    /// Whether the camera follows rotation of the target bone in addition to its position.
+    pub fn set_camera_follow_rotation(&mut self, value: bool) {
+        self.project.set_camera_follow_mode(if value {
+            CameraFollowMode::PositionAndRotation
+        } else {
+            CameraFollowMode::Position
+        });
+        self.project.reset_all_model_edges();
     }
 
     pub fn set_light_color(&mut self, value: Vector3<f32>) {
@@ -158,6 +853,91 @@ impl BaseApplicationService {
         self.project.global_light_mut().set_direction(value);
     }
 
+    pub fn local_light_count(&self) -> usize {
+        self.project.local_lights().len()
+    }
+
+    /// Returns the index of the new light, used by the other local light APIs.
+    pub fn add_point_light(
+        &mut self,
+        position: [f32; 3],
+        color: [f32; 3],
+        range: f32,
+    ) -> Result<usize, MdanceioError> {
+        let light = LocalLight::new_point(position.into(), color.into(), range);
+        let result = self.project.add_local_light(light);
+        self.report(result)
+    }
+
+    /// Spot lights cast shadows by default, angles are half angles of the cone in radians.
+    pub fn add_spot_light(
+        &mut self,
+        position: [f32; 3],
+        direction: [f32; 3],
+        color: [f32; 3],
+        range: f32,
+        inner_angle: f32,
+        outer_angle: f32,
+    ) -> Result<usize, MdanceioError> {
+        let mut light =
+            LocalLight::new_spot(position.into(), direction.into(), color.into(), range);
+        light.set_cone_angles(Rad(inner_angle), Rad(outer_angle));
+        let result = self.project.add_local_light(light);
+        self.report(result)
+    }
+
+    pub fn remove_local_light(&mut self, index: usize) -> Result<(), MdanceioError> {
+        let result = self.project.remove_local_light(index).map(|_| ());
+        self.report(result)
+    }
+
+    pub fn set_local_light_position(
+        &mut self,
+        index: usize,
+        value: [f32; 3],
+    ) -> Result<(), MdanceioError> {
+        self.update_local_light(index, |light| light.set_position(value.into()))
+    }
+
+    pub fn set_local_light_direction(
+        &mut self,
+        index: usize,
+        value: [f32; 3],
+    ) -> Result<(), MdanceioError> {
+        self.update_local_light(index, |light| light.set_direction(value.into()))
+    }
+
+    pub fn set_local_light_color(
+        &mut self,
+        index: usize,
+        value: [f32; 3],
+    ) -> Result<(), MdanceioError> {
+        self.update_local_light(index, |light| light.set_color(value.into()))
+    }
+
+    pub fn set_local_light_range(&mut self, index: usize, value: f32) -> Result<(), MdanceioError> {
+        self.update_local_light(index, |light| light.set_range(value))
+    }
+
+    pub fn set_local_light_cone_angles(
+        &mut self,
+        index: usize,
+        inner_angle: f32,
+        outer_angle: f32,
+    ) -> Result<(), MdanceioError> {
+        self.update_local_light(index, |light| {
+            light.set_cone_angles(Rad(inner_angle), Rad(outer_angle))
+        })
+    }
+
+    pub fn set_local_light_shadow_enabled(
+        &mut self,
+        index: usize,
+        value: bool,
+    ) -> Result<(), MdanceioError> {
+        self.update_local_light(index, |light| light.set_shadow_enabled(value))
+    }
+
     pub fn set_model_bone_orientation(
         &mut self,
         model_handle: Option<ModelHandle>,
@@ -208,6 +988,70 @@ impl BaseApplicationService {
         }
     }
 
+    pub fn set_model_show_all_bones(&mut self, model_handle: Option<ModelHandle>, value: bool) {
+        if let Some(handle) = self.resolve_model_handle(model_handle) {
+            self.project.set_model_show_all_bones(handle, value);
+            self.dispatch_events();
+        }
+    }
+
+    pub fn set_model_show_all_vertex_faces(
+        &mut self,
+        model_handle: Option<ModelHandle>,
+        value: bool,
+    ) {
+        if let Some(handle) = self.resolve_model_handle(model_handle) {
+            self.project.set_model_show_all_vertex_faces(handle, value);
+            self.dispatch_events();
+        }
+    }
+
+    pub fn set_model_show_all_vertex_points(
+        &mut self,
+        model_handle: Option<ModelHandle>,
+        value: bool,
+    ) {
+        if let Some(handle) = self.resolve_model_handle(model_handle) {
+            self.project.set_model_show_all_vertex_points(handle, value);
+            self.dispatch_events();
+        }
+    }
+
+    pub fn set_model_vertex_weight_bone(
+        &mut self,
+        model_handle: Option<ModelHandle>,
+        bone_name: Option<&str>,
+    ) {
+        if let Some(model) = match model_handle {
+            Some(handle) => self.project.model_mut(handle),
+            None => self.project.active_model_mut(),
+        } {
+            let bone = bone_name.and_then(|name| model.find_bone(name).map(|bone| bone.handle));
+            model.set_vertex_weight_bone(bone);
+            model.set_show_all_vertex_weights(bone_name.is_some());
+        }
+    }
+
+    pub fn select_model_vertices(
+        &mut self,
+        model_handle: Option<ModelHandle>,
+        vertex_indices: &[usize],
+    ) {
+        if let Some(model) = match model_handle {
+            Some(handle) => self.project.model_mut(handle),
+            None => self.project.active_model_mut(),
+        } {
+            model.vertices.clear_selection();
+            for idx in vertex_indices {
+                model.vertices.set_selected(*idx, true);
+            }
+        }
+    }
+
+    pub fn set_show_hidden_bones(&mut self, value: bool) {
+        self.project.bone_drawer_mut().show_hidden_bones = value;
+    }
+
     // pub fn register_all_selected_bone_keyframes(
     //     &mut self,
     //     model_handle: Option<ModelHandle>,
@@ -233,22 +1077,11 @@ impl BaseApplicationService {
         device: &wgpu::Device,
         queue: &wgpu::Queue,
     ) {
-        if let Some(format) =
-            image::ImageFormat::from_extension(key.split('.').rev().next().unwrap())
+        if let Err(err) = self
+            .project
+            .load_image_texture(key, data, update_bind, device, queue)
         {
-            let img = image::io::Reader::with_format(Cursor::new(data), format)
-                .decode()
-                .unwrap();
-            self.load_decoded_texture(
-                key,
-                &img.to_rgba8(),
-                img.dimensions(),
-                update_bind,
-                device,
-                queue,
-            );
-        } else {
-            log::warn!("Texture File {} Not supported", key);
+            log::warn!("{}", err);
         }
     }
 
@@ -44,6 +44,11 @@ impl BezierCurve {
         }
     }
 
+    /// Points on the curve at `interval + 1` evenly spaced parameters, sorted on the time axis.
+    pub fn points(&self) -> &[Vector2<f32>] {
+        &self.points
+    }
+
     pub fn split(&self, t: f32) -> (Self, Self) {
         let t = t.clamp(0f32, 1f32);
         let points = vec![Self::P0, self.c0, self.c1, Self::P1];
@@ -70,7 +75,130 @@ impl BezierCurve {
             z: self.c1.x,
             w: self.c1.y,
         }
-        .map(|v| (v * 127f32) as u8)
+        .map(|v| (v.clamp(0f32, 1f32) * 127f32).round() as u8)
+    }
+
+    /// Part of the curve between `from` and `to` on the time axis, renormalized to the unit square.
+    pub fn sub_curve(&self, from: f32, to: f32, interval: u32) -> Self {
+        let t0 = self.parameter_at(from.clamp(0f32, 1f32));
+        let t1 = self.parameter_at(to.clamp(0f32, 1f32));
+        if t1 - t0 <= f32::EPSILON {
+            return Self::new(self.c0, self.c1, interval);
+        }
+        let points = vec![Self::P0, self.c0, self.c1, Self::P1];
+        let mut left = vec![];
+        let mut right = vec![];
+        Self::split_bezier_curve(&points, t1, &mut left, &mut right);
+        let (mut head, mut tail) = (vec![], vec![]);
+        Self::split_bezier_curve(&left, t0 / t1, &mut head, &mut tail);
+        // right half of a split is collected from the end point
+        tail.reverse();
+        let origin = tail[0];
+        let extent = tail[3] - origin;
+        let normalize = |point: Vector2<f32>, linear: Vector2<f32>| {
+            Vector2::new(
+                if extent.x.abs() > f32::EPSILON {
+                    (point.x - origin.x) / extent.x
+                } else {
+                    linear.x
+                },
+                if extent.y.abs() > f32::EPSILON {
+                    (point.y - origin.y) / extent.y
+                } else {
+                    linear.y
+                },
+            )
+            .map(|v| v.clamp(0f32, 1f32))
+        };
+        Self::new(
+            normalize(tail[1], Vector2::new(1f32 / 3f32, 1f32 / 3f32)),
+            normalize(tail[2], Vector2::new(2f32 / 3f32, 2f32 / 3f32)),
+            interval,
+        )
+    }
+
+    /// Parameters of a curve within `tolerance` of `samples` in the unit square on the value axis,
+    /// `None` if no curve VMD can store follows them.
+    pub fn fit_parameters(samples: &[Vector2<f32>], tolerance: f32) -> Option<Vector4<u8>> {
+        // control points on the time axis are searched, those on the value axis are least squares
+        let fit = |x0: u8, x1: u8| {
+            let (c0x, c1x) = (x0 as f32 / 127f32, x1 as f32 / 127f32);
+            let bases = samples
+                .iter()
+                .map(|sample| {
+                    let t = Self::solve_parameter(c0x, c1x, sample.x);
+                    let it = 1f32 - t;
+                    (
+                        t * it.powi(2) * 3f32,
+                        t.powi(2) * it * 3f32,
+                        sample.y - t.powi(3),
+                    )
+                })
+                .collect::<Vec<_>>();
+            let (mut a00, mut a01, mut a11, mut b0, mut b1) = (0f32, 0f32, 0f32, 0f32, 0f32);
+            for &(u, v, r) in &bases {
+                a00 += u * u;
+                a01 += u * v;
+                a11 += v * v;
+                b0 += u * r;
+                b1 += v * r;
+            }
+            let det = a00 * a11 - a01 * a01;
+            if det.abs() <= f32::EPSILON {
+                return (f32::INFINITY, Vector4::new(x0, 0, x1, 0));
+            }
+            let quantize = |v: f32| (v.clamp(0f32, 1f32) * 127f32).round() as u8;
+            let y0 = quantize((b0 * a11 - b1 * a01) / det);
+            let y1 = quantize((b1 * a00 - b0 * a01) / det);
+            let (c0y, c1y) = (y0 as f32 / 127f32, y1 as f32 / 127f32);
+            let error = bases
+                .iter()
+                .map(|&(u, v, r)| (u * c0y + v * c1y - r).abs())
+                .fold(0f32, f32::max);
+            (error, Vector4::new(x0, y0, x1, y1))
+        };
+        let mut best = (f32::INFINITY, Vector4::new(0, 0, 0, 0));
+        for x0 in (0..=128).step_by(16) {
+            for x1 in (0..=128).step_by(16) {
+                let candidate = fit(x0.min(127) as u8, x1.min(127) as u8);
+                if candidate.0 < best.0 {
+                    best = candidate;
+                }
+            }
+        }
+        for step in [8i32, 4, 2, 1] {
+            let (x0, x1) = (best.1.x as i32, best.1.z as i32);
+            for d0 in [-step, 0, step] {
+                for d1 in [-step, 0, step] {
+                    let candidate =
+                        fit((x0 + d0).clamp(0, 127) as u8, (x1 + d1).clamp(0, 127) as u8);
+                    if candidate.0 < best.0 {
+                        best = candidate;
+                    }
+                }
+            }
+        }
+        (best.0 <= tolerance).then_some(best.1)
+    }
+
+    /// Finds curve parameter whose point is at `x` on the time axis by bisection.
+    fn parameter_at(&self, x: f32) -> f32 {
+        Self::solve_parameter(self.c0.x, self.c1.x, x)
+    }
+
+    fn solve_parameter(c0x: f32, c1x: f32, x: f32) -> f32 {
+        let (mut low, mut high) = (0f32, 1f32);
+        for _ in 0..32 {
+            let t = (low + high) * 0.5f32;
+            let it = 1f32 - t;
+            let value = c0x * t * it.powi(2) * 3f32 + c1x * t.powi(2) * it * 3f32 + t.powi(3);
+            if value < x {
+                low = t;
+            } else {
+                high = t;
+            }
+        }
+        (low + high) * 0.5f32
     }
 
     fn split_bezier_curve(
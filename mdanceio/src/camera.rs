@@ -1,6 +1,6 @@
 use cgmath::{
-    AbsDiffEq, Deg, ElementWise, InnerSpace, Matrix3, Matrix4, MetricSpace, Quaternion, Rad,
-    Rotation3, SquareMatrix, Vector2, Vector3, Vector4, Zero,
+    AbsDiffEq, Deg, ElementWise, InnerSpace, Matrix3, Matrix4, MetricSpace, One, Quaternion, Rad,
+    Rotation, Rotation3, SquareMatrix, Vector2, Vector3, Vector4, Zero,
 };
 
 use crate::{
@@ -24,10 +24,38 @@ pub enum FollowingType {
     Bone,
 }
 
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum CameraFollowMode {
+    /// Look at moves with the bound bone keeping the view orientation
+    Position,
+    /// Look at and view orientation move with the bound bone
+    PositionAndRotation,
+}
+
+impl CameraFollowMode {
+    pub const ALL: [Self; 2] = [Self::Position, Self::PositionAndRotation];
+
+    pub fn from_index(value: u32) -> Option<Self> {
+        Self::ALL.get(value as usize).copied()
+    }
+}
+
 fn bone_pos(bone: &Bone) -> Vector3<f32> {
     (bone.matrices.skinning_transform * f128_to_vec3(bone.origin.origin).extend(1f32)).truncate()
 }
 
+/// Bone the camera follows when following the whole model.
+fn model_follow_bone(model: &Model) -> Option<&Bone> {
+    model
+        .find_bone(Bone::NAME_CENTER_OF_VIEWPOINT_IN_JAPANESE)
+        .or_else(|| {
+            model
+                .find_bone(Bone::NAME_CENTER_OFFSET_IN_JAPANESE)
+                .and_then(|bone| model.parent_bone(bone))
+        })
+        .or_else(|| model.bones().get(0))
+}
+
 pub trait Camera {
     fn get_view_transform(&self) -> (Matrix4<f32>, Matrix4<f32>);
 
@@ -47,7 +75,9 @@ struct CurveCacheKey {
 #[derive(Debug, Clone)]
 pub struct PerspectiveCamera {
     // TODO: undo_stack_t *m_undoStack;
+    /// Model and bone names the look at is relative to, the bone name may be empty to follow the model
     pub outside_parent: (String, String),
+    pub follow_mode: CameraFollowMode,
     pub transform_coordinate_type: TransformCoordinateType,
     pub view_matrix: Matrix4<f32>,
     pub projection_matrix: Matrix4<f32>,
@@ -68,6 +98,7 @@ impl Default for PerspectiveCamera {
     fn default() -> Self {
         Self {
             outside_parent: (String::default(), String::default()),
+            follow_mode: CameraFollowMode::Position,
             transform_coordinate_type: TransformCoordinateType::Local,
             view_matrix: Matrix4::identity(),
             projection_matrix: Matrix4::identity(),
@@ -94,6 +125,7 @@ impl PerspectiveCamera {
     pub const MAX_FOV: i32 = 135;
     pub const MIN_FOV: i32 = 1;
     pub const INITIAL_FOV: i32 = 30;
+    pub const MIN_ORTHOGRAPHIC_EXTENT: f32 = 0.01;
     pub const DEFAULT_BEZIER_CONTROL_POINT: Vector4<u8> = Vector4::new(20, 20, 107, 107);
     pub const DEFAULT_AUTOMATIC_BEZIER_CONTROL_POINT: Vector4<u8> = Vector4::new(64, 0, 64, 127);
 
@@ -110,12 +142,17 @@ impl PerspectiveCamera {
         self.set_dirty(true);
     }
 
-    pub fn update(&mut self, viewport_image_size: Vector2<u32>, bound_look_at: Vector3<f32>) {
+    pub fn update(
+        &mut self,
+        viewport_image_size: Vector2<u32>,
+        bound_look_at: Vector3<f32>,
+        bound_orientation: Quaternion<f32>,
+    ) {
         let angle = self.angle.mul_element_wise(Self::ANGLE_SCALE_FACTOR);
         let x = Quaternion::from_angle_x(Rad(angle.x));
         let y = Quaternion::from_angle_y(Rad(angle.y));
         let z = Quaternion::from_angle_z(Rad(angle.z));
-        let view_orientation = Matrix3::from(z * x * y);
+        let view_orientation = Matrix3::from(z * x * y * bound_orientation.invert());
         self.view_matrix =
             Matrix4::from(view_orientation) * Matrix4::from_translation(-bound_look_at);
         self.view_matrix[3] += Vector4::new(0.0f32, 0.0f32, self.distance, 0.0f32);
@@ -138,16 +175,36 @@ impl PerspectiveCamera {
                 0.5,
             );
         } else {
-            let inverse_distance = 1.0 / self.distance;
-            let mut projection_matrix: Matrix4<f32> = Matrix4::identity();
-            projection_matrix[0][0] = 2.0f32
-                * (viewport_image_size.y / viewport_image_size.x).max(1.0)
-                * inverse_distance;
-            projection_matrix[1][1] = 2.0f32
-                * (viewport_image_size.x / viewport_image_size.y).max(1.0)
-                * inverse_distance;
-            projection_matrix[2][2] = 2.0f32 / (self.zfar() - 0.5f32);
-            self.projection_matrix = projection_matrix;
+            self.projection_matrix = self.orthographic_projection(viewport_image_size);
+        }
+    }
+
+    /// Orthographic projection whose longer side spans the distance, so zooming works by distance.
+    ///
+    /// Depth maps view z from 0 to zfar onto 0 to 1, so anything behind the eye is clipped.
+    fn orthographic_projection(&self, viewport_image_size: Vector2<f32>) -> Matrix4<f32> {
+        let inverse_distance = 1.0 / self.orthographic_extent();
+        let zfar = self.zfar();
+        let mut projection_matrix: Matrix4<f32> = Matrix4::identity();
+        projection_matrix[0][0] =
+            2.0f32 * (viewport_image_size.y / viewport_image_size.x).max(1.0) * inverse_distance;
+        projection_matrix[1][1] =
+            2.0f32 * (viewport_image_size.x / viewport_image_size.y).max(1.0) * inverse_distance;
+        projection_matrix[2][2] = 1.0f32 / zfar;
+        projection_matrix
+    }
+
+    /// Length of the longer side of the orthographic view volume.
+    pub fn orthographic_extent(&self) -> f32 {
+        self.distance.abs().max(Self::MIN_ORTHOGRAPHIC_EXTENT)
+    }
+
+    /// Center of the visible region used to fit shadow maps.
+    pub fn focus_point(&self) -> Vector3<f32> {
+        if self.perspective {
+            self.position
+        } else {
+            self.position + self.direction * self.distance.abs()
         }
     }
 
@@ -240,22 +297,8 @@ impl PerspectiveCamera {
     pub fn look_at(&self, active_model: Option<&Model>) -> Vector3<f32> {
         match self.following_type {
             FollowingType::None => self.look_at,
-            FollowingType::Model => match active_model {
-                Some(model) => {
-                    if let Some(bone) = model.find_bone(Bone::NAME_CENTER_OF_VIEWPOINT_IN_JAPANESE)
-                    {
-                        self.look_at + bone_pos(bone)
-                    } else if let Some(bone) = model
-                        .find_bone(Bone::NAME_CENTER_OFFSET_IN_JAPANESE)
-                        .and_then(|bone| model.parent_bone(bone))
-                    {
-                        self.look_at + bone_pos(bone)
-                    } else if let Some(bone) = model.bones().get(0) {
-                        self.look_at + bone_pos(bone)
-                    } else {
-                        self.look_at
-                    }
-                }
+            FollowingType::Model => match active_model.and_then(model_follow_bone) {
+                Some(bone) => self.look_at + bone_pos(bone),
                 None => self.look_at,
             },
             FollowingType::Bone => match active_model.and_then(|model| model.active_bone()) {
@@ -265,11 +308,35 @@ impl PerspectiveCamera {
         }
     }
 
-    pub fn bound_look_at(&self, project: &Project) -> Vector3<f32> {
-        (match project.resolve_bone((&self.outside_parent.0, &self.outside_parent.1)) {
-            Some(bone) => bone.world_translation(),
-            None => Vector3::zero(),
-        }) + self.look_at(project.active_model())
+    fn resolve_outside_parent<'a>(&self, project: &'a Project) -> Option<&'a Bone> {
+        let (model_name, bone_name) = &self.outside_parent;
+        if bone_name.is_empty() {
+            project
+                .find_model_by_name(model_name)
+                .and_then(model_follow_bone)
+        } else {
+            project.resolve_bone((model_name, bone_name))
+        }
+    }
+
+    /// Look at and orientation in world space applying the outside parent by the follow mode.
+    pub fn bound_transform(&self, project: &Project) -> (Vector3<f32>, Quaternion<f32>) {
+        let look_at = self.look_at(project.active_model());
+        match self.resolve_outside_parent(project) {
+            Some(bone) => match self.follow_mode {
+                CameraFollowMode::Position => {
+                    (bone.world_translation() + look_at, Quaternion::one())
+                }
+                CameraFollowMode::PositionAndRotation => {
+                    let orientation = bone.world_orientation();
+                    (
+                        bone.world_translation() + orientation.rotate_vector(look_at),
+                        orientation,
+                    )
+                }
+            },
+            None => (look_at, Quaternion::one()),
+        }
     }
 
     pub fn set_dirty(&mut self, value: bool) {
@@ -356,9 +423,17 @@ impl PerspectiveCamera {
         value: FollowingType,
         viewport_image_size: Vector2<u32>,
         bound_look_at: Vector3<f32>,
+        bound_orientation: Quaternion<f32>,
     ) {
         self.following_type = value;
-        self.update(viewport_image_size, bound_look_at);
+        self.update(viewport_image_size, bound_look_at, bound_orientation);
+    }
+
+    pub fn set_follow_mode(&mut self, value: CameraFollowMode) {
+        if value != self.follow_mode {
+            self.follow_mode = value;
+            self.dirty = true;
+        }
     }
 
     pub fn zfar(&self) -> f32 {
@@ -396,3 +471,56 @@ impl Camera for PerspectiveCamera {
         self.locked
     }
 }
+
+#[test]
+fn test_camera_follow_rotation() {
+    let mut camera = PerspectiveCamera::new();
+    let viewport_image_size = Vector2::new(640, 480);
+    let look_at = Vector3::new(0f32, 10f32, 0f32);
+    camera.update(viewport_image_size, look_at, Quaternion::one());
+    let (position, direction) = (camera.position, camera.direction);
+    let orientation = Quaternion::from_angle_y(Deg(90f32));
+    camera.update(viewport_image_size, look_at, orientation);
+    let expected = look_at + orientation.rotate_vector(position - look_at);
+    assert!(camera.position.distance(expected) < 1e-3f32);
+    assert!(
+        camera
+            .direction
+            .distance(orientation.rotate_vector(direction))
+            < 1e-5f32
+    );
+    assert_eq!(
+        Some(CameraFollowMode::PositionAndRotation),
+        CameraFollowMode::from_index(1)
+    );
+    assert_eq!(None, CameraFollowMode::from_index(2));
+}
+
+#[test]
+fn test_orthographic_camera_ray() {
+    let mut camera = PerspectiveCamera::new();
+    camera.set_perspective(false);
+    let viewport_size = Vector2::new(640, 480);
+    camera.update(
+        viewport_size,
+        PerspectiveCamera::INITIAL_LOOK_AT,
+        Quaternion::one(),
+    );
+    for position in [Vector2::new(320, 240), Vector2::new(0, 0)] {
+        let ray = camera.create_ray(position, viewport_size);
+        assert!(ray.direction.distance(camera.direction) < 1e-4f32);
+    }
+    let left = camera
+        .cast_ray(Vector2::new(0, 240), viewport_size)
+        .unwrap();
+    let right = camera
+        .cast_ray(Vector2::new(640, 240), viewport_size)
+        .unwrap();
+    assert!((left.distance(right) - camera.distance()).abs() < 1e-2f32);
+    assert!(
+        camera
+            .focus_point()
+            .distance(PerspectiveCamera::INITIAL_LOOK_AT)
+            < 1e-3f32
+    );
+}
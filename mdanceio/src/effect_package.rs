@@ -0,0 +1,670 @@
+use std::collections::HashMap;
+
+use nanoem::motion::MotionEffectParameterValue;
+
+use crate::{
+    error::MdanceioError,
+    graphics::effect::RendererLayout,
+    project_file::{resolve_relative_path, AssetLoader},
+};
+
+pub use crate::graphics::effect::{fx::FxEffect, technique::TechniqueType};
+
+/// Culling applied to every material drawn with the effect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EffectCullMode {
+    Material,
+    Back,
+    Front,
+    None,
+}
+
+/// Color blending of the effect, `Model` follows the add blend flag of the model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EffectBlendMode {
+    Model,
+    Alpha,
+    Add,
+    Opaque,
+}
+
+/// Value bound at `effect_parameters.values[index]` in declaration order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EffectParameter {
+    pub name: String,
+    pub value: [f32; 4],
+}
+
+/// Image bound at `@group(3) @binding(2 + index)` in declaration order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EffectTexture {
+    pub name: String,
+    pub source: String,
+}
+
+/// Description of a user effect, shaders and textures are referenced by asset path.
+///
+/// Shaders use the bind groups of the built-in model shaders and may declare
+/// `@group(3) @binding(0) var<uniform> effect_parameters: EffectParameters;` holding
+/// `values: array<vec4<f32>, 16>`, a filtering sampler at binding 1 and textures from binding 2.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EffectManifest {
+    pub name: String,
+    pub depth_enabled: bool,
+    pub cull_mode: EffectCullMode,
+    pub blend_mode: EffectBlendMode,
+    pub techniques: Vec<(TechniqueType, String)>,
+    pub parameters: Vec<EffectParameter>,
+    pub textures: Vec<EffectTexture>,
+}
+
+impl Default for EffectManifest {
+    fn default() -> Self {
+        Self {
+            name: String::default(),
+            depth_enabled: true,
+            cull_mode: EffectCullMode::Material,
+            blend_mode: EffectBlendMode::Model,
+            techniques: vec![],
+            parameters: vec![],
+            textures: vec![],
+        }
+    }
+}
+
+impl EffectManifest {
+    pub const CURRENT_VERSION: u32 = 1;
+    pub const SIGNATURE: &'static str = "mdanceio-effect";
+    pub const MAXIMUM_PARAMETERS: usize = 16;
+    pub const MAXIMUM_TEXTURES: usize = 4;
+    pub const EFFECT_BIND_GROUP: u32 = 3;
+
+    pub fn parse(text: &str) -> Result<Self, MdanceioError> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(idx, line)| (idx + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+        match lines.next().and_then(|(_, line)| line.split_once(' ')) {
+            Some((signature, version)) if signature == Self::SIGNATURE => {
+                let version = version
+                    .trim()
+                    .parse::<u32>()
+                    .map_err(|_| MdanceioError::invalid_effect_package(1, "malformed version"))?;
+                if version > Self::CURRENT_VERSION {
+                    return Err(MdanceioError::invalid_effect_package(
+                        1,
+                        &format!("unsupported version {}", version),
+                    ));
+                }
+            }
+            _ => {
+                return Err(MdanceioError::invalid_effect_package(
+                    1,
+                    "missing signature",
+                ))
+            }
+        }
+        let mut manifest = EffectManifest::default();
+        let mut section = Section::Effect;
+        for (line_number, line) in lines {
+            match line {
+                "[effect]" => {
+                    section = Section::Effect;
+                    continue;
+                }
+                "[technique]" => {
+                    section = Section::Technique;
+                    manifest
+                        .techniques
+                        .push((TechniqueType::Object, String::default()));
+                    continue;
+                }
+                "[parameter]" => {
+                    section = Section::Parameter;
+                    manifest.parameters.push(EffectParameter {
+                        name: String::default(),
+                        value: [0f32; 4],
+                    });
+                    continue;
+                }
+                "[texture]" => {
+                    section = Section::Texture;
+                    manifest.textures.push(EffectTexture {
+                        name: String::default(),
+                        source: String::default(),
+                    });
+                    continue;
+                }
+                _ => {}
+            }
+            let (key, value) = line
+                .split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| {
+                    MdanceioError::invalid_effect_package(line_number, "expected key = value")
+                })?;
+            let invalid = || MdanceioError::invalid_effect_package(line_number, key);
+            match section {
+                Section::Effect => match key {
+                    "name" => manifest.name = value.to_owned(),
+                    "depth" => manifest.depth_enabled = value.parse().map_err(|_| invalid())?,
+                    "cull" => manifest.cull_mode = parse_cull_mode(value).ok_or_else(invalid)?,
+                    "blend" => manifest.blend_mode = parse_blend_mode(value).ok_or_else(invalid)?,
+                    _ => log::warn!("Unknown effect key in effect package: {}", key),
+                },
+                Section::Technique => {
+                    let technique = manifest.techniques.last_mut().unwrap();
+                    match key {
+                        "type" => technique.0 = parse_technique_type(value).ok_or_else(invalid)?,
+                        "source" => technique.1 = value.to_owned(),
+                        _ => log::warn!("Unknown technique key in effect package: {}", key),
+                    }
+                }
+                Section::Parameter => {
+                    let parameter = manifest.parameters.last_mut().unwrap();
+                    match key {
+                        "name" => parameter.name = value.to_owned(),
+                        "value" => parameter.value = parse_vector(value).ok_or_else(invalid)?,
+                        _ => log::warn!("Unknown parameter key in effect package: {}", key),
+                    }
+                }
+                Section::Texture => {
+                    let texture = manifest.textures.last_mut().unwrap();
+                    match key {
+                        "name" => texture.name = value.to_owned(),
+                        "source" => texture.source = value.to_owned(),
+                        _ => log::warn!("Unknown texture key in effect package: {}", key),
+                    }
+                }
+            }
+        }
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    /// Parameter values padded to the size of the uniform buffer, parameters animated by a
+    /// motion take the value of `motion_values` with the same name.
+    pub(crate) fn parameter_values(
+        &self,
+        motion_values: &HashMap<String, MotionEffectParameterValue>,
+    ) -> [[f32; 4]; Self::MAXIMUM_PARAMETERS] {
+        let mut values = [[0f32; 4]; Self::MAXIMUM_PARAMETERS];
+        for (value, parameter) in values.iter_mut().zip(&self.parameters) {
+            *value = match motion_values.get(&parameter.name) {
+                Some(MotionEffectParameterValue::BOOL(v)) => [*v as u8 as f32, 0f32, 0f32, 0f32],
+                Some(MotionEffectParameterValue::INT(v)) => [*v as f32, 0f32, 0f32, 0f32],
+                Some(MotionEffectParameterValue::FLOAT(v)) => [*v, 0f32, 0f32, 0f32],
+                Some(MotionEffectParameterValue::VECTOR4(v)) => *v,
+                None => parameter.value,
+            };
+        }
+        values
+    }
+
+    fn validate(&self) -> Result<(), MdanceioError> {
+        let invalid = |reason: &str| MdanceioError::invalid_effect_package(0, reason);
+        if self.name.is_empty() {
+            return Err(invalid("effect has no name"));
+        }
+        if !self
+            .techniques
+            .iter()
+            .any(|(typ, _)| *typ == TechniqueType::Object)
+        {
+            return Err(invalid("object technique is required"));
+        }
+        for (idx, (typ, source)) in self.techniques.iter().enumerate() {
+            if source.is_empty() {
+                return Err(invalid(&format!("technique {} has no source", idx)));
+            }
+            if self.techniques[..idx].iter().any(|(other, _)| other == typ) {
+                return Err(invalid(&format!("technique {:?} is declared twice", typ)));
+            }
+        }
+        if self.parameters.len() > Self::MAXIMUM_PARAMETERS {
+            return Err(invalid(&format!(
+                "at most {} parameters are supported",
+                Self::MAXIMUM_PARAMETERS
+            )));
+        }
+        if self.textures.len() > Self::MAXIMUM_TEXTURES {
+            return Err(invalid(&format!(
+                "at most {} textures are supported",
+                Self::MAXIMUM_TEXTURES
+            )));
+        }
+        if let Some(idx) = self.textures.iter().position(|t| t.source.is_empty()) {
+            return Err(invalid(&format!("texture {} has no source", idx)));
+        }
+        Ok(())
+    }
+}
+
+/// Effect manifest with its shaders and textures loaded and validated.
+#[derive(Debug, Clone)]
+pub struct EffectPackage {
+    pub manifest: EffectManifest,
+    shaders: HashMap<TechniqueType, String>,
+    textures: Vec<image::RgbaImage>,
+}
+
+impl EffectPackage {
+    pub fn load(
+        manifest: EffectManifest,
+        loader: &mut dyn AssetLoader,
+    ) -> Result<Self, MdanceioError> {
+        let mut shaders = HashMap::new();
+        for (typ, source) in &manifest.techniques {
+            let data = loader.load(source)?;
+            let text = String::from_utf8(data)
+                .map_err(|_| MdanceioError::asset_not_loaded(source, "shader is not UTF-8"))?;
+            shaders.insert(*typ, text);
+        }
+        let textures = load_textures(&manifest.textures, loader)?;
+        Self::new(manifest, shaders, textures)
+    }
+
+    /// Loads the MME `.fx` effect at `path` with its shaders translated into WGSL.
+    ///
+    /// The effect is named after the file, textures and `#include`s are relative to it.
+    pub fn load_fx(path: &str, loader: &mut dyn AssetLoader) -> Result<Self, MdanceioError> {
+        let data = loader.load(path)?;
+        let effect = FxEffect::parse(&String::from_utf8_lossy(&data), path, loader)?;
+        let translation = effect.translate()?;
+        let file_name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+        let name = file_name
+            .rsplit_once('.')
+            .map_or(file_name, |(stem, _)| stem)
+            .to_owned();
+        let manifest = EffectManifest {
+            name,
+            depth_enabled: translation.depth_enabled,
+            cull_mode: translation.cull_mode,
+            blend_mode: translation.blend_mode,
+            techniques: [
+                TechniqueType::Object,
+                TechniqueType::ObjectSs,
+                TechniqueType::Edge,
+                TechniqueType::Shadow,
+                TechniqueType::Zplot,
+            ]
+            .into_iter()
+            .filter(|typ| translation.shaders.contains_key(typ))
+            .map(|typ| (typ, path.to_owned()))
+            .collect(),
+            parameters: translation.parameters,
+            textures: translation
+                .textures
+                .into_iter()
+                .map(|texture| EffectTexture {
+                    source: resolve_relative_path(path, &texture.source),
+                    ..texture
+                })
+                .collect(),
+        };
+        manifest.validate()?;
+        let textures = load_textures(&manifest.textures, loader)?;
+        Self::new(manifest, translation.shaders, textures)
+    }
+
+    pub(crate) fn new(
+        manifest: EffectManifest,
+        shaders: HashMap<TechniqueType, String>,
+        textures: Vec<image::RgbaImage>,
+    ) -> Result<Self, MdanceioError> {
+        for (typ, source) in &shaders {
+            validate_shader(*typ, source, manifest.textures.len())?;
+        }
+        Ok(Self {
+            manifest,
+            shaders,
+            textures,
+        })
+    }
+
+    pub(crate) fn shaders(&self) -> &HashMap<TechniqueType, String> {
+        &self.shaders
+    }
+
+    pub(crate) fn textures(&self) -> &[image::RgbaImage] {
+        &self.textures
+    }
+}
+
+fn load_textures(
+    textures: &[EffectTexture],
+    loader: &mut dyn AssetLoader,
+) -> Result<Vec<image::RgbaImage>, MdanceioError> {
+    textures
+        .iter()
+        .map(|texture| {
+            let data = loader.load(&texture.source)?;
+            let img = image::load_from_memory(&data).map_err(|err| {
+                MdanceioError::asset_not_loaded(&texture.source, &err.to_string())
+            })?;
+            Ok(img.to_rgba8())
+        })
+        .collect()
+}
+
+/// Parses and validates WGSL before wgpu sees it, as wgpu reports invalid shaders and shaders
+/// mismatching `RendererLayout` by panicking.
+fn validate_shader(
+    typ: TechniqueType,
+    source: &str,
+    texture_count: usize,
+) -> Result<(), MdanceioError> {
+    let invalid =
+        |reason: &str| MdanceioError::invalid_effect_shader(&format!("{:?}", typ), reason);
+    let module =
+        naga::front::wgsl::parse_str(source).map_err(|err| invalid(&err.emit_to_string(source)))?;
+    naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::empty(),
+    )
+    .validate(&module)
+    .map_err(|err| invalid(&err.into_inner().to_string()))?;
+    let find_entry_point = |stage: naga::ShaderStage, name: &str| {
+        module
+            .entry_points
+            .iter()
+            .find(|entry| entry.stage == stage && entry.name == name)
+            .ok_or_else(|| invalid(&format!("missing entry point {}", name)))
+    };
+    let vertex = find_entry_point(naga::ShaderStage::Vertex, "vs_main")?;
+    find_entry_point(naga::ShaderStage::Fragment, "fs_main")?;
+    for (_, variable) in module.global_variables.iter() {
+        if let Some(binding) = &variable.binding {
+            let entry = RendererLayout::bind_group_layout_entries(binding.group, texture_count)
+                .into_iter()
+                .find(|entry| entry.binding == binding.binding)
+                .ok_or_else(|| {
+                    invalid(&format!(
+                        "binding {} of group {} is not provided",
+                        binding.binding, binding.group
+                    ))
+                })?;
+            if !is_binding_compatible(&module, variable, &entry.ty) {
+                return Err(invalid(&format!(
+                    "binding {} of group {} does not match {:?}",
+                    binding.binding, binding.group, entry.ty
+                )));
+            }
+        }
+    }
+    for argument in &vertex.function.arguments {
+        let inputs = match (&argument.binding, &module.types[argument.ty].inner) {
+            (Some(binding), _) => vec![(binding, argument.ty)],
+            (None, naga::TypeInner::Struct { members, .. }) => members
+                .iter()
+                .filter_map(|member| member.binding.as_ref().map(|binding| (binding, member.ty)))
+                .collect(),
+            _ => vec![],
+        };
+        for (binding, ty) in inputs {
+            if let naga::Binding::Location { location, .. } = binding {
+                // every attribute of the vertex buffer is a float vector
+                let is_float = matches!(
+                    module.types[ty].inner,
+                    naga::TypeInner::Scalar {
+                        kind: naga::ScalarKind::Float,
+                        ..
+                    } | naga::TypeInner::Vector {
+                        kind: naga::ScalarKind::Float,
+                        ..
+                    }
+                );
+                if !is_float
+                    || !RendererLayout::VERTEX_ATTRIBUTES
+                        .iter()
+                        .any(|attribute| attribute.shader_location == *location)
+                {
+                    return Err(invalid(&format!(
+                        "vertex input at location {} is not provided",
+                        location
+                    )));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Whether a global declared by the shader can be bound to `binding_type` of `RendererLayout`.
+fn is_binding_compatible(
+    module: &naga::Module,
+    variable: &naga::GlobalVariable,
+    binding_type: &wgpu::BindingType,
+) -> bool {
+    let inner = &module.types[variable.ty].inner;
+    match (binding_type, inner) {
+        (
+            wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                min_binding_size,
+                ..
+            },
+            _,
+        ) => {
+            // shaders may declare a prefix of the uniform struct
+            let size = inner.size(module.to_ctx()) as u64;
+            variable.space == naga::AddressSpace::Uniform
+                && min_binding_size.is_none_or(|min_size| size <= min_size.get())
+        }
+        (wgpu::BindingType::Sampler(sampler), naga::TypeInner::Sampler { comparison }) => {
+            *comparison == (*sampler == wgpu::SamplerBindingType::Comparison)
+        }
+        (
+            wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { .. },
+                view_dimension,
+                multisampled,
+            },
+            naga::TypeInner::Image {
+                dim: naga::ImageDimension::D2,
+                arrayed,
+                class:
+                    naga::ImageClass::Sampled {
+                        kind: naga::ScalarKind::Float,
+                        multi,
+                    },
+            },
+        ) => {
+            multi == multisampled
+                && match view_dimension {
+                    wgpu::TextureViewDimension::D2 => !arrayed,
+                    wgpu::TextureViewDimension::D2Array => *arrayed,
+                    _ => false,
+                }
+        }
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Section {
+    Effect,
+    Technique,
+    Parameter,
+    Texture,
+}
+
+pub(crate) fn parse_technique_type(value: &str) -> Option<TechniqueType> {
+    match value {
+        "object" => Some(TechniqueType::Object),
+        "object_ss" => Some(TechniqueType::ObjectSs),
+        "edge" => Some(TechniqueType::Edge),
+        "shadow" => Some(TechniqueType::Shadow),
+        "zplot" => Some(TechniqueType::Zplot),
+        _ => None,
+    }
+}
+
+fn parse_cull_mode(value: &str) -> Option<EffectCullMode> {
+    match value {
+        "material" => Some(EffectCullMode::Material),
+        "back" => Some(EffectCullMode::Back),
+        "front" => Some(EffectCullMode::Front),
+        "none" => Some(EffectCullMode::None),
+        _ => None,
+    }
+}
+
+fn parse_blend_mode(value: &str) -> Option<EffectBlendMode> {
+    match value {
+        "model" => Some(EffectBlendMode::Model),
+        "alpha" => Some(EffectBlendMode::Alpha),
+        "add" => Some(EffectBlendMode::Add),
+        "opaque" => Some(EffectBlendMode::Opaque),
+        _ => None,
+    }
+}
+
+fn parse_vector(value: &str) -> Option<[f32; 4]> {
+    let values = value
+        .split_whitespace()
+        .map(|v| v.parse::<f32>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    let mut result = [0f32, 0f32, 0f32, 1f32];
+    if values.is_empty() || values.len() > 4 {
+        return None;
+    }
+    result[..values.len()].copy_from_slice(&values);
+    Some(result)
+}
+
+#[cfg(test)]
+const TEST_SHADER: &str = "
+struct EffectParameters {
+    values: array<vec4<f32>, 16>,
+}
+@group(3) @binding(0)
+var<uniform> effect_parameters: EffectParameters;
+
+@vertex
+fn vs_main(@location(0) position: vec3<f32>) -> @builtin(position) vec4<f32> {
+    return vec4<f32>(position, 1.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return effect_parameters.values[0];
+}
+";
+
+#[test]
+fn test_effect_manifest_parse() {
+    let text = "mdanceio-effect 1
+[effect]
+name = rim
+cull = none
+blend = add
+[technique]
+type = object
+source = shaders/rim.wgsl
+[parameter]
+name = rim_color
+value = 1 0.5 0
+";
+    let manifest = EffectManifest::parse(text).unwrap();
+    assert_eq!(manifest.name, "rim");
+    assert_eq!(manifest.cull_mode, EffectCullMode::None);
+    assert_eq!(manifest.blend_mode, EffectBlendMode::Add);
+    assert_eq!(
+        manifest.techniques,
+        vec![(TechniqueType::Object, "shaders/rim.wgsl".to_owned())]
+    );
+    assert_eq!(manifest.parameters[0].value, [1f32, 0.5f32, 0f32, 1f32]);
+    assert!(EffectManifest::parse("mdanceio-effect 1\n[effect]\nname = empty\n").is_err());
+    assert!(EffectManifest::parse("mdanceio-effect 1\n[technique]\ntype = bloom\n").is_err());
+}
+
+#[test]
+fn test_effect_package_validates_shaders() {
+    let manifest = EffectManifest {
+        name: "test".to_owned(),
+        techniques: vec![(TechniqueType::Object, "object.wgsl".to_owned())],
+        ..Default::default()
+    };
+    let shaders = HashMap::from([(TechniqueType::Object, TEST_SHADER.to_owned())]);
+    assert!(EffectPackage::new(manifest.clone(), shaders, vec![]).is_ok());
+    let shaders = HashMap::from([(TechniqueType::Object, "fn broken(".to_owned())]);
+    assert!(EffectPackage::new(manifest.clone(), shaders, vec![]).is_err());
+    let shaders = HashMap::from([(
+        TechniqueType::Object,
+        TEST_SHADER.replace("@binding(0)", "@binding(2)"),
+    )]);
+    assert!(EffectPackage::new(manifest.clone(), shaders, vec![]).is_err());
+    let shaders = HashMap::from([(
+        TechniqueType::Object,
+        TEST_SHADER.replace("array<vec4<f32>, 16>", "array<vec4<f32>, 17>"),
+    )]);
+    assert!(EffectPackage::new(manifest.clone(), shaders, vec![]).is_err());
+    let shaders = HashMap::from([(
+        TechniqueType::Object,
+        TEST_SHADER.replace("@location(0) position", "@location(9) position"),
+    )]);
+    assert!(EffectPackage::new(manifest, shaders, vec![]).is_err());
+}
+
+#[test]
+fn test_effect_manifest_parameter_values_follow_motion() {
+    use nanoem::motion::{
+        MotionEffectParameter, MotionKeyframeBase, MotionModelKeyframe, MotionTrack,
+    };
+
+    use crate::motion::seek::Seek;
+
+    let keyframe = |frame_index: u32, strength: f32| MotionModelKeyframe {
+        base: MotionKeyframeBase {
+            frame_index,
+            annotations: HashMap::new(),
+        },
+        visible: true,
+        constraint_states: vec![],
+        effect_parameters: vec![MotionEffectParameter {
+            parameter_id: 1,
+            value: MotionEffectParameterValue::FLOAT(strength),
+        }],
+        outside_parents: vec![],
+        has_edge_option: false,
+        edge_scale_factor: 1f32,
+        edge_color: [0f32, 0f32, 0f32, 1f32],
+        is_add_blending_enabled: false,
+        is_physics_simulation_enabled: true,
+    };
+    let mut track = MotionTrack::new("model", 0);
+    track.insert_keyframe(keyframe(0, 0f32));
+    track.insert_keyframe(keyframe(10, 1f32));
+    let manifest = EffectManifest {
+        name: "test".to_owned(),
+        parameters: vec![
+            EffectParameter {
+                name: "color".to_owned(),
+                value: [1f32, 0f32, 0f32, 1f32],
+            },
+            EffectParameter {
+                name: "strength".to_owned(),
+                value: [0.25f32, 0f32, 0f32, 0f32],
+            },
+        ],
+        ..Default::default()
+    };
+    let cache = crate::bezier_curve::BezierCurveCache::new();
+    let values_at = |frame_index: u32| {
+        let frame = track.seek(frame_index, &cache).unwrap();
+        // parameter ids are resolved to names through the global track bundle of the motion
+        let motion_values = frame
+            .effect_parameters
+            .values()
+            .map(|value| ("strength".to_owned(), *value))
+            .collect::<HashMap<_, _>>();
+        manifest.parameter_values(&motion_values)
+    };
+    assert_eq!(values_at(0)[1], [0f32; 4]);
+    assert_eq!(values_at(5)[1], [0.5f32, 0f32, 0f32, 0f32]);
+    assert_eq!(values_at(10)[1], [1f32, 0f32, 0f32, 0f32]);
+    assert_eq!(values_at(5)[0], [1f32, 0f32, 0f32, 1f32]);
+    assert_eq!(manifest.parameter_values(&HashMap::new())[1][0], 0.25f32);
+}
@@ -0,0 +1,380 @@
+use std::f32::consts::PI;
+
+use cgmath::{InnerSpace, Matrix4, Vector3};
+
+use crate::{error::MdanceioError, graphics::Skybox};
+
+/// Skybox and image based lighting of an environment image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EnvironmentSettings {
+    /// Draws the image behind models instead of the background color
+    pub skybox: bool,
+    pub skybox_intensity: f32,
+    /// Adds irradiance and reflection of the image on top of the toon and sphere shading
+    pub lighting: bool,
+    pub diffuse_intensity: f32,
+    pub specular_intensity: f32,
+    /// Rotation of the image around the Y axis in degrees
+    pub rotation: f32,
+}
+
+impl Default for EnvironmentSettings {
+    fn default() -> Self {
+        Self {
+            skybox: true,
+            skybox_intensity: 1f32,
+            lighting: true,
+            diffuse_intensity: 0.5f32,
+            specular_intensity: 1f32,
+            rotation: 0f32,
+        }
+    }
+}
+
+/// Order 2 spherical harmonics of the image, scaled so that shaders only evaluate
+/// `c0 + c1 y + c2 z + c3 x + c4 xy + c5 yz + c6 (3z^2 - 1) + c7 xz + c8 (x^2 - y^2)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EnvironmentLighting {
+    /// Cosine convolved radiance divided by pi, multiplied with the albedo
+    pub irradiance: [[f32; 4]; 9],
+    /// Band limited radiance sampled along the reflection
+    pub radiance: [[f32; 4]; 9],
+}
+
+pub struct Environment {
+    settings: EnvironmentSettings,
+    skybox: Skybox,
+    lighting: Option<EnvironmentLighting>,
+}
+
+impl Environment {
+    /// Images are resampled into faces of at most this size.
+    pub const MAX_FACE_SIZE: u32 = 512;
+
+    pub fn new(format: wgpu::TextureFormat, device: &wgpu::Device) -> Self {
+        Self {
+            settings: EnvironmentSettings::default(),
+            skybox: Skybox::new(format, device),
+            lighting: None,
+        }
+    }
+
+    pub fn settings(&self) -> &EnvironmentSettings {
+        &self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: EnvironmentSettings) {
+        self.settings = settings;
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.lighting.is_some()
+    }
+
+    /// Loads an equirectangular image of 2:1 or a cubemap strip of 6:1 or 1:6, Radiance HDR
+    /// images are taken as linear and others as sRGB.
+    pub fn load(
+        &mut self,
+        data: &[u8],
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<(), MdanceioError> {
+        let img = image::load_from_memory(data).map_err(|err| {
+            MdanceioError::asset_not_loaded("environment image", &err.to_string())
+        })?;
+        let (width, height) = (img.width(), img.height());
+        let texels = match img {
+            image::DynamicImage::ImageRgb32F(_) | image::DynamicImage::ImageRgba32F(_) => img
+                .to_rgb32f()
+                .pixels()
+                .map(|pixel| pixel.0)
+                .collect::<Vec<_>>(),
+            _ => img
+                .to_rgb8()
+                .pixels()
+                .map(|pixel| pixel.0.map(|value| srgb_to_linear(value as f32 / 255f32)))
+                .collect::<Vec<_>>(),
+        };
+        let (size, faces) = cube_faces(width, height, &texels)
+            .ok_or_else(|| MdanceioError::invalid_environment_image(width, height))?;
+        self.lighting = Some(project_lighting(size, &faces));
+        self.skybox.set_texture(size, &faces, device, queue);
+        Ok(())
+    }
+
+    pub fn clear(&mut self) {
+        self.lighting = None;
+        self.skybox.clear_texture();
+    }
+
+    /// Returns `None` unless an image is loaded and the lighting is enabled.
+    pub fn lighting(&self) -> Option<&EnvironmentLighting> {
+        self.lighting.as_ref().filter(|_| self.settings.lighting)
+    }
+
+    pub fn update_texture_format(
+        &mut self,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        device: &wgpu::Device,
+    ) {
+        self.skybox
+            .update_texture_format(format, sample_count, device);
+    }
+
+    pub fn draw(
+        &self,
+        view: &wgpu::TextureView,
+        view_projection: Matrix4<f32>,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        if self.settings.skybox {
+            self.skybox.draw(
+                view,
+                view_projection,
+                self.settings.skybox_intensity,
+                self.settings.rotation.to_radians(),
+                device,
+                queue,
+            );
+        }
+    }
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045f32 {
+        value / 12.92f32
+    } else {
+        ((value + 0.055f32) / 1.055f32).powf(2.4f32)
+    }
+}
+
+/// Direction of the texel at `(u, v)` in [-1, 1] on the cube face, same as GPU cube sampling.
+fn face_direction(face: usize, u: f32, v: f32) -> Vector3<f32> {
+    match face {
+        0 => Vector3::new(1f32, -v, -u),
+        1 => Vector3::new(-1f32, -v, u),
+        2 => Vector3::new(u, 1f32, v),
+        3 => Vector3::new(u, -1f32, -v),
+        4 => Vector3::new(u, -v, 1f32),
+        _ => Vector3::new(-u, -v, -1f32),
+    }
+}
+
+fn face_texel_coord(size: u32, idx: u32) -> f32 {
+    (idx as f32 + 0.5f32) / size as f32 * 2f32 - 1f32
+}
+
+/// Returns the face size and texels of the six faces, or `None` if the layout is unknown.
+fn cube_faces(width: u32, height: u32, texels: &[[f32; 3]]) -> Option<(u32, Vec<[f32; 3]>)> {
+    if width == 0 || height == 0 {
+        None
+    } else if width == height * 6 {
+        let size = height;
+        let mut faces = Vec::with_capacity(texels.len());
+        for face in 0..6 {
+            for y in 0..size {
+                let offset = (y * width + face * size) as usize;
+                faces.extend_from_slice(&texels[offset..offset + size as usize]);
+            }
+        }
+        Some(downsample_faces(size, faces))
+    } else if height == width * 6 {
+        Some(downsample_faces(width, texels.to_vec()))
+    } else if width == height * 2 {
+        let size = (height / 2).clamp(1, Environment::MAX_FACE_SIZE);
+        let mut faces = Vec::with_capacity((size * size * 6) as usize);
+        for face in 0..6 {
+            for y in 0..size {
+                for x in 0..size {
+                    let direction =
+                        face_direction(face, face_texel_coord(size, x), face_texel_coord(size, y))
+                            .normalize();
+                    faces.push(sample_equirectangular(width, height, texels, direction));
+                }
+            }
+        }
+        Some((size, faces))
+    } else {
+        None
+    }
+}
+
+/// Box filters faces larger than `Environment::MAX_FACE_SIZE` down to that size.
+fn downsample_faces(size: u32, faces: Vec<[f32; 3]>) -> (u32, Vec<[f32; 3]>) {
+    let target = size.min(Environment::MAX_FACE_SIZE);
+    if target == size {
+        return (size, faces);
+    }
+    let range = |idx: u32| idx * size / target..((idx + 1) * size).div_ceil(target);
+    let mut downsampled = Vec::with_capacity((target * target * 6) as usize);
+    for face in faces.chunks_exact((size * size) as usize) {
+        for y in 0..target {
+            for x in 0..target {
+                let mut sum = Vector3::new(0f32, 0f32, 0f32);
+                let mut count = 0f32;
+                for source_y in range(y) {
+                    for source_x in range(x) {
+                        sum += Vector3::from(face[(source_y * size + source_x) as usize]);
+                        count += 1f32;
+                    }
+                }
+                downsampled.push((sum / count).into());
+            }
+        }
+    }
+    (target, downsampled)
+}
+
+/// Bilinear sample, the image center faces +Z and the top faces +Y.
+fn sample_equirectangular(
+    width: u32,
+    height: u32,
+    texels: &[[f32; 3]],
+    direction: Vector3<f32>,
+) -> [f32; 3] {
+    let u = 0.5f32 + direction.x.atan2(direction.z) / (2f32 * PI);
+    let v = 0.5f32 - direction.y.clamp(-1f32, 1f32).asin() / PI;
+    let x = u * width as f32 - 0.5f32;
+    let y = (v * height as f32 - 0.5f32).clamp(0f32, (height - 1) as f32);
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let texel = |x: f32, y: f32| {
+        let x = (x as i64).rem_euclid(width as i64) as usize;
+        let y = (y as u32).min(height - 1) as usize;
+        Vector3::from(texels[y * width as usize + x])
+    };
+    let top = texel(x0, y0) * (1f32 - fx) + texel(x0 + 1f32, y0) * fx;
+    let bottom = texel(x0, y0 + 1f32) * (1f32 - fx) + texel(x0 + 1f32, y0 + 1f32) * fx;
+    (top * (1f32 - fy) + bottom * fy).into()
+}
+
+fn sh_basis(direction: Vector3<f32>) -> [f32; 9] {
+    let Vector3 { x, y, z } = direction;
+    [
+        1f32,
+        y,
+        z,
+        x,
+        x * y,
+        y * z,
+        3f32 * z * z - 1f32,
+        x * z,
+        x * x - y * y,
+    ]
+}
+
+fn project_lighting(size: u32, faces: &[[f32; 3]]) -> EnvironmentLighting {
+    const NORMALIZATION: [f32; 9] = [
+        0.282095, 0.488603, 0.488603, 0.488603, 1.092548, 1.092548, 0.315392, 1.092548, 0.546274,
+    ];
+    const COSINE_LOBE: [f32; 9] = [
+        1f32,
+        2f32 / 3f32,
+        2f32 / 3f32,
+        2f32 / 3f32,
+        0.25f32,
+        0.25f32,
+        0.25f32,
+        0.25f32,
+        0.25f32,
+    ];
+    let mut coefficients = [Vector3::new(0f32, 0f32, 0f32); 9];
+    let mut total_weight = 0f32;
+    let mut texels = faces.iter();
+    for face in 0..6 {
+        for y in 0..size {
+            for x in 0..size {
+                let (u, v) = (face_texel_coord(size, x), face_texel_coord(size, y));
+                let weight = (1f32 + u * u + v * v).powf(-1.5f32);
+                let direction = face_direction(face, u, v).normalize();
+                let radiance = Vector3::from(*texels.next().unwrap());
+                for (coefficient, (basis, normalization)) in coefficients
+                    .iter_mut()
+                    .zip(sh_basis(direction).iter().zip(NORMALIZATION))
+                {
+                    *coefficient += radiance * (basis * normalization * weight);
+                }
+                total_weight += weight;
+            }
+        }
+    }
+    let scale = 4f32 * PI / total_weight;
+    let mut lighting = EnvironmentLighting {
+        irradiance: [[0f32; 4]; 9],
+        radiance: [[0f32; 4]; 9],
+    };
+    for idx in 0..9 {
+        let coefficient = coefficients[idx] * (scale * NORMALIZATION[idx]);
+        lighting.irradiance[idx] = (coefficient * COSINE_LOBE[idx]).extend(0f32).into();
+        lighting.radiance[idx] = coefficient.extend(0f32).into();
+    }
+    lighting
+}
+
+#[test]
+fn test_environment_cube_layout() {
+    let texels = |count: u32| vec![[0.5f32; 3]; count as usize];
+    assert_eq!(
+        Some(4),
+        cube_faces(24, 4, &texels(96)).map(|(size, _)| size)
+    );
+    assert_eq!(
+        Some(4),
+        cube_faces(4, 24, &texels(96)).map(|(size, _)| size)
+    );
+    let (size, faces) = cube_faces(16, 8, &texels(128)).unwrap();
+    assert_eq!(4, size);
+    assert_eq!(96, faces.len());
+    assert!(faces.iter().all(|texel| *texel == [0.5f32; 3]));
+    assert!(cube_faces(12, 8, &texels(96)).is_none());
+    let mut strip = texels(96);
+    // the second face of a horizontal strip is -X
+    strip[4] = [1f32, 0f32, 0f32];
+    let (_, faces) = cube_faces(24, 4, &strip).unwrap();
+    assert_eq!([1f32, 0f32, 0f32], faces[16]);
+    let size = Environment::MAX_FACE_SIZE + 88;
+    let (downsampled_size, faces) = cube_faces(size, size * 6, &texels(size * size * 6)).unwrap();
+    assert_eq!(Environment::MAX_FACE_SIZE, downsampled_size);
+    assert_eq!(
+        (downsampled_size * downsampled_size * 6) as usize,
+        faces.len()
+    );
+    assert!(faces.iter().all(|texel| *texel == [0.5f32; 3]));
+}
+
+#[test]
+fn test_environment_lighting() {
+    let evaluate = |coefficients: &[[f32; 4]; 9], direction: Vector3<f32>| {
+        coefficients
+            .iter()
+            .zip(sh_basis(direction))
+            .map(|(coefficient, basis)| coefficient[0] * basis)
+            .sum::<f32>()
+    };
+    let size = 8u32;
+    let (_, faces) = cube_faces(size, size * 6, &vec![[0.5f32; 3]; 384]).unwrap();
+    let lighting = project_lighting(size, &faces);
+    for direction in [
+        Vector3::unit_x(),
+        -Vector3::unit_y(),
+        Vector3::new(1f32, 1f32, 1f32),
+    ] {
+        let direction = direction.normalize();
+        assert!((evaluate(&lighting.irradiance, direction) - 0.5f32).abs() < 1e-3);
+        assert!((evaluate(&lighting.radiance, direction) - 0.5f32).abs() < 1e-3);
+    }
+    // the upper half of an equirectangular image is bright
+    let (width, height) = (32u32, 16u32);
+    let texels = (0..width * height)
+        .map(|idx| [if idx / width < height / 2 { 1f32 } else { 0f32 }; 3])
+        .collect::<Vec<_>>();
+    let (size, faces) = cube_faces(width, height, &texels).unwrap();
+    let lighting = project_lighting(size, &faces);
+    let up = evaluate(&lighting.irradiance, Vector3::unit_y());
+    let down = evaluate(&lighting.irradiance, -Vector3::unit_y());
+    let side = evaluate(&lighting.irradiance, Vector3::unit_z());
+    assert!(up > 0.85f32 && down < 0.15f32, "{} {}", up, down);
+    assert!((side - 0.5f32).abs() < 0.05f32);
+}
@@ -10,7 +10,7 @@ pub enum DomainType {
     Cancel,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct MdanceioError {
     reason: String,
     recovery_suggestion: String,
@@ -98,4 +98,237 @@ impl MdanceioError {
             domain: DomainType::Application,
         }
     }
+
+    pub fn asset_not_loaded(path: &str, reason: &str) -> Self {
+        Self {
+            reason: format!("Failed to load asset {}: {}", path, reason),
+            recovery_suggestion: "Check that the asset exists".to_owned(),
+            code: 101,
+            domain: DomainType::Application,
+        }
+    }
+
+    pub fn invalid_project_file(line: usize, reason: &str) -> Self {
+        Self {
+            reason: format!("Invalid project file at line {}: {}", line, reason),
+            recovery_suggestion: "".to_owned(),
+            code: 102,
+            domain: DomainType::Application,
+        }
+    }
+
+    pub fn unsupported_project_version(version: u32) -> Self {
+        Self {
+            reason: format!("Unsupported project file version {}", version),
+            recovery_suggestion: "Update to the latest version".to_owned(),
+            code: 103,
+            domain: DomainType::Application,
+        }
+    }
+
+    pub fn project_not_empty() -> Self {
+        Self {
+            reason: "Project already contains models".to_owned(),
+            recovery_suggestion: "Create a new project before loading".to_owned(),
+            code: 104,
+            domain: DomainType::Application,
+        }
+    }
+
+    pub fn bone_not_found() -> Self {
+        Self {
+            reason: "Bone not Found".to_owned(),
+            recovery_suggestion: "".to_owned(),
+            code: 105,
+            domain: DomainType::Application,
+        }
+    }
+
+    pub fn outside_parent_cycle() -> Self {
+        Self {
+            reason: "Outside parent makes models depend on each other".to_owned(),
+            recovery_suggestion: "Choose a bone of another model".to_owned(),
+            code: 106,
+            domain: DomainType::Application,
+        }
+    }
+
+    pub fn keyframe_not_found() -> Self {
+        Self {
+            reason: "Keyframe not Found".to_owned(),
+            recovery_suggestion: "".to_owned(),
+            code: 107,
+            domain: DomainType::Application,
+        }
+    }
+
+    pub fn no_keyframe_interval() -> Self {
+        Self {
+            reason: "Frame is not between two keyframes".to_owned(),
+            recovery_suggestion: "Choose a frame without keyframe between keyframes".to_owned(),
+            code: 108,
+            domain: DomainType::Application,
+        }
+    }
+
+    pub fn invalid_interpolation_channel() -> Self {
+        Self {
+            reason: "Invalid interpolation channel".to_owned(),
+            recovery_suggestion: "".to_owned(),
+            code: 109,
+            domain: DomainType::Application,
+        }
+    }
+
+    pub fn camera_not_found(name: &str) -> Self {
+        Self {
+            reason: format!("Camera {} not Found", name),
+            recovery_suggestion: "".to_owned(),
+            code: 110,
+            domain: DomainType::Application,
+        }
+    }
+
+    pub fn camera_already_exists(name: &str) -> Self {
+        Self {
+            reason: format!("Camera {} already exists", name),
+            recovery_suggestion: "Choose another name".to_owned(),
+            code: 111,
+            domain: DomainType::Application,
+        }
+    }
+
+    pub fn last_camera_not_removable() -> Self {
+        Self {
+            reason: "Project must have at least one camera".to_owned(),
+            recovery_suggestion: "Add another camera before removing".to_owned(),
+            code: 112,
+            domain: DomainType::Application,
+        }
+    }
+
+    pub fn too_many_local_lights(maximum: usize) -> Self {
+        Self {
+            reason: format!("Project can have at most {} point and spot lights", maximum),
+            recovery_suggestion: "Remove another light before adding".to_owned(),
+            code: 113,
+            domain: DomainType::Application,
+        }
+    }
+
+    pub fn local_light_not_found(index: usize) -> Self {
+        Self {
+            reason: format!("Light {} not Found", index),
+            recovery_suggestion: "".to_owned(),
+            code: 114,
+            domain: DomainType::Application,
+        }
+    }
+
+    pub fn invalid_effect_package(line: usize, reason: &str) -> Self {
+        Self {
+            reason: format!("Invalid effect package at line {}: {}", line, reason),
+            recovery_suggestion: "Check the effect manifest".to_owned(),
+            code: 115,
+            domain: DomainType::Application,
+        }
+    }
+
+    pub fn invalid_effect_shader(technique: &str, reason: &str) -> Self {
+        Self {
+            reason: format!("Invalid shader of {} technique: {}", technique, reason),
+            recovery_suggestion: "Fix the WGSL source of the effect".to_owned(),
+            code: 116,
+            domain: DomainType::Application,
+        }
+    }
+
+    pub fn effect_not_found(name: &str) -> Self {
+        Self {
+            reason: format!("Effect {} not Found", name),
+            recovery_suggestion: "Load the effect before assigning".to_owned(),
+            code: 117,
+            domain: DomainType::Application,
+        }
+    }
+
+    pub fn material_not_found(index: usize) -> Self {
+        Self {
+            reason: format!("Material {} not Found", index),
+            recovery_suggestion: "".to_owned(),
+            code: 118,
+            domain: DomainType::Application,
+        }
+    }
+
+    pub fn invalid_fx_effect(path: &str, line: usize, reason: &str) -> Self {
+        Self {
+            reason: format!("Invalid effect {} at line {}: {}", path, line, reason),
+            recovery_suggestion: "Check the effect source".to_owned(),
+            code: 119,
+            domain: DomainType::Application,
+        }
+    }
+
+    pub fn unsupported_fx_effect(path: &str, reason: &str) -> Self {
+        Self {
+            reason: format!("Effect {} is not supported: {}", path, reason),
+            recovery_suggestion: "Use an effect within the supported subset".to_owned(),
+            code: 120,
+            domain: DomainType::Application,
+        }
+    }
+
+    pub fn invalid_color_grading_lut(width: u32, height: u32) -> Self {
+        Self {
+            reason: format!(
+                "Color grading LUT of {}x{} is not a strip of N slices of NxN",
+                width, height
+            ),
+            recovery_suggestion: "Use a LUT image such as 256x16 or 1024x32".to_owned(),
+            code: 121,
+            domain: DomainType::Application,
+        }
+    }
+
+    pub fn invalid_tone_mapping_operator() -> Self {
+        Self {
+            reason: "Invalid tone mapping operator".to_owned(),
+            recovery_suggestion: "".to_owned(),
+            code: 122,
+            domain: DomainType::Application,
+        }
+    }
+
+    pub fn invalid_environment_image(width: u32, height: u32) -> Self {
+        Self {
+            reason: format!(
+                "Environment image of {}x{} is neither equirectangular nor a cubemap strip",
+                width, height
+            ),
+            recovery_suggestion: "Use a 2:1 equirectangular image or a 6:1 or 1:6 cubemap strip"
+                .to_owned(),
+            code: 123,
+            domain: DomainType::Application,
+        }
+    }
+
+    pub fn unsupported_sample_count(sample_count: u32) -> Self {
+        Self {
+            reason: format!("MSAA with {} samples is not supported", sample_count),
+            recovery_suggestion: "Use a lower sample level".to_owned(),
+            code: 124,
+            domain: DomainType::Application,
+        }
+    }
+
+    pub fn unsupported_offscreen_format(format: &str) -> Self {
+        Self {
+            reason: format!("Offscreen rendering into {} is not supported", format),
+            recovery_suggestion: "Use an uncompressed color format such as Rgba8UnormSrgb"
+                .to_owned(),
+            code: 125,
+            domain: DomainType::Application,
+        }
+    }
 }
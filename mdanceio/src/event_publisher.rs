@@ -1,76 +1,217 @@
-use crate::error::{MdanceioError};
+use crate::{error::MdanceioError, project::ModelHandle};
 
-pub trait EventPublisher {
-    fn publish_tracking_event(&mut self, screen: String, action: String, category: String, label: String);
-    fn publish_undo_evnet(&mut self, can_undo: bool, can_redo: bool);
-    fn publish_redo_event(&mut self, can_redo: bool, can_undo: bool);
-    // TODO
-    fn publish_push_undo_command_event(&mut self, );
-    fn publish_add_model_event(&mut self, );
-    fn publish_set_active_model_event(&mut self, );
-    fn publish_set_active_bone_event(&mut self, );
-    fn publish_set_active_morph_event(&mut self, );
-    fn publish_remove_model_event(&mut self, );
-    fn publish_add_accessory_event(&mut self, );
-    fn publish_set_active_accessory_event(&mut self, );
-    fn publish_remove_accessory_event(&mut self, );
-    fn publish_add_motion_event(&mut self, );
-    fn publish_remove_motion_event(&mut self, );
-    fn publish_play_event(&mut self, );
-    fn publish_pause_event(&mut self, );
-    fn publish_resume_event(&mut self, );
-    fn publish_stop_event(&mut self, );
-    fn publish_seek_event(&mut self, );
-    fn publish_update_duration_event(&mut self, );
-    fn publish_toggle_project_effect_enabled_event(&mut self, value: bool);
-    fn publish_toggle_project_ground_shadow_enabled_event(&mut self, value: bool);
-    fn publish_toggle_project_vertex_shader_skinning_enabled_event(&mut self, value: bool);
-    fn publish_toggle_project_compute_shader_skinning_enabled_event(&mut self, value: bool);
-    fn publish_set_project_sample_level_event(&mut self, value: u32);
-    fn publish_toggle_grid_enabled_event(&mut self, value: bool);
-    fn publish_set_grid_cell_event(&mut self, );
-    fn publish_set_grid_size_event(&mut self, );
-    fn publish_set_preferred_motion_fps_event(&mut self, value: u32, unlimited: bool);
-    fn publish_set_preferred_editing_fps_event(&mut self, value: u32);
-    fn publish_set_physics_simulation_mode_event(&mut self, value: u32);
-    fn publish_set_physics_simulation_engine_debug_flag_event(&mut self, value: u32);
-    fn publish_toggle_shadow_map_enabled_event(&mut self, value: bool);
-    fn publish_set_shadow_map_mode_event(&mut self, value: u32);
-    fn publish_set_shadow_map_distance_event(&mut self, value: u32);
-    fn publish_toggle_active_model_add_blend_enabled_event(&mut self, value: bool);
-    fn publish_toggle_active_model_shadow_map_enabled_event(&mut self, value: bool);
-    fn publish_toggle_active_model_visible_event(&mut self, value: bool);
-    fn publish_toggle_active_model_compute_shader_skinning_enabled_event(&mut self, value: bool);
-    fn publish_toggle_active_model_show_all_bones_event(&mut self, value: bool);
-    fn publish_toggle_active_model_show_all_rigid_bodies_event(&mut self, value: bool);
-    fn publish_toggle_active_model_show_all_vertex_faces_event(&mut self, value: bool);
-    fn publish_toggle_active_model_show_all_vertex_points_event(&mut self, value: bool);
-    fn publish_toggle_active_model_vertex_shader_skinning_enabled_event(&mut self, value: bool);
-    fn publish_toggle_project_playing_with_loop_enabled_event(&mut self, value: bool);
-    fn publish_toggle_active_accessory_add_blend_enabled_event(&mut self, value: bool);
-    fn publish_toggle_active_accessory_ground_shadow_enabled_event(&mut self, value: bool);
-    fn publish_toggle_active_accessory_visible_event(&mut self, value: bool);
-    fn publish_toggle_model_editing_enabled_event(&mut self, value: bool);
-    fn publish_consume_pass_event(&mut self, value: u32);
-    fn publish_add_model_dialog_event(&mut self, );
-    fn publish_clear_model_dialog_event(&mut self, );
-    fn publish_set_language_event(&mut self, value: u32);
-    fn publish_query_open_single_file_dialog_event(&mut self, );
-    fn publish_query_open_multiple_files_dialog_event(&mut self, );
-    fn publish_query_save_file_dialog_event(&mut self, );
-    fn publish_update_progress_event(&mut self, value: u32, total: u32, typ: u32, text: String);
-    fn publish_start_progress_event(&mut self, title: String, text: String, total: u32);
-    fn publish_stop_progress_event(&mut self, );
-    fn publish_setup_progress_event(&mut self, );
-    fn publish_set_editing_mode_event(&mut self, );
-    fn publish_complete_exporting_image_configuration_event(&mut self, );
-    fn publish_complete_exporting_video_configuration_event(&mut self, );
-    fn publish_can_copy_event(&mut self, value: bool);
-    fn publish_can_paste_event(&mut self, value: bool);
-    fn publish_set_window_device_pixel_ratio_event(&mut self, value: u32);
-    fn publish_set_viewport_device_pixel_ration_event(&mut self, value: u32);
-    fn publish_disable_cursor_event(&mut self, );
-    fn publish_enable_cursor_event(&mut self, );
-    fn publish_quit_application_event(&mut self, );
-    fn publish_error_event(&mut self, error: MdanceioError);
-}
\ No newline at end of file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MotionTarget {
+    Model(ModelHandle),
+    Camera,
+    Light,
+    SelfShadow,
+}
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    AddModel(ModelHandle),
+    RemoveModel(ModelHandle),
+    SetActiveModel(Option<ModelHandle>),
+    SetActiveBone {
+        model: ModelHandle,
+        bone: Option<String>,
+    },
+    SetActiveMorph {
+        model: ModelHandle,
+        morph: Option<String>,
+    },
+    SetActiveCamera(String),
+    AddMotion(MotionTarget),
+    RemoveMotion(MotionTarget),
+    Play {
+        frame_index: u32,
+        duration: u32,
+    },
+    Pause {
+        frame_index: u32,
+        duration: u32,
+    },
+    Resume {
+        frame_index: u32,
+        duration: u32,
+    },
+    Stop {
+        frame_index: u32,
+        duration: u32,
+    },
+    Seek {
+        frame_index: u32,
+        last_frame_index: u32,
+        duration: u32,
+    },
+    UpdateDuration {
+        duration: u32,
+        last_duration: u32,
+    },
+    SetPreferredMotionFps {
+        value: u32,
+        unlimited: bool,
+    },
+    SetPhysicsSimulationMode(u32),
+    SetShadowMapMode(u32),
+    SetShadowMapDistance(f32),
+    SetSampleLevel(u32),
+    ToggleGridEnabled(bool),
+    ToggleModelVisible {
+        model: ModelHandle,
+        value: bool,
+    },
+    ToggleModelShadowMapEnabled {
+        model: ModelHandle,
+        value: bool,
+    },
+    ToggleModelShowAllBones {
+        model: ModelHandle,
+        value: bool,
+    },
+    ToggleModelShowAllVertexFaces {
+        model: ModelHandle,
+        value: bool,
+    },
+    ToggleModelShowAllVertexPoints {
+        model: ModelHandle,
+        value: bool,
+    },
+    UpdateUndoAvailability {
+        can_undo: bool,
+        can_redo: bool,
+    },
+    Error(MdanceioError),
+}
+
+impl Event {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::AddModel(_) => "AddModel",
+            Event::RemoveModel(_) => "RemoveModel",
+            Event::SetActiveModel(_) => "SetActiveModel",
+            Event::SetActiveBone { .. } => "SetActiveBone",
+            Event::SetActiveMorph { .. } => "SetActiveMorph",
+            Event::SetActiveCamera(_) => "SetActiveCamera",
+            Event::AddMotion(_) => "AddMotion",
+            Event::RemoveMotion(_) => "RemoveMotion",
+            Event::Play { .. } => "Play",
+            Event::Pause { .. } => "Pause",
+            Event::Resume { .. } => "Resume",
+            Event::Stop { .. } => "Stop",
+            Event::Seek { .. } => "Seek",
+            Event::UpdateDuration { .. } => "UpdateDuration",
+            Event::SetPreferredMotionFps { .. } => "SetPreferredMotionFps",
+            Event::SetPhysicsSimulationMode(_) => "SetPhysicsSimulationMode",
+            Event::SetShadowMapMode(_) => "SetShadowMapMode",
+            Event::SetShadowMapDistance(_) => "SetShadowMapDistance",
+            Event::SetSampleLevel(_) => "SetSampleLevel",
+            Event::ToggleGridEnabled(_) => "ToggleGridEnabled",
+            Event::ToggleModelVisible { .. } => "ToggleModelVisible",
+            Event::ToggleModelShadowMapEnabled { .. } => "ToggleModelShadowMapEnabled",
+            Event::ToggleModelShowAllBones { .. } => "ToggleModelShowAllBones",
+            Event::ToggleModelShowAllVertexFaces { .. } => "ToggleModelShowAllVertexFaces",
+            Event::ToggleModelShowAllVertexPoints { .. } => "ToggleModelShowAllVertexPoints",
+            Event::UpdateUndoAvailability { .. } => "UpdateUndoAvailability",
+            Event::Error(_) => "Error",
+        }
+    }
+}
+
+/// Queue of events emitted by `Project`, drained by the owner and forwarded to an `EventBus`.
+#[derive(Debug, Default)]
+pub struct EventPublisher {
+    pending_events: Vec<Event>,
+}
+
+impl EventPublisher {
+    pub fn publish(&mut self, event: Event) {
+        log::trace!("Publish event: {:?}", event);
+        self.pending_events.push(event);
+    }
+
+    pub fn has_pending_events(&self) -> bool {
+        !self.pending_events.is_empty()
+    }
+
+    pub fn take_events(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.pending_events)
+    }
+}
+
+pub type SubscriptionId = u32;
+pub type EventCallback = Box<dyn FnMut(&Event)>;
+
+/// Dispatches events to subscribers in subscription order.
+#[derive(Default)]
+pub struct EventBus {
+    subscribers: Vec<(SubscriptionId, EventCallback)>,
+    next_id: SubscriptionId,
+}
+
+impl EventBus {
+    pub fn subscribe(&mut self, callback: EventCallback) -> SubscriptionId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.subscribers.push((id, callback));
+        id
+    }
+
+    pub fn unsubscribe(&mut self, id: SubscriptionId) -> bool {
+        let len = self.subscribers.len();
+        self.subscribers.retain(|(subscriber, _)| *subscriber != id);
+        self.subscribers.len() != len
+    }
+
+    pub fn dispatch(&mut self, events: &[Event]) {
+        for event in events {
+            for (_, callback) in &mut self.subscribers {
+                callback(event);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_event_bus_dispatch() {
+    use std::{cell::RefCell, rc::Rc};
+
+    let received = Rc::new(RefCell::new(vec![]));
+    let mut bus = EventBus::default();
+    let sink = received.clone();
+    let id = bus.subscribe(Box::new(move |event| {
+        sink.borrow_mut().push(event.name());
+    }));
+    let mut publisher = EventPublisher::default();
+    publisher.publish(Event::AddModel(1));
+    publisher.publish(Event::SetActiveModel(Some(1)));
+    bus.dispatch(&publisher.take_events());
+    assert!(!publisher.has_pending_events());
+    assert_eq!(vec!["AddModel", "SetActiveModel"], *received.borrow());
+    assert!(bus.unsubscribe(id));
+    assert!(!bus.unsubscribe(id));
+    bus.dispatch(&[Event::ToggleGridEnabled(false)]);
+    assert_eq!(2, received.borrow().len());
+}
+
+#[test]
+fn test_event_update_undo_availability() {
+    let mut publisher = EventPublisher::default();
+    publisher.publish(Event::UpdateUndoAvailability {
+        can_undo: false,
+        can_redo: false,
+    });
+    let events = publisher.take_events();
+    assert_eq!(
+        vec!["UpdateUndoAvailability"],
+        events.iter().map(Event::name).collect::<Vec<_>>()
+    );
+    assert!(matches!(
+        events[0],
+        Event::UpdateUndoAvailability {
+            can_undo: false,
+            can_redo: false
+        }
+    ));
+}
@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+
+use cgmath::{Matrix4, Vector2};
+
+use crate::project::ModelHandle;
+
+/// Extra image drawn alongside the color pass, e.g. as training data. Every target is 0 where
+/// no model is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AuxiliaryTarget {
+    /// Linear distance along the view direction as `R32Float`
+    Depth,
+    /// View space normal with 1 in w as `Rgba16Float`
+    Normal,
+    /// Model handle plus one as `R32Uint`
+    ModelId,
+    /// Material index plus one as `R32Uint`
+    MaterialId,
+    /// Pixels moved since the previous drawn frame with y pointing down as `Rg32Float`
+    Motion,
+}
+
+impl AuxiliaryTarget {
+    pub const ALL: [Self; 5] = [
+        Self::Depth,
+        Self::Normal,
+        Self::ModelId,
+        Self::MaterialId,
+        Self::Motion,
+    ];
+
+    pub fn format(self) -> wgpu::TextureFormat {
+        match self {
+            Self::Depth => wgpu::TextureFormat::R32Float,
+            Self::Normal => wgpu::TextureFormat::Rgba16Float,
+            Self::ModelId | Self::MaterialId => wgpu::TextureFormat::R32Uint,
+            Self::Motion => wgpu::TextureFormat::Rg32Float,
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Owns the auxiliary textures and what the motion vectors of the next frame start from.
+pub struct AuxiliaryTargets {
+    size: Vector2<u32>,
+    textures: Vec<wgpu::Texture>,
+    views: Vec<wgpu::TextureView>,
+    depth_view: wgpu::TextureView,
+    previous_transforms: HashMap<ModelHandle, Matrix4<f32>>,
+}
+
+impl AuxiliaryTargets {
+    pub fn new(
+        size: Vector2<u32>,
+        depth_format: wgpu::TextureFormat,
+        device: &wgpu::Device,
+    ) -> Self {
+        let create_texture = |label: &str, format: wgpu::TextureFormat| {
+            device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(format!("AuxiliaryTargets/{}", label).as_str()),
+                size: wgpu::Extent3d {
+                    width: size.x.max(1),
+                    height: size.y.max(1),
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+                view_formats: &[],
+            })
+        };
+        let textures = AuxiliaryTarget::ALL
+            .iter()
+            .map(|target| create_texture(format!("{:?}", target).as_str(), target.format()))
+            .collect::<Vec<_>>();
+        let views = textures
+            .iter()
+            .map(|texture| texture.create_view(&wgpu::TextureViewDescriptor::default()))
+            .collect();
+        let depth_view = create_texture("DepthStencil", depth_format)
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        Self {
+            size,
+            textures,
+            views,
+            depth_view,
+            previous_transforms: HashMap::new(),
+        }
+    }
+
+    pub fn size(&self) -> Vector2<u32> {
+        self.size
+    }
+
+    pub fn texture(&self, target: AuxiliaryTarget) -> &wgpu::Texture {
+        &self.textures[target.index()]
+    }
+
+    /// Color attachments in the order of `AuxiliaryTarget::ALL`.
+    pub fn views(&self) -> Vec<&wgpu::TextureView> {
+        self.views.iter().collect()
+    }
+
+    pub fn depth_view(&self) -> &wgpu::TextureView {
+        &self.depth_view
+    }
+
+    /// Model view projection of the model in the last drawn frame.
+    pub fn previous_transform(&self, handle: ModelHandle) -> Option<&Matrix4<f32>> {
+        self.previous_transforms.get(&handle)
+    }
+
+    pub fn set_previous_transforms(&mut self, transforms: HashMap<ModelHandle, Matrix4<f32>>) {
+        self.previous_transforms = transforms;
+    }
+
+    pub fn clear(&self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("AuxiliaryTargets/CommandEncoder"),
+        });
+        let color_attachments = self
+            .views
+            .iter()
+            .map(|view| {
+                Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: true,
+                    },
+                })
+            })
+            .collect::<Vec<_>>();
+        {
+            let _rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("AuxiliaryTargets/ClearPass"),
+                color_attachments: &color_attachments,
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1f32),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+        }
+        queue.submit(Some(encoder.finish()));
+    }
+}
+
+#[test]
+fn test_auxiliary_target_formats() {
+    for (idx, target) in AuxiliaryTarget::ALL.iter().enumerate() {
+        assert_eq!(idx, target.index());
+        assert!(target.format().block_size(None).is_some());
+    }
+    assert_eq!(
+        wgpu::TextureFormat::R32Uint,
+        AuxiliaryTarget::ModelId.format()
+    );
+}
+
+#[test]
+fn test_auxiliary_shader() {
+    super::validate_wgsl(include_str!("../../resources/shaders/model_auxiliary.wgsl"));
+}
@@ -0,0 +1,288 @@
+use cgmath::{
+    EuclideanSpace, InnerSpace, Matrix4, Point3, SquareMatrix, Transform, Vector3, Vector4,
+};
+
+use crate::{
+    forward::LineVertexUnit,
+    model::{Bone, Model},
+    utils::f128_to_vec3,
+};
+
+use super::LineDrawer;
+
+pub struct BoneColors {
+    pub normal: [u8; 4],
+    pub active: [u8; 4],
+    pub constraint: [u8; 4],
+    pub constraint_chain: [u8; 4],
+    pub inherent: [u8; 4],
+    pub physics: [u8; 4],
+    pub hidden: [u8; 4],
+}
+
+impl Default for BoneColors {
+    fn default() -> Self {
+        Self {
+            normal: [0x00, 0x80, 0xff, 0xff],
+            active: [0xff, 0x00, 0x00, 0xff],
+            constraint: [0xff, 0xa5, 0x00, 0xff],
+            constraint_chain: [0xff, 0xff, 0x00, 0xff],
+            inherent: [0xb0, 0x40, 0xff, 0xff],
+            physics: [0x80, 0x80, 0x80, 0xff],
+            hidden: [0x60, 0x60, 0x60, 0x80],
+        }
+    }
+}
+
+/// Draws skeleton of models as wireframe octahedrons from bone head to tail as an overlay.
+pub struct BoneDrawer {
+    line_drawer: Option<LineDrawer>,
+    num_vertices: usize,
+    texture_format: wgpu::TextureFormat,
+    pub colors: BoneColors,
+    pub show_hidden_bones: bool,
+    pub show_constraint_chains: bool,
+}
+
+impl BoneDrawer {
+    const OCTAHEDRON_WIDTH_RATIO: f32 = 0.1;
+    const OCTAHEDRON_RING_RATIO: f32 = 0.2;
+    const HEAD_ONLY_RADIUS: f32 = 0.1;
+
+    pub fn new(texture_format: wgpu::TextureFormat) -> Self {
+        Self {
+            line_drawer: None,
+            num_vertices: 0,
+            texture_format,
+            colors: BoneColors::default(),
+            show_hidden_bones: false,
+            show_constraint_chains: true,
+        }
+    }
+
+    pub fn draw<'a>(
+        &mut self,
+        models: impl Iterator<Item = &'a Model>,
+        view_projection: Matrix4<f32>,
+        color_view: &wgpu::TextureView,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        let mut vertices = vec![];
+        for model in models {
+            if model.is_visible() && model.states.show_all_bones {
+                self.build_model_vertices(model, &mut vertices);
+            }
+        }
+        if vertices.is_empty() {
+            return;
+        }
+        match &mut self.line_drawer {
+            Some(line_drawer) if self.num_vertices == vertices.len() => {
+                line_drawer.update_vertex_buffer(&vertices, queue)
+            }
+            Some(line_drawer) => line_drawer.replace_vertices(&vertices, device),
+            None => {
+                self.line_drawer = Some(LineDrawer::new(&vertices, self.texture_format, device))
+            }
+        }
+        self.num_vertices = vertices.len();
+        if let Some(line_drawer) = &self.line_drawer {
+            line_drawer.update_uniform(
+                view_projection,
+                Vector4::new(1f32, 1f32, 1f32, 1f32),
+                queue,
+            );
+            line_drawer.draw(color_view, device, queue);
+        }
+    }
+
+    pub fn update_texture_format(
+        &mut self,
+        texture_format: wgpu::TextureFormat,
+        device: &wgpu::Device,
+    ) {
+        self.texture_format = texture_format;
+        if let Some(line_drawer) = &mut self.line_drawer {
+            // bones are drawn over the final view
+            line_drawer.update_texture_format(texture_format, 1, device);
+        }
+    }
+
+    fn build_model_vertices(&self, model: &Model, vertices: &mut Vec<LineVertexUnit>) {
+        let world = model.world_transform(&Matrix4::identity());
+        let to_world = |v: Vector3<f32>| world.transform_point(Point3::from_vec(v)).to_vec();
+        let bones = model.bones();
+        let active_bone = model.active_bone().map(|bone| bone.handle);
+        for bone in bones.iter() {
+            let visible = bone.origin.flags.is_visible;
+            if !visible && !self.show_hidden_bones {
+                continue;
+            }
+            let color = if Some(bone.handle) == active_bone {
+                self.colors.active
+            } else if !visible {
+                self.colors.hidden
+            } else if bone.origin.flags.is_affected_by_physics_simulation {
+                self.colors.physics
+            } else if bone.origin.flags.has_constraint || bone.constraint_joint.is_some() {
+                self.colors.constraint
+            } else if bone.origin.has_inherent_orientation()
+                || bone.origin.has_inherent_translation()
+            {
+                self.colors.inherent
+            } else {
+                self.colors.normal
+            };
+            let head = to_world(bone.world_translation());
+            match Self::bone_tail(bone, model).map(to_world) {
+                Some(tail) => build_octahedron(head, tail, color, vertices),
+                None => {
+                    let transform = world * bone.matrices.world_transform;
+                    build_head_marker(&transform, Self::HEAD_ONLY_RADIUS, color, vertices);
+                }
+            }
+        }
+        if self.show_constraint_chains {
+            for constraint in bones.constraints().iter() {
+                if !constraint.enabled() {
+                    continue;
+                }
+                let target = bones.try_get(constraint.origin.target_bone_index);
+                let effector = bones.try_get(constraint.origin.effector_bone_index);
+                if let (Some(target), Some(effector)) = (target, effector) {
+                    if !self.show_hidden_bones && !target.origin.flags.is_visible {
+                        continue;
+                    }
+                    let color = self.colors.constraint_chain;
+                    let mut chain = vec![effector];
+                    chain.extend(
+                        constraint
+                            .origin
+                            .joints
+                            .iter()
+                            .filter_map(|joint| bones.try_get(joint.bone_index)),
+                    );
+                    for pair in chain.windows(2) {
+                        push_line(
+                            to_world(pair[0].world_translation()),
+                            to_world(pair[1].world_translation()),
+                            color,
+                            vertices,
+                        );
+                    }
+                    push_line(
+                        to_world(effector.world_translation()),
+                        to_world(target.world_translation()),
+                        color,
+                        vertices,
+                    );
+                }
+            }
+        }
+    }
+
+    fn bone_tail(bone: &Bone, model: &Model) -> Option<Vector3<f32>> {
+        if bone.origin.flags.has_destination_bone_index {
+            model
+                .bones()
+                .target_of(bone.handle)
+                .filter(|target| target.handle != bone.handle)
+                .map(|target| target.world_translation())
+        } else {
+            let offset = f128_to_vec3(bone.origin.destination_origin);
+            if offset.magnitude2() <= f32::EPSILON {
+                None
+            } else {
+                Some(
+                    bone.world_translation()
+                        + bone.matrices.world_transform.transform_vector(offset),
+                )
+            }
+        }
+    }
+}
+
+fn push_line(
+    from: Vector3<f32>,
+    to: Vector3<f32>,
+    color: [u8; 4],
+    vertices: &mut Vec<LineVertexUnit>,
+) {
+    vertices.push(LineVertexUnit {
+        position: from.into(),
+        color,
+    });
+    vertices.push(LineVertexUnit {
+        position: to.into(),
+        color,
+    });
+}
+
+fn build_octahedron(
+    head: Vector3<f32>,
+    tail: Vector3<f32>,
+    color: [u8; 4],
+    vertices: &mut Vec<LineVertexUnit>,
+) {
+    let direction = tail - head;
+    let length = direction.magnitude();
+    if length <= f32::EPSILON {
+        push_line(head, tail, color, vertices);
+        return;
+    }
+    let axis = direction / length;
+    let reference = if axis.y.abs() < 0.99f32 {
+        Vector3::unit_y()
+    } else {
+        Vector3::unit_x()
+    };
+    let width = length * BoneDrawer::OCTAHEDRON_WIDTH_RATIO;
+    let u = axis.cross(reference).normalize() * width;
+    let v = axis.cross(u).normalize() * width;
+    let center = head + direction * BoneDrawer::OCTAHEDRON_RING_RATIO;
+    let ring = [center + u, center + v, center - u, center - v];
+    for (i, point) in ring.iter().enumerate() {
+        push_line(head, *point, color, vertices);
+        push_line(*point, tail, color, vertices);
+        push_line(*point, ring[(i + 1) % ring.len()], color, vertices);
+    }
+}
+
+fn build_head_marker(
+    transform: &Matrix4<f32>,
+    radius: f32,
+    color: [u8; 4],
+    vertices: &mut Vec<LineVertexUnit>,
+) {
+    let origin = transform.w.truncate();
+    for axis in [Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z()] {
+        let offset = transform.transform_vector(axis * radius);
+        push_line(origin - offset, origin + offset, color, vertices);
+    }
+}
+
+#[test]
+fn test_build_octahedron() {
+    let mut vertices = vec![];
+    let color = [0xff, 0xff, 0xff, 0xff];
+    build_octahedron(
+        Vector3::new(0f32, 0f32, 0f32),
+        Vector3::new(0f32, 10f32, 0f32),
+        color,
+        &mut vertices,
+    );
+    assert_eq!(24, vertices.len());
+    assert!(vertices
+        .iter()
+        .all(|v| v.position[1] >= 0f32 && v.position[1] <= 10f32));
+    assert!(vertices.iter().any(|v| v.position == [0f32, 10f32, 0f32]));
+    vertices.clear();
+    build_octahedron(
+        Vector3::new(1f32, 1f32, 1f32),
+        Vector3::new(1f32, 1f32, 1f32),
+        color,
+        &mut vertices,
+    );
+    assert_eq!(2, vertices.len());
+}
@@ -11,12 +11,14 @@ pub struct ClearPass {
     render_bundle: wgpu::RenderBundle,
     color_formats: Vec<Option<wgpu::TextureFormat>>,
     depth_format: Option<wgpu::TextureFormat>,
+    sample_count: u32,
 }
 
 impl ClearPass {
     pub fn new(
         color_formats: &[Option<wgpu::TextureFormat>],
         depth_format: Option<wgpu::TextureFormat>,
+        sample_count: u32,
         device: &wgpu::Device,
     ) -> Self {
         let vertex_buffer = wgpu::util::DeviceExt::create_buffer_init(
@@ -27,12 +29,13 @@ impl ClearPass {
                 usage: wgpu::BufferUsages::VERTEX,
             },
         );
-        let pipeline = Self::build_pipeline(color_formats, depth_format, device);
+        let pipeline = Self::build_pipeline(color_formats, depth_format, sample_count, device);
         let render_bundle = Self::build_render_bundle(
             &vertex_buffer,
             &pipeline,
             color_formats,
             depth_format,
+            sample_count,
             device,
         );
         Self {
@@ -41,6 +44,7 @@ impl ClearPass {
             render_bundle,
             color_formats: color_formats.to_vec(),
             depth_format,
+            sample_count,
         }
     }
 
@@ -48,6 +52,7 @@ impl ClearPass {
         &self,
         color_textures: &[Option<&wgpu::TextureView>],
         depth_texture: Option<&wgpu::TextureView>,
+        color: wgpu::Color,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
     ) {
@@ -61,7 +66,7 @@ impl ClearPass {
                     view: tv,
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
+                        load: wgpu::LoadOp::Clear(color),
                         store: true,
                     },
                 })
@@ -90,6 +95,7 @@ impl ClearPass {
     fn build_pipeline(
         color_formats: &[Option<wgpu::TextureFormat>],
         depth_format: Option<wgpu::TextureFormat>,
+        sample_count: u32,
         device: &wgpu::Device,
     ) -> wgpu::RenderPipeline {
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
@@ -163,7 +169,10 @@ impl ClearPass {
                 conservative: false,
             },
             depth_stencil: depth_stencil_state,
-            multisample: wgpu::MultisampleState::default(),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
             multiview: None,
         })
     }
@@ -173,6 +182,7 @@ impl ClearPass {
         pipeline: &wgpu::RenderPipeline,
         color_formats: &[Option<wgpu::TextureFormat>],
         depth_format: Option<wgpu::TextureFormat>,
+        sample_count: u32,
         device: &wgpu::Device,
     ) -> wgpu::RenderBundle {
         let mut encoder =
@@ -184,7 +194,7 @@ impl ClearPass {
                     depth_read_only: false,
                     stencil_read_only: true,
                 }),
-                sample_count: 1,
+                sample_count,
                 ..Default::default()
             });
         encoder.set_vertex_buffer(0, vertex_buffer.slice(..));
@@ -1,4 +1,15 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use nanoem::motion::MotionEffectParameterValue;
+use wgpu::util::DeviceExt;
+
+use crate::{
+    effect_package::{EffectBlendMode, EffectCullMode, EffectManifest, EffectPackage},
+    project::ModelHandle,
+};
 
 use super::{
     layout::RendererLayout,
@@ -9,11 +20,15 @@ use super::{
 #[derive(Debug, Clone, Copy)]
 pub struct EffectConfig {
     pub depth_enabled: bool,
+    pub cull_mode: EffectCullMode,
+    pub blend_mode: EffectBlendMode,
 }
 
 pub struct Effect {
     layout: Arc<RendererLayout>,
     pub technique: HashMap<TechniqueType, Technique>,
+    /// Effect providing the techniques this one does not declare
+    fallback: Option<Arc<Effect>>,
 }
 
 impl Effect {
@@ -26,6 +41,8 @@ impl Effect {
         let layout = Arc::new(RendererLayout::new(device));
         let config = EffectConfig {
             depth_enabled,
+            cull_mode: EffectCullMode::Material,
+            blend_mode: EffectBlendMode::Model,
         };
         let technique = shaders
             .iter()
@@ -35,17 +52,115 @@ impl Effect {
                     source: wgpu::ShaderSource::Wgsl((*shader).into()),
                 });
                 (*typ, {
-                    Technique::new(*typ, config, shader, &layout, fallback_shadow_bind)
+                    Technique::new(*typ, config, shader, &layout, fallback_shadow_bind, None)
                 })
             })
             .collect();
         Self {
             layout,
             technique,
+            fallback: None,
+        }
+    }
+
+    /// Builds a user effect, draw types without a technique are drawn by `fallback`.
+    pub fn from_package(
+        package: &EffectPackage,
+        fallback: &Arc<Effect>,
+        fallback_shadow_bind: &Arc<wgpu::BindGroup>,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Self {
+        let manifest = &package.manifest;
+        let layout = Arc::new(RendererLayout::new_with_effect(
+            package.textures().len(),
+            device,
+        ));
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some(format!("Effect/{}/Sampler", manifest.name).as_str()),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let texture_views = package
+            .textures()
+            .iter()
+            .zip(&manifest.textures)
+            .map(|(image, desc)| {
+                device
+                    .create_texture_with_data(
+                        queue,
+                        &wgpu::TextureDescriptor {
+                            label: Some(format!("Effect/{}/{}", manifest.name, desc.name).as_str()),
+                            size: wgpu::Extent3d {
+                                width: image.width(),
+                                height: image.height(),
+                                depth_or_array_layers: 1,
+                            },
+                            mip_level_count: 1,
+                            sample_count: 1,
+                            dimension: wgpu::TextureDimension::D2,
+                            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                                | wgpu::TextureUsages::COPY_DST,
+                            view_formats: &[],
+                        },
+                        image.as_raw(),
+                    )
+                    .create_view(&wgpu::TextureViewDescriptor::default())
+            })
+            .collect::<Vec<_>>();
+        let effect_bind = Arc::new(EffectBind {
+            manifest: manifest.clone(),
+            layout: layout.clone(),
+            sampler,
+            texture_views,
+            binds: RwLock::new(HashMap::new()),
+        });
+        let config = EffectConfig {
+            depth_enabled: manifest.depth_enabled,
+            cull_mode: manifest.cull_mode,
+            blend_mode: manifest.blend_mode,
+        };
+        let technique = package
+            .shaders()
+            .iter()
+            .map(|(typ, source)| {
+                let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                    label: Some(format!("Effect/{}/{:?}/Shader", manifest.name, typ).as_str()),
+                    source: wgpu::ShaderSource::Wgsl(source.as_str().into()),
+                });
+                (
+                    *typ,
+                    Technique::new(
+                        *typ,
+                        config,
+                        shader,
+                        &layout,
+                        fallback_shadow_bind,
+                        Some(&effect_bind),
+                    ),
+                )
+            })
+            .collect();
+        Self {
+            layout,
+            technique,
+            fallback: Some(fallback.clone()),
         }
     }
 
     pub fn find_technique(&self, draw_type: DrawType) -> Option<&Technique> {
+        self.find_own_technique(draw_type).or_else(|| {
+            self.fallback
+                .as_ref()
+                .and_then(|fallback| fallback.find_technique(draw_type))
+        })
+    }
+
+    fn find_own_technique(&self, draw_type: DrawType) -> Option<&Technique> {
         match draw_type {
             DrawType::Color(true) => {
                 if self.technique.get(&TechniqueType::Zplot).is_some() {
@@ -63,6 +178,84 @@ impl Effect {
             DrawType::Edge => self.technique.get(&TechniqueType::Edge),
             DrawType::GroundShadow => self.technique.get(&TechniqueType::Shadow),
             DrawType::ShadowMap => self.technique.get(&TechniqueType::Zplot),
+            DrawType::Normal => self.technique.get(&TechniqueType::Normal),
+            DrawType::Auxiliary => self.technique.get(&TechniqueType::Auxiliary),
         }
     }
-}
\ No newline at end of file
+}
+
+#[derive(Debug)]
+struct EffectModelBind {
+    parameter_buffer: wgpu::Buffer,
+    bind_group: Arc<wgpu::BindGroup>,
+}
+
+/// Resources of a user effect at `@group(3)`, each model draws with its own parameter buffer
+/// to follow the effect parameters of its motion.
+#[derive(Debug)]
+pub struct EffectBind {
+    manifest: EffectManifest,
+    layout: Arc<RendererLayout>,
+    sampler: wgpu::Sampler,
+    texture_views: Vec<wgpu::TextureView>,
+    binds: RwLock<HashMap<ModelHandle, EffectModelBind>>,
+}
+
+impl EffectBind {
+    pub fn get(&self, handle: ModelHandle, device: &wgpu::Device) -> Arc<wgpu::BindGroup> {
+        if let Some(bind) = self.binds.read().unwrap().get(&handle) {
+            return bind.bind_group.clone();
+        }
+        let name = &self.manifest.name;
+        let parameter_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(format!("Effect/{}/Parameters", name).as_str()),
+            contents: bytemuck::cast_slice(&self.manifest.parameter_values(&HashMap::new())),
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
+        });
+        let mut entries = vec![
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: parameter_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&self.sampler),
+            },
+        ];
+        for (idx, view) in self.texture_views.iter().enumerate() {
+            entries.push(wgpu::BindGroupEntry {
+                binding: 2 + idx as u32,
+                resource: wgpu::BindingResource::TextureView(view),
+            });
+        }
+        let bind_group = Arc::new(device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(format!("Effect/{}/BindGroup", name).as_str()),
+            layout: self.layout.effect_bind_layout.as_ref().unwrap(),
+            entries: &entries,
+        }));
+        self.binds.write().unwrap().insert(
+            handle,
+            EffectModelBind {
+                parameter_buffer,
+                bind_group: bind_group.clone(),
+            },
+        );
+        bind_group
+    }
+
+    /// Uploads the effect parameter values of the model's motion, matched by parameter name.
+    pub fn update(
+        &self,
+        handle: ModelHandle,
+        motion_values: &HashMap<String, MotionEffectParameterValue>,
+        queue: &wgpu::Queue,
+    ) {
+        if let Some(bind) = self.binds.read().unwrap().get(&handle) {
+            queue.write_buffer(
+                &bind.parameter_buffer,
+                0,
+                bytemuck::cast_slice(&self.manifest.parameter_values(motion_values)),
+            );
+        }
+    }
+}
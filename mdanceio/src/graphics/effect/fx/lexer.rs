@@ -0,0 +1,352 @@
+use std::collections::HashMap;
+
+use crate::{
+    error::MdanceioError,
+    project_file::{resolve_relative_path, AssetLoader},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Ident(String),
+    Number { text: String, float: bool },
+    Str(String),
+    Punct(&'static str),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lexeme {
+    pub token: Token,
+    pub line: usize,
+}
+
+const PUNCTUATIONS: [&str; 45] = [
+    "<<=", ">>=", "++", "--", "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=", "==", "!=", "<=",
+    ">=", "&&", "||", "<<", ">>", "::", "{", "}", "(", ")", "[", "]", ";", ",", ".", "<", ">", "=",
+    "+", "-", "*", "/", "%", "!", "?", ":", "&", "|", "^", "~",
+];
+
+const MAXIMUM_INCLUDE_DEPTH: usize = 8;
+
+/// Runs the preprocessor over `source` and splits the result into tokens.
+///
+/// `#include` is resolved relative to `path` through `loader`, object-like `#define`s are
+/// expanded and `#if`/`#ifdef`/`#ifndef`/`#elif`/`#else`/`#endif` accept integer literals,
+/// macro names, `defined(NAME)` and `!`.
+pub fn tokenize(
+    source: &str,
+    path: &str,
+    loader: &mut dyn AssetLoader,
+) -> Result<Vec<Lexeme>, MdanceioError> {
+    let mut defines = HashMap::new();
+    let mut lexemes = vec![];
+    preprocess(source, path, loader, &mut defines, &mut lexemes, 0)?;
+    Ok(lexemes)
+}
+
+fn preprocess(
+    source: &str,
+    path: &str,
+    loader: &mut dyn AssetLoader,
+    defines: &mut HashMap<String, Vec<Token>>,
+    lexemes: &mut Vec<Lexeme>,
+    depth: usize,
+) -> Result<(), MdanceioError> {
+    let source = strip_comments(source);
+    // each entry is (active, taken), `taken` marks a group whose branch already matched
+    let mut conditions: Vec<(bool, bool)> = vec![];
+    for (idx, line) in source.lines().enumerate() {
+        let line_number = idx + 1;
+        let invalid = |reason: &str| MdanceioError::invalid_fx_effect(path, line_number, reason);
+        let active = conditions.iter().all(|(active, _)| *active);
+        let trimmed = line.trim();
+        let Some(directive) = trimmed.strip_prefix('#') else {
+            if active {
+                let mut line_lexemes = vec![];
+                tokenize_line(line, line_number, &mut line_lexemes)
+                    .map_err(|reason| invalid(&reason))?;
+                expand_macros(line_lexemes, defines, lexemes);
+            }
+            continue;
+        };
+        let (name, rest) = split_identifier(directive.trim_start());
+        let rest = rest.trim();
+        match name {
+            "ifdef" | "ifndef" => {
+                let defined = defines.contains_key(rest);
+                let value = active && (defined == (name == "ifdef"));
+                conditions.push((value, value));
+            }
+            "if" => {
+                let value = active
+                    && evaluate_condition(rest, defines).map_err(|reason| invalid(&reason))?;
+                conditions.push((value, value));
+            }
+            "elif" => {
+                let parent_active = conditions.len() < 2
+                    || conditions[..conditions.len() - 1]
+                        .iter()
+                        .all(|(active, _)| *active);
+                let (value, taken) = conditions
+                    .last_mut()
+                    .ok_or_else(|| invalid("#elif without #if"))?;
+                if *taken || !parent_active {
+                    *value = false;
+                } else {
+                    *value =
+                        evaluate_condition(rest, defines).map_err(|reason| invalid(&reason))?;
+                    *taken = *value;
+                }
+            }
+            "else" => {
+                let parent_active = conditions.len() < 2
+                    || conditions[..conditions.len() - 1]
+                        .iter()
+                        .all(|(active, _)| *active);
+                let (value, taken) = conditions
+                    .last_mut()
+                    .ok_or_else(|| invalid("#else without #if"))?;
+                *value = !*taken && parent_active;
+                *taken = true;
+            }
+            "endif" => {
+                conditions
+                    .pop()
+                    .ok_or_else(|| invalid("#endif without #if"))?;
+            }
+            _ if !active => {}
+            "define" => {
+                let (macro_name, body) = split_identifier(rest);
+                if macro_name.is_empty() {
+                    return Err(invalid("#define without a name"));
+                }
+                if body.starts_with('(') {
+                    return Err(invalid("function-like macros are not supported"));
+                }
+                let mut body_lexemes = vec![];
+                tokenize_line(body, line_number, &mut body_lexemes)
+                    .map_err(|reason| invalid(&reason))?;
+                defines.insert(
+                    macro_name.to_owned(),
+                    body_lexemes
+                        .into_iter()
+                        .map(|lexeme| lexeme.token)
+                        .collect(),
+                );
+            }
+            "undef" => {
+                defines.remove(rest);
+            }
+            "include" => {
+                let include = rest
+                    .trim_matches(|c| c == '"' || c == '<' || c == '>')
+                    .to_owned();
+                if depth >= MAXIMUM_INCLUDE_DEPTH {
+                    return Err(invalid("#include is nested too deeply"));
+                }
+                let include_path = resolve_relative_path(path, &include);
+                let data = loader.load(&include_path)?;
+                let text = String::from_utf8_lossy(&data);
+                preprocess(&text, &include_path, loader, defines, lexemes, depth + 1)?;
+            }
+            "pragma" | "line" => {}
+            _ => return Err(invalid(&format!("unknown directive #{}", name))),
+        }
+    }
+    if !conditions.is_empty() {
+        return Err(MdanceioError::invalid_fx_effect(
+            path,
+            source.lines().count(),
+            "missing #endif",
+        ));
+    }
+    Ok(())
+}
+
+fn evaluate_condition(
+    expression: &str,
+    defines: &HashMap<String, Vec<Token>>,
+) -> Result<bool, String> {
+    let expression = expression.trim();
+    if let Some(inner) = expression.strip_prefix('!') {
+        return evaluate_condition(inner, defines).map(|value| !value);
+    }
+    if let Some(inner) = expression.strip_prefix("defined") {
+        let name = inner.trim().trim_start_matches('(').trim_end_matches(')');
+        return Ok(defines.contains_key(name.trim()));
+    }
+    if let Some(inner) = expression
+        .strip_prefix('(')
+        .and_then(|inner| inner.strip_suffix(')'))
+    {
+        return evaluate_condition(inner, defines);
+    }
+    if let Ok(value) = expression.parse::<i64>() {
+        return Ok(value != 0);
+    }
+    match defines.get(expression).map(|tokens| tokens.as_slice()) {
+        Some([Token::Number { text, .. }]) => Ok(text.parse::<f64>().unwrap_or(0f64) != 0f64),
+        Some(_) => Ok(true),
+        None if is_identifier(expression) => Ok(false),
+        None => Err(format!("unsupported #if expression: {}", expression)),
+    }
+}
+
+fn expand_macros(
+    lexemes: Vec<Lexeme>,
+    defines: &HashMap<String, Vec<Token>>,
+    output: &mut Vec<Lexeme>,
+) {
+    fn expand(
+        token: Token,
+        line: usize,
+        defines: &HashMap<String, Vec<Token>>,
+        expanding: &mut Vec<String>,
+        output: &mut Vec<Lexeme>,
+    ) {
+        match &token {
+            Token::Ident(name) if defines.contains_key(name) && !expanding.contains(name) => {
+                expanding.push(name.clone());
+                for token in &defines[name] {
+                    expand(token.clone(), line, defines, expanding, output);
+                }
+                expanding.pop();
+            }
+            _ => output.push(Lexeme { token, line }),
+        }
+    }
+    for lexeme in lexemes {
+        expand(lexeme.token, lexeme.line, defines, &mut vec![], output);
+    }
+}
+
+/// Replaces comments with spaces, keeping line breaks so line numbers stay intact.
+fn strip_comments(source: &str) -> String {
+    let mut output = String::with_capacity(source.len());
+    let mut chars = source.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            in_string = c != '"';
+            output.push(c);
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                output.push(c);
+            }
+            ('/', Some('/')) => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        output.push('\n');
+                        break;
+                    }
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut last = ' ';
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        output.push('\n');
+                    }
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+                output.push(' ');
+            }
+            _ => output.push(c),
+        }
+    }
+    output
+}
+
+fn tokenize_line(line: &str, line_number: usize, lexemes: &mut Vec<Lexeme>) -> Result<(), String> {
+    let chars = line.chars().collect::<Vec<_>>();
+    let mut idx = 0;
+    while idx < chars.len() {
+        let c = chars[idx];
+        let start = idx;
+        let token = if c.is_whitespace() {
+            idx += 1;
+            continue;
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            while idx < chars.len() && (chars[idx].is_ascii_alphanumeric() || chars[idx] == '_') {
+                idx += 1;
+            }
+            Token::Ident(chars[start..idx].iter().collect())
+        } else if c.is_ascii_digit()
+            || (c == '.' && chars.get(idx + 1).is_some_and(|c| c.is_ascii_digit()))
+        {
+            let mut float = false;
+            while idx < chars.len() {
+                let c = chars[idx];
+                if c.is_ascii_digit() {
+                    idx += 1;
+                } else if c == '.' {
+                    float = true;
+                    idx += 1;
+                } else if (c == 'e' || c == 'E')
+                    && chars[idx + 1..]
+                        .iter()
+                        .find(|c| **c != '+' && **c != '-')
+                        .is_some_and(|c| c.is_ascii_digit())
+                {
+                    float = true;
+                    idx += 1;
+                    if matches!(chars.get(idx), Some('+') | Some('-')) {
+                        idx += 1;
+                    }
+                } else {
+                    break;
+                }
+            }
+            let text = chars[start..idx].iter().collect::<String>();
+            // suffixes like 1.0f and 2u carry no meaning in the translated shader
+            while idx < chars.len() && matches!(chars[idx], 'f' | 'F' | 'h' | 'H' | 'u' | 'U') {
+                float |= matches!(chars[idx], 'f' | 'F' | 'h' | 'H');
+                idx += 1;
+            }
+            Token::Number { text, float }
+        } else if c == '"' {
+            idx += 1;
+            while idx < chars.len() && chars[idx] != '"' {
+                idx += 1;
+            }
+            if idx >= chars.len() {
+                return Err("unterminated string".to_owned());
+            }
+            idx += 1;
+            Token::Str(chars[start + 1..idx - 1].iter().collect())
+        } else {
+            let rest = chars[idx..].iter().take(3).collect::<String>();
+            let punct = PUNCTUATIONS
+                .iter()
+                .find(|punct| rest.starts_with(**punct))
+                .ok_or_else(|| format!("unexpected character {}", c))?;
+            idx += punct.len();
+            Token::Punct(punct)
+        };
+        lexemes.push(Lexeme {
+            token,
+            line: line_number,
+        });
+    }
+    Ok(())
+}
+
+fn split_identifier(text: &str) -> (&str, &str) {
+    text.split_at(
+        text.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(text.len()),
+    )
+}
+
+fn is_identifier(text: &str) -> bool {
+    text.chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && text.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
@@ -0,0 +1,776 @@
+//! Subset of MikuMikuEffect `.fx` files translated into WGSL effect packages.
+//!
+//! The annotation and semantic layer is parsed as MME defines it: `STANDARDSGLOBAL` script
+//! class and order, techniques with `MMDPass`/`UseTexture`/`UseSphereMap`/`UseToon`/`Subset`,
+//! passes with their render states, `CONTROLOBJECT`, `OFFSCREENRENDERTARGET`,
+//! `RENDERCOLORTARGET` and `RENDERDEPTHSTENCILTARGET`.
+//!
+//! Shader bodies are translated for object effects only, with these limits:
+//!
+//! - one technique per `MMDPass`, the one using the most of texture, sphere map and toon, and
+//!   only its first pass. A technique without passes hides that draw type.
+//! - types are `float`/`half`/`int`/`uint`/`bool` scalars, vectors, `floatRxC` matrices,
+//!   arrays and structs. Statements are `if`, `for`, `while`, `return`, `discard`, `break`,
+//!   `continue`, declarations and assignments, swizzled ones included.
+//! - `tex2D`, `tex2Dlod`, `tex2Dbias`, `tex2Dproj`, `mul`, `clip` and the common math
+//!   intrinsics. Textures are sampled at level 0 as models have no mipmaps.
+//! - matrices `WORLD`, `WORLDVIEW` and `WORLDVIEWPROJECTION` of the camera or the light,
+//!   `POSITION` of the camera, `DIRECTION` of the light, material and light colors, `TOONCOLOR`,
+//!   `EDGECOLOR`, `MATERIALTEXTURE`, `MATERIALSPHEREMAP`, `MATERIALTOONTEXTURE` and the shadow
+//!   map sampler at `register(s0)`. Following MMD, `AMBIENT` of the geometry is its diffuse color
+//!   and `EMISSIVE` its ambient color, while `DIFFUSE` of the light is black.
+//! - the boolean globals `use_texture`, `use_spheremap`, `use_toon`, `use_subtexture`, `spadd`,
+//!   `parthf` and `transp` MMD sets on each material.
+//! - up to 16 tweakable scalar or vector globals, `CONTROLOBJECT` included, which keep their
+//!   initial value, and 4 textures loaded from `ResourceName`.
+//! - vertex inputs `POSITION`, `NORMAL`, `TEXCOORD0` and `TEXCOORD1` to `TEXCOORD4` for the
+//!   additional UVs. Varyings are matched by semantic.
+//!
+//! Scene effects, offscreen render targets, function-like macros, overloaded functions and
+//! other semantics are reported as errors.
+
+mod lexer;
+mod parser;
+mod wgsl;
+
+use crate::{
+    effect_package::{parse_technique_type, EffectBlendMode, EffectCullMode, TechniqueType},
+    error::MdanceioError,
+    project_file::AssetLoader,
+};
+
+use self::parser::{Annotation, AnnotationValue, Expr, Module, PassDecl, StateValue};
+
+use super::{RenderFormat, ScriptClass, ScriptOrder};
+
+pub use self::wgsl::Translation;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FxSemantic {
+    World,
+    WorldView,
+    WorldViewProjection,
+    Position,
+    Direction,
+    Diffuse,
+    Ambient,
+    Emissive,
+    Specular,
+    SpecularPower,
+    ToonColor,
+    EdgeColor,
+    MaterialTexture,
+    MaterialSphereMap,
+    MaterialToonTexture,
+    ControlObject {
+        name: String,
+        item: Option<String>,
+    },
+    OffscreenRenderTarget,
+    RenderColorTarget,
+    RenderDepthStencilTarget,
+    StandardsGlobal,
+    /// Samplers bound by `register(sN)`
+    Register(String),
+    Other(String),
+}
+
+impl FxSemantic {
+    fn parse(semantic: &str, annotations: &[Annotation]) -> Self {
+        match semantic.to_ascii_uppercase().as_str() {
+            "WORLD" => Self::World,
+            "WORLDVIEW" => Self::WorldView,
+            "WORLDVIEWPROJECTION" => Self::WorldViewProjection,
+            "POSITION" => Self::Position,
+            "DIRECTION" => Self::Direction,
+            "DIFFUSE" => Self::Diffuse,
+            "AMBIENT" => Self::Ambient,
+            "EMISSIVE" => Self::Emissive,
+            "SPECULAR" => Self::Specular,
+            "SPECULARPOWER" => Self::SpecularPower,
+            "TOONCOLOR" => Self::ToonColor,
+            "EDGECOLOR" => Self::EdgeColor,
+            "MATERIALTEXTURE" => Self::MaterialTexture,
+            "MATERIALSPHEREMAP" => Self::MaterialSphereMap,
+            "MATERIALTOONTEXTURE" => Self::MaterialToonTexture,
+            "CONTROLOBJECT" => Self::ControlObject {
+                name: find_string(annotations, "Name").unwrap_or_default(),
+                item: find_string(annotations, "Item"),
+            },
+            "OFFSCREENRENDERTARGET" => Self::OffscreenRenderTarget,
+            "RENDERCOLORTARGET" => Self::RenderColorTarget,
+            "RENDERDEPTHSTENCILTARGET" => Self::RenderDepthStencilTarget,
+            "STANDARDSGLOBAL" => Self::StandardsGlobal,
+            other => match other
+                .strip_prefix("REGISTER(")
+                .and_then(|register| register.strip_suffix(')'))
+            {
+                Some(register) => Self::Register(register.to_ascii_lowercase()),
+                None => Self::Other(other.to_owned()),
+            },
+        }
+    }
+}
+
+/// Global variable with a semantic, `object` is the lower cased `Object` annotation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FxParameter {
+    pub name: String,
+    pub semantic: FxSemantic,
+    pub object: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FxTargetSize {
+    ViewportRatio([f32; 2]),
+    Dimensions([u32; 2]),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FxRenderTarget {
+    pub name: String,
+    pub semantic: FxSemantic,
+    pub description: String,
+    pub size: FxTargetSize,
+    pub format: RenderFormat,
+    pub clear_color: [f32; 4],
+    pub clear_depth: f32,
+    pub anti_alias: bool,
+    /// Pairs of model name pattern and effect file of `DefaultEffect`
+    pub default_effects: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FxPass {
+    pub name: String,
+    pub vertex_shader: Option<String>,
+    pub pixel_shader: Option<String>,
+    pub depth_enabled: Option<bool>,
+    pub cull_mode: Option<EffectCullMode>,
+    pub blend_mode: Option<EffectBlendMode>,
+    decl: PassDecl,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FxTechnique {
+    pub name: String,
+    /// `None` when `MMDPass` names a pass this renderer does not have
+    pub typ: Option<TechniqueType>,
+    pub use_texture: Option<bool>,
+    pub use_sphere_map: Option<bool>,
+    pub use_toon: Option<bool>,
+    pub subset: Option<String>,
+    pub script: Option<String>,
+    pub passes: Vec<FxPass>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FxEffect {
+    pub script_class: ScriptClass,
+    pub script_order: ScriptOrder,
+    pub parameters: Vec<FxParameter>,
+    pub render_targets: Vec<FxRenderTarget>,
+    pub techniques: Vec<FxTechnique>,
+    path: String,
+    module: Module,
+}
+
+impl FxEffect {
+    /// `path` resolves `#include`s through `loader`.
+    pub fn parse(
+        source: &str,
+        path: &str,
+        loader: &mut dyn AssetLoader,
+    ) -> Result<Self, MdanceioError> {
+        let lexemes = lexer::tokenize(source, path, loader)?;
+        let module = parser::parse(&lexemes, path)?;
+        let mut script_class = ScriptClass::Object;
+        let mut script_order = ScriptOrder::Standard;
+        let mut parameters = vec![];
+        let mut render_targets = vec![];
+        for global in &module.globals {
+            let Some(semantic) = &global.semantic else {
+                continue;
+            };
+            let semantic = FxSemantic::parse(semantic, &global.annotations);
+            match semantic {
+                FxSemantic::StandardsGlobal => {
+                    let invalid =
+                        |reason: &str| MdanceioError::invalid_fx_effect(path, global.line, reason);
+                    if let Some(class) = find_string(&global.annotations, "ScriptClass") {
+                        script_class = match class.to_ascii_lowercase().as_str() {
+                            "object" => ScriptClass::Object,
+                            "scene" => ScriptClass::Scene,
+                            "sceneorobject" => ScriptClass::SceneObject,
+                            _ => return Err(invalid(&format!("unknown ScriptClass {}", class))),
+                        };
+                    }
+                    if let Some(order) = find_string(&global.annotations, "ScriptOrder") {
+                        script_order = match order.to_ascii_lowercase().as_str() {
+                            "standard" => ScriptOrder::Standard,
+                            "preprocess" => ScriptOrder::PreProcess,
+                            "postprocess" => ScriptOrder::PostProcess,
+                            "dependsonscriptexternal" => ScriptOrder::DependsOnScriptExternal,
+                            _ => return Err(invalid(&format!("unknown ScriptOrder {}", order))),
+                        };
+                    }
+                }
+                FxSemantic::OffscreenRenderTarget
+                | FxSemantic::RenderColorTarget
+                | FxSemantic::RenderDepthStencilTarget => {
+                    render_targets.push(parse_render_target(
+                        &global.name,
+                        semantic,
+                        &global.annotations,
+                    ));
+                }
+                semantic => parameters.push(FxParameter {
+                    name: global.name.clone(),
+                    semantic,
+                    object: find_string(&global.annotations, "Object")
+                        .map(|object| object.to_ascii_lowercase()),
+                }),
+            }
+        }
+        let techniques = module
+            .techniques
+            .iter()
+            .map(|decl| FxTechnique {
+                name: decl.name.clone(),
+                typ: parse_technique_type(
+                    &find_string(&decl.annotations, "MMDPass")
+                        .unwrap_or_else(|| "object".to_owned())
+                        .to_ascii_lowercase(),
+                ),
+                use_texture: find_bool(&decl.annotations, "UseTexture"),
+                use_sphere_map: find_bool(&decl.annotations, "UseSphereMap"),
+                use_toon: find_bool(&decl.annotations, "UseToon"),
+                subset: find_string(&decl.annotations, "Subset"),
+                script: find_string(&decl.annotations, "Script"),
+                passes: decl.passes.iter().map(parse_pass).collect(),
+            })
+            .collect();
+        Ok(Self {
+            script_class,
+            script_order,
+            parameters,
+            render_targets,
+            techniques,
+            path: path.to_owned(),
+            module,
+        })
+    }
+
+    /// Translates the techniques into WGSL sources sharing one set of parameters and textures.
+    pub fn translate(&self) -> Result<Translation, MdanceioError> {
+        wgsl::translate(self)
+    }
+
+    /// Technique drawn for `typ`, preferring the one using the most material textures.
+    pub fn find_technique(&self, typ: TechniqueType) -> Option<&FxTechnique> {
+        let score = |technique: &FxTechnique| {
+            [
+                technique.use_texture,
+                technique.use_sphere_map,
+                technique.use_toon,
+            ]
+            .iter()
+            .filter(|value| value.unwrap_or(true))
+            .count()
+        };
+        self.techniques
+            .iter()
+            .filter(|technique| technique.typ == Some(typ))
+            .rev()
+            .max_by_key(|technique| score(technique))
+    }
+}
+
+fn parse_pass(decl: &PassDecl) -> FxPass {
+    let state = |name: &str| {
+        decl.states
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+    };
+    let shader = |name: &str| match state(name) {
+        Some(StateValue::Shader { entry, .. }) => Some(entry.clone()),
+        _ => None,
+    };
+    let flag = |name: &str| match state(name) {
+        Some(StateValue::Expr(Expr::Bool(value))) => Some(*value),
+        Some(StateValue::Expr(Expr::Number { text, .. })) => Some(text != "0"),
+        _ => None,
+    };
+    let constant = |name: &str| match state(name) {
+        Some(StateValue::Expr(Expr::Ident(value))) => Some(value.to_ascii_uppercase()),
+        _ => None,
+    };
+    let depth_enabled = match (flag("ZEnable"), flag("ZWriteEnable")) {
+        (None, None) => None,
+        (z, write) => Some(z.unwrap_or(true) && write.unwrap_or(true)),
+    };
+    let cull_mode = constant("CullMode").and_then(|mode| match mode.as_str() {
+        "NONE" => Some(EffectCullMode::None),
+        "CW" => Some(EffectCullMode::Front),
+        "CCW" => Some(EffectCullMode::Back),
+        _ => None,
+    });
+    let blend_mode = if flag("AlphaBlendEnable") == Some(false) {
+        Some(EffectBlendMode::Opaque)
+    } else {
+        match (
+            constant("SrcBlend").as_deref(),
+            constant("DestBlend").as_deref(),
+        ) {
+            (_, Some("ONE")) => Some(EffectBlendMode::Add),
+            (_, Some("INVSRCALPHA")) => Some(EffectBlendMode::Alpha),
+            _ => None,
+        }
+    };
+    FxPass {
+        name: decl.name.clone(),
+        vertex_shader: shader("VertexShader"),
+        pixel_shader: shader("PixelShader"),
+        depth_enabled,
+        cull_mode,
+        blend_mode,
+        decl: decl.clone(),
+    }
+}
+
+fn parse_render_target(
+    name: &str,
+    semantic: FxSemantic,
+    annotations: &[Annotation],
+) -> FxRenderTarget {
+    let number = |key: &str| match find_annotation(annotations, key) {
+        Some(AnnotationValue::Number(value)) => Some(*value),
+        _ => None,
+    };
+    let vector = |key: &str| match find_annotation(annotations, key) {
+        Some(AnnotationValue::Vector(values)) => Some(values.clone()),
+        Some(AnnotationValue::Number(value)) => Some(vec![*value]),
+        _ => None,
+    };
+    let size = match (number("Width"), number("Height")) {
+        (Some(width), Some(height)) => FxTargetSize::Dimensions([width as u32, height as u32]),
+        _ => {
+            let ratio = vector("ViewPortRatio")
+                .or_else(|| vector("ViewportRatio"))
+                .unwrap_or_else(|| vec![1f64, 1f64]);
+            FxTargetSize::ViewportRatio([
+                ratio[0] as f32,
+                *ratio.get(1).unwrap_or(&ratio[0]) as f32,
+            ])
+        }
+    };
+    let format = find_string(annotations, "Format").unwrap_or_else(|| {
+        if semantic == FxSemantic::RenderDepthStencilTarget {
+            "D24S8".to_owned()
+        } else {
+            "A8R8G8B8".to_owned()
+        }
+    });
+    let format = format.to_ascii_uppercase();
+    let format = parse_texture_format(format.trim_start_matches("D3DFMT_"));
+    let (color, depth) = if semantic == FxSemantic::RenderDepthStencilTarget {
+        (wgpu::TextureFormat::Rgba8Unorm, Some(format))
+    } else if semantic == FxSemantic::OffscreenRenderTarget {
+        (format, Some(wgpu::TextureFormat::Depth24PlusStencil8))
+    } else {
+        (format, None)
+    };
+    let mut clear_color = [0f32, 0f32, 0f32, 0f32];
+    for (dst, src) in clear_color
+        .iter_mut()
+        .zip(vector("ClearColor").unwrap_or_default())
+    {
+        *dst = src as f32;
+    }
+    FxRenderTarget {
+        name: name.to_owned(),
+        semantic,
+        description: find_string(annotations, "Description").unwrap_or_default(),
+        size,
+        format: RenderFormat {
+            color,
+            depth,
+            sample_count: 1,
+        },
+        clear_color,
+        clear_depth: number("ClearDepth").unwrap_or(1f64) as f32,
+        anti_alias: find_bool(annotations, "AntiAlias").unwrap_or(false),
+        default_effects: find_string(annotations, "DefaultEffect")
+            .unwrap_or_default()
+            .split(';')
+            .filter_map(|entry| entry.split_once('='))
+            .map(|(pattern, effect)| (pattern.trim().to_owned(), effect.trim().to_owned()))
+            .collect(),
+    }
+}
+
+fn parse_texture_format(format: &str) -> wgpu::TextureFormat {
+    match format {
+        "A8R8G8B8" | "X8R8G8B8" | "A8B8G8R8" | "X8B8G8R8" => wgpu::TextureFormat::Rgba8Unorm,
+        "A16B16G16R16F" => wgpu::TextureFormat::Rgba16Float,
+        "A32B32G32R32F" => wgpu::TextureFormat::Rgba32Float,
+        "G16R16F" => wgpu::TextureFormat::Rg16Float,
+        "G32R32F" => wgpu::TextureFormat::Rg32Float,
+        "R16F" => wgpu::TextureFormat::R16Float,
+        "R32F" => wgpu::TextureFormat::R32Float,
+        "D24S8" | "D24X8" => wgpu::TextureFormat::Depth24PlusStencil8,
+        "D16" => wgpu::TextureFormat::Depth16Unorm,
+        "D32" | "D32F_LOCKABLE" => wgpu::TextureFormat::Depth32Float,
+        _ => {
+            log::warn!(
+                "Unsupported render target format {}, using A8R8G8B8",
+                format
+            );
+            wgpu::TextureFormat::Rgba8Unorm
+        }
+    }
+}
+
+fn find_annotation<'a>(annotations: &'a [Annotation], name: &str) -> Option<&'a AnnotationValue> {
+    annotations
+        .iter()
+        .find(|annotation| annotation.name.eq_ignore_ascii_case(name))
+        .map(|annotation| &annotation.value)
+}
+
+fn find_string(annotations: &[Annotation], name: &str) -> Option<String> {
+    match find_annotation(annotations, name) {
+        Some(AnnotationValue::Str(value)) | Some(AnnotationValue::Ident(value)) => {
+            Some(value.clone())
+        }
+        _ => None,
+    }
+}
+
+fn find_bool(annotations: &[Annotation], name: &str) -> Option<bool> {
+    match find_annotation(annotations, name) {
+        Some(AnnotationValue::Bool(value)) => Some(*value),
+        Some(AnnotationValue::Number(value)) => Some(*value != 0f64),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+struct TestLoader(std::collections::HashMap<String, String>);
+
+#[cfg(test)]
+impl AssetLoader for TestLoader {
+    fn load(&mut self, path: &str) -> Result<Vec<u8>, MdanceioError> {
+        self.0
+            .get(path)
+            .map(|text| text.as_bytes().to_vec())
+            .ok_or_else(|| MdanceioError::asset_not_loaded(path, "not found"))
+    }
+}
+
+#[cfg(test)]
+const TEST_EFFECT: &str = r#"
+#include "common.fxsub"
+
+float Script : STANDARDSGLOBAL <
+    string ScriptOutput = "color";
+    string ScriptClass = "object";
+    string ScriptOrder = "standard";
+> = 0.8;
+
+float4x4 WorldViewProjMatrix : WORLDVIEWPROJECTION;
+float4x4 WorldMatrix : WORLD;
+float4x4 LightWorldViewProjMatrix : WORLDVIEWPROJECTION < string Object = "Light"; >;
+float3 LightDirection : DIRECTION < string Object = "Light"; >;
+float3 CameraPosition : POSITION < string Object = "Camera"; >;
+float4 MaterialDiffuse : DIFFUSE < string Object = "Geometry"; >;
+float3 MaterialAmbient : AMBIENT < string Object = "Geometry"; >;
+float3 MaterialEmissive : EMISSIVE < string Object = "Geometry"; >;
+float3 MaterialSpecular : SPECULAR < string Object = "Geometry"; >;
+float SpecularPower : SPECULARPOWER < string Object = "Geometry"; >;
+float3 MaterialToon : TOONCOLOR;
+float4 EdgeColor : EDGECOLOR;
+float3 LightAmbient : AMBIENT < string Object = "Light"; >;
+float3 LightSpecular : SPECULAR < string Object = "Light"; >;
+float4x4 HeadMatrix : CONTROLOBJECT < string Name = "(self)"; string Item = "head"; >;
+float Strength : CONTROLOBJECT < string Name = "controller.pmx"; string Item = "Si"; > = 0.5;
+float3 RimColor = float3(1, 0.5, 0);
+static const float RimPower = RIM_POWER;
+bool spadd;
+bool use_toon;
+bool parthf;
+
+texture MirrorRT : OFFSCREENRENDERTARGET <
+    string Description = "mirror";
+    float2 ViewPortRatio = { 0.5, 0.5 };
+    float4 ClearColor = { 0, 0, 0, 1 };
+    string Format = "D3DFMT_A16B16G16R16F";
+    string DefaultEffect = "self = hide; * = default.fx";
+>;
+sampler MirrorView = sampler_state { texture = <MirrorRT>; };
+
+texture ObjectTexture : MATERIALTEXTURE;
+sampler ObjTexSampler = sampler_state {
+    texture = <ObjectTexture>;
+    MINFILTER = LINEAR;
+    MAGFILTER = LINEAR;
+};
+texture ObjectSphereMap : MATERIALSPHEREMAP;
+sampler ObjSphareSampler = sampler_state { texture = <ObjectSphereMap>; };
+sampler MMDSamp0 : register(s0);
+sampler DefSampler : register(s0);
+
+struct VS_OUTPUT {
+    float4 Pos : POSITION;
+    float2 Tex : TEXCOORD1;
+    float3 Normal : TEXCOORD2;
+    float3 Eye : TEXCOORD3;
+    float2 SpTex : TEXCOORD4;
+    float4 Color : COLOR0;
+    float4 ZCalcTex : TEXCOORD0;
+};
+
+float Rim(float3 normal, float3 eye)
+{
+    float rim = 1 - saturate(dot(normalize(normal), normalize(eye)));
+    return pow(rim, RimPower) * Strength;
+}
+
+void Tint(inout float4 color, float amount)
+{
+    color.rgb = lerp(color.rgb, RimColor, amount);
+}
+
+VS_OUTPUT Basic_VS(float4 Pos : POSITION, float3 Normal : NORMAL, float2 Tex : TEXCOORD0, uniform bool useTexture, uniform bool useSphereMap, uniform bool useToon)
+{
+    VS_OUTPUT Out = (VS_OUTPUT)0;
+    Out.Pos = mul(Pos, WorldViewProjMatrix);
+    Out.Eye = CameraPosition - mul(Pos, WorldMatrix).xyz;
+    Out.Normal = normalize(mul(Normal, (float3x3)WorldMatrix));
+    Out.ZCalcTex = mul(Pos, LightWorldViewProjMatrix);
+    Out.Color.rgb = AmbientColor;
+    if (!useToon) {
+        Out.Color.rgb += max(0, dot(Out.Normal, -LightDirection)) * MaterialDiffuse.rgb;
+    }
+    Out.Color.a = MaterialDiffuse.a;
+    Out.Color = saturate(Out.Color);
+    Out.Tex = Tex;
+    if (useSphereMap) {
+        float2 NormalWV = mul(Out.Normal, (float3x3)WorldMatrix).xy;
+        Out.SpTex.x = NormalWV.x * 0.5f + 0.5f;
+        Out.SpTex.y = NormalWV.y * -0.5f + 0.5f;
+    }
+    return Out;
+}
+
+float4 Basic_PS(VS_OUTPUT IN, uniform bool useTexture, uniform bool useSphereMap, uniform bool useToon) : COLOR0
+{
+    float3 HalfVector = normalize(normalize(IN.Eye) + -LightDirection);
+    float3 Specular = pow(max(0, dot(HalfVector, normalize(IN.Normal))), SpecularPower) * MaterialSpecular * LightSpecular;
+    float4 Color = IN.Color;
+    if (useTexture) {
+        float4 TexColor = tex2D(ObjTexSampler, IN.Tex);
+        Color *= TexColor;
+    }
+    if (useSphereMap) {
+        if (spadd) Color.rgb += tex2D(ObjSphareSampler, IN.SpTex).rgb;
+        else Color.rgb *= tex2D(ObjSphareSampler, IN.SpTex).rgb;
+    }
+    clip(Color.a - 0.01);
+    if (useToon) {
+        float LightNormal = dot(IN.Normal, -LightDirection);
+        Color.rgb *= lerp(MaterialToon, float3(1, 1, 1), saturate(LightNormal * 16 + 0.5));
+    }
+    [unroll] for (int i = 0; i < 2; i++) {
+        Color.rgb += Specular / 2;
+    }
+    Tint(Color, Rim(IN.Normal, IN.Eye));
+    return Color;
+}
+
+float4 Edge_VS(float4 Pos : POSITION) : POSITION
+{
+    return mul(Pos, WorldViewProjMatrix);
+}
+
+float4 Edge_PS() : COLOR
+{
+    return EdgeColor;
+}
+
+struct BufferShadow_OUTPUT {
+    float4 Pos : POSITION;
+    float4 ZCalcTex : TEXCOORD0;
+    float2 Tex : TEXCOORD1;
+};
+
+BufferShadow_OUTPUT BufferShadow_VS(float4 Pos : POSITION, float2 Tex : TEXCOORD0)
+{
+    BufferShadow_OUTPUT Out;
+    Out.Pos = mul(Pos, WorldViewProjMatrix);
+    Out.ZCalcTex = mul(Pos, LightWorldViewProjMatrix);
+    Out.Tex = Tex;
+    return Out;
+}
+
+float4 BufferShadow_PS(BufferShadow_OUTPUT IN) : COLOR
+{
+    float4 Color = MaterialDiffuse;
+    float2 TransTexCoord = float2(1.0f + IN.ZCalcTex.x / IN.ZCalcTex.w, 1.0f - IN.ZCalcTex.y / IN.ZCalcTex.w) * 0.5f;
+    if (any(saturate(TransTexCoord) != TransTexCoord)) {
+        return Color;
+    }
+    float comp = parthf ? 0.5 : 1.0;
+    comp *= 1 - saturate(max(IN.ZCalcTex.z - tex2D(DefSampler, TransTexCoord).r, 0.0f) * 1500 - 0.3f);
+    Color.rgb *= lerp(1, comp, 0.5);
+    return Color;
+}
+
+technique MainTec0 < string MMDPass = "object"; bool UseTexture = false; bool UseSphereMap = false; bool UseToon = false; > {
+    pass DrawObject {
+        VertexShader = compile vs_2_0 Basic_VS(false, false, false);
+        PixelShader = compile ps_2_0 Basic_PS(false, false, false);
+    }
+}
+
+technique MainTec7 < string MMDPass = "object"; bool UseTexture = true; bool UseSphereMap = true; bool UseToon = true; > {
+    pass DrawObject {
+        CullMode = NONE;
+        AlphaBlendEnable = TRUE;
+        SrcBlend = SRCALPHA;
+        DestBlend = INVSRCALPHA;
+        VertexShader = compile vs_3_0 Basic_VS(true, true, true);
+        PixelShader = compile ps_3_0 Basic_PS(true, true, true);
+    }
+}
+
+technique MainTecBS < string MMDPass = "object_ss"; > {
+    pass DrawObject {
+        VertexShader = compile vs_3_0 BufferShadow_VS();
+        PixelShader = compile ps_3_0 BufferShadow_PS();
+    }
+}
+
+technique EdgeTec < string MMDPass = "edge"; > {
+    pass DrawEdge {
+        VertexShader = compile vs_2_0 Edge_VS();
+        PixelShader = compile ps_2_0 Edge_PS();
+    }
+}
+
+technique ShadowTec < string MMDPass = "shadow"; > {}
+"#;
+
+#[cfg(test)]
+fn test_loader() -> TestLoader {
+    TestLoader(std::collections::HashMap::from([
+        ("effects/rim.fx".to_owned(), TEST_EFFECT.to_owned()),
+        (
+            "effects/common.fxsub".to_owned(),
+            "#define RIM_POWER 3\n#ifdef RIM_POWER\n#define AmbientColor (MaterialEmissive + MaterialAmbient * LightAmbient)\n#else\n#error\n#endif\n".to_owned(),
+        ),
+    ]))
+}
+
+#[test]
+fn test_fx_preprocess() {
+    let mut loader = test_loader();
+    let source = "#include \"common.fxsub\"\n#if defined(RIM_POWER) && 0\nbroken\n#elif !defined(MISSING)\nfloat x = RIM_POWER;\n#endif\n";
+    let lexemes = lexer::tokenize(source, "effects/rim.fx", &mut loader).unwrap();
+    let tokens = lexemes
+        .iter()
+        .map(|lexeme| &lexeme.token)
+        .collect::<Vec<_>>();
+    assert_eq!(tokens[0], &lexer::Token::Ident("float".to_owned()));
+    assert_eq!(
+        tokens[3],
+        &lexer::Token::Number {
+            text: "3".to_owned(),
+            float: false
+        }
+    );
+    assert_eq!(lexemes[3].line, 5);
+    assert!(lexer::tokenize("#define F(x) x\n", "a.fx", &mut loader).is_err());
+    assert!(lexer::tokenize("#ifdef A\n", "a.fx", &mut loader).is_err());
+    assert!(lexer::tokenize("#include \"missing.fx\"\n", "a.fx", &mut loader).is_err());
+}
+
+#[test]
+fn test_fx_effect_parse() {
+    let mut loader = test_loader();
+    let effect = FxEffect::parse(TEST_EFFECT, "effects/rim.fx", &mut loader).unwrap();
+    assert_eq!(effect.script_class, ScriptClass::Object);
+    assert_eq!(effect.script_order, ScriptOrder::Standard);
+    assert_eq!(effect.techniques.len(), 5);
+    assert_eq!(effect.techniques[2].typ, Some(TechniqueType::ObjectSs));
+    assert!(effect.techniques[4].passes.is_empty());
+    let technique = effect.find_technique(TechniqueType::Object).unwrap();
+    assert_eq!(technique.name, "MainTec7");
+    let pass = &technique.passes[0];
+    assert_eq!(pass.vertex_shader.as_deref(), Some("Basic_VS"));
+    assert_eq!(pass.cull_mode, Some(EffectCullMode::None));
+    assert_eq!(pass.blend_mode, Some(EffectBlendMode::Alpha));
+    assert_eq!(pass.depth_enabled, None);
+    let target = &effect.render_targets[0];
+    assert_eq!(target.name, "MirrorRT");
+    assert_eq!(target.size, FxTargetSize::ViewportRatio([0.5, 0.5]));
+    assert_eq!(target.format.color, wgpu::TextureFormat::Rgba16Float);
+    assert_eq!(target.clear_color, [0f32, 0f32, 0f32, 1f32]);
+    assert_eq!(
+        target.default_effects,
+        vec![
+            ("self".to_owned(), "hide".to_owned()),
+            ("*".to_owned(), "default.fx".to_owned())
+        ]
+    );
+    let strength = effect
+        .parameters
+        .iter()
+        .find(|parameter| parameter.name == "Strength")
+        .unwrap();
+    assert_eq!(
+        strength.semantic,
+        FxSemantic::ControlObject {
+            name: "controller.pmx".to_owned(),
+            item: Some("Si".to_owned())
+        }
+    );
+    assert!(FxEffect::parse("technique T { pass P { } ", "a.fx", &mut loader).is_err());
+}
+
+#[test]
+fn test_fx_effect_translate() {
+    let mut loader = test_loader();
+    let package =
+        crate::effect_package::EffectPackage::load_fx("effects/rim.fx", &mut loader).unwrap();
+    let manifest = &package.manifest;
+    assert_eq!(manifest.name, "rim");
+    assert_eq!(manifest.cull_mode, EffectCullMode::None);
+    assert_eq!(
+        manifest
+            .techniques
+            .iter()
+            .map(|(typ, _)| *typ)
+            .collect::<Vec<_>>(),
+        vec![
+            TechniqueType::Object,
+            TechniqueType::ObjectSs,
+            TechniqueType::Edge,
+            TechniqueType::Shadow
+        ]
+    );
+    let parameter = |name: &str| {
+        manifest
+            .parameters
+            .iter()
+            .find(|parameter| parameter.name == name)
+            .map(|parameter| parameter.value)
+    };
+    assert_eq!(manifest.parameters.len(), 2);
+    assert_eq!(parameter("Strength"), Some([0.5f32, 0f32, 0f32, 0f32]));
+    assert_eq!(parameter("RimColor"), Some([1f32, 0.5f32, 0f32, 0f32]));
+    let unsupported = TEST_EFFECT.replace("tex2D(ObjTexSampler", "tex2D(MirrorView");
+    let effect = FxEffect::parse(&unsupported, "effects/rim.fx", &mut loader).unwrap();
+    assert!(effect.translate().is_err());
+}
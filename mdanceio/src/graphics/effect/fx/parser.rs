@@ -0,0 +1,1031 @@
+use crate::error::MdanceioError;
+
+use super::lexer::{Lexeme, Token};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scalar {
+    Bool,
+    Int,
+    Uint,
+    Float,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Type {
+    Void,
+    Scalar(Scalar),
+    Vector(Scalar, u8),
+    /// HLSL `floatRxC`
+    Matrix(u8, u8),
+    Array(Box<Type>, u32),
+    Struct(String),
+    Texture,
+    Sampler,
+    String,
+}
+
+impl Type {
+    pub fn scalar(&self) -> Option<Scalar> {
+        match self {
+            Type::Scalar(scalar) | Type::Vector(scalar, _) => Some(*scalar),
+            Type::Matrix(_, _) => Some(Scalar::Float),
+            _ => None,
+        }
+    }
+
+    /// Number of components of scalars and vectors.
+    pub fn width(&self) -> Option<u8> {
+        match self {
+            Type::Scalar(_) => Some(1),
+            Type::Vector(_, n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// Scalar of `self` widened to `width` components.
+    pub fn vector(&self, width: u8) -> Type {
+        match (self.scalar(), width) {
+            (Some(scalar), 1) => Type::Scalar(scalar),
+            (Some(scalar), width) => Type::Vector(scalar, width),
+            _ => self.clone(),
+        }
+    }
+
+    pub fn with_scalar(&self, scalar: Scalar) -> Type {
+        match self {
+            Type::Vector(_, n) => Type::Vector(scalar, *n),
+            _ => Type::Scalar(scalar),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnnotationValue {
+    Str(String),
+    Number(f64),
+    Bool(bool),
+    Vector(Vec<f64>),
+    Ident(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    pub name: String,
+    pub value: AnnotationValue,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number {
+        text: String,
+        float: bool,
+    },
+    Bool(bool),
+    Ident(String),
+    Unary(&'static str, Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+    Assign(&'static str, Box<Expr>, Box<Expr>),
+    /// `++` and `--` in both prefix and postfix position
+    Step(&'static str, Box<Expr>),
+    Ternary(Box<Expr>, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+    Construct(Type, Vec<Expr>),
+    Cast(Type, Box<Expr>),
+    Member(Box<Expr>, String),
+    Index(Box<Expr>, Box<Expr>),
+    List(Vec<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stmt {
+    Block(Vec<Stmt>),
+    Var {
+        typ: Type,
+        name: String,
+        init: Option<Expr>,
+        is_const: bool,
+    },
+    Expr(Expr),
+    If {
+        cond: Expr,
+        then: Box<Stmt>,
+        otherwise: Option<Box<Stmt>>,
+    },
+    For {
+        init: Vec<Stmt>,
+        cond: Option<Expr>,
+        step: Option<Expr>,
+        body: Box<Stmt>,
+    },
+    While {
+        cond: Expr,
+        body: Box<Stmt>,
+    },
+    Return(Option<Expr>),
+    Discard,
+    Break,
+    Continue,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StateValue {
+    Shader {
+        profile: String,
+        entry: String,
+        args: Vec<Expr>,
+    },
+    Texture(String),
+    Expr(Expr),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlobalVariable {
+    pub line: usize,
+    pub name: String,
+    pub typ: Type,
+    pub is_static: bool,
+    pub is_const: bool,
+    pub semantic: Option<String>,
+    pub annotations: Vec<Annotation>,
+    pub initializer: Option<Expr>,
+    pub sampler_state: Vec<(String, StateValue)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub name: String,
+    pub typ: Type,
+    pub semantic: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructDecl {
+    pub name: String,
+    pub fields: Vec<Field>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamModifier {
+    In,
+    Out,
+    InOut,
+    Uniform,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub name: String,
+    pub typ: Type,
+    pub modifier: ParamModifier,
+    pub semantic: Option<String>,
+    pub default: Option<Expr>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub line: usize,
+    pub name: String,
+    pub return_type: Type,
+    pub semantic: Option<String>,
+    pub params: Vec<Param>,
+    pub body: Vec<Stmt>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PassDecl {
+    pub name: String,
+    pub annotations: Vec<Annotation>,
+    pub states: Vec<(String, StateValue)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TechniqueDecl {
+    pub line: usize,
+    pub name: String,
+    pub annotations: Vec<Annotation>,
+    pub passes: Vec<PassDecl>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Module {
+    pub globals: Vec<GlobalVariable>,
+    pub structs: Vec<StructDecl>,
+    pub functions: Vec<Function>,
+    pub techniques: Vec<TechniqueDecl>,
+}
+
+impl Module {
+    pub fn find_struct(&self, name: &str) -> Option<&StructDecl> {
+        self.structs.iter().find(|decl| decl.name == name)
+    }
+
+    pub fn find_function(&self, name: &str) -> Option<&Function> {
+        self.functions.iter().find(|function| function.name == name)
+    }
+}
+
+pub fn parse(lexemes: &[Lexeme], path: &str) -> Result<Module, MdanceioError> {
+    let mut parser = Parser {
+        lexemes,
+        pos: 0,
+        module: Module::default(),
+    };
+    parser
+        .parse_module()
+        .map_err(|reason| MdanceioError::invalid_fx_effect(path, parser.line(), &reason))?;
+    Ok(parser.module)
+}
+
+type ParseResult<T> = Result<T, String>;
+
+const STORAGE_MODIFIERS: [&str; 11] = [
+    "static",
+    "const",
+    "uniform",
+    "shared",
+    "extern",
+    "volatile",
+    "inline",
+    "row_major",
+    "column_major",
+    "nointerpolation",
+    "precise",
+];
+
+const ASSIGNMENT_OPERATORS: [&str; 6] = ["=", "+=", "-=", "*=", "/=", "%="];
+
+const BINARY_OPERATORS: [&[&str]; 10] = [
+    &["||"],
+    &["&&"],
+    &["|"],
+    &["^"],
+    &["&"],
+    &["==", "!="],
+    &["<", ">", "<=", ">="],
+    &["<<", ">>"],
+    &["+", "-"],
+    &["*", "/", "%"],
+];
+
+struct Parser<'a> {
+    lexemes: &'a [Lexeme],
+    pos: usize,
+    module: Module,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Token> {
+        self.peek_at(0)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&Token> {
+        self.lexemes
+            .get(self.pos + offset)
+            .map(|lexeme| &lexeme.token)
+    }
+
+    fn line(&self) -> usize {
+        self.lexemes
+            .get(self.pos.min(self.lexemes.len().saturating_sub(1)))
+            .map_or(0, |lexeme| lexeme.line)
+    }
+
+    fn next(&mut self) -> ParseResult<Token> {
+        let token = self
+            .peek()
+            .cloned()
+            .ok_or_else(|| "unexpected end of file".to_owned())?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn is_punct(&self, punct: &str) -> bool {
+        matches!(self.peek(), Some(Token::Punct(p)) if *p == punct)
+    }
+
+    fn is_ident(&self, ident: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(name)) if name == ident)
+    }
+
+    fn eat_punct(&mut self, punct: &str) -> bool {
+        if self.is_punct(punct) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_ident(&mut self, ident: &str) -> bool {
+        if self.is_ident(ident) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_punct(&mut self, punct: &str) -> ParseResult<()> {
+        if self.eat_punct(punct) {
+            Ok(())
+        } else {
+            Err(format!("expected {} but found {}", punct, self.describe()))
+        }
+    }
+
+    fn expect_ident(&mut self) -> ParseResult<String> {
+        match self.peek() {
+            Some(Token::Ident(name)) => {
+                let name = name.clone();
+                self.pos += 1;
+                Ok(name)
+            }
+            _ => Err(format!("expected identifier but found {}", self.describe())),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self.peek() {
+            Some(Token::Ident(name)) => name.clone(),
+            Some(Token::Number { text, .. }) => text.clone(),
+            Some(Token::Str(text)) => format!("\"{}\"", text),
+            Some(Token::Punct(punct)) => (*punct).to_owned(),
+            None => "end of file".to_owned(),
+        }
+    }
+
+    fn parse_module(&mut self) -> ParseResult<()> {
+        while self.peek().is_some() {
+            if self.eat_punct(";") {
+                continue;
+            }
+            if self.is_ident("technique") || self.is_ident("technique9") {
+                self.pos += 1;
+                let technique = self.parse_technique()?;
+                self.module.techniques.push(technique);
+            } else if self.is_ident("technique10") || self.is_ident("technique11") {
+                return Err("only Direct3D 9 style techniques are supported".to_owned());
+            } else if self.is_ident("struct") {
+                self.pos += 1;
+                let decl = self.parse_struct()?;
+                self.module.structs.push(decl);
+                self.eat_punct(";");
+            } else {
+                self.parse_global_or_function()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_struct(&mut self) -> ParseResult<StructDecl> {
+        let name = self.expect_ident()?;
+        self.expect_punct("{")?;
+        let mut fields = vec![];
+        while !self.eat_punct("}") {
+            self.skip_modifiers();
+            let typ = self.parse_type()?;
+            loop {
+                let name = self.expect_ident()?;
+                let typ = self.parse_array_suffix(typ.clone())?;
+                let semantic = self.parse_semantic()?;
+                fields.push(Field {
+                    name,
+                    typ,
+                    semantic,
+                });
+                if !self.eat_punct(",") {
+                    break;
+                }
+            }
+            self.expect_punct(";")?;
+        }
+        Ok(StructDecl { name, fields })
+    }
+
+    fn skip_modifiers(&mut self) -> Vec<String> {
+        let mut modifiers = vec![];
+        while let Some(Token::Ident(name)) = self.peek() {
+            if STORAGE_MODIFIERS.contains(&name.as_str()) {
+                modifiers.push(name.clone());
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        modifiers
+    }
+
+    fn parse_global_or_function(&mut self) -> ParseResult<()> {
+        let line = self.line();
+        let modifiers = self.skip_modifiers();
+        let typ = self.parse_type()?;
+        let name = self.expect_ident()?;
+        if self.eat_punct("(") {
+            let function = self.parse_function(line, typ, name)?;
+            self.module.functions.push(function);
+            return Ok(());
+        }
+        let mut name = name;
+        loop {
+            let typ = self.parse_array_suffix(typ.clone())?;
+            let semantic = self.parse_semantic()?;
+            let annotations = self.parse_annotations()?;
+            let mut initializer = None;
+            let mut sampler_state = vec![];
+            if self.eat_punct("=") {
+                if self.eat_ident("sampler_state") {
+                    sampler_state = self.parse_state_block()?;
+                } else {
+                    initializer = Some(self.parse_initializer()?);
+                }
+            }
+            self.module.globals.push(GlobalVariable {
+                line,
+                name,
+                typ,
+                is_static: modifiers.iter().any(|m| m == "static"),
+                is_const: modifiers.iter().any(|m| m == "const"),
+                semantic,
+                annotations,
+                initializer,
+                sampler_state,
+            });
+            if !self.eat_punct(",") {
+                break;
+            }
+            name = self.expect_ident()?;
+        }
+        self.expect_punct(";")
+    }
+
+    fn parse_function(
+        &mut self,
+        line: usize,
+        return_type: Type,
+        name: String,
+    ) -> ParseResult<Function> {
+        let mut params = vec![];
+        if !(self.is_ident("void") && matches!(self.peek_at(1), Some(Token::Punct(")")))) {
+            while !self.is_punct(")") {
+                let mut modifier = ParamModifier::In;
+                loop {
+                    if self.eat_ident("in") {
+                        modifier = ParamModifier::In;
+                    } else if self.eat_ident("out") {
+                        modifier = ParamModifier::Out;
+                    } else if self.eat_ident("inout") {
+                        modifier = ParamModifier::InOut;
+                    } else if self.eat_ident("uniform") {
+                        modifier = ParamModifier::Uniform;
+                    } else if self.skip_modifiers().is_empty() {
+                        break;
+                    }
+                }
+                let typ = self.parse_type()?;
+                let name = self.expect_ident()?;
+                let typ = self.parse_array_suffix(typ)?;
+                let semantic = self.parse_semantic()?;
+                let default = if self.eat_punct("=") {
+                    Some(self.parse_expr()?)
+                } else {
+                    None
+                };
+                params.push(Param {
+                    name,
+                    typ,
+                    modifier,
+                    semantic,
+                    default,
+                });
+                if !self.eat_punct(",") {
+                    break;
+                }
+            }
+        } else {
+            self.pos += 1;
+        }
+        self.expect_punct(")")?;
+        let semantic = self.parse_semantic()?;
+        if self.eat_punct(";") {
+            return Err(format!("function {} has no body", name));
+        }
+        let body = self.parse_block()?;
+        Ok(Function {
+            line,
+            name,
+            return_type,
+            semantic,
+            params,
+            body,
+        })
+    }
+
+    fn parse_technique(&mut self) -> ParseResult<TechniqueDecl> {
+        let line = self.line();
+        let name = match self.peek() {
+            Some(Token::Ident(_)) => self.expect_ident()?,
+            _ => String::default(),
+        };
+        let annotations = self.parse_annotations()?;
+        self.expect_punct("{")?;
+        let mut passes = vec![];
+        while !self.eat_punct("}") {
+            if !self.eat_ident("pass") {
+                return Err(format!("expected pass but found {}", self.describe()));
+            }
+            let name = match self.peek() {
+                Some(Token::Ident(_)) => self.expect_ident()?,
+                _ => String::default(),
+            };
+            let annotations = self.parse_annotations()?;
+            let states = self.parse_state_block()?;
+            passes.push(PassDecl {
+                name,
+                annotations,
+                states,
+            });
+        }
+        Ok(TechniqueDecl {
+            line,
+            name,
+            annotations,
+            passes,
+        })
+    }
+
+    /// Parses `{ Name = value; ... }` of passes and `sampler_state`.
+    fn parse_state_block(&mut self) -> ParseResult<Vec<(String, StateValue)>> {
+        self.expect_punct("{")?;
+        let mut states = vec![];
+        while !self.eat_punct("}") {
+            let name = self.expect_ident()?;
+            if self.eat_punct("[") {
+                self.parse_expr()?;
+                self.expect_punct("]")?;
+            }
+            self.expect_punct("=")?;
+            let value = if self.eat_ident("compile") {
+                let profile = self.expect_ident()?;
+                let entry = self.expect_ident()?;
+                self.expect_punct("(")?;
+                let args = self.parse_arguments()?;
+                StateValue::Shader {
+                    profile,
+                    entry,
+                    args,
+                }
+            } else if self.eat_punct("<") {
+                let texture = self.expect_ident()?;
+                self.expect_punct(">")?;
+                StateValue::Texture(texture)
+            } else if self.eat_punct("(") {
+                let value = self.parse_expr()?;
+                self.expect_punct(")")?;
+                match value {
+                    Expr::Ident(texture) if name.eq_ignore_ascii_case("texture") => {
+                        StateValue::Texture(texture)
+                    }
+                    value => StateValue::Expr(value),
+                }
+            } else {
+                StateValue::Expr(self.parse_expr()?)
+            };
+            self.expect_punct(";")?;
+            states.push((name, value));
+        }
+        Ok(states)
+    }
+
+    fn parse_semantic(&mut self) -> ParseResult<Option<String>> {
+        if !self.eat_punct(":") {
+            return Ok(None);
+        }
+        let name = self.expect_ident()?;
+        if name == "register" {
+            self.expect_punct("(")?;
+            let register = self.expect_ident()?;
+            self.expect_punct(")")?;
+            return Ok(Some(format!("register({})", register)));
+        }
+        Ok(Some(name))
+    }
+
+    fn parse_annotations(&mut self) -> ParseResult<Vec<Annotation>> {
+        let mut annotations = vec![];
+        if !self.eat_punct("<") {
+            return Ok(annotations);
+        }
+        while !self.eat_punct(">") {
+            self.parse_type()?;
+            let name = self.expect_ident()?;
+            self.expect_punct("=")?;
+            let value = match self.next()? {
+                Token::Str(mut text) => {
+                    // adjacent string literals are concatenated
+                    while let Some(Token::Str(next)) = self.peek() {
+                        text.push_str(next);
+                        self.pos += 1;
+                    }
+                    AnnotationValue::Str(text)
+                }
+                Token::Number { text, .. } => AnnotationValue::Number(parse_number(&text)?),
+                Token::Punct("-") => match self.next()? {
+                    Token::Number { text, .. } => AnnotationValue::Number(-parse_number(&text)?),
+                    _ => return Err("expected number after -".to_owned()),
+                },
+                Token::Ident(ident) => match ident.to_ascii_lowercase().as_str() {
+                    "true" => AnnotationValue::Bool(true),
+                    "false" => AnnotationValue::Bool(false),
+                    _ => AnnotationValue::Ident(ident),
+                },
+                Token::Punct("{") => {
+                    let mut values = vec![];
+                    while !self.eat_punct("}") {
+                        let negative = self.eat_punct("-");
+                        match self.next()? {
+                            Token::Number { text, .. } => {
+                                let value = parse_number(&text)?;
+                                values.push(if negative { -value } else { value });
+                            }
+                            _ => return Err("expected number in annotation".to_owned()),
+                        }
+                        self.eat_punct(",");
+                    }
+                    AnnotationValue::Vector(values)
+                }
+                _ => return Err(format!("unsupported value of annotation {}", name)),
+            };
+            self.expect_punct(";")?;
+            annotations.push(Annotation { name, value });
+        }
+        Ok(annotations)
+    }
+
+    fn is_type_name(&self, offset: usize) -> bool {
+        match self.peek_at(offset) {
+            Some(Token::Ident(name)) => {
+                builtin_type(name).is_some() || self.module.find_struct(name).is_some()
+            }
+            _ => false,
+        }
+    }
+
+    fn parse_type(&mut self) -> ParseResult<Type> {
+        let name = self.expect_ident()?;
+        if let Some(typ) = builtin_type(&name) {
+            return Ok(typ);
+        }
+        if self.module.find_struct(&name).is_some() {
+            return Ok(Type::Struct(name));
+        }
+        Err(format!("unknown type {}", name))
+    }
+
+    fn parse_array_suffix(&mut self, typ: Type) -> ParseResult<Type> {
+        if !self.eat_punct("[") {
+            return Ok(typ);
+        }
+        let size = match self.next()? {
+            Token::Number { text, float: false } => text
+                .parse::<u32>()
+                .map_err(|_| format!("invalid array size {}", text))?,
+            _ => return Err("array size must be an integer literal".to_owned()),
+        };
+        self.expect_punct("]")?;
+        Ok(Type::Array(Box::new(typ), size))
+    }
+
+    fn parse_block(&mut self) -> ParseResult<Vec<Stmt>> {
+        self.expect_punct("{")?;
+        let mut stmts = vec![];
+        while !self.eat_punct("}") {
+            self.parse_statement(&mut stmts)?;
+        }
+        Ok(stmts)
+    }
+
+    fn parse_single_statement(&mut self) -> ParseResult<Stmt> {
+        let mut stmts = vec![];
+        self.parse_statement(&mut stmts)?;
+        Ok(if stmts.len() == 1 {
+            stmts.pop().unwrap()
+        } else {
+            Stmt::Block(stmts)
+        })
+    }
+
+    fn parse_statement(&mut self, stmts: &mut Vec<Stmt>) -> ParseResult<()> {
+        // attributes like [unroll] or [loop]
+        if self.is_punct("[") && matches!(self.peek_at(2), Some(Token::Punct("]"))) {
+            self.pos += 3;
+        }
+        if self.is_punct("{") {
+            let block = self.parse_block()?;
+            stmts.push(Stmt::Block(block));
+        } else if self.eat_punct(";") {
+            // empty statement
+        } else if self.eat_ident("if") {
+            self.expect_punct("(")?;
+            let cond = self.parse_expr()?;
+            self.expect_punct(")")?;
+            let then = Box::new(self.parse_single_statement()?);
+            let otherwise = if self.eat_ident("else") {
+                Some(Box::new(self.parse_single_statement()?))
+            } else {
+                None
+            };
+            stmts.push(Stmt::If {
+                cond,
+                then,
+                otherwise,
+            });
+        } else if self.eat_ident("for") {
+            self.expect_punct("(")?;
+            let mut init = vec![];
+            if !self.eat_punct(";") {
+                self.parse_statement(&mut init)?;
+            }
+            let cond = if self.is_punct(";") {
+                None
+            } else {
+                Some(self.parse_expr()?)
+            };
+            self.expect_punct(";")?;
+            let step = if self.is_punct(")") {
+                None
+            } else {
+                Some(self.parse_expr()?)
+            };
+            self.expect_punct(")")?;
+            let body = Box::new(self.parse_single_statement()?);
+            stmts.push(Stmt::For {
+                init,
+                cond,
+                step,
+                body,
+            });
+        } else if self.eat_ident("while") {
+            self.expect_punct("(")?;
+            let cond = self.parse_expr()?;
+            self.expect_punct(")")?;
+            let body = Box::new(self.parse_single_statement()?);
+            stmts.push(Stmt::While { cond, body });
+        } else if self.eat_ident("return") {
+            let value = if self.is_punct(";") {
+                None
+            } else {
+                Some(self.parse_expr()?)
+            };
+            self.expect_punct(";")?;
+            stmts.push(Stmt::Return(value));
+        } else if self.eat_ident("discard") {
+            self.expect_punct(";")?;
+            stmts.push(Stmt::Discard);
+        } else if self.eat_ident("break") {
+            self.expect_punct(";")?;
+            stmts.push(Stmt::Break);
+        } else if self.eat_ident("continue") {
+            self.expect_punct(";")?;
+            stmts.push(Stmt::Continue);
+        } else if self.is_ident("do") || self.is_ident("switch") {
+            return Err(format!("{} statements are not supported", self.describe()));
+        } else {
+            let mut offset = 0;
+            while matches!(self.peek_at(offset), Some(Token::Ident(name)) if STORAGE_MODIFIERS.contains(&name.as_str()))
+            {
+                offset += 1;
+            }
+            if self.is_type_name(offset)
+                && matches!(self.peek_at(offset + 1), Some(Token::Ident(_)))
+            {
+                let is_const = self.skip_modifiers().iter().any(|m| m == "const");
+                let typ = self.parse_type()?;
+                loop {
+                    let name = self.expect_ident()?;
+                    let typ = self.parse_array_suffix(typ.clone())?;
+                    let init = if self.eat_punct("=") {
+                        Some(self.parse_initializer()?)
+                    } else {
+                        None
+                    };
+                    stmts.push(Stmt::Var {
+                        typ,
+                        name,
+                        init,
+                        is_const,
+                    });
+                    if !self.eat_punct(",") {
+                        break;
+                    }
+                }
+            } else {
+                let expr = self.parse_expr()?;
+                stmts.push(Stmt::Expr(expr));
+            }
+            self.expect_punct(";")?;
+        }
+        Ok(())
+    }
+
+    fn parse_initializer(&mut self) -> ParseResult<Expr> {
+        if self.eat_punct("{") {
+            let mut items = vec![];
+            while !self.eat_punct("}") {
+                items.push(self.parse_initializer()?);
+                if !self.eat_punct(",") {
+                    self.expect_punct("}")?;
+                    break;
+                }
+            }
+            Ok(Expr::List(items))
+        } else {
+            self.parse_assignment()
+        }
+    }
+
+    fn parse_arguments(&mut self) -> ParseResult<Vec<Expr>> {
+        let mut args = vec![];
+        while !self.eat_punct(")") {
+            args.push(self.parse_assignment()?);
+            if !self.eat_punct(",") {
+                self.expect_punct(")")?;
+                break;
+            }
+        }
+        Ok(args)
+    }
+
+    fn parse_expr(&mut self) -> ParseResult<Expr> {
+        self.parse_assignment()
+    }
+
+    fn parse_assignment(&mut self) -> ParseResult<Expr> {
+        let lhs = self.parse_ternary()?;
+        for op in ASSIGNMENT_OPERATORS {
+            if self.eat_punct(op) {
+                let rhs = self.parse_assignment()?;
+                return Ok(Expr::Assign(op, Box::new(lhs), Box::new(rhs)));
+            }
+        }
+        Ok(lhs)
+    }
+
+    fn parse_ternary(&mut self) -> ParseResult<Expr> {
+        let cond = self.parse_binary(0)?;
+        if self.eat_punct("?") {
+            let then = self.parse_assignment()?;
+            self.expect_punct(":")?;
+            let otherwise = self.parse_assignment()?;
+            return Ok(Expr::Ternary(
+                Box::new(cond),
+                Box::new(then),
+                Box::new(otherwise),
+            ));
+        }
+        Ok(cond)
+    }
+
+    fn parse_binary(&mut self, level: usize) -> ParseResult<Expr> {
+        if level >= BINARY_OPERATORS.len() {
+            return self.parse_unary();
+        }
+        let mut lhs = self.parse_binary(level + 1)?;
+        'outer: loop {
+            for op in BINARY_OPERATORS[level] {
+                if self.eat_punct(op) {
+                    let rhs = self.parse_binary(level + 1)?;
+                    lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+                    continue 'outer;
+                }
+            }
+            return Ok(lhs);
+        }
+    }
+
+    fn parse_unary(&mut self) -> ParseResult<Expr> {
+        for op in ["-", "+", "!", "~"] {
+            if self.eat_punct(op) {
+                let expr = self.parse_unary()?;
+                return Ok(if op == "+" {
+                    expr
+                } else {
+                    Expr::Unary(op, Box::new(expr))
+                });
+            }
+        }
+        for op in ["++", "--"] {
+            if self.eat_punct(op) {
+                let expr = self.parse_unary()?;
+                return Ok(Expr::Step(op, Box::new(expr)));
+            }
+        }
+        if self.is_punct("(")
+            && self.is_type_name(1)
+            && matches!(self.peek_at(2), Some(Token::Punct(")")))
+        {
+            self.pos += 1;
+            let typ = self.parse_type()?;
+            self.expect_punct(")")?;
+            let expr = self.parse_unary()?;
+            return Ok(Expr::Cast(typ, Box::new(expr)));
+        }
+        self.parse_postfix()
+    }
+
+    fn parse_postfix(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.parse_primary()?;
+        loop {
+            if self.eat_punct(".") {
+                let member = self.expect_ident()?;
+                expr = Expr::Member(Box::new(expr), member);
+            } else if self.eat_punct("[") {
+                let index = self.parse_expr()?;
+                self.expect_punct("]")?;
+                expr = Expr::Index(Box::new(expr), Box::new(index));
+            } else if self.eat_punct("++") {
+                expr = Expr::Step("++", Box::new(expr));
+            } else if self.eat_punct("--") {
+                expr = Expr::Step("--", Box::new(expr));
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn parse_primary(&mut self) -> ParseResult<Expr> {
+        if self.is_type_name(0) && matches!(self.peek_at(1), Some(Token::Punct("("))) {
+            let typ = self.parse_type()?;
+            self.expect_punct("(")?;
+            let args = self.parse_arguments()?;
+            return Ok(Expr::Construct(typ, args));
+        }
+        match self.next()? {
+            Token::Number { text, float } => Ok(Expr::Number { text, float }),
+            Token::Ident(name) => match name.as_str() {
+                "true" | "TRUE" => Ok(Expr::Bool(true)),
+                "false" | "FALSE" => Ok(Expr::Bool(false)),
+                _ if self.eat_punct("(") => {
+                    let args = self.parse_arguments()?;
+                    Ok(Expr::Call(name, args))
+                }
+                _ => Ok(Expr::Ident(name)),
+            },
+            Token::Punct("(") => {
+                let expr = self.parse_expr()?;
+                self.expect_punct(")")?;
+                Ok(expr)
+            }
+            Token::Punct("{") => {
+                self.pos -= 1;
+                self.parse_initializer()
+            }
+            token => Err(format!("unexpected {:?}", token)),
+        }
+    }
+}
+
+fn parse_number(text: &str) -> ParseResult<f64> {
+    text.parse::<f64>()
+        .map_err(|_| format!("invalid number {}", text))
+}
+
+fn builtin_type(name: &str) -> Option<Type> {
+    let (scalar, dimensions) = [
+        ("float", Scalar::Float),
+        ("half", Scalar::Float),
+        ("double", Scalar::Float),
+        ("int", Scalar::Int),
+        ("uint", Scalar::Uint),
+        ("dword", Scalar::Uint),
+        ("bool", Scalar::Bool),
+    ]
+    .iter()
+    .find_map(|(prefix, scalar)| name.strip_prefix(prefix).map(|rest| (*scalar, rest)))
+    .unwrap_or((Scalar::Float, name));
+    if dimensions.len() != name.len() {
+        let digits = dimensions.as_bytes();
+        return match digits {
+            [] => Some(Type::Scalar(scalar)),
+            [n @ b'1'..=b'4'] => Some(if *n == b'1' {
+                Type::Scalar(scalar)
+            } else {
+                Type::Vector(scalar, n - b'0')
+            }),
+            [r @ b'2'..=b'4', b'x', c @ b'2'..=b'4'] if scalar == Scalar::Float => {
+                Some(Type::Matrix(r - b'0', c - b'0'))
+            }
+            _ => None,
+        };
+    }
+    match name {
+        "void" => Some(Type::Void),
+        "texture" | "texture2D" | "Texture" | "Texture2D" | "texture3D" | "textureCUBE" => {
+            Some(Type::Texture)
+        }
+        "sampler" | "sampler2D" | "sampler3D" | "samplerCUBE" | "sampler_state" => {
+            Some(Type::Sampler)
+        }
+        "string" => Some(Type::String),
+        _ => None,
+    }
+}
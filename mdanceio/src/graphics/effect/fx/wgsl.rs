@@ -0,0 +1,1903 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::{
+    effect_package::{
+        EffectBlendMode, EffectCullMode, EffectManifest, EffectParameter, EffectTexture,
+        TechniqueType,
+    },
+    error::MdanceioError,
+};
+
+use super::{
+    find_string,
+    parser::{
+        Expr, Function, GlobalVariable, Module, ParamModifier, Scalar, StateValue, Stmt, Type,
+    },
+    FxEffect, FxPass, FxSemantic,
+};
+
+/// WGSL sources of an `.fx` effect sharing one set of parameters and textures.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Translation {
+    pub shaders: HashMap<TechniqueType, String>,
+    pub parameters: Vec<EffectParameter>,
+    /// Sources are the `ResourceName`s relative to the effect file
+    pub textures: Vec<EffectTexture>,
+    pub depth_enabled: bool,
+    pub cull_mode: EffectCullMode,
+    pub blend_mode: EffectBlendMode,
+}
+
+pub fn translate(effect: &FxEffect) -> Result<Translation, MdanceioError> {
+    let unsupported = |reason: &str| MdanceioError::unsupported_fx_effect(&effect.path, reason);
+    if effect.script_class == super::ScriptClass::Scene {
+        return Err(unsupported("scene effects are not supported"));
+    }
+    let mut context = Context {
+        module: &effect.module,
+        globals: effect
+            .module
+            .globals
+            .iter()
+            .enumerate()
+            .map(|(idx, global)| (global.name.as_str(), (idx, classify(global))))
+            .collect(),
+        parameters: vec![],
+        textures: vec![],
+    };
+    let mut shaders = HashMap::new();
+    for typ in [
+        TechniqueType::Object,
+        TechniqueType::ObjectSs,
+        TechniqueType::Edge,
+        TechniqueType::Shadow,
+        TechniqueType::Zplot,
+    ] {
+        let Some(technique) = effect.find_technique(typ) else {
+            continue;
+        };
+        if technique.passes.len() > 1 {
+            log::warn!(
+                "Only the first pass of technique {} is translated",
+                technique.name
+            );
+        }
+        let source = match technique.passes.first() {
+            Some(pass) => ShaderBuilder::new(&mut context)
+                .build(pass)
+                .map_err(|reason| {
+                    unsupported(&format!("technique {}: {}", technique.name, reason))
+                })?,
+            None => HIDDEN_SHADER.to_owned(),
+        };
+        shaders.insert(typ, source);
+    }
+    if !shaders.contains_key(&TechniqueType::Object) {
+        return Err(unsupported("object technique is required"));
+    }
+    let pass = effect
+        .find_technique(TechniqueType::Object)
+        .and_then(|technique| technique.passes.first());
+    Ok(Translation {
+        shaders,
+        parameters: context.parameters,
+        textures: context.textures,
+        depth_enabled: pass.and_then(|pass| pass.depth_enabled).unwrap_or(true),
+        cull_mode: pass
+            .and_then(|pass| pass.cull_mode)
+            .unwrap_or(EffectCullMode::Material),
+        blend_mode: pass
+            .and_then(|pass| pass.blend_mode)
+            .unwrap_or(EffectBlendMode::Model),
+    })
+}
+
+type TranslateResult<T> = Result<T, String>;
+
+/// How a global variable of the effect is provided to the translated shader.
+#[derive(Debug, Clone, PartialEq)]
+enum GlobalKind {
+    /// Expression over the uniforms of the built-in shaders
+    Builtin(&'static str, Type),
+    Parameter,
+    Private,
+    MaterialTexture(&'static str),
+    ResourceTexture(String),
+    ShadowSampler,
+    Sampler(String),
+    Ignored,
+    Unsupported(String),
+}
+
+fn classify(global: &GlobalVariable) -> GlobalKind {
+    let Some(semantic) = &global.semantic else {
+        return match &global.typ {
+            Type::Texture => match find_string(&global.annotations, "ResourceName") {
+                Some(source) => GlobalKind::ResourceTexture(source),
+                None => {
+                    GlobalKind::Unsupported(format!("texture {} has no ResourceName", global.name))
+                }
+            },
+            Type::Sampler => global
+                .sampler_state
+                .iter()
+                .find_map(|(key, value)| match value {
+                    StateValue::Texture(texture) if key.eq_ignore_ascii_case("texture") => {
+                        Some(GlobalKind::Sampler(texture.clone()))
+                    }
+                    _ => None,
+                })
+                .unwrap_or_else(|| {
+                    GlobalKind::Unsupported(format!("sampler {} has no texture", global.name))
+                }),
+            Type::String => GlobalKind::Ignored,
+            Type::Scalar(Scalar::Bool) if !global.is_static => match material_flag(&global.name) {
+                Some(code) => GlobalKind::Builtin(code, Type::Scalar(Scalar::Bool)),
+                None => GlobalKind::Parameter,
+            },
+            _ if global.is_static || global.is_const => GlobalKind::Private,
+            Type::Scalar(_) | Type::Vector(_, _) => GlobalKind::Parameter,
+            _ => GlobalKind::Private,
+        };
+    };
+    let is_light = find_string(&global.annotations, "Object")
+        .is_some_and(|object| object.eq_ignore_ascii_case("light"));
+    let float4 = |code| GlobalKind::Builtin(code, Type::Vector(Scalar::Float, 4));
+    let float3 = |code| GlobalKind::Builtin(code, Type::Vector(Scalar::Float, 3));
+    let matrix = |code| GlobalKind::Builtin(code, Type::Matrix(4, 4));
+    match FxSemantic::parse(semantic, &global.annotations) {
+        FxSemantic::World => matrix("model_uniform.model_matrix"),
+        FxSemantic::WorldView if !is_light => matrix("model_uniform.model_view_matrix"),
+        FxSemantic::WorldViewProjection if is_light => {
+            matrix("model_uniform.light_view_projection_matrix")
+        }
+        FxSemantic::WorldViewProjection => matrix("model_uniform.model_view_projection_matrix"),
+        FxSemantic::Position if !is_light => float3("model_uniform.camera_position.xyz"),
+        FxSemantic::Direction if is_light => float3("model_uniform.light_direction.xyz"),
+        FxSemantic::Diffuse if is_light => float4("vec4<f32>(0.0, 0.0, 0.0, 1.0)"),
+        FxSemantic::Ambient | FxSemantic::Specular if is_light => {
+            float4("model_uniform.light_color")
+        }
+        FxSemantic::Diffuse | FxSemantic::Ambient => float4("material_uniform.diffuse"),
+        FxSemantic::Emissive => float4("material_uniform.ambient"),
+        FxSemantic::Specular => float3("material_uniform.specular.xyz"),
+        FxSemantic::SpecularPower => {
+            GlobalKind::Builtin("material_uniform.specular.w", Type::Scalar(Scalar::Float))
+        }
+        FxSemantic::ToonColor => float4(
+            "textureSampleLevel(toon_texture, toon_texture_sampler, vec2<f32>(1.0, 1.0), 0.0)",
+        ),
+        FxSemantic::EdgeColor => float4("material_uniform.edge_color"),
+        FxSemantic::MaterialTexture => GlobalKind::MaterialTexture("diffuse_texture"),
+        FxSemantic::MaterialSphereMap => GlobalKind::MaterialTexture("sphere_map_texture"),
+        FxSemantic::MaterialToonTexture => GlobalKind::MaterialTexture("toon_texture"),
+        FxSemantic::ControlObject { .. } if global.typ.width().is_some() => GlobalKind::Parameter,
+        FxSemantic::OffscreenRenderTarget
+        | FxSemantic::RenderColorTarget
+        | FxSemantic::RenderDepthStencilTarget => {
+            GlobalKind::Unsupported(format!("render target {} is not supported", global.name))
+        }
+        FxSemantic::StandardsGlobal => GlobalKind::Ignored,
+        FxSemantic::Register(register) if register == "s0" => GlobalKind::ShadowSampler,
+        _ => GlobalKind::Unsupported(format!(
+            "semantic {} of {} is not supported",
+            semantic, global.name
+        )),
+    }
+}
+
+/// Flags MMD sets on each material through globals of these names.
+fn material_flag(name: &str) -> Option<&'static str> {
+    match name {
+        "use_texture" => Some("(material_uniform.use_texture_sampler.x != 0.0)"),
+        "use_spheremap" => Some("(material_uniform.use_texture_sampler.y != 0.0)"),
+        "use_toon" => Some("(material_uniform.use_texture_sampler.z != 0.0)"),
+        "use_subtexture" => Some("(material_uniform.sphere_texture_type.y != 0.0)"),
+        "spadd" => Some("(material_uniform.sphere_texture_type.z != 0.0)"),
+        "parthf" => Some("(model_uniform.shadow_map_size.w == 2.0)"),
+        "transp" => Some("false"),
+        _ => None,
+    }
+}
+
+/// Numbers of a constant initializer, used as the initial value of a parameter.
+fn constant_value(expr: Option<&Expr>) -> [f32; 4] {
+    fn flatten(expr: &Expr, values: &mut Vec<f32>) {
+        match expr {
+            Expr::Number { text, .. } => values.push(text.parse().unwrap_or_default()),
+            Expr::Bool(value) => values.push(if *value { 1f32 } else { 0f32 }),
+            Expr::Unary("-", inner) => {
+                let start = values.len();
+                flatten(inner, values);
+                values[start..]
+                    .iter_mut()
+                    .for_each(|value| *value = -*value);
+            }
+            Expr::Construct(_, items) | Expr::List(items) => {
+                items.iter().for_each(|item| flatten(item, values))
+            }
+            _ => log::warn!("Initial value {:?} of a parameter is not constant", expr),
+        }
+    }
+    let mut values = vec![];
+    if let Some(expr) = expr {
+        flatten(expr, &mut values);
+    }
+    let mut result = [0f32; 4];
+    for (dst, src) in result.iter_mut().zip(values) {
+        *dst = src;
+    }
+    result
+}
+
+struct Context<'a> {
+    module: &'a Module,
+    globals: HashMap<&'a str, (usize, GlobalKind)>,
+    parameters: Vec<EffectParameter>,
+    textures: Vec<EffectTexture>,
+}
+
+impl<'a> Context<'a> {
+    fn parameter_slot(&mut self, global: &GlobalVariable) -> TranslateResult<usize> {
+        if let Some(slot) = self.parameters.iter().position(|p| p.name == global.name) {
+            return Ok(slot);
+        }
+        if self.parameters.len() >= EffectManifest::MAXIMUM_PARAMETERS {
+            return Err(format!(
+                "at most {} parameters are supported",
+                EffectManifest::MAXIMUM_PARAMETERS
+            ));
+        }
+        self.parameters.push(EffectParameter {
+            name: global.name.clone(),
+            value: constant_value(global.initializer.as_ref()),
+        });
+        Ok(self.parameters.len() - 1)
+    }
+
+    fn texture_slot(&mut self, name: &str, source: &str) -> TranslateResult<usize> {
+        if let Some(slot) = self.textures.iter().position(|t| t.name == name) {
+            return Ok(slot);
+        }
+        if self.textures.len() >= EffectManifest::MAXIMUM_TEXTURES {
+            return Err(format!(
+                "at most {} textures are supported",
+                EffectManifest::MAXIMUM_TEXTURES
+            ));
+        }
+        self.textures.push(EffectTexture {
+            name: name.to_owned(),
+            source: source.to_owned(),
+        });
+        Ok(self.textures.len() - 1)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Value {
+    code: String,
+    typ: Type,
+}
+
+impl Value {
+    fn new(code: String, typ: Type) -> Self {
+        Self { code, typ }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Local {
+    typ: Type,
+    is_pointer: bool,
+}
+
+/// Translates the entry points of one pass and everything they reference into a WGSL module.
+struct ShaderBuilder<'a, 'b> {
+    context: &'b mut Context<'a>,
+    module: &'a Module,
+    functions: Vec<String>,
+    requested: Vec<&'a Function>,
+    structs: BTreeSet<String>,
+    privates: BTreeSet<usize>,
+    uses_parameters: bool,
+    uses_effect_sampler: bool,
+    textures: BTreeSet<usize>,
+    scopes: Vec<HashMap<String, Local>>,
+    return_type: Type,
+    temporaries: usize,
+}
+
+impl<'a, 'b> ShaderBuilder<'a, 'b> {
+    fn new(context: &'b mut Context<'a>) -> Self {
+        let module = context.module;
+        Self {
+            context,
+            module,
+            functions: vec![],
+            requested: vec![],
+            structs: BTreeSet::new(),
+            privates: BTreeSet::new(),
+            uses_parameters: false,
+            uses_effect_sampler: false,
+            textures: BTreeSet::new(),
+            scopes: vec![],
+            return_type: Type::Void,
+            temporaries: 0,
+        }
+    }
+
+    fn build(mut self, pass: &FxPass) -> TranslateResult<String> {
+        let shader = |name: &str| {
+            pass.decl
+                .states
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .and_then(|(_, value)| match value {
+                    StateValue::Shader { entry, args, .. } => Some((entry.as_str(), args)),
+                    _ => None,
+                })
+                .ok_or_else(|| format!("pass {} has no {}", pass.name, name))
+        };
+        let (vertex_entry, vertex_args) = shader("VertexShader")?;
+        let (pixel_entry, pixel_args) = shader("PixelShader")?;
+        let vertex_function = self.request_function(vertex_entry)?;
+        let pixel_function = self.request_function(pixel_entry)?;
+        let (varyings, vertex_main) = self.vertex_main(vertex_function, vertex_args)?;
+        let fragment_main = self.fragment_main(pixel_function, pixel_args, &varyings)?;
+        let mut translated = 0;
+        let mut initialized = BTreeSet::new();
+        let mut initializers = vec![];
+        loop {
+            if translated < self.requested.len() {
+                let function = self.requested[translated];
+                translated += 1;
+                let code = self.function(function)?;
+                self.functions.push(code);
+            } else if let Some(idx) = self.privates.difference(&initialized).next().copied() {
+                initialized.insert(idx);
+                let global = &self.module.globals[idx];
+                let typ = self.type_name(&global.typ)?;
+                self.scopes.push(HashMap::new());
+                let init = match &global.initializer {
+                    Some(init) => Some(self.initializer(init, &global.typ)?),
+                    None => None,
+                };
+                self.scopes.pop();
+                initializers.push((idx, typ, init));
+            } else {
+                break;
+            }
+        }
+        initializers.sort_by_key(|(idx, _, _)| *idx);
+        let mut out = String::from(PRELUDE);
+        if self.uses_parameters {
+            out.push_str(
+                "struct EffectParameters {\n    values: array<vec4<f32>, 16>,\n}\n\n@group(3) @binding(0)\nvar<uniform> effect_parameters: EffectParameters;\n\n",
+            );
+        }
+        if self.uses_effect_sampler {
+            out.push_str("@group(3) @binding(1)\nvar effect_sampler: sampler;\n\n");
+        }
+        for slot in &self.textures {
+            out.push_str(&format!(
+                "@group(3) @binding({})\nvar effect_texture{}: texture_2d<f32>;\n\n",
+                slot + 2,
+                slot
+            ));
+        }
+        for name in &self.structs {
+            let decl = self.module.find_struct(name).unwrap();
+            out.push_str(&format!("struct fx_{} {{\n", name));
+            for field in &decl.fields {
+                out.push_str(&format!(
+                    "    fx_{}: {},\n",
+                    field.name,
+                    wgsl_type(&field.typ)?
+                ));
+            }
+            out.push_str("}\n\n");
+        }
+        for (idx, typ, _) in &initializers {
+            out.push_str(&format!(
+                "var<private> fx_{}: {};\n",
+                self.module.globals[*idx].name, typ
+            ));
+        }
+        out.push_str("\nfn init_globals() {\n");
+        for (idx, _, init) in &initializers {
+            if let Some(init) = init {
+                out.push_str(&format!(
+                    "    fx_{} = {};\n",
+                    self.module.globals[*idx].name, init
+                ));
+            }
+        }
+        out.push_str("}\n\n");
+        for function in &self.functions {
+            out.push_str(function);
+            out.push('\n');
+        }
+        out.push_str(&vertex_main);
+        out.push('\n');
+        out.push_str(&fragment_main);
+        Ok(out)
+    }
+
+    fn request_function(&mut self, name: &str) -> TranslateResult<&'a Function> {
+        let mut candidates = self.module.functions.iter().filter(|f| f.name == name);
+        let function = candidates
+            .next()
+            .ok_or_else(|| format!("unknown function {}", name))?;
+        if candidates.next().is_some() {
+            return Err(format!("overloaded function {} is not supported", name));
+        }
+        if !self.requested.iter().any(|f| std::ptr::eq(*f, function)) {
+            self.requested.push(function);
+        }
+        Ok(function)
+    }
+
+    /// Binds the arguments of an entry point in the order HLSL does, semantic parameters first
+    /// and the `compile` arguments for the rest.
+    fn entry_arguments(
+        &mut self,
+        function: &Function,
+        args: &[Expr],
+        mut input: impl FnMut(&str, &Type) -> TranslateResult<Option<Value>>,
+        outputs: &mut Vec<(String, Type, String)>,
+        prologue: &mut String,
+    ) -> TranslateResult<Vec<String>> {
+        let mut codes = vec![];
+        let mut args = args.iter();
+        for (idx, param) in function.params.iter().enumerate() {
+            let semantic = param.semantic.as_deref().map(normalize_semantic);
+            match (param.modifier, semantic) {
+                (ParamModifier::Out, Some(semantic)) | (ParamModifier::InOut, Some(semantic)) => {
+                    let name = format!("out{}", idx);
+                    prologue.push_str(&format!(
+                        "    var {}: {};\n",
+                        name,
+                        self.type_name(&param.typ)?
+                    ));
+                    codes.push(format!("&{}", name));
+                    outputs.push((semantic, param.typ.clone(), name));
+                }
+                (ParamModifier::Out, None) | (ParamModifier::InOut, None) => {
+                    return Err(format!("output {} has no semantic", param.name))
+                }
+                (_, Some(semantic)) => {
+                    let value = match input(&semantic, &param.typ)? {
+                        Some(value) => coerce(value, &param.typ)?,
+                        None => format!("{}()", self.type_name(&param.typ)?),
+                    };
+                    codes.push(value);
+                }
+                (_, None) if self.is_semantic_struct(&param.typ) => {
+                    let Type::Struct(name) = &param.typ else {
+                        unreachable!()
+                    };
+                    let mut fields = vec![];
+                    for field in &self.module.find_struct(name).unwrap().fields {
+                        let semantic = normalize_semantic(field.semantic.as_deref().unwrap());
+                        fields.push(match input(&semantic, &field.typ)? {
+                            Some(value) => coerce(value, &field.typ)?,
+                            None => format!("{}()", self.type_name(&field.typ)?),
+                        });
+                    }
+                    codes.push(format!(
+                        "{}({})",
+                        self.type_name(&param.typ)?,
+                        fields.join(", ")
+                    ));
+                }
+                (_, None) => {
+                    let value = match (args.next(), &param.default) {
+                        (Some(arg), _) | (None, Some(arg)) => self.initializer(arg, &param.typ)?,
+                        (None, None) => return Err(format!("{} has no argument", param.name)),
+                    };
+                    codes.push(value);
+                }
+            }
+        }
+        Ok(codes)
+    }
+
+    fn is_semantic_struct(&self, typ: &Type) -> bool {
+        match typ {
+            Type::Struct(name) => self
+                .module
+                .find_struct(name)
+                .is_some_and(|decl| decl.fields.iter().all(|f| f.semantic.is_some())),
+            _ => false,
+        }
+    }
+
+    /// Semantic, type and access of every value returned by `function` called as `result`.
+    fn entry_results(
+        &mut self,
+        function: &Function,
+    ) -> TranslateResult<Vec<(String, Type, String)>> {
+        match &function.return_type {
+            Type::Void => Ok(vec![]),
+            Type::Struct(name) => {
+                let decl = self.module.find_struct(name).unwrap();
+                decl.fields
+                    .iter()
+                    .map(|field| match &field.semantic {
+                        Some(semantic) => Ok((
+                            normalize_semantic(semantic),
+                            field.typ.clone(),
+                            format!("result.fx_{}", field.name),
+                        )),
+                        None => Err(format!("field {} of {} has no semantic", field.name, name)),
+                    })
+                    .collect()
+            }
+            typ => match &function.semantic {
+                Some(semantic) => Ok(vec![(
+                    normalize_semantic(semantic),
+                    typ.clone(),
+                    "result".to_owned(),
+                )]),
+                None => Err(format!("return value of {} has no semantic", function.name)),
+            },
+        }
+    }
+
+    fn vertex_main(
+        &mut self,
+        function: &Function,
+        args: &[Expr],
+    ) -> TranslateResult<(Vec<(String, Type)>, String)> {
+        self.scopes.push(HashMap::new());
+        let mut prologue = String::new();
+        let mut outputs = vec![];
+        let codes = self.entry_arguments(
+            function,
+            args,
+            |semantic, _| Ok(vertex_input(semantic)),
+            &mut outputs,
+            &mut prologue,
+        )?;
+        self.scopes.pop();
+        let mut results = self.entry_results(function)?;
+        results.append(&mut outputs);
+        let mut position = None;
+        let mut varyings = vec![];
+        for (semantic, typ, code) in results {
+            if semantic == "POSITION" {
+                position = Some(coerce(
+                    Value::new(code, typ),
+                    &Type::Vector(Scalar::Float, 4),
+                )?);
+            } else if matches!(
+                typ.scalar(),
+                Some(Scalar::Float | Scalar::Int | Scalar::Uint)
+            ) && typ.width().is_some()
+            {
+                varyings.push((semantic, typ, code));
+            } else {
+                return Err(format!("varying {} must be a scalar or a vector", semantic));
+            }
+        }
+        let position = position.ok_or("vertex shader does not output POSITION")?;
+        varyings.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut out =
+            String::from("struct FxVertexOutput {\n    @builtin(position) position: vec4<f32>,\n");
+        for (idx, (_, typ, _)) in varyings.iter().enumerate() {
+            let interpolation = if typ.scalar() == Some(Scalar::Float) {
+                ""
+            } else {
+                " @interpolate(flat)"
+            };
+            out.push_str(&format!(
+                "    @location({}){} v{}: {},\n",
+                idx,
+                interpolation,
+                idx,
+                wgsl_type(typ)?
+            ));
+        }
+        out.push_str("}\n\n@vertex\nfn vs_main(vin: FxVertexInput) -> FxVertexOutput {\n    init_globals();\n");
+        out.push_str(&prologue);
+        let call = format!("fx_{}({})", function.name, codes.join(", "));
+        if function.return_type == Type::Void {
+            out.push_str(&format!("    {};\n", call));
+        } else {
+            out.push_str(&format!("    let result = {};\n", call));
+        }
+        out.push_str(&format!(
+            "    var vout: FxVertexOutput;\n    vout.position = {};\n",
+            position
+        ));
+        for (idx, (_, _, code)) in varyings.iter().enumerate() {
+            out.push_str(&format!("    vout.v{} = {};\n", idx, code));
+        }
+        out.push_str("    return vout;\n}\n");
+        Ok((
+            varyings
+                .into_iter()
+                .map(|(semantic, typ, _)| (semantic, typ))
+                .collect(),
+            out,
+        ))
+    }
+
+    fn fragment_main(
+        &mut self,
+        function: &Function,
+        args: &[Expr],
+        varyings: &[(String, Type)],
+    ) -> TranslateResult<String> {
+        self.scopes.push(HashMap::new());
+        let mut prologue = String::new();
+        let mut outputs = vec![];
+        let codes = self.entry_arguments(
+            function,
+            args,
+            |semantic, _| {
+                match semantic {
+                    "VPOS" => {
+                        return Ok(Some(Value::new(
+                            "fin.position.xy".to_owned(),
+                            Type::Vector(Scalar::Float, 2),
+                        )))
+                    }
+                    "POSITION" => {
+                        return Ok(Some(Value::new(
+                            "fin.position".to_owned(),
+                            Type::Vector(Scalar::Float, 4),
+                        )))
+                    }
+                    _ => {}
+                }
+                Ok(varyings
+                    .iter()
+                    .position(|(name, _)| name == semantic)
+                    .map(|idx| Value::new(format!("fin.v{}", idx), varyings[idx].1.clone())))
+            },
+            &mut outputs,
+            &mut prologue,
+        )?;
+        self.scopes.pop();
+        let mut results = self.entry_results(function)?;
+        results.append(&mut outputs);
+        let mut color = None;
+        for (semantic, typ, code) in results {
+            if semantic != "COLOR0" {
+                return Err(format!("pixel shader output {} is not supported", semantic));
+            }
+            color = Some(match typ.width() {
+                Some(3) if typ.scalar() == Some(Scalar::Float) => {
+                    format!("vec4<f32>({}, 1.0)", code)
+                }
+                _ => coerce(Value::new(code, typ), &Type::Vector(Scalar::Float, 4))?,
+            });
+        }
+        let color = color.ok_or("pixel shader does not output COLOR0")?;
+        let mut out = String::from(
+            "@fragment\nfn fs_main(fin: FxVertexOutput) -> @location(0) vec4<f32> {\n    init_globals();\n",
+        );
+        out.push_str(&prologue);
+        let call = format!("fx_{}({})", function.name, codes.join(", "));
+        if function.return_type == Type::Void {
+            out.push_str(&format!("    {};\n", call));
+        } else {
+            out.push_str(&format!("    let result = {};\n", call));
+        }
+        out.push_str(&format!("    return {};\n}}\n", color));
+        Ok(out)
+    }
+
+    fn function(&mut self, function: &Function) -> TranslateResult<String> {
+        let in_function = |reason: String| format!("{} in function {}", reason, function.name);
+        self.scopes.push(HashMap::new());
+        self.return_type = function.return_type.clone();
+        let mut params = vec![];
+        let mut body = String::new();
+        for param in &function.params {
+            let typ = self.type_name(&param.typ).map_err(in_function)?;
+            let is_pointer = matches!(param.modifier, ParamModifier::Out | ParamModifier::InOut);
+            if is_pointer {
+                params.push(format!("fx_{}: ptr<function, {}>", param.name, typ));
+            } else {
+                params.push(format!("p_{}: {}", param.name, typ));
+                body.push_str(&format!("    var fx_{} = p_{};\n", param.name, param.name));
+            }
+            self.declare(
+                &param.name,
+                Local {
+                    typ: param.typ.clone(),
+                    is_pointer,
+                },
+            );
+        }
+        for stmt in &function.body {
+            self.stmt(stmt, 1, &mut body).map_err(in_function)?;
+        }
+        self.scopes.pop();
+        let signature = match &function.return_type {
+            Type::Void => format!("fn fx_{}({})", function.name, params.join(", ")),
+            typ => format!(
+                "fn fx_{}({}) -> {}",
+                function.name,
+                params.join(", "),
+                self.type_name(typ).map_err(in_function)?
+            ),
+        };
+        Ok(format!("{} {{\n{}}}\n", signature, body))
+    }
+
+    fn declare(&mut self, name: &str, local: Local) {
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert(name.to_owned(), local);
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Local> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    /// WGSL name of `typ`, recording the structs it uses.
+    fn type_name(&mut self, typ: &Type) -> TranslateResult<String> {
+        match typ {
+            Type::Struct(name) if self.structs.insert(name.clone()) => {
+                let decl = self.module.find_struct(name).unwrap();
+                for field in &decl.fields {
+                    self.type_name(&field.typ)?;
+                }
+            }
+            Type::Array(inner, _) => {
+                self.type_name(inner)?;
+            }
+            _ => {}
+        }
+        wgsl_type(typ)
+    }
+
+    fn temporary(&mut self) -> String {
+        self.temporaries += 1;
+        format!("tmp{}", self.temporaries)
+    }
+
+    fn block(&mut self, stmt: &Stmt, indent: usize, out: &mut String) -> TranslateResult<()> {
+        self.scopes.push(HashMap::new());
+        let result = match stmt {
+            Stmt::Block(stmts) => stmts
+                .iter()
+                .try_for_each(|stmt| self.stmt(stmt, indent, out)),
+            stmt => self.stmt(stmt, indent, out),
+        };
+        self.scopes.pop();
+        result
+    }
+
+    fn stmt(&mut self, stmt: &Stmt, indent: usize, out: &mut String) -> TranslateResult<()> {
+        let pad = "    ".repeat(indent);
+        match stmt {
+            Stmt::Block(_) => {
+                out.push_str(&format!("{}{{\n", pad));
+                self.block(stmt, indent + 1, out)?;
+                out.push_str(&format!("{}}}\n", pad));
+            }
+            Stmt::Var {
+                typ, name, init, ..
+            } => {
+                let type_name = self.type_name(typ)?;
+                match init {
+                    Some(init) => {
+                        let init = self.initializer(init, typ)?;
+                        out.push_str(&format!(
+                            "{}var fx_{}: {} = {};\n",
+                            pad, name, type_name, init
+                        ));
+                    }
+                    None => out.push_str(&format!("{}var fx_{}: {};\n", pad, name, type_name)),
+                }
+                self.declare(
+                    name,
+                    Local {
+                        typ: typ.clone(),
+                        is_pointer: false,
+                    },
+                );
+            }
+            Stmt::Expr(Expr::Call(name, args)) if name == "clip" && args.len() == 1 => {
+                let value = self.expr(&args[0])?;
+                let width = value.typ.width().ok_or("clip needs a scalar or a vector")?;
+                let value = coerce(value, &Type::Scalar(Scalar::Float).vector(width))?;
+                let cond = if width == 1 {
+                    format!("{} < 0.0", value)
+                } else {
+                    format!("any({} < vec{}<f32>(0.0))", value, width)
+                };
+                out.push_str(&format!(
+                    "{}if ({}) {{\n{}    discard;\n{}}}\n",
+                    pad, cond, pad, pad
+                ));
+            }
+            Stmt::Expr(expr) => {
+                for line in self.expr_stmt(expr)? {
+                    out.push_str(&format!("{}{};\n", pad, line));
+                }
+            }
+            Stmt::If {
+                cond,
+                then,
+                otherwise,
+            } => {
+                let cond = self.condition(cond)?;
+                out.push_str(&format!("{}if ({}) {{\n", pad, cond));
+                self.block(then, indent + 1, out)?;
+                if let Some(otherwise) = otherwise {
+                    out.push_str(&format!("{}}} else {{\n", pad));
+                    self.block(otherwise, indent + 1, out)?;
+                }
+                out.push_str(&format!("{}}}\n", pad));
+            }
+            Stmt::For {
+                init,
+                cond,
+                step,
+                body,
+            } => {
+                self.scopes.push(HashMap::new());
+                out.push_str(&format!("{}{{\n", pad));
+                for stmt in init {
+                    self.stmt(stmt, indent + 1, out)?;
+                }
+                let cond = match cond {
+                    Some(cond) => self.condition(cond)?,
+                    None => String::new(),
+                };
+                let step = match step {
+                    Some(step) => match self.expr_stmt(step)?.as_slice() {
+                        [line] => line.clone(),
+                        _ => return Err("for loop step must be a single assignment".to_owned()),
+                    },
+                    None => String::new(),
+                };
+                out.push_str(&format!("{}    for (; {}; {}) {{\n", pad, cond, step));
+                self.block(body, indent + 2, out)?;
+                out.push_str(&format!("{}    }}\n{}}}\n", pad, pad));
+                self.scopes.pop();
+            }
+            Stmt::While { cond, body } => {
+                let cond = self.condition(cond)?;
+                out.push_str(&format!("{}while ({}) {{\n", pad, cond));
+                self.block(body, indent + 1, out)?;
+                out.push_str(&format!("{}}}\n", pad));
+            }
+            Stmt::Return(None) => out.push_str(&format!("{}return;\n", pad)),
+            Stmt::Return(Some(expr)) => {
+                let return_type = self.return_type.clone();
+                let value = self.initializer(expr, &return_type)?;
+                out.push_str(&format!("{}return {};\n", pad, value));
+            }
+            Stmt::Discard => out.push_str(&format!("{}discard;\n", pad)),
+            Stmt::Break => out.push_str(&format!("{}break;\n", pad)),
+            Stmt::Continue => out.push_str(&format!("{}continue;\n", pad)),
+        }
+        Ok(())
+    }
+
+    /// Lines without the trailing semicolon of an expression used as a statement.
+    fn expr_stmt(&mut self, expr: &Expr) -> TranslateResult<Vec<String>> {
+        match expr {
+            Expr::Assign(op, lhs, rhs) => self.assign(op, lhs, rhs),
+            Expr::Step(op, target) => {
+                let target = self.expr(target)?;
+                let one = match target.typ.scalar() {
+                    Some(Scalar::Float) => "1.0",
+                    Some(Scalar::Uint) => "1u",
+                    _ => "1",
+                };
+                Ok(vec![format!("{} {}= {}", target.code, &op[..1], one)])
+            }
+            Expr::Call(name, _) if self.module.find_function(name).is_some() => {
+                Ok(vec![self.expr(expr)?.code])
+            }
+            expr => Ok(vec![format!("_ = {}", self.expr(expr)?.code)]),
+        }
+    }
+
+    fn assign(&mut self, op: &str, lhs: &Expr, rhs: &Expr) -> TranslateResult<Vec<String>> {
+        if let Expr::Member(base, member) = lhs {
+            let base = self.expr(base)?;
+            if let Some(components) = swizzle(&base.typ, member).filter(|c| c.len() > 1) {
+                let target = Type::Vector(base.typ.scalar().unwrap(), components.len() as u8);
+                let value = self.expr(rhs)?;
+                let value = coerce(value, &target)?;
+                let temporary = self.temporary();
+                let mut lines = vec![format!("let {} = {}", temporary, value)];
+                for (idx, component) in components.chars().enumerate() {
+                    lines.push(format!(
+                        "{}.{} {} {}.{}",
+                        base.code,
+                        component,
+                        op,
+                        temporary,
+                        &"xyzw"[idx..idx + 1]
+                    ));
+                }
+                return Ok(lines);
+            }
+        }
+        let target = self.expr(lhs)?;
+        let value = self.expr(rhs)?;
+        let value = match (&target.typ, &value.typ) {
+            (Type::Matrix(_, _), Type::Matrix(_, _)) if op == "*=" => {
+                return Err("use mul() for matrix products".to_owned())
+            }
+            (Type::Matrix(_, _), Type::Scalar(_)) if op == "*=" || op == "/=" => {
+                coerce(value, &Type::Scalar(Scalar::Float))?
+            }
+            _ => coerce(value, &target.typ)?,
+        };
+        Ok(vec![format!("{} {} {}", target.code, op, value)])
+    }
+
+    fn condition(&mut self, expr: &Expr) -> TranslateResult<String> {
+        let value = self.expr(expr)?;
+        coerce(value, &Type::Scalar(Scalar::Bool))
+    }
+
+    /// Translates `expr` converted to `typ`, accepting initializer lists.
+    fn initializer(&mut self, expr: &Expr, typ: &Type) -> TranslateResult<String> {
+        match (expr, typ) {
+            (Expr::List(items), _) => self.construct(typ, items),
+            (Expr::Number { text, .. }, Type::Struct(_)) if text == "0" => {
+                Ok(format!("{}()", self.type_name(typ)?))
+            }
+            _ => {
+                let value = self.expr(expr)?;
+                coerce(value, typ)
+            }
+        }
+    }
+
+    fn construct(&mut self, typ: &Type, args: &[Expr]) -> TranslateResult<String> {
+        let type_name = self.type_name(typ)?;
+        let codes = match typ {
+            Type::Struct(name) => {
+                let decl = self.module.find_struct(name).unwrap();
+                if decl.fields.len() != args.len() {
+                    return Err(format!("{} needs {} values", name, decl.fields.len()));
+                }
+                decl.fields
+                    .iter()
+                    .zip(args)
+                    .map(|(field, arg)| self.initializer(arg, &field.typ))
+                    .collect::<TranslateResult<Vec<_>>>()?
+            }
+            Type::Array(inner, size) => {
+                if *size as usize != args.len() {
+                    return Err(format!("array needs {} values", size));
+                }
+                args.iter()
+                    .map(|arg| self.initializer(arg, inner))
+                    .collect::<TranslateResult<Vec<_>>>()?
+            }
+            _ => {
+                let values = args
+                    .iter()
+                    .map(|arg| self.expr(arg))
+                    .collect::<TranslateResult<Vec<_>>>()?;
+                return construct_value(typ, values);
+            }
+        };
+        Ok(format!("{}({})", type_name, codes.join(", ")))
+    }
+
+    fn expr(&mut self, expr: &Expr) -> TranslateResult<Value> {
+        match expr {
+            Expr::Number { text, float } => Ok(number(text, *float)),
+            Expr::Bool(value) => Ok(Value::new(value.to_string(), Type::Scalar(Scalar::Bool))),
+            Expr::Ident(name) => self.ident(name),
+            Expr::Unary(op, inner) => {
+                let value = self.expr(inner)?;
+                match *op {
+                    "!" => {
+                        let width = value.typ.width().ok_or("! needs a scalar or a vector")?;
+                        let code = coerce(value, &Type::Scalar(Scalar::Bool).vector(width))?;
+                        Ok(Value::new(
+                            format!("!{}", parenthesize(&code)),
+                            Type::Scalar(Scalar::Bool).vector(width),
+                        ))
+                    }
+                    "-" if value.typ.scalar() == Some(Scalar::Bool) => {
+                        Err("cannot negate bool".to_owned())
+                    }
+                    _ => Ok(Value::new(
+                        format!("{}{}", op, parenthesize(&value.code)),
+                        value.typ,
+                    )),
+                }
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs = self.expr(lhs)?;
+                let rhs = self.expr(rhs)?;
+                binary(op, lhs, rhs)
+            }
+            Expr::Ternary(cond, then, otherwise) => {
+                let then = self.expr(then)?;
+                let otherwise = self.expr(otherwise)?;
+                let typ = unify(&then.typ, &otherwise.typ)?;
+                let cond = self.expr(cond)?;
+                let cond_type = match (cond.typ.width(), typ.width()) {
+                    (Some(1), _) | (_, None) => Type::Scalar(Scalar::Bool),
+                    (_, Some(width)) => Type::Scalar(Scalar::Bool).vector(width),
+                };
+                let cond = coerce(cond, &cond_type)?;
+                Ok(Value::new(
+                    format!(
+                        "select({}, {}, {})",
+                        coerce(otherwise, &typ)?,
+                        coerce(then, &typ)?,
+                        cond
+                    ),
+                    typ,
+                ))
+            }
+            Expr::Call(name, args) => self.call(name, args),
+            Expr::Construct(typ, args) => {
+                let code = self.construct(typ, args)?;
+                Ok(Value::new(code, typ.clone()))
+            }
+            Expr::Cast(typ, inner) => {
+                let code = self.initializer(inner, typ)?;
+                Ok(Value::new(code, typ.clone()))
+            }
+            Expr::Member(base, member) => {
+                let base = self.expr(base)?;
+                if let Some(components) = swizzle(&base.typ, member) {
+                    let scalar = base.typ.scalar().unwrap();
+                    let typ = Type::Scalar(scalar).vector(components.len() as u8);
+                    return Ok(if base.typ.width() == Some(1) {
+                        let code = coerce(base, &typ)?;
+                        Value::new(code, typ)
+                    } else {
+                        Value::new(format!("{}.{}", parenthesize(&base.code), components), typ)
+                    });
+                }
+                match &base.typ {
+                    Type::Struct(name) => {
+                        let field = self
+                            .module
+                            .find_struct(name)
+                            .unwrap()
+                            .fields
+                            .iter()
+                            .find(|field| field.name == *member)
+                            .ok_or_else(|| format!("{} has no field {}", name, member))?;
+                        Ok(Value::new(
+                            format!("{}.fx_{}", parenthesize(&base.code), member),
+                            field.typ.clone(),
+                        ))
+                    }
+                    _ => Err(format!("member {} is not supported", member)),
+                }
+            }
+            Expr::Index(base, index) => {
+                let base = self.expr(base)?;
+                let index = self.expr(index)?;
+                let index = match index.typ {
+                    Type::Scalar(Scalar::Uint) => index.code,
+                    _ => coerce(index, &Type::Scalar(Scalar::Int))?,
+                };
+                let typ = match &base.typ {
+                    Type::Array(inner, _) => (**inner).clone(),
+                    Type::Vector(scalar, _) => Type::Scalar(*scalar),
+                    Type::Matrix(_, columns) => Type::Vector(Scalar::Float, *columns),
+                    _ => return Err("indexing needs an array, a vector or a matrix".to_owned()),
+                };
+                Ok(Value::new(
+                    format!("{}[{}]", parenthesize(&base.code), index),
+                    typ,
+                ))
+            }
+            Expr::Assign(_, _, _) | Expr::Step(_, _) => {
+                Err("assignments inside expressions are not supported".to_owned())
+            }
+            Expr::List(_) => Err("initializer lists need a declared type".to_owned()),
+        }
+    }
+
+    fn ident(&mut self, name: &str) -> TranslateResult<Value> {
+        if let Some(local) = self.lookup(name) {
+            let code = if local.is_pointer {
+                format!("(*fx_{})", name)
+            } else {
+                format!("fx_{}", name)
+            };
+            return Ok(Value::new(code, local.typ.clone()));
+        }
+        let (idx, kind) = self
+            .context
+            .globals
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("unknown identifier {}", name))?;
+        let global = &self.module.globals[idx];
+        match kind {
+            GlobalKind::Builtin(code, typ) => {
+                let code = coerce(Value::new(code.to_owned(), typ), &global.typ)?;
+                Ok(Value::new(code, global.typ.clone()))
+            }
+            GlobalKind::Parameter => {
+                let slot = self.context.parameter_slot(global)?;
+                self.uses_parameters = true;
+                let width = global.typ.width().unwrap();
+                let code = format!(
+                    "effect_parameters.values[{}]{}",
+                    slot,
+                    if width == 4 {
+                        String::new()
+                    } else {
+                        format!(".{}", &"xyzw"[..width as usize])
+                    }
+                );
+                let value = Value::new(code, Type::Scalar(Scalar::Float).vector(width));
+                Ok(Value::new(coerce(value, &global.typ)?, global.typ.clone()))
+            }
+            GlobalKind::Private => {
+                self.privates.insert(idx);
+                Ok(Value::new(format!("fx_{}", name), global.typ.clone()))
+            }
+            GlobalKind::Unsupported(reason) => Err(reason),
+            _ => Err(format!("{} can only be sampled", name)),
+        }
+    }
+
+    /// Texture, sampler and whether it is the layered shadow map for a sampler global.
+    fn sampler(&mut self, expr: &Expr) -> TranslateResult<(String, String, bool)> {
+        let Expr::Ident(name) = expr else {
+            return Err("textures must be sampled through a sampler global".to_owned());
+        };
+        let kind = self
+            .context
+            .globals
+            .get(name.as_str())
+            .map(|(_, kind)| kind.clone());
+        let texture = match kind {
+            Some(GlobalKind::ShadowSampler) => {
+                return Ok((
+                    "shadow_texture".to_owned(),
+                    "shadow_texture_sampler".to_owned(),
+                    true,
+                ))
+            }
+            Some(GlobalKind::Sampler(texture)) => texture,
+            Some(GlobalKind::Unsupported(reason)) => return Err(reason),
+            _ => return Err(format!("{} is not a sampler", name)),
+        };
+        match self
+            .context
+            .globals
+            .get(texture.as_str())
+            .map(|(_, kind)| kind.clone())
+        {
+            Some(GlobalKind::MaterialTexture(texture)) => {
+                Ok((texture.to_owned(), format!("{}_sampler", texture), false))
+            }
+            Some(GlobalKind::ResourceTexture(source)) => {
+                let slot = self.context.texture_slot(&texture, &source)?;
+                self.textures.insert(slot);
+                self.uses_effect_sampler = true;
+                Ok((
+                    format!("effect_texture{}", slot),
+                    "effect_sampler".to_owned(),
+                    false,
+                ))
+            }
+            Some(GlobalKind::Unsupported(reason)) => Err(reason),
+            _ => Err(format!("{} is not a texture", texture)),
+        }
+    }
+
+    fn call(&mut self, name: &str, args: &[Expr]) -> TranslateResult<Value> {
+        let float = |width: u8| Type::Scalar(Scalar::Float).vector(width);
+        match name {
+            "tex2D" | "tex2Dlod" | "tex2Dbias" | "tex2Dproj" => {
+                let [sampler, coord] = args else {
+                    return Err(format!("{} needs 2 arguments", name));
+                };
+                let (texture, sampler, is_layered) = self.sampler(sampler)?;
+                let coord = self.expr(coord)?;
+                let (coord, level) = if name == "tex2D" {
+                    (coerce(coord, &float(2))?, "0.0".to_owned())
+                } else {
+                    let coord = parenthesize(&coerce(coord, &float(4))?);
+                    match name {
+                        "tex2Dlod" => (format!("{}.xy", coord), format!("{}.w", coord)),
+                        "tex2Dproj" => (format!("{}.xy / {}.w", coord, coord), "0.0".to_owned()),
+                        _ => (format!("{}.xy", coord), "0.0".to_owned()),
+                    }
+                };
+                let layer = if is_layered { "0, " } else { "" };
+                return Ok(Value::new(
+                    format!(
+                        "textureSampleLevel({}, {}, {}, {}{})",
+                        texture, sampler, coord, layer, level
+                    ),
+                    float(4),
+                ));
+            }
+            "mul" => {
+                let [lhs, rhs] = args else {
+                    return Err("mul needs 2 arguments".to_owned());
+                };
+                let lhs = self.expr(lhs)?;
+                let rhs = self.expr(rhs)?;
+                return multiply(lhs, rhs);
+            }
+            "clip" => return Err("clip can only be used as a statement".to_owned()),
+            _ => {}
+        }
+        if let Some(value) = self.intrinsic(name, args)? {
+            return Ok(value);
+        }
+        let function = self.request_function(name)?;
+        let mut codes = vec![];
+        for (idx, param) in function.params.iter().enumerate() {
+            let code = match (args.get(idx), &param.default) {
+                (Some(arg), _)
+                    if matches!(param.modifier, ParamModifier::Out | ParamModifier::InOut) =>
+                {
+                    let value = self.expr(arg)?;
+                    if value.typ != param.typ {
+                        return Err(format!(
+                            "output {} of {} has a different type",
+                            param.name, name
+                        ));
+                    }
+                    match value
+                        .code
+                        .strip_prefix("(*")
+                        .and_then(|code| code.strip_suffix(')'))
+                    {
+                        Some(pointer) => pointer.to_owned(),
+                        None => format!("&{}", value.code),
+                    }
+                }
+                (Some(arg), _) | (None, Some(arg)) => self.initializer(arg, &param.typ)?,
+                (None, None) => {
+                    return Err(format!(
+                        "{} needs {} arguments",
+                        name,
+                        function.params.len()
+                    ))
+                }
+            };
+            codes.push(code);
+        }
+        Ok(Value::new(
+            format!("fx_{}({})", name, codes.join(", ")),
+            function.return_type.clone(),
+        ))
+    }
+
+    fn intrinsic(&mut self, name: &str, args: &[Expr]) -> TranslateResult<Option<Value>> {
+        if !COMPONENT_WISE.iter().any(|(hlsl, ..)| *hlsl == name) && !INTRINSICS.contains(&name) {
+            return Ok(None);
+        }
+        let float = |width: u8| Type::Scalar(Scalar::Float).vector(width);
+        let mut values = args
+            .iter()
+            .map(|arg| self.expr(arg))
+            .collect::<TranslateResult<Vec<_>>>()?;
+        let arity = |count: usize| {
+            if values.len() == count {
+                Ok(())
+            } else {
+                Err(format!("{} needs {} arguments", name, count))
+            }
+        };
+        let common = |values: &[Value], keeps_integers: bool| {
+            let mut typ = values[0].typ.clone();
+            for value in &values[1..] {
+                typ = unify(&typ, &value.typ)?;
+            }
+            if !keeps_integers || typ.scalar() == Some(Scalar::Bool) {
+                typ = typ.with_scalar(Scalar::Float);
+            }
+            Ok::<_, String>(typ)
+        };
+        let convert = |values: Vec<Value>, typ: &Type| {
+            values
+                .into_iter()
+                .map(|value| coerce(value, typ))
+                .collect::<TranslateResult<Vec<_>>>()
+        };
+        if let Some((_, wgsl, count, keeps_integers)) =
+            COMPONENT_WISE.iter().find(|(hlsl, ..)| *hlsl == name)
+        {
+            arity(*count)?;
+            let typ = common(&values, *keeps_integers)?;
+            let codes = convert(values, &typ)?;
+            return Ok(Some(Value::new(
+                format!("{}({})", wgsl, codes.join(", ")),
+                typ,
+            )));
+        }
+        let value = match name {
+            "fmod" | "mad" | "rcp" | "log10" => {
+                arity(match name {
+                    "fmod" => 2,
+                    "mad" => 3,
+                    _ => 1,
+                })?;
+                let typ = common(&values, false)?;
+                let codes = convert(values, &typ)?;
+                let code = match name {
+                    "fmod" => format!("({} % {})", codes[0], codes[1]),
+                    "mad" => format!("({} * {} + {})", codes[0], codes[1], codes[2]),
+                    "rcp" => format!("(1.0 / {})", codes[0]),
+                    _ => format!("(log2({}) * 0.30103)", codes[0]),
+                };
+                Value::new(code, typ)
+            }
+            "dot" | "distance" => {
+                arity(2)?;
+                let typ = common(&values, false)?;
+                let codes = convert(values, &typ)?;
+                let code = if typ.width() == Some(1) {
+                    match name {
+                        "dot" => format!("({} * {})", codes[0], codes[1]),
+                        _ => format!("abs({} - {})", codes[0], codes[1]),
+                    }
+                } else {
+                    format!("{}({}, {})", name, codes[0], codes[1])
+                };
+                Value::new(code, float(1))
+            }
+            "length" => {
+                arity(1)?;
+                let typ = common(&values, false)?;
+                let codes = convert(values, &typ)?;
+                let code = if typ.width() == Some(1) {
+                    format!("abs({})", codes[0])
+                } else {
+                    format!("length({})", codes[0])
+                };
+                Value::new(code, float(1))
+            }
+            "cross" => {
+                arity(2)?;
+                let codes = convert(values, &float(3))?;
+                Value::new(format!("cross({}, {})", codes[0], codes[1]), float(3))
+            }
+            "refract" => {
+                arity(3)?;
+                let eta = coerce(values.pop().unwrap(), &float(1))?;
+                let typ = common(&values, false)?;
+                let codes = convert(values, &typ)?;
+                Value::new(format!("refract({}, {}, {})", codes[0], codes[1], eta), typ)
+            }
+            "faceforward" => {
+                arity(3)?;
+                let typ = common(&values, false)?;
+                let codes = convert(values, &typ)?;
+                Value::new(format!("faceForward({})", codes.join(", ")), typ)
+            }
+            "transpose" | "determinant" => {
+                arity(1)?;
+                let value = values.pop().unwrap();
+                let Type::Matrix(rows, columns) = value.typ else {
+                    return Err(format!("{} needs a matrix", name));
+                };
+                let typ = if name == "transpose" {
+                    Type::Matrix(columns, rows)
+                } else {
+                    float(1)
+                };
+                Value::new(format!("{}({})", name, value.code), typ)
+            }
+            "any" | "all" => {
+                arity(1)?;
+                let value = values.pop().unwrap();
+                let width = value
+                    .typ
+                    .width()
+                    .ok_or_else(|| format!("{} needs a vector", name))?;
+                let code = coerce(value, &Type::Scalar(Scalar::Bool).vector(width))?;
+                let code = if width == 1 {
+                    code
+                } else {
+                    format!("{}({})", name, code)
+                };
+                Value::new(code, Type::Scalar(Scalar::Bool))
+            }
+            _ => unreachable!(),
+        };
+        Ok(Some(value))
+    }
+}
+
+/// Intrinsics applied per component as (HLSL name, WGSL name, argument count, keeps integers).
+const COMPONENT_WISE: [(&str, &str, usize, bool); 38] = [
+    ("abs", "abs", 1, true),
+    ("sign", "sign", 1, true),
+    ("floor", "floor", 1, false),
+    ("ceil", "ceil", 1, false),
+    ("round", "round", 1, false),
+    ("trunc", "trunc", 1, false),
+    ("frac", "fract", 1, false),
+    ("sqrt", "sqrt", 1, false),
+    ("rsqrt", "inverseSqrt", 1, false),
+    ("exp", "exp", 1, false),
+    ("exp2", "exp2", 1, false),
+    ("log", "log", 1, false),
+    ("log2", "log2", 1, false),
+    ("sin", "sin", 1, false),
+    ("cos", "cos", 1, false),
+    ("tan", "tan", 1, false),
+    ("asin", "asin", 1, false),
+    ("acos", "acos", 1, false),
+    ("atan", "atan", 1, false),
+    ("sinh", "sinh", 1, false),
+    ("cosh", "cosh", 1, false),
+    ("tanh", "tanh", 1, false),
+    ("saturate", "saturate", 1, false),
+    ("degrees", "degrees", 1, false),
+    ("radians", "radians", 1, false),
+    ("ddx", "dpdx", 1, false),
+    ("ddy", "dpdy", 1, false),
+    ("fwidth", "fwidth", 1, false),
+    ("normalize", "normalize", 1, false),
+    ("min", "min", 2, true),
+    ("max", "max", 2, true),
+    ("pow", "pow", 2, false),
+    ("step", "step", 2, false),
+    ("atan2", "atan2", 2, false),
+    ("reflect", "reflect", 2, false),
+    ("clamp", "clamp", 3, true),
+    ("lerp", "mix", 3, false),
+    ("smoothstep", "smoothstep", 3, false),
+];
+
+const INTRINSICS: [&str; 14] = [
+    "fmod",
+    "mad",
+    "rcp",
+    "log10",
+    "dot",
+    "distance",
+    "length",
+    "cross",
+    "refract",
+    "faceforward",
+    "transpose",
+    "determinant",
+    "any",
+    "all",
+];
+
+fn wgsl_type(typ: &Type) -> TranslateResult<String> {
+    Ok(match typ {
+        Type::Scalar(scalar) => scalar_name(*scalar).to_owned(),
+        Type::Vector(scalar, width) => format!("vec{}<{}>", width, scalar_name(*scalar)),
+        Type::Matrix(rows, columns) => format!("mat{}x{}<f32>", rows, columns),
+        Type::Array(inner, size) => format!("array<{}, {}>", wgsl_type(inner)?, size),
+        Type::Struct(name) => format!("fx_{}", name),
+        Type::Void => return Err("void values are not supported".to_owned()),
+        Type::Texture | Type::Sampler | Type::String => {
+            return Err(format!("{:?} values are not supported", typ))
+        }
+    })
+}
+
+fn scalar_name(scalar: Scalar) -> &'static str {
+    match scalar {
+        Scalar::Bool => "bool",
+        Scalar::Int => "i32",
+        Scalar::Uint => "u32",
+        Scalar::Float => "f32",
+    }
+}
+
+fn scalar_rank(scalar: Scalar) -> u8 {
+    match scalar {
+        Scalar::Bool => 0,
+        Scalar::Int => 1,
+        Scalar::Uint => 2,
+        Scalar::Float => 3,
+    }
+}
+
+fn number(text: &str, float: bool) -> Value {
+    if !float {
+        return Value::new(text.to_owned(), Type::Scalar(Scalar::Int));
+    }
+    let mut code = text.to_owned();
+    if code.starts_with('.') {
+        code.insert(0, '0');
+    }
+    if code.ends_with('.') {
+        code.push('0');
+    } else if !code.contains(['.', 'e', 'E']) {
+        code.push_str(".0");
+    }
+    Value::new(code, Type::Scalar(Scalar::Float))
+}
+
+fn parenthesize(code: &str) -> String {
+    if code
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+        || (code.starts_with('(') && code.ends_with(')') && balanced(&code[1..code.len() - 1]))
+    {
+        code.to_owned()
+    } else {
+        format!("({})", code)
+    }
+}
+
+/// Whether the parentheses of `code` never close more than they open.
+fn balanced(code: &str) -> bool {
+    let mut depth = 0i32;
+    for c in code.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => {}
+        }
+        if depth < 0 {
+            return false;
+        }
+    }
+    depth == 0
+}
+
+/// Normalized `xyzw` components of `member` when it swizzles a scalar or a vector.
+fn swizzle(typ: &Type, member: &str) -> Option<String> {
+    let width = typ.width()? as usize;
+    if member.is_empty() || member.len() > 4 {
+        return None;
+    }
+    member
+        .chars()
+        .map(|c| {
+            let idx = "xyzw".find(c).or_else(|| "rgba".find(c))?;
+            (idx < width).then(|| "xyzw".as_bytes()[idx] as char)
+        })
+        .collect()
+}
+
+/// Semantics named the same way whatever alias or index suffix the effect uses.
+fn normalize_semantic(semantic: &str) -> String {
+    let semantic = semantic.to_ascii_uppercase();
+    match semantic.as_str() {
+        "SV_POSITION" | "POSITION0" => "POSITION".to_owned(),
+        "NORMAL0" => "NORMAL".to_owned(),
+        "COLOR" | "SV_TARGET" | "SV_TARGET0" => "COLOR0".to_owned(),
+        "TEXCOORD" => "TEXCOORD0".to_owned(),
+        _ => semantic,
+    }
+}
+
+fn vertex_input(semantic: &str) -> Option<Value> {
+    let float = |width: u8| Type::Scalar(Scalar::Float).vector(width);
+    let (code, typ) = match semantic {
+        "POSITION" => ("vec4<f32>(vin.position, 1.0)".to_owned(), float(4)),
+        "NORMAL" => ("vin.normal".to_owned(), float(3)),
+        "TEXCOORD0" => ("vin.texcoord0".to_owned(), float(2)),
+        "COLOR0" => ("vin.color0".to_owned(), float(4)),
+        _ => {
+            let idx = semantic
+                .strip_prefix("TEXCOORD")?
+                .parse::<u8>()
+                .ok()
+                .filter(|idx| (1..=4).contains(idx))?;
+            (format!("vin.uva{}", idx), float(4))
+        }
+    };
+    Some(Value::new(code, typ))
+}
+
+fn unify(lhs: &Type, rhs: &Type) -> TranslateResult<Type> {
+    match (lhs.width(), rhs.width(), lhs.scalar(), rhs.scalar()) {
+        (Some(lw), Some(rw), Some(ls), Some(rs)) => {
+            let scalar = if scalar_rank(ls) >= scalar_rank(rs) {
+                ls
+            } else {
+                rs
+            };
+            let width = if lw == 1 {
+                rw
+            } else if rw == 1 {
+                lw
+            } else {
+                lw.min(rw)
+            };
+            Ok(Type::Scalar(scalar).vector(width))
+        }
+        _ if lhs == rhs => Ok(lhs.clone()),
+        _ => Err(format!("{:?} and {:?} cannot be combined", lhs, rhs)),
+    }
+}
+
+/// Converts `value` to `target` the way HLSL does implicitly, truncating vectors and matrices.
+fn coerce(value: Value, target: &Type) -> TranslateResult<String> {
+    if value.typ == *target {
+        return Ok(value.code);
+    }
+    let invalid = || format!("cannot convert {:?} to {:?}", value.typ, target);
+    match (&value.typ, target) {
+        (Type::Matrix(rows, columns), Type::Matrix(target_rows, target_columns))
+            if target_rows <= rows && target_columns <= columns =>
+        {
+            let base = parenthesize(&value.code);
+            let vectors = (0..*target_rows)
+                .map(|row| format!("{}[{}].{}", base, row, &"xyzw"[..*target_columns as usize]))
+                .collect::<Vec<_>>();
+            Ok(format!(
+                "mat{}x{}<f32>({})",
+                target_rows,
+                target_columns,
+                vectors.join(", ")
+            ))
+        }
+        (from, to) if from.width().is_some() && to.width().is_some() => {
+            let (width, target_width) = (from.width().unwrap(), to.width().unwrap());
+            let (scalar, target_scalar) = (from.scalar().unwrap(), to.scalar().unwrap());
+            let (mut code, width) = if width == target_width || width == 1 {
+                (value.code.clone(), width)
+            } else if target_width < width {
+                (
+                    format!(
+                        "{}.{}",
+                        parenthesize(&value.code),
+                        &"xyzw"[..target_width as usize]
+                    ),
+                    target_width,
+                )
+            } else {
+                return Err(invalid());
+            };
+            if scalar != target_scalar {
+                let is_integer_literal = !code.is_empty()
+                    && code
+                        .trim_start_matches('-')
+                        .chars()
+                        .all(|c| c.is_ascii_digit());
+                code = match target_scalar {
+                    Scalar::Float if is_integer_literal => format!("{}.0", code),
+                    Scalar::Uint if is_integer_literal && !code.starts_with('-') => {
+                        format!("{}u", code)
+                    }
+                    _ => format!(
+                        "{}({})",
+                        wgsl_type(&Type::Scalar(target_scalar).vector(width))?,
+                        code
+                    ),
+                };
+            }
+            if width != target_width {
+                code = format!("{}({})", wgsl_type(to)?, code);
+            }
+            Ok(code)
+        }
+        _ => Err(invalid()),
+    }
+}
+
+fn construct_value(typ: &Type, values: Vec<Value>) -> TranslateResult<String> {
+    let type_name = wgsl_type(typ)?;
+    if let [value] = values.as_slice() {
+        if value.typ.width() == Some(1)
+            || typ.width() == Some(1)
+            || matches!(value.typ, Type::Matrix(..))
+        {
+            return coerce(value.clone(), typ);
+        }
+    }
+    let (scalar, width) = match typ {
+        Type::Matrix(rows, columns) => (Scalar::Float, rows * columns),
+        _ => (
+            typ.scalar().ok_or("cannot construct this type")?,
+            typ.width().ok_or("cannot construct this type")?,
+        ),
+    };
+    let mut total = 0;
+    let mut codes = vec![];
+    for value in values {
+        let value_width = value
+            .typ
+            .width()
+            .ok_or_else(|| format!("cannot construct {} from {:?}", type_name, value.typ))?;
+        total += value_width;
+        codes.push(coerce(value, &Type::Scalar(scalar).vector(value_width))?);
+    }
+    if total != width {
+        return Err(format!("{} needs {} components", type_name, width));
+    }
+    Ok(format!("{}({})", type_name, codes.join(", ")))
+}
+
+fn binary(op: &'static str, lhs: Value, rhs: Value) -> TranslateResult<Value> {
+    match op {
+        "&&" | "||" => {
+            let boolean = Type::Scalar(Scalar::Bool);
+            let code = format!(
+                "({} {} {})",
+                coerce(lhs, &boolean)?,
+                op,
+                coerce(rhs, &boolean)?
+            );
+            Ok(Value::new(code, boolean))
+        }
+        "==" | "!=" | "<" | ">" | "<=" | ">=" => {
+            let typ = unify(&lhs.typ, &rhs.typ)?;
+            let width = typ
+                .width()
+                .ok_or("only scalars and vectors can be compared")?;
+            let code = format!("({} {} {})", coerce(lhs, &typ)?, op, coerce(rhs, &typ)?);
+            Ok(Value::new(code, Type::Scalar(Scalar::Bool).vector(width)))
+        }
+        _ => {
+            let is_arithmetic = matches!(op, "+" | "-" | "*" | "/" | "%");
+            let typ = match (&lhs.typ, &rhs.typ) {
+                (Type::Matrix(_, _), Type::Matrix(_, _)) if op == "*" => {
+                    return Err("use mul() for matrix products".to_owned())
+                }
+                (Type::Matrix(_, _), Type::Scalar(_)) if op == "*" || op == "/" => {
+                    let code = format!(
+                        "({} {} {})",
+                        lhs.code,
+                        op,
+                        coerce(rhs, &Type::Scalar(Scalar::Float))?
+                    );
+                    return Ok(Value::new(code, lhs.typ));
+                }
+                (Type::Scalar(_), Type::Matrix(_, _)) if op == "*" => {
+                    let code = format!(
+                        "({} * {})",
+                        coerce(lhs, &Type::Scalar(Scalar::Float))?,
+                        rhs.code
+                    );
+                    return Ok(Value::new(code, rhs.typ));
+                }
+                _ => unify(&lhs.typ, &rhs.typ)?,
+            };
+            let typ = match typ.scalar() {
+                Some(Scalar::Bool) if is_arithmetic => typ.with_scalar(Scalar::Int),
+                Some(Scalar::Float) if !is_arithmetic => {
+                    return Err(format!("{} needs integers", op))
+                }
+                _ => typ,
+            };
+            let code = format!("({} {} {})", coerce(lhs, &typ)?, op, coerce(rhs, &typ)?);
+            Ok(Value::new(code, typ))
+        }
+    }
+}
+
+/// `mul` of HLSL, whose matrices are the transposes of the column major matrices of WGSL.
+fn multiply(lhs: Value, rhs: Value) -> TranslateResult<Value> {
+    let float = |width: u8| Type::Scalar(Scalar::Float).vector(width);
+    match (&lhs.typ, &rhs.typ) {
+        (Type::Matrix(rows, inner), Type::Matrix(inner_rows, columns)) => {
+            let (rows, columns) = (*rows, *columns);
+            let inner = (*inner).min(*inner_rows);
+            let lhs = coerce(lhs, &Type::Matrix(rows, inner))?;
+            let rhs = coerce(rhs, &Type::Matrix(inner, columns))?;
+            Ok(Value::new(
+                format!("({} * {})", rhs, lhs),
+                Type::Matrix(rows, columns),
+            ))
+        }
+        (Type::Scalar(_) | Type::Vector(_, _), Type::Matrix(rows, columns)) => {
+            let (rows, columns) = (*rows, *columns);
+            let vector = coerce(lhs, &float(rows))?;
+            Ok(Value::new(
+                format!("({} * {})", rhs.code, vector),
+                float(columns),
+            ))
+        }
+        (Type::Matrix(rows, columns), Type::Scalar(_) | Type::Vector(_, _)) => {
+            let (rows, columns) = (*rows, *columns);
+            let vector = coerce(rhs, &float(columns))?;
+            Ok(Value::new(
+                format!("({} * {})", vector, lhs.code),
+                float(rows),
+            ))
+        }
+        _ => {
+            let typ = unify(&lhs.typ, &rhs.typ)?;
+            if lhs.typ.width() != Some(1) && rhs.typ.width() != Some(1) {
+                let code = format!("dot({}, {})", coerce(lhs, &typ)?, coerce(rhs, &typ)?);
+                return Ok(Value::new(code, Type::Scalar(typ.scalar().unwrap())));
+            }
+            let code = format!("({} * {})", coerce(lhs, &typ)?, coerce(rhs, &typ)?);
+            Ok(Value::new(code, typ))
+        }
+    }
+}
+
+/// Shader of a technique without passes, whose geometry is clipped before rasterization.
+const HIDDEN_SHADER: &str = "@vertex
+fn vs_main(@location(0) position: vec3<f32>) -> @builtin(position) vec4<f32> {
+    return vec4<f32>(0.0, 0.0, 2.0, 1.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    discard;
+}
+";
+
+const PRELUDE: &str = "struct FxVertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) texcoord0: vec2<f32>,
+    @location(3) uva1: vec4<f32>,
+    @location(4) uva2: vec4<f32>,
+    @location(5) uva3: vec4<f32>,
+    @location(6) uva4: vec4<f32>,
+    @location(7) color0: vec4<f32>,
+}
+
+struct LocalLight {
+    position: vec4<f32>,
+    direction: vec4<f32>,
+    color: vec4<f32>,
+    shadow: vec4<f32>,
+    shadow_matrix: mat4x4<f32>,
+}
+
+struct ModelUniform {
+    model_matrix: mat4x4<f32>,
+    model_view_matrix: mat4x4<f32>,
+    model_view_projection_matrix: mat4x4<f32>,
+    light_view_projection_matrix: mat4x4<f32>,
+    light_color: vec4<f32>,
+    light_direction: vec4<f32>,
+    camera_position: vec4<f32>,
+    shadow_map_size: vec4<f32>,
+    local_light_size: vec4<f32>,
+    local_lights: array<LocalLight, 4>,
+    shadow_cascade_splits: vec4<f32>,
+    shadow_filter: vec4<f32>,
+    shadow_cascade_matrices: array<mat4x4<f32>, 4>,
+}
+
+struct MaterialUniform {
+    ambient: vec4<f32>,
+    diffuse: vec4<f32>,
+    specular: vec4<f32>,
+    edge_color: vec4<f32>,
+    enable_vertex_color: vec4<f32>,
+    diffuse_blend_factor: vec4<f32>,
+    sphere_blend_factor: vec4<f32>,
+    toon_blend_factor: vec4<f32>,
+    use_texture_sampler: vec4<f32>,
+    sphere_texture_type: vec4<f32>,
+    edge_size: f32,
+}
+
+@group(0) @binding(0)
+var diffuse_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var diffuse_texture_sampler: sampler;
+@group(0) @binding(2)
+var sphere_map_texture: texture_2d<f32>;
+@group(0) @binding(3)
+var sphere_map_texture_sampler: sampler;
+@group(0) @binding(4)
+var toon_texture: texture_2d<f32>;
+@group(0) @binding(5)
+var toon_texture_sampler: sampler;
+
+@group(1) @binding(0)
+var<uniform> model_uniform: ModelUniform;
+@group(1) @binding(1)
+var<uniform> material_uniform: MaterialUniform;
+
+@group(2) @binding(0)
+var shadow_texture: texture_2d_array<f32>;
+@group(2) @binding(1)
+var shadow_texture_sampler: sampler;
+
+";
@@ -1,6 +1,6 @@
 use std::num::NonZeroU64;
 
-use crate::model::VertexUnit;
+use crate::{effect_package::EffectManifest, model::VertexUnit};
 
 use super::uniform::{MaterialUniform, ModelUniform};
 
@@ -9,150 +9,300 @@ pub struct RendererLayout {
     pub color_bind_layout: wgpu::BindGroupLayout,
     pub uniform_bind_layout: wgpu::BindGroupLayout,
     pub shadow_bind_layout: wgpu::BindGroupLayout,
+    pub effect_bind_layout: Option<wgpu::BindGroupLayout>,
     pub pipeline_layout: wgpu::PipelineLayout,
     pub vertex_buffer_layout: wgpu::VertexBufferLayout<'static>,
+    /// Positions of the previous frame for motion vectors of the auxiliary technique
+    pub previous_vertex_buffer_layout: wgpu::VertexBufferLayout<'static>,
 }
 
 impl RendererLayout {
     pub fn new(
         device: &wgpu::Device,
     ) -> Self {
+        Self::build(None, device)
+    }
+
+    /// Layout with an extra group for effect parameters, a sampler and `texture_count` textures.
+    pub fn new_with_effect(texture_count: usize, device: &wgpu::Device) -> Self {
+        Self::build(
+            Some(Self::build_effect_bind_layout(texture_count, device)),
+            device,
+        )
+    }
+
+    fn build(effect_bind_layout: Option<wgpu::BindGroupLayout>, device: &wgpu::Device) -> Self {
         let color_bind_layout = Self::build_color_bind_layout(device);
         let uniform_bind_layout = Self::build_uniform_bind_layout(device);
         let shadow_bind_layout = Self::build_shadow_bind_layout(device);
+        let mut bind_group_layouts = vec![
+            &color_bind_layout,
+            &uniform_bind_layout,
+            &shadow_bind_layout,
+        ];
+        if let Some(effect_bind_layout) = &effect_bind_layout {
+            bind_group_layouts.push(effect_bind_layout);
+        }
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("ModelProgramBundle/PipelineLayout"),
-            bind_group_layouts: &[
-                &color_bind_layout,
-                &uniform_bind_layout,
-                &shadow_bind_layout,
-            ],
+            bind_group_layouts: &bind_group_layouts,
             push_constant_ranges: &[],
         });
         let vertex_buffer_layout = Self::build_vertex_buffer_layout(device);
+        let previous_vertex_buffer_layout = Self::build_previous_vertex_buffer_layout();
         Self {
             color_bind_layout,
             uniform_bind_layout,
             shadow_bind_layout,
+            effect_bind_layout,
             pipeline_layout,
             vertex_buffer_layout,
+            previous_vertex_buffer_layout,
         }
     }
 }
 
 impl RendererLayout {
+    /// Attributes of `VertexUnit` bound to the vertex stage of model shaders.
+    pub(crate) const VERTEX_ATTRIBUTES: [wgpu::VertexAttribute; 8] = [
+        wgpu::VertexAttribute {
+            format: wgpu::VertexFormat::Float32x3,
+            offset: 0,
+            shader_location: 0,
+        },
+        wgpu::VertexAttribute {
+            format: wgpu::VertexFormat::Float32x3,
+            offset: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+            shader_location: 1,
+        },
+        wgpu::VertexAttribute {
+            format: wgpu::VertexFormat::Float32x2,
+            offset: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress * 2,
+            shader_location: 2,
+        },
+        wgpu::VertexAttribute {
+            format: wgpu::VertexFormat::Float32x4,
+            offset: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress * 3,
+            shader_location: 3,
+        },
+        wgpu::VertexAttribute {
+            format: wgpu::VertexFormat::Float32x4,
+            offset: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress * 4,
+            shader_location: 4,
+        },
+        wgpu::VertexAttribute {
+            format: wgpu::VertexFormat::Float32x4,
+            offset: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress * 5,
+            shader_location: 5,
+        },
+        wgpu::VertexAttribute {
+            format: wgpu::VertexFormat::Float32x4,
+            offset: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress * 6,
+            shader_location: 6,
+        },
+        wgpu::VertexAttribute {
+            format: wgpu::VertexFormat::Float32x4,
+            offset: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress * 7,
+            shader_location: 7,
+        },
+    ];
+
+    /// Entries of bind group `group` as declared by model shaders, where the effect group holds
+    /// `texture_count` textures.
+    pub(crate) fn bind_group_layout_entries(
+        group: u32,
+        texture_count: usize,
+    ) -> Vec<wgpu::BindGroupLayoutEntry> {
+        match group {
+            0 => Self::color_bind_layout_entries().to_vec(),
+            1 => Self::uniform_bind_layout_entries().to_vec(),
+            2 => Self::shadow_bind_layout_entries().to_vec(),
+            EffectManifest::EFFECT_BIND_GROUP => Self::effect_bind_layout_entries(texture_count),
+            _ => vec![],
+        }
+    }
+
     fn build_color_bind_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
         device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("ModelProgramBundle/BindGroupLayout/Color"),
-            entries: &[
-                wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Texture {
-                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                        multisampled: false,
-                    },
-                    count: None,
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                    count: None,
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 2,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Texture {
-                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                        multisampled: false,
-                    },
-                    count: None,
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 3,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                    count: None,
+            entries: &Self::color_bind_layout_entries(),
+        })
+    }
+
+    fn color_bind_layout_entries() -> [wgpu::BindGroupLayoutEntry; 6] {
+        [
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
                 },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 4,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Texture {
-                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                        multisampled: false,
-                    },
-                    count: None,
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
                 },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 5,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                    count: None,
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 3,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 4,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
                 },
-            ],
-        })
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 5,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ]
     }
 
     fn build_shadow_bind_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
         device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("ModelProgramBundle/BindGroupLayout/Shadow"),
-            entries: &[
-                wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Texture {
-                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
-                        view_dimension: wgpu::TextureViewDimension::D2,
-                        multisampled: false,
-                    },
-                    count: None,
+            entries: &Self::shadow_bind_layout_entries(),
+        })
+    }
+
+    fn shadow_bind_layout_entries() -> [wgpu::BindGroupLayoutEntry; 3] {
+        [
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2Array,
+                    multisampled: false,
                 },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::NonFiltering),
-                    count: None,
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::NonFiltering),
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2Array,
+                    multisampled: false,
                 },
-            ],
+                count: None,
+            },
+        ]
+    }
+
+    fn build_effect_bind_layout(
+        texture_count: usize,
+        device: &wgpu::Device,
+    ) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("ModelProgramBundle/BindGroupLayout/Effect"),
+            entries: &Self::effect_bind_layout_entries(texture_count),
         })
     }
 
+    fn effect_bind_layout_entries(texture_count: usize) -> Vec<wgpu::BindGroupLayoutEntry> {
+        let parameters_size =
+            std::mem::size_of::<[[f32; 4]; EffectManifest::MAXIMUM_PARAMETERS]>() as u64;
+        let mut entries = vec![
+            wgpu::BindGroupLayoutEntry {
+                // EffectParameters
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: NonZeroU64::new(parameters_size),
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ];
+        for idx in 0..texture_count {
+            entries.push(wgpu::BindGroupLayoutEntry {
+                binding: 2 + idx as u32,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            });
+        }
+        entries
+    }
+
     fn build_uniform_bind_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
         device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("ModelProgramBundle/BindGroupLayout/Uniform"),
-            entries: &[
-                wgpu::BindGroupLayoutEntry {
-                    // ModelUniform
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: Some(
-                            NonZeroU64::new(std::mem::size_of::<ModelUniform>() as u64).unwrap(),
-                        ),
-                    },
-                    count: None,
+            entries: &Self::uniform_bind_layout_entries(),
+        })
+    }
+
+    fn uniform_bind_layout_entries() -> [wgpu::BindGroupLayoutEntry; 2] {
+        [
+            wgpu::BindGroupLayoutEntry {
+                // ModelUniform
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: Some(
+                        NonZeroU64::new(std::mem::size_of::<ModelUniform>() as u64).unwrap(),
+                    ),
                 },
-                wgpu::BindGroupLayoutEntry {
-                    // MaterialUniform
-                    binding: 1,
-                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: true,
-                        min_binding_size: Some(
-                            NonZeroU64::new(std::mem::size_of::<MaterialUniform>() as u64).unwrap(),
-                        ),
-                    },
-                    count: None,
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                // MaterialUniform
+                binding: 1,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: Some(
+                        NonZeroU64::new(std::mem::size_of::<MaterialUniform>() as u64).unwrap(),
+                    ),
                 },
-            ],
-        })
+                count: None,
+            },
+        ]
     }
 
     fn build_vertex_buffer_layout(device: &wgpu::Device) -> wgpu::VertexBufferLayout<'static> {
@@ -160,48 +310,19 @@ impl RendererLayout {
         wgpu::VertexBufferLayout {
             array_stride: vertex_size as wgpu::BufferAddress,
             step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &[
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x3,
-                    offset: 0,
-                    shader_location: 0,
-                },
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x3,
-                    offset: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
-                    shader_location: 1,
-                },
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x2,
-                    offset: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress * 2,
-                    shader_location: 2,
-                },
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x4,
-                    offset: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress * 3,
-                    shader_location: 3,
-                },
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x4,
-                    offset: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress * 4,
-                    shader_location: 4,
-                },
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x4,
-                    offset: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress * 5,
-                    shader_location: 5,
-                },
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x4,
-                    offset: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress * 6,
-                    shader_location: 6,
-                },
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x4,
-                    offset: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress * 7,
-                    shader_location: 7,
-                },
-            ],
+            attributes: &Self::VERTEX_ATTRIBUTES,
+        }
+    }
+
+    fn build_previous_vertex_buffer_layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<VertexUnit>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x3,
+                offset: 0,
+                shader_location: 8,
+            }],
         }
     }
 }
@@ -1,9 +1,11 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ScriptClass {
     Object,
     Scene,
     SceneObject,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ScriptOrder {
     DependsOnScriptExternal,
     PreProcess,
@@ -15,12 +17,15 @@ pub enum ScriptOrder {
 pub struct RenderFormat {
     pub color: wgpu::TextureFormat,
     pub depth: Option<wgpu::TextureFormat>,
+    pub sample_count: u32,
 }
 
 mod effect;
+pub mod fx;
 mod layout;
 pub mod render_target;
 pub mod technique;
 mod uniform;
 
 pub use effect::Effect;
+pub(crate) use layout::RendererLayout;
@@ -29,6 +29,8 @@ pub enum DrawType {
     Edge,
     GroundShadow,
     ShadowMap,
+    Normal,
+    Auxiliary,
 }
 
 #[derive(Debug, Clone)]
@@ -121,6 +123,19 @@ impl ScreenRenderTarget {
         self.renderers.remove(&model_handle);
     }
 
+    pub fn set_shadow_bind(
+        &mut self,
+        models: &HashMap<ModelHandle, Model>,
+        shadow_bind: &Arc<wgpu::BindGroup>,
+        device: &wgpu::Device,
+    ) {
+        for (model_handle, renderer) in self.renderers.iter_mut() {
+            if let Some(model) = models.get(model_handle) {
+                renderer.set_shadow_bind(model, shadow_bind, device);
+            }
+        }
+    }
+
     pub fn set_model_effect(
         &mut self,
         model_handle: ModelHandle,
@@ -156,10 +171,16 @@ impl ScreenRenderTarget {
 
     pub fn update_format(
         &mut self,
-        color_format: wgpu::TextureFormat,
-        depth_format: Option<wgpu::TextureFormat>,
+        format: RenderFormat,
+        models: &HashMap<ModelHandle, Model>,
+        device: &wgpu::Device,
     ) {
-        todo!("update_format")
+        self.config.format = format;
+        for (model_handle, renderer) in self.renderers.iter_mut() {
+            if let Some(model) = models.get(model_handle) {
+                renderer.set_config(model, &self.config, device);
+            }
+        }
     }
 
     pub fn update_bind(
@@ -194,6 +215,19 @@ impl ScreenRenderTarget {
         depth_view: Option<&wgpu::TextureView>,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
+    ) {
+        self.draw_multiple(draw_type, updater, &[view], depth_view, device, queue);
+    }
+
+    /// Draws techniques writing into several color attachments like `DrawType::Auxiliary`.
+    pub fn draw_multiple(
+        &self,
+        draw_type: DrawType,
+        updater: &dyn Fn(ModelHandle, &mut UniformBindData),
+        views: &[&wgpu::TextureView],
+        depth_view: Option<&wgpu::TextureView>,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
     ) {
         for (model_handle, renderer) in &self.renderers {
             for renderer in &renderer.renderers {
@@ -209,18 +243,23 @@ impl ScreenRenderTarget {
         let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
             label: Some(format!("ScreenRenderTarget/{:?}/CommandEncoder", draw_type).as_str()),
         });
-        let color_attachments = wgpu::RenderPassColorAttachment {
-            view,
-            resolve_target: None,
-            ops: wgpu::Operations {
-                load: wgpu::LoadOp::Load,
-                store: true,
-            },
-        };
+        let color_attachments = views
+            .iter()
+            .map(|&view| {
+                Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })
+            })
+            .collect::<Vec<_>>();
         {
             let mut _rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some(format!("ScreenRenderTarget/{:?}/RenderPass", draw_type).as_str()),
-                color_attachments: &[Some(color_attachments)],
+                color_attachments: &color_attachments,
                 depth_stencil_attachment: depth_view.map(|tv| {
                     wgpu::RenderPassDepthStencilAttachment {
                         view: tv,
@@ -261,6 +300,7 @@ impl ModelRenderer {
             material_size: model.materials.len(),
             add_blend: model.states.enable_add_blend,
             buffer: model.vertex_buffer.clone(),
+            previous_buffer: model.previous_vertex_buffer.clone(),
             index_buffer: model.index_buffer.clone(),
         };
         for (idx, material) in model.materials.iter().enumerate() {
@@ -291,6 +331,31 @@ impl ModelRenderer {
         }
     }
 
+    pub fn set_shadow_bind(
+        &mut self,
+        model: &Model,
+        shadow_bind: &Arc<wgpu::BindGroup>,
+        device: &wgpu::Device,
+    ) {
+        self.shadow_bind = shadow_bind.clone();
+        for (idx, renderer) in self.renderers.iter_mut().enumerate() {
+            let material = model.materials.get(idx).expect("material idx out of range");
+            renderer.shadow_bind = shadow_bind.clone();
+            let effect = renderer.effect.clone();
+            renderer.set_effect(material, &effect, device);
+        }
+    }
+
+    pub fn set_config(&mut self, model: &Model, config: &RendererConfig, device: &wgpu::Device) {
+        self.config = config.clone();
+        for (idx, renderer) in self.renderers.iter_mut().enumerate() {
+            let material = model.materials.get(idx).expect("material idx out of range");
+            renderer.config = config.clone();
+            let effect = renderer.effect.clone();
+            renderer.set_effect(material, &effect, device);
+        }
+    }
+
     pub fn remove_effect(
         &mut self,
         model: &Model,
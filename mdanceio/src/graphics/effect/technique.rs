@@ -3,10 +3,15 @@ use std::{
     sync::{Arc, RwLock, Weak},
 };
 
-use crate::{model::Material, project::ModelHandle};
+use crate::{
+    effect_package::{EffectBlendMode, EffectCullMode},
+    graphics::AuxiliaryTarget,
+    model::Material,
+    project::ModelHandle,
+};
 
 use super::{
-    effect::EffectConfig,
+    effect::{EffectBind, EffectConfig},
     layout::RendererLayout,
     render_target::RendererConfig,
     uniform::{MaterialUniform, UniformBind, UniformBindData},
@@ -20,6 +25,23 @@ pub enum TechniqueType {
     Edge,
     Shadow,
     Zplot,
+    /// View space normals for screen space effects, only provided by the default effect
+    Normal,
+    /// All auxiliary targets at once, only provided by the default effect
+    Auxiliary,
+}
+
+impl TechniqueType {
+    /// Formats of the color attachments the technique draws into.
+    fn color_formats(self, color: wgpu::TextureFormat) -> Vec<wgpu::TextureFormat> {
+        match self {
+            Self::Auxiliary => AuxiliaryTarget::ALL
+                .iter()
+                .map(|target| target.format())
+                .collect(),
+            _ => vec![color],
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
@@ -37,6 +59,7 @@ pub struct DrawPassModelContext {
     pub material_size: usize,
     pub add_blend: bool,
     pub buffer: Arc<wgpu::Buffer>,
+    pub previous_buffer: Arc<wgpu::Buffer>,
     pub index_buffer: Arc<wgpu::Buffer>,
 }
 
@@ -44,6 +67,7 @@ impl DrawPassModelContext {
     pub fn to_pass_vertex(&self, offset: u32, num: u32) -> DrawPassVertex {
         DrawPassVertex {
             buffer: self.buffer.clone(),
+            previous_buffer: self.previous_buffer.clone(),
             index_buffer: self.index_buffer.clone(),
             offset,
             num,
@@ -58,17 +82,21 @@ pub struct Technique {
     shader: wgpu::ShaderModule,
     layout: Arc<RendererLayout>,
     fallback_shadow_bind: Arc<wgpu::BindGroup>,
+    effect_bind: Option<Arc<EffectBind>>,
     pipelines: RwLock<HashMap<PipelineKey, Weak<wgpu::RenderPipeline>>>,
     uniforms: RwLock<HashMap<ModelHandle, UniformBind>>,
 }
 
 impl Technique {
+    pub const NORMAL_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
     pub fn new(
         typ: TechniqueType,
         config: EffectConfig,
         shader: wgpu::ShaderModule,
         layout: &Arc<RendererLayout>,
         fallback_shadow_bind: &Arc<wgpu::BindGroup>,
+        effect_bind: Option<&Arc<EffectBind>>,
     ) -> Self {
         Self {
             typ,
@@ -76,6 +104,7 @@ impl Technique {
             shader,
             layout: layout.clone(),
             fallback_shadow_bind: fallback_shadow_bind.clone(),
+            effect_bind: effect_bind.cloned(),
             pipelines: RwLock::new(HashMap::new()),
             uniforms: RwLock::new(HashMap::new()),
         }
@@ -91,15 +120,23 @@ impl Technique {
         let cull_mode = match self.typ {
             TechniqueType::Edge => Some(wgpu::Face::Front),
             TechniqueType::Shadow => None,
-            _ => {
-                if material.is_culling_disabled() {
-                    None
-                } else {
-                    Some(wgpu::Face::Back)
+            _ => match self.config.cull_mode {
+                EffectCullMode::Material => {
+                    if material.is_culling_disabled() {
+                        None
+                    } else {
+                        Some(wgpu::Face::Back)
+                    }
                 }
-            }
+                EffectCullMode::Back => Some(wgpu::Face::Back),
+                EffectCullMode::Front => Some(wgpu::Face::Front),
+                EffectCullMode::None => None,
+            },
         };
-        let color_blend = if matches!(self.typ, TechniqueType::Zplot) {
+        let color_blend = if matches!(
+            self.typ,
+            TechniqueType::Zplot | TechniqueType::Normal | TechniqueType::Auxiliary
+        ) {
             None
         } else {
             Some(color_blend)
@@ -113,7 +150,10 @@ impl Technique {
         };
         let depth_compare = if self.config.depth_enabled {
             match self.typ {
-                TechniqueType::Object | TechniqueType::ObjectSs => wgpu::CompareFunction::LessEqual,
+                TechniqueType::Object
+                | TechniqueType::ObjectSs
+                | TechniqueType::Normal
+                | TechniqueType::Auxiliary => wgpu::CompareFunction::LessEqual,
                 _ => wgpu::CompareFunction::Less,
             }
         } else {
@@ -144,6 +184,22 @@ impl Technique {
     }
 
     fn build_pipeline(&self, key: &PipelineKey, device: &wgpu::Device) -> wgpu::RenderPipeline {
+        let targets = self
+            .typ
+            .color_formats(key.format.color)
+            .into_iter()
+            .map(|format| {
+                Some(wgpu::ColorTargetState {
+                    format,
+                    blend: key.color_blend,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut buffers = vec![self.layout.vertex_buffer_layout.clone()];
+        if self.typ == TechniqueType::Auxiliary {
+            buffers.push(self.layout.previous_vertex_buffer_layout.clone());
+        }
         device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: Some(""),
             layout: Some(&self.layout.pipeline_layout),
@@ -159,16 +215,12 @@ impl Technique {
             vertex: wgpu::VertexState {
                 module: &self.shader,
                 entry_point: "vs_main",
-                buffers: &[self.layout.vertex_buffer_layout.clone()],
+                buffers: &buffers,
             },
             fragment: Some(wgpu::FragmentState {
                 module: &self.shader,
                 entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: key.format.color,
-                    blend: key.color_blend,
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
+                targets: &targets,
             }),
             depth_stencil: key.format.depth.map(|format| wgpu::DepthStencilState {
                 format,
@@ -177,7 +229,10 @@ impl Technique {
                 stencil: wgpu::StencilState::default(),
                 bias: wgpu::DepthBiasState::default(),
             }),
-            multisample: wgpu::MultisampleState::default(),
+            multisample: wgpu::MultisampleState {
+                count: key.format.sample_count,
+                ..Default::default()
+            },
             multiview: None,
         })
     }
@@ -215,6 +270,9 @@ impl Technique {
                     let mut data = bind.get_empty_uniform_data();
                     updater(&mut data);
                     bind.update(&data, queue);
+                    if let Some(effect_bind) = &self.effect_bind {
+                        effect_bind.update(handle, data.effect_parameters(), queue);
+                    }
                 }
             }
             Err(e) => {
@@ -232,21 +290,37 @@ impl Technique {
         shadow_bind: &Arc<wgpu::BindGroup>,
         device: &wgpu::Device,
     ) -> DrawPass {
-        let color_blend = if model_ctx.add_blend {
-            wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING
-        } else {
-            wgpu::BlendState::ALPHA_BLENDING
+        let color_blend = match self.config.blend_mode {
+            EffectBlendMode::Model if model_ctx.add_blend => {
+                wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING
+            }
+            EffectBlendMode::Model | EffectBlendMode::Alpha => wgpu::BlendState::ALPHA_BLENDING,
+            EffectBlendMode::Add => wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+            EffectBlendMode::Opaque => wgpu::BlendState::REPLACE,
         };
         let color_bind = material.bind_group();
-        let format = if self.typ == TechniqueType::Zplot {
-            RenderFormat {
+        let format = match self.typ {
+            TechniqueType::Zplot => RenderFormat {
                 color: wgpu::TextureFormat::R32Float,
                 depth: config.format.depth,
-            }
-        } else {
-            config.format
+                sample_count: 1,
+            },
+            TechniqueType::Normal => RenderFormat {
+                color: Self::NORMAL_TEXTURE_FORMAT,
+                depth: config.format.depth,
+                sample_count: 1,
+            },
+            TechniqueType::Auxiliary => RenderFormat {
+                color: AuxiliaryTarget::ALL[0].format(),
+                depth: config.format.depth,
+                sample_count: 1,
+            },
+            _ => config.format,
         };
-        let shadow_bind = if self.typ == TechniqueType::Zplot {
+        let shadow_bind = if matches!(
+            self.typ,
+            TechniqueType::Zplot | TechniqueType::Normal | TechniqueType::Auxiliary
+        ) {
             &self.fallback_shadow_bind
         } else {
             shadow_bind
@@ -254,11 +328,16 @@ impl Technique {
         let pipeline = self.get_pipeline(format, color_blend, material, device);
         DrawPass::new(
             pipeline,
+            self.typ,
             format,
             DrawPassBind {
                 color_bind,
                 shadow_bind: shadow_bind.clone(),
                 uniform_bind: self.get_uniform(model_ctx.handle, model_ctx.material_size, device),
+                effect_bind: self
+                    .effect_bind
+                    .as_ref()
+                    .map(|effect_bind| effect_bind.get(model_ctx.handle, device)),
                 material_idx,
             },
             model_ctx.to_pass_vertex(material.index_offset, material.num_indices),
@@ -272,12 +351,14 @@ pub struct DrawPassBind {
     pub color_bind: Arc<wgpu::BindGroup>,
     pub shadow_bind: Arc<wgpu::BindGroup>,
     pub uniform_bind: Arc<wgpu::BindGroup>,
+    pub effect_bind: Option<Arc<wgpu::BindGroup>>,
     pub material_idx: usize,
 }
 
 #[derive(Debug, Clone)]
 pub struct DrawPassVertex {
     pub buffer: Arc<wgpu::Buffer>,
+    pub previous_buffer: Arc<wgpu::Buffer>,
     pub index_buffer: Arc<wgpu::Buffer>,
     pub offset: u32,
     pub num: u32,
@@ -286,6 +367,7 @@ pub struct DrawPassVertex {
 #[derive(Debug)]
 pub struct DrawPass {
     pub pipeline: Arc<wgpu::RenderPipeline>,
+    pub typ: TechniqueType,
     pub format: RenderFormat,
     pub bind: DrawPassBind,
     pub vertex: DrawPassVertex,
@@ -295,14 +377,16 @@ pub struct DrawPass {
 impl DrawPass {
     fn new(
         pipeline: Arc<wgpu::RenderPipeline>,
+        typ: TechniqueType,
         format: RenderFormat,
         bind: DrawPassBind,
         vertex: DrawPassVertex,
         device: &wgpu::Device,
     ) -> Self {
-        let render_bundle = Self::build_bundle(&pipeline, format, &bind, &vertex, device);
+        let render_bundle = Self::build_bundle(&pipeline, typ, format, &bind, &vertex, device);
         Self {
             pipeline,
+            typ,
             format,
             bind: bind.clone(),
             vertex: vertex.clone(),
@@ -318,6 +402,7 @@ impl DrawPass {
     pub fn rebuild_bundle(&self, device: &wgpu::Device) -> wgpu::RenderBundle {
         Self::build_bundle(
             &self.pipeline,
+            self.typ,
             self.format,
             &self.bind,
             &self.vertex,
@@ -329,21 +414,27 @@ impl DrawPass {
 impl DrawPass {
     fn build_bundle(
         pipeline: &wgpu::RenderPipeline,
+        typ: TechniqueType,
         format: RenderFormat,
         bind: &DrawPassBind,
         vertex: &DrawPassVertex,
         device: &wgpu::Device,
     ) -> wgpu::RenderBundle {
+        let color_formats = typ
+            .color_formats(format.color)
+            .into_iter()
+            .map(Some)
+            .collect::<Vec<_>>();
         let mut encoder =
             device.create_render_bundle_encoder(&wgpu::RenderBundleEncoderDescriptor {
                 label: Some("ModelProgramBundle/RenderBundleEncoder"),
-                color_formats: &[Some(format.color)],
+                color_formats: &color_formats,
                 depth_stencil: format.depth.map(|format| wgpu::RenderBundleDepthStencil {
                     format,
                     depth_read_only: false,
                     stencil_read_only: true,
                 }),
-                sample_count: 1,
+                sample_count: format.sample_count,
                 multiview: None,
             });
         encoder.set_pipeline(pipeline);
@@ -354,7 +445,13 @@ impl DrawPass {
             &[(bind.material_idx * std::mem::size_of::<MaterialUniform>()) as u32],
         );
         encoder.set_bind_group(2, &bind.shadow_bind, &[]);
+        if let Some(effect_bind) = &bind.effect_bind {
+            encoder.set_bind_group(3, effect_bind, &[]);
+        }
         encoder.set_vertex_buffer(0, vertex.buffer.slice(..));
+        if typ == TechniqueType::Auxiliary {
+            encoder.set_vertex_buffer(1, vertex.previous_buffer.slice(..));
+        }
         encoder.set_index_buffer(vertex.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
         let vertex_indices = vertex.offset..(vertex.offset + vertex.num);
         encoder.draw_indexed(vertex_indices, 0, 0..1);
@@ -1,14 +1,16 @@
-use std::{num::NonZeroU64, sync::Arc};
+use std::{collections::HashMap, num::NonZeroU64, sync::Arc};
 
 use bytemuck::Zeroable;
-use cgmath::Matrix4;
+use cgmath::{Matrix4, SquareMatrix, Vector2};
+use nanoem::motion::MotionEffectParameterValue;
 use wgpu::util::DeviceExt;
 
 use crate::{
     camera::{Camera, PerspectiveCamera},
-    light::{DirectionalLight, Light},
+    environment::Environment,
+    light::{DirectionalLight, Light, LocalLight, LocalLightType, MAX_LOCAL_LIGHTS},
     model::{Material, Model},
-    shadow_camera::ShadowCamera,
+    shadow_camera::{ShadowCamera, ShadowCascade},
 };
 
 use super::technique::TechniqueType;
@@ -24,6 +26,31 @@ pub struct ModelUniform {
     light_direction: [f32; 4],                   // light
     camera_position: [f32; 4],                   // camera
     shadow_map_size: [f32; 4],                   // zplot
+    local_light_size: [f32; 4],                  // light
+    // light, shadow
+    local_lights: [LocalLightUniform; MAX_LOCAL_LIGHTS],
+    shadow_cascade_splits: [f32; 4], // shadow
+    shadow_filter: [f32; 4],         // shadow
+    // shadow
+    shadow_cascade_matrices: [[[f32; 4]; 4]; ShadowCamera::MAXIMUM_CASCADE_COUNT],
+    // diffuse intensity, specular intensity, cos and sin of the rotation
+    environment: [f32; 4],                 // environment
+    environment_irradiance: [[f32; 4]; 9], // environment
+    environment_radiance: [[f32; 4]; 9],   // environment
+    // auxiliary
+    previous_model_view_projection_matrix: [[f32; 4]; 4],
+    // auxiliary, xy: size of the targets
+    auxiliary_target_size: [f32; 4],
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LocalLightUniform {
+    position: [f32; 4],           // xyz: position, w: range
+    direction: [f32; 4],          // xyz: direction, w: cos of outer angle
+    color: [f32; 4],              // rgb: color, a: cos of inner angle
+    shadow: [f32; 4],             // x: type, y: shadow layer or -1, z: depth bias
+    shadow_matrix: [[f32; 4]; 4], // crop * view projection of the spot
 }
 
 #[repr(C)]
@@ -40,13 +67,17 @@ pub struct MaterialUniform {
     use_texture_sampler: [f32; 4],  // material(0-2), zplot(3)
     sphere_texture_type: [f32; 4],  // material
     edge_size: f32,                 // edge
-    padding: [f32; 23], // DynamicOffset must be aligned to `min_uniform_buffer_offset_alignment`, which is 256 by default
+    edge_padding: [f32; 3],
+    // x: ambient occlusion excluded, y: outline excluded, z: model handle, w: material index
+    screen_space: [f32; 4],
+    padding: [f32; 16], // DynamicOffset must be aligned to `min_uniform_buffer_offset_alignment`, which is 256 by default
 }
 
 pub struct UniformBindData {
     model: ModelUniform,
     material: Vec<MaterialUniform>,
     opacity: f32,
+    effect_parameters: HashMap<String, MotionEffectParameterValue>,
 }
 
 impl UniformBindData {
@@ -55,9 +86,14 @@ impl UniformBindData {
             model: ModelUniform::zeroed(),
             material: vec![MaterialUniform::zeroed(); material_size],
             opacity: 1f32,
+            effect_parameters: HashMap::new(),
         }
     }
 
+    pub fn effect_parameters(&self) -> &HashMap<String, MotionEffectParameterValue> {
+        &self.effect_parameters
+    }
+
     pub fn set_camera_parameters(
         &mut self,
         camera: &dyn Camera,
@@ -77,12 +113,98 @@ impl UniformBindData {
         self.model.light_direction = light.direction().extend(0f32).into();
     }
 
+    pub fn set_environment_parameters(&mut self, environment: &Environment) {
+        match environment.lighting() {
+            Some(lighting) => {
+                let settings = environment.settings();
+                let (sin, cos) = settings.rotation.to_radians().sin_cos();
+                self.model.environment = [
+                    settings.diffuse_intensity,
+                    settings.specular_intensity,
+                    cos,
+                    sin,
+                ];
+                self.model.environment_irradiance = lighting.irradiance;
+                self.model.environment_radiance = lighting.radiance;
+            }
+            None => self.model.environment = [0f32, 0f32, 1f32, 0f32],
+        }
+    }
+
+    /// Lights beyond `MAX_LOCAL_LIGHTS` are ignored, a spot light shadow uses the layer of its index.
+    pub fn set_local_light_parameters(
+        &mut self,
+        lights: &[LocalLight],
+        shadow_camera: &ShadowCamera,
+    ) {
+        let size = lights.len().min(MAX_LOCAL_LIGHTS);
+        self.model.local_light_size = [size as f32, 0f32, 0f32, 0f32];
+        let crop = shadow_camera.get_crop_matrix();
+        for (idx, uniform) in self.model.local_lights.iter_mut().enumerate() {
+            *uniform = match lights.get(idx) {
+                Some(light) => {
+                    let (inner, outer) = light.cone_angles();
+                    let layer = if shadow_camera.is_enabled() && light.casts_shadow() {
+                        idx as f32
+                    } else {
+                        -1f32
+                    };
+                    LocalLightUniform {
+                        position: light.position().extend(light.range()).into(),
+                        direction: light.direction().extend(outer.0.cos()).into(),
+                        color: light.color().extend(inner.0.cos()).into(),
+                        shadow: [
+                            u32::from(light.typ()) as f32,
+                            layer,
+                            LocalLight::SHADOW_BIAS,
+                            0f32,
+                        ],
+                        shadow_matrix: if light.typ() == LocalLightType::Spot {
+                            (crop * light.shadow_view_projection()).into()
+                        } else {
+                            Matrix4::identity().into()
+                        },
+                    }
+                }
+                None => LocalLightUniform::zeroed(),
+            };
+        }
+    }
+
+    /// Renders the zplot technique into the layer of `cascade`, the world matrix includes the model transform.
+    pub fn set_shadow_map_cascade_parameters(
+        &mut self,
+        cascade: &ShadowCascade,
+        world: &Matrix4<f32>,
+        shadow_camera: &ShadowCamera,
+    ) {
+        self.model.light_view_projection_matrix = (cascade.view_projection * world).into();
+        self.model.shadow_map_size = shadow_camera
+            .image_size()
+            .map(|x| x as f32)
+            .extend(0.005f32)
+            .extend(u32::from(shadow_camera.coverage_mode()) as f32)
+            .into();
+    }
+
+    /// Renders the zplot technique from the spot light instead of the directional light.
+    pub fn set_local_shadow_map_parameters(&mut self, light: &LocalLight, world: &Matrix4<f32>) {
+        self.model.light_view_projection_matrix = (light.shadow_view_projection() * world).into();
+        self.model.shadow_map_size = [
+            ShadowCamera::LOCAL_TEXTURE_SIZE as f32,
+            ShadowCamera::LOCAL_TEXTURE_SIZE as f32,
+            LocalLight::SHADOW_BIAS,
+            0f32,
+        ];
+    }
+
     pub fn set_all_model_parameters(
         &mut self,
         model: &Model,
         models: &dyn Iterator<Item = &Model>,
     ) {
         self.opacity = model.opacity();
+        self.effect_parameters.clone_from(model.effect_parameters());
     }
 
     pub fn set_material_parameters(&mut self, material_idx: usize, material: &Material) {
@@ -133,6 +255,28 @@ impl UniformBindData {
         } else {
             0f32
         };
+        let flag = |value: bool| if value { 1f32 } else { 0f32 };
+        uniform.screen_space = [
+            flag(material.is_ambient_occlusion_excluded()),
+            flag(material.is_outline_excluded()),
+            0f32,
+            0f32,
+        ];
+    }
+
+    /// Identifies the model of `handle` and projects its previous vertices for motion vectors.
+    pub fn set_auxiliary_parameters(
+        &mut self,
+        handle: u32,
+        previous_model_view_projection: &Matrix4<f32>,
+        target_size: Vector2<u32>,
+    ) {
+        self.model.previous_model_view_projection_matrix = (*previous_model_view_projection).into();
+        self.model.auxiliary_target_size = [target_size.x as f32, target_size.y as f32, 0f32, 0f32];
+        for (idx, uniform) in self.material.iter_mut().enumerate() {
+            uniform.screen_space[2] = handle as f32;
+            uniform.screen_space[3] = idx as f32;
+        }
     }
 
     pub fn set_edge_parameters(
@@ -179,10 +323,21 @@ impl UniformBindData {
         camera: &PerspectiveCamera,
         light: &DirectionalLight,
     ) {
-        let (view, projection) = shadow_camera.get_view_projection(camera, light);
+        let cascades = shadow_camera.get_cascades(camera, light);
         let crop = shadow_camera.get_crop_matrix();
-        let shadow_map_matrix = projection * view * world;
+        let shadow_map_matrix = cascades[0].view_projection * world;
         self.model.light_view_projection_matrix = (crop * shadow_map_matrix).into();
+        self.model.shadow_cascade_splits = [f32::MAX; 4];
+        for (idx, cascade) in cascades.iter().enumerate() {
+            self.model.shadow_cascade_splits[idx] = cascade.split;
+            self.model.shadow_cascade_matrices[idx] = (crop * cascade.view_projection).into();
+        }
+        self.model.shadow_filter = [
+            cascades.len() as f32,
+            shadow_camera.filter_radius() as f32,
+            shadow_camera.depth_bias(),
+            1f32 / shadow_camera.image_size().x.max(1) as f32,
+        ];
         self.model.shadow_map_size = shadow_camera
             .image_size()
             .map(|x| x as f32)
@@ -21,6 +21,7 @@ pub struct LineDrawer {
     render_bundle: wgpu::RenderBundle,
     primitive_type: wgpu::PrimitiveTopology,
     texture_format: wgpu::TextureFormat,
+    sample_count: u32,
     vertex_buffer: wgpu::Buffer,
     num_vertices: u32,
 }
@@ -32,6 +33,7 @@ impl LineDrawer {
         device: &wgpu::Device,
     ) -> Self {
         let primitive_type = wgpu::PrimitiveTopology::LineList;
+        let sample_count = 1;
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("LineDrawer/Shader"),
             source: wgpu::ShaderSource::Wgsl(
@@ -62,6 +64,7 @@ impl LineDrawer {
             &pipeline_layout,
             primitive_type,
             texture_format,
+            sample_count,
             device,
         );
         let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -82,6 +85,7 @@ impl LineDrawer {
             &vertex_buffer,
             num_vertices,
             texture_format,
+            sample_count,
             device,
         );
         Self {
@@ -92,6 +96,7 @@ impl LineDrawer {
             render_pipeline,
             primitive_type,
             texture_format,
+            sample_count,
             vertex_buffer,
             num_vertices,
             render_bundle,
@@ -128,9 +133,11 @@ impl LineDrawer {
     pub fn update_texture_format(
         &mut self,
         texture_format: wgpu::TextureFormat,
+        sample_count: u32,
         device: &wgpu::Device,
     ) {
         self.texture_format = texture_format;
+        self.sample_count = sample_count;
         self.rebuild_pipeline(device);
     }
 
@@ -222,6 +229,7 @@ impl LineDrawer {
         pipeline_layout: &wgpu::PipelineLayout,
         primitive_type: wgpu::PrimitiveTopology,
         texture_format: wgpu::TextureFormat,
+        sample_count: u32,
         device: &wgpu::Device,
     ) -> wgpu::RenderPipeline {
         device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
@@ -237,7 +245,10 @@ impl LineDrawer {
                 ..Default::default()
             },
             depth_stencil: None,
-            multisample: wgpu::MultisampleState::default(),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
             fragment: Some(wgpu::FragmentState {
                 module: shader,
                 entry_point: "fs_main",
@@ -264,6 +275,7 @@ impl LineDrawer {
             &self.pipeline_layout,
             self.primitive_type,
             self.texture_format,
+            self.sample_count,
             device,
         );
         self.rebuild_render_bundle(device);
@@ -276,6 +288,7 @@ impl LineDrawer {
         vertex_buffer: &wgpu::Buffer,
         num_vertices: u32,
         texture_format: wgpu::TextureFormat,
+        sample_count: u32,
         device: &wgpu::Device,
     ) -> wgpu::RenderBundle {
         let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
@@ -291,7 +304,7 @@ impl LineDrawer {
                 label: Some("LineDrawer/RenderBundleEncoder"),
                 color_formats: &[Some(texture_format)],
                 depth_stencil: None,
-                sample_count: 1,
+                sample_count,
                 ..Default::default()
             });
         encoder.set_pipeline(render_pipeline);
@@ -311,6 +324,7 @@ impl LineDrawer {
             &self.vertex_buffer,
             self.num_vertices,
             self.texture_format,
+            self.sample_count,
             device,
         );
     }
@@ -1,7 +1,37 @@
 pub mod line_drawer;
+pub mod auxiliary_targets;
+pub mod bone_drawer;
 pub mod clear_pass;
 pub mod effect;
+pub mod model_overlay;
+pub mod motion_blur;
 pub mod physics_debug;
+pub mod post_process;
+pub mod skybox;
+pub mod supersampler;
+pub mod unpremultiplier;
 
+pub use auxiliary_targets::{AuxiliaryTarget, AuxiliaryTargets};
+pub use bone_drawer::BoneDrawer;
 pub use clear_pass::ClearPass;
-pub use line_drawer::LineDrawer;
\ No newline at end of file
+pub use line_drawer::LineDrawer;
+pub use model_overlay::ModelOverlayDrawer;
+pub use motion_blur::{MotionBlur, MotionBlurSettings};
+pub use post_process::{PostProcess, PostProcessSettings, ToneMappingOperator};
+pub use skybox::Skybox;
+pub use supersampler::{DownsampleFilter, Supersampler, SupersamplingSettings};
+pub use unpremultiplier::{Unpremultiplier, ViewportAlphaMode};
+
+/// Parses and validates a WGSL source with naga, panicking with the error on failure.
+#[cfg(test)]
+pub(crate) fn validate_wgsl(source: &str) {
+    let module = naga::front::wgsl::parse_str(source)
+        .map_err(|err| err.emit_to_string(source))
+        .unwrap();
+    naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::empty(),
+    )
+    .validate(&module)
+    .unwrap();
+}
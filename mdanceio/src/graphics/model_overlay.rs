@@ -0,0 +1,333 @@
+use std::{
+    collections::{HashMap, HashSet},
+    iter, mem,
+};
+
+use cgmath::Matrix4;
+use wgpu::util::DeviceExt;
+
+use crate::{
+    model::{Model, VertexUnit},
+    project::ModelHandle,
+};
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, bytemuck::Zeroable, bytemuck::Pod)]
+struct OverlayUniform {
+    view_projection_matrix: [[f32; 4]; 4],
+}
+
+pub struct OverlayColors {
+    pub face: [u8; 4],
+    pub point: [u8; 4],
+    pub selected: [u8; 4],
+}
+
+impl Default for OverlayColors {
+    fn default() -> Self {
+        Self {
+            face: [0xff, 0xff, 0xff, 0x40],
+            point: [0x00, 0xff, 0x00, 0xff],
+            selected: [0xff, 0x00, 0x00, 0xff],
+        }
+    }
+}
+
+struct ModelOverlay {
+    edge_index_buffer: wgpu::Buffer,
+    num_edge_indices: u32,
+    color_buffer: wgpu::Buffer,
+    num_vertices: u32,
+}
+
+/// Draws deformed triangle edges and vertex points of models as an overlay.
+pub struct ModelOverlayDrawer {
+    uniform_buffer: wgpu::Buffer,
+    uniform_bind_group: wgpu::BindGroup,
+    face_pipeline: wgpu::RenderPipeline,
+    point_pipeline: wgpu::RenderPipeline,
+    overlays: HashMap<ModelHandle, ModelOverlay>,
+    pub colors: OverlayColors,
+}
+
+impl ModelOverlayDrawer {
+    pub fn new(color_format: wgpu::TextureFormat, device: &wgpu::Device) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("ModelOverlayDrawer/Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                include_str!("../../resources/shaders/model_overlay.wgsl").into(),
+            ),
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("ModelOverlayDrawer/BindGroupLayout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("ModelOverlayDrawer/PipelineLayout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("ModelOverlayDrawer/UniformBuffer"),
+            contents: bytemuck::bytes_of(&OverlayUniform::default()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("ModelOverlayDrawer/BindGroup"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+        let face_pipeline = Self::build_pipeline(
+            &shader,
+            &pipeline_layout,
+            wgpu::PrimitiveTopology::LineList,
+            color_format,
+            device,
+        );
+        let point_pipeline = Self::build_pipeline(
+            &shader,
+            &pipeline_layout,
+            wgpu::PrimitiveTopology::PointList,
+            color_format,
+            device,
+        );
+        Self {
+            uniform_buffer,
+            uniform_bind_group,
+            face_pipeline,
+            point_pipeline,
+            overlays: HashMap::new(),
+            colors: OverlayColors::default(),
+        }
+    }
+
+    pub fn remove_model(&mut self, handle: ModelHandle) {
+        self.overlays.remove(&handle);
+    }
+
+    pub fn draw<'a>(
+        &mut self,
+        models: impl Iterator<Item = (ModelHandle, &'a Model)>,
+        view_projection: Matrix4<f32>,
+        color_view: &wgpu::TextureView,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        let models = models
+            .filter(|(_, model)| {
+                model.is_visible()
+                    && (model.states.show_all_vertex_faces || model.states.show_all_vertex_points)
+            })
+            .collect::<Vec<_>>();
+        if models.is_empty() {
+            return;
+        }
+        let uniform = OverlayUniform {
+            view_projection_matrix: view_projection.into(),
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&uniform));
+        for (handle, model) in &models {
+            let colors = self.build_vertex_colors(model);
+            let overlay = self
+                .overlays
+                .entry(*handle)
+                .or_insert_with(|| Self::build_overlay(model, device));
+            queue.write_buffer(&overlay.color_buffer, 0, bytemuck::cast_slice(&colors));
+        }
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("ModelOverlayDrawer/Encoder"),
+        });
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("ModelOverlayDrawer/Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: color_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            rpass.set_bind_group(0, &self.uniform_bind_group, &[]);
+            for (handle, model) in &models {
+                if let Some(overlay) = self.overlays.get(handle) {
+                    rpass.set_vertex_buffer(0, model.vertex_buffer.slice(..));
+                    rpass.set_vertex_buffer(1, overlay.color_buffer.slice(..));
+                    if model.states.show_all_vertex_faces && overlay.num_edge_indices > 0 {
+                        rpass.set_pipeline(&self.face_pipeline);
+                        rpass.set_index_buffer(
+                            overlay.edge_index_buffer.slice(..),
+                            wgpu::IndexFormat::Uint32,
+                        );
+                        rpass.draw_indexed(0..overlay.num_edge_indices, 0, 0..1);
+                    }
+                    if model.states.show_all_vertex_points {
+                        rpass.set_pipeline(&self.point_pipeline);
+                        rpass.draw(0..overlay.num_vertices, 0..1);
+                    }
+                }
+            }
+        }
+        queue.submit(iter::once(encoder.finish()));
+    }
+
+    fn build_vertex_colors(&self, model: &Model) -> Vec<[u8; 4]> {
+        let weight_bone = if model.states.show_all_vertex_weights {
+            model.vertex_weight_bone()
+        } else {
+            None
+        };
+        let base_color = if model.states.show_all_vertex_faces {
+            self.colors.face
+        } else {
+            self.colors.point
+        };
+        model
+            .vertices()
+            .iter()
+            .map(|vertex| {
+                if vertex.is_selected() {
+                    self.colors.selected
+                } else if let Some(bone) = weight_bone {
+                    weight_heat_color(vertex.bone_weight(bone))
+                } else {
+                    base_color
+                }
+            })
+            .collect()
+    }
+
+    fn build_overlay(model: &Model, device: &wgpu::Device) -> ModelOverlay {
+        let edge_indices = build_edge_indices(model.vertices().indices());
+        let num_vertices = model.vertices_len();
+        let edge_index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("ModelOverlayDrawer/EdgeIndexBuffer"),
+            contents: bytemuck::cast_slice(&edge_indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        let color_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("ModelOverlayDrawer/ColorBuffer"),
+            size: (num_vertices * mem::size_of::<[u8; 4]>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        ModelOverlay {
+            edge_index_buffer,
+            num_edge_indices: edge_indices.len() as u32,
+            color_buffer,
+            num_vertices: num_vertices as u32,
+        }
+    }
+
+    fn build_pipeline(
+        shader: &wgpu::ShaderModule,
+        pipeline_layout: &wgpu::PipelineLayout,
+        topology: wgpu::PrimitiveTopology,
+        color_format: wgpu::TextureFormat,
+        device: &wgpu::Device,
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("ModelOverlayDrawer/Pipeline"),
+            layout: Some(pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: "vs_main",
+                buffers: &[
+                    wgpu::VertexBufferLayout {
+                        array_stride: mem::size_of::<VertexUnit>() as wgpu::BufferAddress,
+                        step_mode: wgpu::VertexStepMode::Vertex,
+                        attributes: &wgpu::vertex_attr_array![0 => Float32x3],
+                    },
+                    wgpu::VertexBufferLayout {
+                        array_stride: mem::size_of::<[u8; 4]>() as wgpu::BufferAddress,
+                        step_mode: wgpu::VertexStepMode::Vertex,
+                        attributes: &wgpu::vertex_attr_array![1 => Unorm8x4],
+                    },
+                ],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: color_format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::COLOR,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        })
+    }
+}
+
+/// Converts triangle list indices to line list indices with shared edges deduplicated
+fn build_edge_indices(indices: &[u32]) -> Vec<u32> {
+    let mut edges = HashSet::new();
+    let mut edge_indices = vec![];
+    for triangle in indices.chunks_exact(3) {
+        for (a, b) in [
+            (triangle[0], triangle[1]),
+            (triangle[1], triangle[2]),
+            (triangle[2], triangle[0]),
+        ] {
+            if edges.insert((a.min(b), a.max(b))) {
+                edge_indices.push(a);
+                edge_indices.push(b);
+            }
+        }
+    }
+    edge_indices
+}
+
+/// Maps weight in [0, 1] to blue (0) -> green (0.5) -> red (1)
+fn weight_heat_color(weight: f32) -> [u8; 4] {
+    let weight = weight.clamp(0f32, 1f32);
+    let (r, g, b) = if weight < 0.5f32 {
+        let t = weight * 2f32;
+        (0f32, t, 1f32 - t)
+    } else {
+        let t = (weight - 0.5f32) * 2f32;
+        (t, 1f32 - t, 0f32)
+    };
+    [
+        (r * 255f32) as u8,
+        (g * 255f32) as u8,
+        (b * 255f32) as u8,
+        0xff,
+    ]
+}
+
+#[test]
+fn test_build_edge_indices() {
+    let indices = [0u32, 1, 2, 2, 1, 3];
+    let edges = build_edge_indices(&indices);
+    assert_eq!(10, edges.len());
+    assert_eq!(&[0, 1, 1, 2, 2, 0], &edges[0..6]);
+}
+
+#[test]
+fn test_weight_heat_color() {
+    assert_eq!([0, 0, 255, 255], weight_heat_color(0f32));
+    assert_eq!([0, 255, 0, 255], weight_heat_color(0.5f32));
+    assert_eq!([255, 0, 0, 255], weight_heat_color(1f32));
+    assert_eq!([255, 0, 0, 255], weight_heat_color(2f32));
+}
@@ -0,0 +1,339 @@
+use cgmath::Vector2;
+
+use crate::forward::QuadVertexUnit;
+
+/// Sub-frame sampling of offline renders, averaging frames across the open shutter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MotionBlurSettings {
+    pub enabled: bool,
+    /// Number of sub-frames averaged into one output frame
+    pub sample_count: u32,
+    /// Fraction of the frame interval the shutter is open, 0.5 is a 180 degree shutter
+    pub shutter: f32,
+}
+
+impl Default for MotionBlurSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sample_count: 8,
+            shutter: 0.5f32,
+        }
+    }
+}
+
+impl MotionBlurSettings {
+    pub const MAX_SAMPLE_COUNT: u32 = 64;
+
+    pub fn is_active(&self) -> bool {
+        self.enabled && self.sample_count > 1 && self.shutter > 0f32
+    }
+
+    /// Offsets of the sub-frames from the output frame in frames. The shutter closes at the
+    /// output frame so that consecutive frames step the physics forward without gaps.
+    pub fn sample_offsets(&self) -> Vec<f32> {
+        if !self.is_active() {
+            return vec![0f32];
+        }
+        let count = self.sample_count.min(Self::MAX_SAMPLE_COUNT);
+        let shutter = self.shutter.min(1f32);
+        (1..=count)
+            .map(|idx| shutter * (idx as f32 / count as f32 - 1f32))
+            .collect()
+    }
+}
+
+struct MotionBlurTargets {
+    size: Vector2<u32>,
+    sample_view: wgpu::TextureView,
+    accumulation_view: wgpu::TextureView,
+    sample_bind: wgpu::BindGroup,
+    accumulation_bind: wgpu::BindGroup,
+}
+
+/// Accumulates sub-frames drawn into `sample_view` and resolves their average into a view.
+pub struct MotionBlur {
+    format: wgpu::TextureFormat,
+    vertex_buffer: wgpu::Buffer,
+    bind_group_layout: wgpu::BindGroupLayout,
+    accumulate_pipeline: wgpu::RenderPipeline,
+    resolve_pipeline: wgpu::RenderPipeline,
+    targets: Option<MotionBlurTargets>,
+}
+
+impl MotionBlur {
+    pub const ACCUMULATION_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+    pub fn new(format: wgpu::TextureFormat, device: &wgpu::Device) -> Self {
+        let vertex_buffer = wgpu::util::DeviceExt::create_buffer_init(
+            device,
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("MotionBlur/Vertices"),
+                contents: bytemuck::cast_slice(&QuadVertexUnit::generate_quad_tri_strip()),
+                usage: wgpu::BufferUsages::VERTEX,
+            },
+        );
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("MotionBlur/BindGroupLayout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }],
+        });
+        let accumulate_pipeline = Self::build_pipeline(
+            Self::ACCUMULATION_TEXTURE_FORMAT,
+            Some(wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::Constant,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::Constant,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+            }),
+            &bind_group_layout,
+            device,
+        );
+        let resolve_pipeline = Self::build_pipeline(format, None, &bind_group_layout, device);
+        Self {
+            format,
+            vertex_buffer,
+            bind_group_layout,
+            accumulate_pipeline,
+            resolve_pipeline,
+            targets: None,
+        }
+    }
+
+    /// Creates the sub-frame and accumulation textures on first use or when resized.
+    pub fn prepare(&mut self, size: Vector2<u32>, device: &wgpu::Device) {
+        if self.targets.as_ref().map(|targets| targets.size) == Some(size) {
+            return;
+        }
+        let create_view = |label: &str, format: wgpu::TextureFormat| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(format!("MotionBlur/{}", label).as_str()),
+                    size: wgpu::Extent3d {
+                        width: size.x.max(1),
+                        height: size.y.max(1),
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        };
+        let create_bind = |view: &wgpu::TextureView| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("MotionBlur/BindGroup"),
+                layout: &self.bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(view),
+                }],
+            })
+        };
+        let sample_view = create_view("Sample", self.format);
+        let accumulation_view = create_view("Accumulation", Self::ACCUMULATION_TEXTURE_FORMAT);
+        let sample_bind = create_bind(&sample_view);
+        let accumulation_bind = create_bind(&accumulation_view);
+        self.targets = Some(MotionBlurTargets {
+            size,
+            sample_view,
+            accumulation_view,
+            sample_bind,
+            accumulation_bind,
+        });
+    }
+
+    /// Texture each sub-frame is drawn into, available after `prepare`.
+    pub fn sample_view(&self) -> Option<&wgpu::TextureView> {
+        self.targets.as_ref().map(|targets| &targets.sample_view)
+    }
+
+    pub fn clear(&self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if let Some(targets) = &self.targets {
+            let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("MotionBlur/Clear/CommandEncoder"),
+            });
+            encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("MotionBlur/Clear/RenderPass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &targets.accumulation_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            queue.submit(Some(encoder.finish()));
+        }
+    }
+
+    /// Adds the sub-frame in `sample_view` scaled by `weight` to the accumulation.
+    pub fn accumulate(&self, weight: f32, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if let Some(targets) = &self.targets {
+            let weight = weight as f64;
+            self.encode_pass(
+                &self.accumulate_pipeline,
+                &targets.sample_bind,
+                &targets.accumulation_view,
+                Some(wgpu::Color {
+                    r: weight,
+                    g: weight,
+                    b: weight,
+                    a: weight,
+                }),
+                device,
+                queue,
+            );
+        }
+    }
+
+    /// Writes the accumulated frame into `view`.
+    pub fn resolve(&self, view: &wgpu::TextureView, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if let Some(targets) = &self.targets {
+            self.encode_pass(
+                &self.resolve_pipeline,
+                &targets.accumulation_bind,
+                view,
+                None,
+                device,
+                queue,
+            );
+        }
+    }
+
+    fn encode_pass(
+        &self,
+        pipeline: &wgpu::RenderPipeline,
+        bind_group: &wgpu::BindGroup,
+        target: &wgpu::TextureView,
+        blend_constant: Option<wgpu::Color>,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("MotionBlur/CommandEncoder"),
+        });
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("MotionBlur/RenderPass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            rpass.set_pipeline(pipeline);
+            if let Some(color) = blend_constant {
+                rpass.set_blend_constant(color);
+            }
+            rpass.set_bind_group(0, bind_group, &[]);
+            rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            rpass.draw(0..4, 0..1);
+        }
+        queue.submit(Some(encoder.finish()));
+    }
+
+    fn build_pipeline(
+        format: wgpu::TextureFormat,
+        blend: Option<wgpu::BlendState>,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        device: &wgpu::Device,
+    ) -> wgpu::RenderPipeline {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("MotionBlur/Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                include_str!("../../resources/shaders/motion_blur.wgsl").into(),
+            ),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("MotionBlur/PipelineLayout"),
+            bind_group_layouts: &[bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("MotionBlur/Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<QuadVertexUnit>() as u64,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                strip_index_format: None,
+                cull_mode: None,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        })
+    }
+}
+
+#[test]
+fn test_motion_blur_sample_offsets() {
+    let mut settings = MotionBlurSettings::default();
+    assert_eq!(vec![0f32], settings.sample_offsets());
+    settings.enabled = true;
+    settings.sample_count = 4;
+    settings.shutter = 1f32;
+    assert_eq!(
+        vec![-0.75f32, -0.5f32, -0.25f32, 0f32],
+        settings.sample_offsets()
+    );
+    settings.shutter = 0.5f32;
+    assert_eq!(
+        vec![-0.375f32, -0.25f32, -0.125f32, 0f32],
+        settings.sample_offsets()
+    );
+    settings.sample_count = 1000;
+    assert_eq!(
+        MotionBlurSettings::MAX_SAMPLE_COUNT as usize,
+        settings.sample_offsets().len()
+    );
+    settings.shutter = 0f32;
+    assert_eq!(vec![0f32], settings.sample_offsets());
+}
+
+#[test]
+fn test_motion_blur_shader() {
+    super::validate_wgsl(include_str!("../../resources/shaders/motion_blur.wgsl"));
+}
@@ -0,0 +1,1003 @@
+use cgmath::{Matrix4, SquareMatrix, Vector2};
+
+use crate::forward::QuadVertexUnit;
+
+use super::effect::technique::Technique;
+
+/// Curve mapping HDR colors into the displayable range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ToneMappingOperator {
+    Aces,
+    Reinhard,
+    /// Filmic curve of Uncharted 2
+    Filmic,
+    /// Scales by the exposure and clamps
+    Linear,
+}
+
+impl ToneMappingOperator {
+    pub const ALL: [Self; 4] = [Self::Aces, Self::Reinhard, Self::Filmic, Self::Linear];
+
+    pub fn from_index(value: u32) -> Option<Self> {
+        Self::ALL.get(value as usize).copied()
+    }
+
+    pub fn index(self) -> u32 {
+        self as u32
+    }
+}
+
+/// Effects applied after the viewport is drawn. Enabled effects run in the order of ambient
+/// occlusion, outline, depth of field, bloom, tone mapping, color grading, vignette and FXAA.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PostProcessSettings {
+    pub enabled: bool,
+    pub bloom: bool,
+    pub bloom_threshold: f32,
+    pub bloom_intensity: f32,
+    pub tone_mapping: bool,
+    pub tone_mapping_operator: ToneMappingOperator,
+    pub exposure: f32,
+    /// Draws the viewport into RGBA16F textures so that lighting is not clipped before the
+    /// tone mapping
+    pub hdr: bool,
+    /// Requires a LUT loaded with `PostProcess::set_color_grading_lut`
+    pub color_grading: bool,
+    pub color_grading_intensity: f32,
+    pub vignette: bool,
+    pub vignette_intensity: f32,
+    pub vignette_smoothness: f32,
+    pub depth_of_field: bool,
+    pub focus_distance: f32,
+    pub focus_range: f32,
+    /// Radius of the blur in pixels at the farthest from the focus
+    pub max_blur_radius: f32,
+    pub fxaa: bool,
+    pub ambient_occlusion: bool,
+    /// Radius of the sampled hemisphere in world units
+    pub ambient_occlusion_radius: f32,
+    pub ambient_occlusion_intensity: f32,
+    /// Cosine of the angle below which samples do not occlude, avoids self occlusion
+    pub ambient_occlusion_bias: f32,
+    /// Screen space outline replacing the edge drawn by the models
+    pub outline: bool,
+    /// Width of the outline in pixels
+    pub outline_width: f32,
+    pub outline_color: [f32; 4],
+    /// Relative difference of view distance between neighbors to draw the outline
+    pub outline_depth_threshold: f32,
+    /// Difference of normals between neighbors to draw the outline, from 0 to 2
+    pub outline_normal_threshold: f32,
+}
+
+impl Default for PostProcessSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bloom: false,
+            bloom_threshold: 0.8f32,
+            bloom_intensity: 0.6f32,
+            tone_mapping: false,
+            tone_mapping_operator: ToneMappingOperator::Aces,
+            exposure: 1f32,
+            hdr: false,
+            color_grading: false,
+            color_grading_intensity: 1f32,
+            vignette: false,
+            vignette_intensity: 0.4f32,
+            vignette_smoothness: 0.5f32,
+            depth_of_field: false,
+            focus_distance: 45f32,
+            focus_range: 20f32,
+            max_blur_radius: 8f32,
+            fxaa: false,
+            ambient_occlusion: false,
+            ambient_occlusion_radius: 2f32,
+            ambient_occlusion_intensity: 1f32,
+            ambient_occlusion_bias: 0.1f32,
+            outline: false,
+            outline_width: 1f32,
+            outline_color: [0f32, 0f32, 0f32, 1f32],
+            outline_depth_threshold: 0.05f32,
+            outline_normal_threshold: 0.5f32,
+        }
+    }
+}
+
+impl PostProcessSettings {
+    /// Whether the viewport has to be drawn into intermediate textures.
+    pub fn is_active(&self) -> bool {
+        !self.stages().is_empty()
+    }
+
+    pub fn is_hdr_enabled(&self) -> bool {
+        self.enabled && self.hdr
+    }
+
+    /// Whether the normal prepass has to be drawn for the enabled effects.
+    pub fn requires_normal(&self) -> bool {
+        self.enabled && (self.ambient_occlusion || self.outline)
+    }
+
+    /// Whether the enabled effects read the depth of the scene.
+    pub fn requires_depth(&self) -> bool {
+        self.enabled && (self.ambient_occlusion || self.outline || self.depth_of_field)
+    }
+
+    /// Whether the screen space outline replaces the edge drawn by the models.
+    pub fn is_outline_enabled(&self) -> bool {
+        self.enabled && self.outline
+    }
+
+    fn stages(&self) -> Vec<Stage> {
+        if !self.enabled {
+            return vec![];
+        }
+        let mut stages = vec![];
+        if self.ambient_occlusion {
+            stages.push(Stage::AmbientOcclusion);
+        }
+        if self.outline {
+            stages.push(Stage::Outline);
+        }
+        if self.depth_of_field {
+            stages.push(Stage::DepthOfField);
+        }
+        if self.bloom || self.tone_mapping || self.color_grading || self.vignette || self.hdr {
+            stages.push(Stage::Composite);
+        }
+        if self.fxaa {
+            stages.push(Stage::Fxaa);
+        }
+        stages
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    AmbientOcclusion,
+    Outline,
+    DepthOfField,
+    /// Bloom, tone mapping, color grading and vignette in one pass, also resolves HDR textures
+    Composite,
+    Fxaa,
+}
+
+/// Intermediate textures a pass reads from, indexing `PostProcessTargets::views`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Source {
+    Scene = 0,
+    Ping,
+    Pong,
+    Bloom,
+    BloomBlur,
+    Normal,
+    Occlusion,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct PostProcessUniform {
+    inverse_projection: [[f32; 4]; 4],
+    bloom: [f32; 4],
+    tone_mapping: [f32; 4],
+    color_grading: [f32; 4],
+    vignette: [f32; 4],
+    depth_of_field: [f32; 4],
+    ambient_occlusion: [f32; 4],
+    outline: [f32; 4],
+    outline_color: [f32; 4],
+}
+
+struct PostProcessPipelines {
+    ambient_occlusion: wgpu::RenderPipeline,
+    ambient_occlusion_apply: wgpu::RenderPipeline,
+    outline: wgpu::RenderPipeline,
+    depth_of_field: wgpu::RenderPipeline,
+    bloom_extract: wgpu::RenderPipeline,
+    bloom_blur_horizontal: wgpu::RenderPipeline,
+    bloom_blur_vertical: wgpu::RenderPipeline,
+    composite: wgpu::RenderPipeline,
+    fxaa: wgpu::RenderPipeline,
+}
+
+struct PostProcessTargets {
+    views: Vec<wgpu::TextureView>,
+    input_binds: Vec<wgpu::BindGroup>,
+}
+
+/// Post-process stack drawing the viewport from intermediate textures into the final view.
+pub struct PostProcess {
+    settings: PostProcessSettings,
+    format: wgpu::TextureFormat,
+    scene_format: wgpu::TextureFormat,
+    vertex_buffer: wgpu::Buffer,
+    uniform_buffer: wgpu::Buffer,
+    sampler: wgpu::Sampler,
+    input_bind_group_layout: wgpu::BindGroupLayout,
+    geometry_bind_group_layout: wgpu::BindGroupLayout,
+    lookup_bind_group_layout: wgpu::BindGroupLayout,
+    pipelines: PostProcessPipelines,
+    /// Pipelines drawing into the intermediate textures if they differ from the output in format
+    scene_pipelines: Option<PostProcessPipelines>,
+    targets: PostProcessTargets,
+    lut: wgpu::Texture,
+    lut_size: Option<u32>,
+    geometry_bind: wgpu::BindGroup,
+    lookup_bind: wgpu::BindGroup,
+}
+
+impl PostProcess {
+    pub const BLOOM_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+    pub const OCCLUSION_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R8Unorm;
+    pub const HDR_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+    pub fn new(
+        size: Vector2<u32>,
+        format: wgpu::TextureFormat,
+        depth_view: &wgpu::TextureView,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Self {
+        let vertex_buffer = wgpu::util::DeviceExt::create_buffer_init(
+            device,
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("PostProcess/Vertices"),
+                contents: bytemuck::cast_slice(&QuadVertexUnit::generate_quad_tri_strip()),
+                usage: wgpu::BufferUsages::VERTEX,
+            },
+        );
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("PostProcess/UniformBuffer"),
+            size: std::mem::size_of::<PostProcessUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("PostProcess/Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let texture_entry =
+            |binding: u32, sample_type: wgpu::TextureSampleType| wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            };
+        let color_sample_type = wgpu::TextureSampleType::Float { filterable: true };
+        let input_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("PostProcess/InputBindGroupLayout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    texture_entry(1, color_sample_type),
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+        let geometry_sample_type = wgpu::TextureSampleType::Float { filterable: false };
+        let geometry_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("PostProcess/GeometryBindGroupLayout"),
+                entries: &[
+                    texture_entry(0, geometry_sample_type),
+                    texture_entry(1, geometry_sample_type),
+                ],
+            });
+        let lookup_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("PostProcess/LookupBindGroupLayout"),
+                entries: &[
+                    texture_entry(0, color_sample_type),
+                    texture_entry(1, color_sample_type),
+                    texture_entry(2, color_sample_type),
+                ],
+            });
+        let pipelines = Self::build_pipelines(
+            format,
+            &input_bind_group_layout,
+            &geometry_bind_group_layout,
+            &lookup_bind_group_layout,
+            device,
+        );
+        let targets = Self::build_targets(
+            size,
+            format,
+            &uniform_buffer,
+            &sampler,
+            &input_bind_group_layout,
+            device,
+        );
+        let lut = Self::create_lut_texture(1, 1, device);
+        queue.write_texture(
+            lut.as_image_copy(),
+            &[0xffu8; 4],
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4),
+                rows_per_image: None,
+            },
+            lut.size(),
+        );
+        let geometry_bind = Self::create_geometry_bind_group(
+            depth_view,
+            &targets,
+            &geometry_bind_group_layout,
+            device,
+        );
+        let lookup_bind =
+            Self::create_lookup_bind_group(&targets, &lut, &lookup_bind_group_layout, device);
+        Self {
+            settings: PostProcessSettings::default(),
+            format,
+            scene_format: format,
+            vertex_buffer,
+            uniform_buffer,
+            sampler,
+            input_bind_group_layout,
+            geometry_bind_group_layout,
+            lookup_bind_group_layout,
+            pipelines,
+            scene_pipelines: None,
+            targets,
+            lut,
+            lut_size: None,
+            geometry_bind,
+            lookup_bind,
+        }
+    }
+
+    pub fn settings(&self) -> &PostProcessSettings {
+        &self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: PostProcessSettings) {
+        self.settings = settings;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.settings.is_active()
+    }
+
+    /// Format of the texture the viewport is drawn into while the stack is active.
+    pub fn scene_format(&self) -> wgpu::TextureFormat {
+        self.scene_format
+    }
+
+    /// Rebuilds the intermediate textures if HDR is toggled, returns true if the scene format
+    /// is changed.
+    pub fn update_format(
+        &mut self,
+        size: Vector2<u32>,
+        depth_view: &wgpu::TextureView,
+        device: &wgpu::Device,
+    ) -> bool {
+        let scene_format = if self.settings.is_hdr_enabled() {
+            Self::HDR_TEXTURE_FORMAT
+        } else {
+            self.format
+        };
+        if scene_format == self.scene_format {
+            return false;
+        }
+        self.scene_format = scene_format;
+        self.scene_pipelines = (scene_format != self.format).then(|| {
+            Self::build_pipelines(
+                scene_format,
+                &self.input_bind_group_layout,
+                &self.geometry_bind_group_layout,
+                &self.lookup_bind_group_layout,
+                device,
+            )
+        });
+        self.resize(size, depth_view, device);
+        true
+    }
+
+    /// Texture the viewport is drawn into while the stack is active.
+    pub fn scene_view(&self) -> &wgpu::TextureView {
+        &self.targets.views[Source::Scene as usize]
+    }
+
+    /// Texture the normal prepass is drawn into, see `PostProcessSettings::requires_normal`.
+    pub fn normal_view(&self) -> &wgpu::TextureView {
+        &self.targets.views[Source::Normal as usize]
+    }
+
+    /// Also clears `depth_view` if the prepass draws its own depth instead of reusing the scene.
+    pub fn clear_normal(
+        &self,
+        depth_view: Option<&wgpu::TextureView>,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("PostProcess/ClearNormal/CommandEncoder"),
+        });
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("PostProcess/ClearNormal/RenderPass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: self.normal_view(),
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: depth_view.map(|view| {
+                wgpu::RenderPassDepthStencilAttachment {
+                    view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1f32),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }
+            }),
+        });
+        queue.submit(Some(encoder.finish()));
+    }
+
+    pub fn resize(
+        &mut self,
+        size: Vector2<u32>,
+        depth_view: &wgpu::TextureView,
+        device: &wgpu::Device,
+    ) {
+        self.targets = Self::build_targets(
+            size,
+            self.scene_format,
+            &self.uniform_buffer,
+            &self.sampler,
+            &self.input_bind_group_layout,
+            device,
+        );
+        self.geometry_bind = Self::create_geometry_bind_group(
+            depth_view,
+            &self.targets,
+            &self.geometry_bind_group_layout,
+            device,
+        );
+        self.lookup_bind = Self::create_lookup_bind_group(
+            &self.targets,
+            &self.lut,
+            &self.lookup_bind_group_layout,
+            device,
+        );
+    }
+
+    /// Sets the color grading LUT from RGBA8 texels laid out as a strip of `size` slices of
+    /// `size` x `size`, returns false if the dimensions are not such a strip.
+    pub fn set_color_grading_lut(
+        &mut self,
+        data: &[u8],
+        dimensions: (u32, u32),
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> bool {
+        let size = match lut_size(dimensions) {
+            Some(size) if data.len() == (dimensions.0 * dimensions.1 * 4) as usize => size,
+            _ => return false,
+        };
+        self.lut = Self::create_lut_texture(dimensions.0, dimensions.1, device);
+        queue.write_texture(
+            self.lut.as_image_copy(),
+            data,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * dimensions.0),
+                rows_per_image: None,
+            },
+            self.lut.size(),
+        );
+        self.lut_size = Some(size);
+        self.lookup_bind = Self::create_lookup_bind_group(
+            &self.targets,
+            &self.lut,
+            &self.lookup_bind_group_layout,
+            device,
+        );
+        true
+    }
+
+    /// Draws the scene texture through all enabled effects into `view`.
+    pub fn draw(
+        &self,
+        view: &wgpu::TextureView,
+        projection: Matrix4<f32>,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        let stages = self.settings.stages();
+        if stages.is_empty() {
+            return;
+        }
+        let uniform = self.uniform(projection);
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&uniform));
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("PostProcess/CommandEncoder"),
+        });
+        let geometry_binds = [&self.geometry_bind];
+        let lookup_binds = [&self.geometry_bind, &self.lookup_bind];
+        let mut input = Source::Scene;
+        for (idx, stage) in stages.iter().enumerate() {
+            if *stage == Stage::AmbientOcclusion {
+                self.encode_pass(
+                    &mut encoder,
+                    &self.pipelines.ambient_occlusion,
+                    input,
+                    &geometry_binds,
+                    self.view(Source::Occlusion),
+                );
+            }
+            if *stage == Stage::Composite && self.settings.bloom {
+                self.encode_pass(
+                    &mut encoder,
+                    &self.pipelines.bloom_extract,
+                    input,
+                    &[],
+                    self.view(Source::Bloom),
+                );
+                self.encode_pass(
+                    &mut encoder,
+                    &self.pipelines.bloom_blur_horizontal,
+                    Source::Bloom,
+                    &[],
+                    self.view(Source::BloomBlur),
+                );
+                self.encode_pass(
+                    &mut encoder,
+                    &self.pipelines.bloom_blur_vertical,
+                    Source::BloomBlur,
+                    &[],
+                    self.view(Source::Bloom),
+                );
+            }
+            let output = if idx + 1 == stages.len() {
+                None
+            } else if idx % 2 == 0 {
+                Some(Source::Ping)
+            } else {
+                Some(Source::Pong)
+            };
+            let pipelines = match (output, &self.scene_pipelines) {
+                (Some(_), Some(scene_pipelines)) => scene_pipelines,
+                _ => &self.pipelines,
+            };
+            let (pipeline, binds): (_, &[&wgpu::BindGroup]) = match stage {
+                Stage::AmbientOcclusion => (&pipelines.ambient_occlusion_apply, &lookup_binds),
+                Stage::Outline => (&pipelines.outline, &geometry_binds),
+                Stage::DepthOfField => (&pipelines.depth_of_field, &geometry_binds),
+                Stage::Composite => (&pipelines.composite, &lookup_binds),
+                Stage::Fxaa => (&pipelines.fxaa, &[]),
+            };
+            let target = output.map_or(view, |output| self.view(output));
+            self.encode_pass(&mut encoder, pipeline, input, binds, target);
+            if let Some(output) = output {
+                input = output;
+            }
+        }
+        queue.submit(Some(encoder.finish()));
+    }
+
+    fn view(&self, source: Source) -> &wgpu::TextureView {
+        &self.targets.views[source as usize]
+    }
+
+    fn encode_pass(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        pipeline: &wgpu::RenderPipeline,
+        input: Source,
+        binds: &[&wgpu::BindGroup],
+        target: &wgpu::TextureView,
+    ) {
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("PostProcess/RenderPass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        rpass.set_pipeline(pipeline);
+        rpass.set_bind_group(0, &self.targets.input_binds[input as usize], &[]);
+        for (idx, bind) in binds.iter().enumerate() {
+            rpass.set_bind_group(idx as u32 + 1, bind, &[]);
+        }
+        rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        rpass.draw(0..4, 0..1);
+    }
+
+    fn uniform(&self, projection: Matrix4<f32>) -> PostProcessUniform {
+        let flag = |value: bool| if value { 1f32 } else { 0f32 };
+        let settings = &self.settings;
+        let color_grading = settings.color_grading && self.lut_size.is_some();
+        PostProcessUniform {
+            inverse_projection: projection.invert().unwrap_or_else(Matrix4::identity).into(),
+            bloom: [
+                settings.bloom_threshold,
+                settings.bloom_intensity,
+                flag(settings.bloom),
+                0f32,
+            ],
+            tone_mapping: [
+                settings.exposure,
+                flag(settings.tone_mapping),
+                settings.tone_mapping_operator.index() as f32,
+                0f32,
+            ],
+            color_grading: [
+                settings.color_grading_intensity,
+                self.lut_size.unwrap_or(1) as f32,
+                flag(color_grading),
+                flag(
+                    self.format.is_srgb()
+                        || matches!(
+                            self.format,
+                            wgpu::TextureFormat::Rgba16Float | wgpu::TextureFormat::Rgba32Float
+                        ),
+                ),
+            ],
+            vignette: [
+                settings.vignette_intensity,
+                settings.vignette_smoothness,
+                flag(settings.vignette),
+                0f32,
+            ],
+            depth_of_field: [
+                settings.focus_distance,
+                settings.focus_range,
+                settings.max_blur_radius,
+                0f32,
+            ],
+            ambient_occlusion: [
+                settings.ambient_occlusion_radius,
+                settings.ambient_occlusion_intensity,
+                settings.ambient_occlusion_bias,
+                0f32,
+            ],
+            outline: [
+                settings.outline_width,
+                settings.outline_depth_threshold,
+                settings.outline_normal_threshold,
+                0f32,
+            ],
+            outline_color: settings.outline_color,
+        }
+    }
+
+    fn build_pipelines(
+        format: wgpu::TextureFormat,
+        input_bind_group_layout: &wgpu::BindGroupLayout,
+        geometry_bind_group_layout: &wgpu::BindGroupLayout,
+        lookup_bind_group_layout: &wgpu::BindGroupLayout,
+        device: &wgpu::Device,
+    ) -> PostProcessPipelines {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("PostProcess/Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                include_str!("../../resources/shaders/post_process.wgsl").into(),
+            ),
+        });
+        let input_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("PostProcess/InputPipelineLayout"),
+            bind_group_layouts: &[input_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let geometry_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("PostProcess/GeometryPipelineLayout"),
+            bind_group_layouts: &[input_bind_group_layout, geometry_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let lookup_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("PostProcess/LookupPipelineLayout"),
+            bind_group_layouts: &[
+                input_bind_group_layout,
+                geometry_bind_group_layout,
+                lookup_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+        let build =
+            |entry_point: &str, layout: &wgpu::PipelineLayout, format: wgpu::TextureFormat| {
+                device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some(format!("PostProcess/Pipeline/{}", entry_point).as_str()),
+                    layout: Some(layout),
+                    vertex: wgpu::VertexState {
+                        module: &shader,
+                        entry_point: "vs_main",
+                        buffers: &[wgpu::VertexBufferLayout {
+                            array_stride: std::mem::size_of::<QuadVertexUnit>() as u64,
+                            step_mode: wgpu::VertexStepMode::Vertex,
+                            attributes: &wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4],
+                        }],
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &shader,
+                        entry_point,
+                        targets: &[Some(wgpu::ColorTargetState {
+                            format,
+                            blend: None,
+                            write_mask: wgpu::ColorWrites::ALL,
+                        })],
+                    }),
+                    primitive: wgpu::PrimitiveState {
+                        topology: wgpu::PrimitiveTopology::TriangleStrip,
+                        strip_index_format: None,
+                        cull_mode: None,
+                        ..Default::default()
+                    },
+                    depth_stencil: None,
+                    multisample: wgpu::MultisampleState::default(),
+                    multiview: None,
+                })
+            };
+        PostProcessPipelines {
+            ambient_occlusion: build(
+                "fs_ambient_occlusion",
+                &geometry_layout,
+                Self::OCCLUSION_TEXTURE_FORMAT,
+            ),
+            ambient_occlusion_apply: build("fs_ambient_occlusion_apply", &lookup_layout, format),
+            outline: build("fs_outline", &geometry_layout, format),
+            depth_of_field: build("fs_depth_of_field", &geometry_layout, format),
+            bloom_extract: build(
+                "fs_bloom_extract",
+                &input_layout,
+                Self::BLOOM_TEXTURE_FORMAT,
+            ),
+            bloom_blur_horizontal: build(
+                "fs_bloom_blur_horizontal",
+                &input_layout,
+                Self::BLOOM_TEXTURE_FORMAT,
+            ),
+            bloom_blur_vertical: build(
+                "fs_bloom_blur_vertical",
+                &input_layout,
+                Self::BLOOM_TEXTURE_FORMAT,
+            ),
+            composite: build("fs_composite", &lookup_layout, format),
+            fxaa: build("fs_fxaa", &input_layout, format),
+        }
+    }
+
+    fn build_targets(
+        size: Vector2<u32>,
+        format: wgpu::TextureFormat,
+        uniform_buffer: &wgpu::Buffer,
+        sampler: &wgpu::Sampler,
+        input_bind_group_layout: &wgpu::BindGroupLayout,
+        device: &wgpu::Device,
+    ) -> PostProcessTargets {
+        let create_texture = |label: &str, size: Vector2<u32>, format: wgpu::TextureFormat| {
+            device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(format!("PostProcess/{}", label).as_str()),
+                size: wgpu::Extent3d {
+                    width: size.x.max(1),
+                    height: size.y.max(1),
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+        };
+        let bloom_size = size / 2;
+        let textures = [
+            create_texture("Scene", size, format),
+            create_texture("Ping", size, format),
+            create_texture("Pong", size, format),
+            create_texture("Bloom", bloom_size, Self::BLOOM_TEXTURE_FORMAT),
+            create_texture("BloomBlur", bloom_size, Self::BLOOM_TEXTURE_FORMAT),
+            create_texture("Normal", size, Technique::NORMAL_TEXTURE_FORMAT),
+            create_texture("Occlusion", size, Self::OCCLUSION_TEXTURE_FORMAT),
+        ];
+        let views = textures
+            .iter()
+            .map(|texture| texture.create_view(&wgpu::TextureViewDescriptor::default()))
+            .collect::<Vec<_>>();
+        let input_binds = views
+            .iter()
+            .map(|view| {
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("PostProcess/InputBindGroup"),
+                    layout: input_bind_group_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: uniform_buffer.as_entire_binding(),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: wgpu::BindingResource::TextureView(view),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 2,
+                            resource: wgpu::BindingResource::Sampler(sampler),
+                        },
+                    ],
+                })
+            })
+            .collect();
+        PostProcessTargets { views, input_binds }
+    }
+
+    fn create_geometry_bind_group(
+        depth_view: &wgpu::TextureView,
+        targets: &PostProcessTargets,
+        geometry_bind_group_layout: &wgpu::BindGroupLayout,
+        device: &wgpu::Device,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("PostProcess/GeometryBindGroup"),
+            layout: geometry_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(depth_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(
+                        &targets.views[Source::Normal as usize],
+                    ),
+                },
+            ],
+        })
+    }
+
+    fn create_lookup_bind_group(
+        targets: &PostProcessTargets,
+        lut: &wgpu::Texture,
+        lookup_bind_group_layout: &wgpu::BindGroupLayout,
+        device: &wgpu::Device,
+    ) -> wgpu::BindGroup {
+        let lut_view = lut.create_view(&wgpu::TextureViewDescriptor::default());
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("PostProcess/LookupBindGroup"),
+            layout: lookup_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(
+                        &targets.views[Source::Bloom as usize],
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&lut_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(
+                        &targets.views[Source::Occlusion as usize],
+                    ),
+                },
+            ],
+        })
+    }
+
+    fn create_lut_texture(width: u32, height: u32, device: &wgpu::Device) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("PostProcess/ColorGradingLut"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        })
+    }
+}
+
+/// Number of slices of a LUT strip, which is `size * size` texels wide and `size` texels high.
+pub fn lut_size(dimensions: (u32, u32)) -> Option<u32> {
+    let (width, height) = dimensions;
+    (height >= 2 && height.checked_mul(height) == Some(width)).then_some(height)
+}
+
+#[test]
+fn test_post_process_stages() {
+    let mut settings = PostProcessSettings {
+        fxaa: true,
+        ..Default::default()
+    };
+    assert!(!settings.is_active());
+    settings.enabled = true;
+    assert_eq!(vec![Stage::Fxaa], settings.stages());
+    settings.depth_of_field = true;
+    settings.vignette = true;
+    assert_eq!(
+        vec![Stage::DepthOfField, Stage::Composite, Stage::Fxaa],
+        settings.stages()
+    );
+    assert!(settings.requires_depth());
+    assert!(!settings.requires_normal());
+    settings.fxaa = false;
+    settings.depth_of_field = false;
+    settings.vignette = false;
+    settings.tone_mapping = true;
+    assert_eq!(vec![Stage::Composite], settings.stages());
+    settings.tone_mapping = false;
+    settings.hdr = true;
+    assert!(settings.is_hdr_enabled());
+    assert_eq!(vec![Stage::Composite], settings.stages());
+    settings.hdr = false;
+    assert!(!settings.requires_normal());
+    settings.outline = true;
+    settings.ambient_occlusion = true;
+    assert!(settings.requires_normal());
+    assert_eq!(
+        vec![Stage::AmbientOcclusion, Stage::Outline],
+        settings.stages()
+    );
+    settings.enabled = false;
+    assert!(!settings.requires_normal());
+    assert!(!settings.requires_depth());
+    assert!(!settings.is_outline_enabled());
+}
+
+#[test]
+fn test_tone_mapping_operator_index() {
+    for operator in ToneMappingOperator::ALL {
+        assert_eq!(
+            Some(operator),
+            ToneMappingOperator::from_index(operator.index())
+        );
+    }
+    assert_eq!(None, ToneMappingOperator::from_index(4));
+}
+
+#[test]
+fn test_lut_size() {
+    assert_eq!(Some(16), lut_size((256, 16)));
+    assert_eq!(Some(32), lut_size((1024, 32)));
+    assert_eq!(None, lut_size((256, 256)));
+    assert_eq!(None, lut_size((1, 1)));
+}
+
+#[test]
+fn test_post_process_shader() {
+    for source in [
+        include_str!("../../resources/shaders/post_process.wgsl"),
+        include_str!("../../resources/shaders/model_normal.wgsl"),
+    ] {
+        super::validate_wgsl(source);
+    }
+}
@@ -0,0 +1,302 @@
+use cgmath::{Matrix4, SquareMatrix};
+
+use crate::forward::QuadVertexUnit;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct SkyboxUniform {
+    inverse_view_projection: [[f32; 4]; 4],
+    parameters: [f32; 4],
+}
+
+/// Draws a cube texture behind the scene, faces are ordered +X, -X, +Y, -Y, +Z and -Z.
+pub struct Skybox {
+    vertex_buffer: wgpu::Buffer,
+    uniform_buffer: wgpu::Buffer,
+    sampler: wgpu::Sampler,
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipeline: wgpu::RenderPipeline,
+    bind_group: Option<wgpu::BindGroup>,
+}
+
+impl Skybox {
+    pub const TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+    pub fn new(format: wgpu::TextureFormat, device: &wgpu::Device) -> Self {
+        let vertex_buffer = wgpu::util::DeviceExt::create_buffer_init(
+            device,
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Skybox/Vertices"),
+                contents: bytemuck::cast_slice(&QuadVertexUnit::generate_quad_tri_strip()),
+                usage: wgpu::BufferUsages::VERTEX,
+            },
+        );
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Skybox/UniformBuffer"),
+            size: std::mem::size_of::<SkyboxUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Skybox/Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Skybox/BindGroupLayout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::Cube,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let pipeline = Self::build_pipeline(format, 1, &bind_group_layout, device);
+        Self {
+            vertex_buffer,
+            uniform_buffer,
+            sampler,
+            bind_group_layout,
+            pipeline,
+            bind_group: None,
+        }
+    }
+
+    /// Uploads `6 * size * size` linear texels laid out face by face.
+    pub fn set_texture(
+        &mut self,
+        size: u32,
+        texels: &[[f32; 3]],
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        let texture_size = wgpu::Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 6,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Skybox/Texture"),
+            size: texture_size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::TEXTURE_FORMAT,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let data = texels
+            .iter()
+            .flat_map(|texel| [texel[0], texel[1], texel[2], 1f32])
+            .map(f16_bits)
+            .collect::<Vec<_>>();
+        queue.write_texture(
+            texture.as_image_copy(),
+            bytemuck::cast_slice(&data),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(size * 8),
+                rows_per_image: Some(size),
+            },
+            texture_size,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+        self.bind_group = Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Skybox/BindGroup"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: self.uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        }));
+    }
+
+    pub fn clear_texture(&mut self) {
+        self.bind_group = None;
+    }
+
+    pub fn update_texture_format(
+        &mut self,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        device: &wgpu::Device,
+    ) {
+        self.pipeline = Self::build_pipeline(format, sample_count, &self.bind_group_layout, device);
+    }
+
+    /// `rotation` is the angle around Y in radians.
+    pub fn draw(
+        &self,
+        view: &wgpu::TextureView,
+        view_projection: Matrix4<f32>,
+        intensity: f32,
+        rotation: f32,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        let bind_group = match &self.bind_group {
+            Some(bind_group) => bind_group,
+            None => return,
+        };
+        let (sin, cos) = rotation.sin_cos();
+        let uniform = SkyboxUniform {
+            inverse_view_projection: view_projection
+                .invert()
+                .unwrap_or_else(Matrix4::identity)
+                .into(),
+            parameters: [intensity, cos, sin, 0f32],
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&uniform));
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Skybox/CommandEncoder"),
+        });
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Skybox/RenderPass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            rpass.set_pipeline(&self.pipeline);
+            rpass.set_bind_group(0, bind_group, &[]);
+            rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            rpass.draw(0..4, 0..1);
+        }
+        queue.submit(Some(encoder.finish()));
+    }
+
+    fn build_pipeline(
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        device: &wgpu::Device,
+    ) -> wgpu::RenderPipeline {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Skybox/Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                include_str!("../../resources/shaders/skybox.wgsl").into(),
+            ),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Skybox/PipelineLayout"),
+            bind_group_layouts: &[bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Skybox/Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<QuadVertexUnit>() as u64,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                strip_index_format: None,
+                cull_mode: None,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
+            multiview: None,
+        })
+    }
+}
+
+/// Converts to a half float, rounding toward zero and flushing subnormals.
+fn f16_bits(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    if value.is_nan() {
+        return sign | 0x7e00;
+    }
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+    if exponent <= 0 {
+        sign
+    } else if exponent >= 0x1f {
+        sign | 0x7c00
+    } else {
+        sign | ((exponent as u16) << 10) | ((bits >> 13) & 0x3ff) as u16
+    }
+}
+
+#[test]
+fn test_f16_bits() {
+    assert_eq!(0x3c00, f16_bits(1f32));
+    assert_eq!(0x3800, f16_bits(0.5f32));
+    assert_eq!(0xc000, f16_bits(-2f32));
+    assert_eq!(0x7bff, f16_bits(65504f32));
+    assert_eq!(0x7c00, f16_bits(1e6f32));
+    assert_eq!(0, f16_bits(1e-8f32));
+}
+
+#[test]
+fn test_skybox_shader() {
+    for source in [
+        include_str!("../../resources/shaders/skybox.wgsl"),
+        include_str!("../../resources/shaders/model_color.wgsl"),
+    ] {
+        super::validate_wgsl(source);
+    }
+}
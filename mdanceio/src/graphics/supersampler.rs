@@ -0,0 +1,271 @@
+use cgmath::Vector2;
+
+use crate::forward::QuadVertexUnit;
+
+/// Filter averaging the rendered texels into an output pixel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DownsampleFilter {
+    /// Averages the texels covered by the output pixel
+    Box,
+    /// Weights the texels around the output pixel, softer but without aliasing of thin lines
+    Gaussian,
+}
+
+impl DownsampleFilter {
+    pub const ALL: [Self; 2] = [Self::Box, Self::Gaussian];
+
+    pub fn from_index(value: u32) -> Option<Self> {
+        Self::ALL.get(value as usize).copied()
+    }
+
+    pub fn index(self) -> u32 {
+        self as u32
+    }
+}
+
+/// Renders the viewport at a multiple of its size and filters it down for offline renders.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SupersamplingSettings {
+    /// Scale of the render size in each axis, 1 disables supersampling
+    pub factor: u32,
+    pub filter: DownsampleFilter,
+}
+
+impl Default for SupersamplingSettings {
+    fn default() -> Self {
+        Self {
+            factor: 1,
+            filter: DownsampleFilter::Box,
+        }
+    }
+}
+
+impl SupersamplingSettings {
+    pub const MAX_FACTOR: u32 = 4;
+
+    pub fn factor(&self) -> u32 {
+        self.factor.clamp(1, Self::MAX_FACTOR)
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.factor() > 1
+    }
+}
+
+/// Owns the enlarged texture the viewport is drawn into and filters it into a view.
+pub struct Supersampler {
+    factor: u32,
+    size: Vector2<u32>,
+    vertex_buffer: wgpu::Buffer,
+    uniform_buffer: wgpu::Buffer,
+    view: wgpu::TextureView,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl Supersampler {
+    pub fn new(
+        viewport_size: Vector2<u32>,
+        factor: u32,
+        format: wgpu::TextureFormat,
+        device: &wgpu::Device,
+    ) -> Self {
+        let size = viewport_size * factor;
+        let vertex_buffer = wgpu::util::DeviceExt::create_buffer_init(
+            device,
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Supersampler/Vertices"),
+                contents: bytemuck::cast_slice(&QuadVertexUnit::generate_quad_tri_strip()),
+                usage: wgpu::BufferUsages::VERTEX,
+            },
+        );
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Supersampler/UniformBuffer"),
+            size: std::mem::size_of::<[i32; 4]>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let view = device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("Supersampler/Texture"),
+                size: wgpu::Extent3d {
+                    width: size.x.max(1),
+                    height: size.y.max(1),
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Supersampler/BindGroupLayout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Supersampler/BindGroup"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+            ],
+        });
+        let pipeline = Self::build_pipeline(format, &bind_group_layout, device);
+        Self {
+            factor,
+            size,
+            vertex_buffer,
+            uniform_buffer,
+            view,
+            bind_group,
+            pipeline,
+        }
+    }
+
+    /// Size the viewport is rendered at.
+    pub fn size(&self) -> Vector2<u32> {
+        self.size
+    }
+
+    /// Texture the viewport is drawn into instead of the output view.
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    /// Filters the enlarged texture into `view` of the viewport size.
+    pub fn downsample(
+        &self,
+        view: &wgpu::TextureView,
+        filter: DownsampleFilter,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        let parameters = [self.factor as i32, filter.index() as i32, 0, 0];
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&parameters));
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Supersampler/CommandEncoder"),
+        });
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Supersampler/RenderPass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            rpass.set_pipeline(&self.pipeline);
+            rpass.set_bind_group(0, &self.bind_group, &[]);
+            rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            rpass.draw(0..4, 0..1);
+        }
+        queue.submit(Some(encoder.finish()));
+    }
+
+    fn build_pipeline(
+        format: wgpu::TextureFormat,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        device: &wgpu::Device,
+    ) -> wgpu::RenderPipeline {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Supersampler/Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                include_str!("../../resources/shaders/downsample.wgsl").into(),
+            ),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Supersampler/PipelineLayout"),
+            bind_group_layouts: &[bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Supersampler/Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<QuadVertexUnit>() as u64,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                strip_index_format: None,
+                cull_mode: None,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        })
+    }
+}
+
+#[test]
+fn test_supersampling_settings() {
+    let mut settings = SupersamplingSettings::default();
+    assert!(!settings.is_active());
+    settings.factor = 0;
+    assert_eq!(1, settings.factor());
+    assert!(!settings.is_active());
+    settings.factor = 2;
+    assert!(settings.is_active());
+    settings.factor = 16;
+    assert_eq!(SupersamplingSettings::MAX_FACTOR, settings.factor());
+    assert_eq!(
+        Some(DownsampleFilter::Gaussian),
+        DownsampleFilter::from_index(DownsampleFilter::Gaussian.index())
+    );
+    assert_eq!(None, DownsampleFilter::from_index(2));
+}
+
+#[test]
+fn test_downsample_shader() {
+    super::validate_wgsl(include_str!("../../resources/shaders/downsample.wgsl"));
+}
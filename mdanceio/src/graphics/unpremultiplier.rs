@@ -0,0 +1,179 @@
+use cgmath::Vector2;
+
+use crate::forward::QuadVertexUnit;
+
+/// How the alpha channel of the drawn viewport is written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ViewportAlphaMode {
+    /// Drawn over the opaque background, alpha is always 1
+    #[default]
+    Opaque,
+    /// Transparent background with colors not multiplied by alpha
+    Straight,
+    /// Transparent background with colors multiplied by alpha, as blended with `One` and
+    /// `OneMinusSrcAlpha` by compositors
+    Premultiplied,
+}
+
+impl ViewportAlphaMode {
+    pub fn is_transparent(self) -> bool {
+        self != Self::Opaque
+    }
+}
+
+/// Owns the texture a transparent viewport is drawn into and divides its colors by alpha.
+pub struct Unpremultiplier {
+    size: Vector2<u32>,
+    vertex_buffer: wgpu::Buffer,
+    view: wgpu::TextureView,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl Unpremultiplier {
+    pub fn new(size: Vector2<u32>, format: wgpu::TextureFormat, device: &wgpu::Device) -> Self {
+        let vertex_buffer = wgpu::util::DeviceExt::create_buffer_init(
+            device,
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Unpremultiplier/Vertices"),
+                contents: bytemuck::cast_slice(&QuadVertexUnit::generate_quad_tri_strip()),
+                usage: wgpu::BufferUsages::VERTEX,
+            },
+        );
+        let view = device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("Unpremultiplier/Texture"),
+                size: wgpu::Extent3d {
+                    width: size.x.max(1),
+                    height: size.y.max(1),
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Unpremultiplier/BindGroupLayout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Unpremultiplier/BindGroup"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&view),
+            }],
+        });
+        let pipeline = Self::build_pipeline(format, &bind_group_layout, device);
+        Self {
+            size,
+            vertex_buffer,
+            view,
+            bind_group,
+            pipeline,
+        }
+    }
+
+    pub fn size(&self) -> Vector2<u32> {
+        self.size
+    }
+
+    /// Texture the premultiplied viewport is drawn into instead of the output view.
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    /// Writes the colors of the texture divided by alpha into `view` of the same size.
+    pub fn draw(&self, view: &wgpu::TextureView, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Unpremultiplier/CommandEncoder"),
+        });
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Unpremultiplier/RenderPass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            rpass.set_pipeline(&self.pipeline);
+            rpass.set_bind_group(0, &self.bind_group, &[]);
+            rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            rpass.draw(0..4, 0..1);
+        }
+        queue.submit(Some(encoder.finish()));
+    }
+
+    fn build_pipeline(
+        format: wgpu::TextureFormat,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        device: &wgpu::Device,
+    ) -> wgpu::RenderPipeline {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Unpremultiplier/Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                include_str!("../../resources/shaders/unpremultiply.wgsl").into(),
+            ),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Unpremultiplier/PipelineLayout"),
+            bind_group_layouts: &[bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Unpremultiplier/Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<QuadVertexUnit>() as u64,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                strip_index_format: None,
+                cull_mode: None,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        })
+    }
+}
+
+#[test]
+fn test_unpremultiply_shader() {
+    super::validate_wgsl(include_str!("../../resources/shaders/unpremultiply.wgsl"));
+}
@@ -33,6 +33,42 @@ impl Grid {
         self.visible
     }
 
+    pub fn set_visible(&mut self, value: bool) {
+        self.visible = value;
+    }
+
+    pub fn update_texture_format(
+        &mut self,
+        texture_format: wgpu::TextureFormat,
+        sample_count: u32,
+        device: &wgpu::Device,
+    ) {
+        self.line_drawer
+            .update_texture_format(texture_format, sample_count, device);
+    }
+
+    pub fn cell(&self) -> Vector2<f32> {
+        self.cell
+    }
+
+    pub fn size(&self) -> Vector2<f32> {
+        self.size
+    }
+
+    pub fn set_cell_and_size(
+        &mut self,
+        cell: Vector2<f32>,
+        size: Vector2<f32>,
+        device: &wgpu::Device,
+    ) {
+        if self.cell != cell || self.size != size {
+            self.cell = cell;
+            self.size = size;
+            let vertices = self.build_grid_vertices();
+            self.line_drawer.replace_vertices(&vertices, device);
+        }
+    }
+
     pub fn draw(
         &self,
         color_view: &wgpu::TextureView,
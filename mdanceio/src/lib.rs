@@ -4,8 +4,10 @@ mod bezier_curve;
 mod bounding_box;
 mod camera;
 mod deformer;
+pub mod effect_package;
+mod environment;
 pub mod error;
-mod event_publisher;
+pub mod event_publisher;
 mod forward;
 mod grid;
 pub mod injector;
@@ -17,6 +19,7 @@ mod motion_keyframe_selection;
 pub mod offscreen_proxy;
 mod physics_engine;
 pub mod project;
+pub mod project_file;
 mod ray;
 mod graphics;
 mod shadow_camera;
@@ -1,4 +1,4 @@
-use cgmath::{AbsDiffEq, InnerSpace, Matrix, Matrix4, Vector3, Zero};
+use cgmath::{AbsDiffEq, InnerSpace, Matrix, Matrix4, Rad, Vector3, Zero};
 
 pub trait Light {
     // TODO
@@ -100,3 +100,277 @@ impl Light for DirectionalLight {
         self.translucent
     }
 }
+
+/// Upper bound of point and spot lights passed to model shaders.
+pub const MAX_LOCAL_LIGHTS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocalLightType {
+    Point,
+    Spot,
+}
+
+impl From<u32> for LocalLightType {
+    fn from(v: u32) -> Self {
+        match v {
+            1 => Self::Spot,
+            _ => Self::Point,
+        }
+    }
+}
+
+impl From<LocalLightType> for u32 {
+    fn from(typ: LocalLightType) -> Self {
+        match typ {
+            LocalLightType::Point => 0,
+            LocalLightType::Spot => 1,
+        }
+    }
+}
+
+/// Point or spot light placed in the stage, attenuated by distance up to its range.
+#[derive(Debug, Clone)]
+pub struct LocalLight {
+    typ: LocalLightType,
+    color: Vector3<f32>,
+    position: Vector3<f32>,
+    direction: Vector3<f32>,
+    range: f32,
+    inner_angle: Rad<f32>,
+    outer_angle: Rad<f32>,
+    shadow_enabled: bool,
+}
+
+impl LocalLight {
+    pub const INITIAL_RANGE: f32 = 50f32;
+    pub const INITIAL_INNER_ANGLE: Rad<f32> = Rad(0.3f32);
+    pub const INITIAL_OUTER_ANGLE: Rad<f32> = Rad(0.5f32);
+    pub const MINIMUM_RANGE: f32 = 0.1f32;
+    pub const MAXIMUM_OUTER_ANGLE: Rad<f32> = Rad(1.5f32);
+    pub const SHADOW_NEAR: f32 = 0.5f32;
+    pub const SHADOW_BIAS: f32 = 0.0005f32;
+
+    pub fn new_point(position: Vector3<f32>, color: Vector3<f32>, range: f32) -> Self {
+        Self {
+            typ: LocalLightType::Point,
+            color,
+            position,
+            direction: -Vector3::unit_y(),
+            range: range.max(Self::MINIMUM_RANGE),
+            inner_angle: Self::INITIAL_INNER_ANGLE,
+            outer_angle: Self::INITIAL_OUTER_ANGLE,
+            shadow_enabled: false,
+        }
+    }
+
+    pub fn new_spot(
+        position: Vector3<f32>,
+        direction: Vector3<f32>,
+        color: Vector3<f32>,
+        range: f32,
+    ) -> Self {
+        let mut light = Self::new_point(position, color, range);
+        light.typ = LocalLightType::Spot;
+        light.set_direction(direction);
+        light.shadow_enabled = true;
+        light
+    }
+
+    pub fn typ(&self) -> LocalLightType {
+        self.typ
+    }
+
+    pub fn position(&self) -> Vector3<f32> {
+        self.position
+    }
+
+    pub fn range(&self) -> f32 {
+        self.range
+    }
+
+    /// Inner and outer half angles of the spot cone.
+    pub fn cone_angles(&self) -> (Rad<f32>, Rad<f32>) {
+        (self.inner_angle, self.outer_angle)
+    }
+
+    pub fn is_shadow_enabled(&self) -> bool {
+        self.shadow_enabled
+    }
+
+    pub fn set_color(&mut self, value: Vector3<f32>) {
+        self.color = value;
+    }
+
+    pub fn set_position(&mut self, value: Vector3<f32>) {
+        self.position = value;
+    }
+
+    pub fn set_direction(&mut self, value: Vector3<f32>) {
+        if !value.abs_diff_eq(&Vector3::<f32>::zero(), Vector3::<f32>::default_epsilon()) {
+            self.direction = value.normalize();
+        }
+    }
+
+    pub fn set_range(&mut self, value: f32) {
+        self.range = value.max(Self::MINIMUM_RANGE);
+    }
+
+    pub fn set_cone_angles(&mut self, inner: Rad<f32>, outer: Rad<f32>) {
+        self.outer_angle = Rad(outer.0.clamp(0f32, Self::MAXIMUM_OUTER_ANGLE.0));
+        self.inner_angle = Rad(inner.0.clamp(0f32, self.outer_angle.0));
+    }
+
+    /// Only spot lights render shadow maps, point lights would need a cube map each.
+    pub fn set_shadow_enabled(&mut self, value: bool) {
+        self.shadow_enabled = value;
+    }
+
+    pub fn casts_shadow(&self) -> bool {
+        self.typ == LocalLightType::Spot && self.shadow_enabled
+    }
+
+    /// Same falloff as `local_light_attenuation` in model_color.wgsl.
+    pub fn attenuation(&self, point: Vector3<f32>) -> f32 {
+        let to_light = self.position - point;
+        let distance = to_light.magnitude();
+        if distance >= self.range {
+            return 0f32;
+        }
+        let ratio = distance / self.range;
+        let falloff = (1f32 - ratio * ratio).clamp(0f32, 1f32);
+        let mut attenuation = falloff * falloff;
+        if self.typ == LocalLightType::Spot {
+            let cos_angle = if distance > f32::EPSILON {
+                (-to_light / distance).dot(self.direction)
+            } else {
+                1f32
+            };
+            attenuation *= smoothstep(
+                self.outer_angle.0.cos(),
+                self.inner_angle.0.cos(),
+                cos_angle,
+            );
+        }
+        attenuation
+    }
+
+    /// View projection of the spot shadow map, depth is mapped into 0..1 up to the range.
+    pub fn shadow_view_projection(&self) -> Matrix4<f32> {
+        let up = if self.direction.y.abs() > 0.99f32 {
+            Vector3::unit_z()
+        } else {
+            Vector3::unit_y()
+        };
+        let z = self.direction;
+        let x = up.cross(z).normalize();
+        let y = z.cross(x);
+        let view = Matrix4::new(
+            x.x,
+            y.x,
+            z.x,
+            0f32,
+            x.y,
+            y.y,
+            z.y,
+            0f32,
+            x.z,
+            y.z,
+            z.z,
+            0f32,
+            -x.dot(self.position),
+            -y.dot(self.position),
+            -z.dot(self.position),
+            1f32,
+        );
+        let near = Self::SHADOW_NEAR.min(self.range * 0.5f32);
+        let far = self.range;
+        let f = 1f32 / self.outer_angle.0.max(0.01f32).tan();
+        let depth_scale = far / (far - near);
+        let projection = Matrix4::new(
+            f,
+            0f32,
+            0f32,
+            0f32,
+            0f32,
+            f,
+            0f32,
+            0f32,
+            0f32,
+            0f32,
+            depth_scale,
+            1f32,
+            0f32,
+            0f32,
+            -near * depth_scale,
+            0f32,
+        );
+        projection * view
+    }
+}
+
+impl Light for LocalLight {
+    fn color(&self) -> Vector3<f32> {
+        self.color
+    }
+
+    fn direction(&self) -> Vector3<f32> {
+        self.direction
+    }
+
+    fn get_shadow_transform(&self) -> Matrix4<f32> {
+        self.shadow_view_projection()
+    }
+
+    fn ground_shadow_color(&self) -> Vector3<f32> {
+        self.color
+    }
+
+    fn is_translucent_ground_shadow_enabled(&self) -> bool {
+        false
+    }
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    if edge1 <= edge0 {
+        return if x >= edge1 { 1f32 } else { 0f32 };
+    }
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0f32, 1f32);
+    t * t * (3f32 - 2f32 * t)
+}
+
+#[test]
+fn test_local_light_attenuation() {
+    let point = LocalLight::new_point(Vector3::zero(), Vector3::new(1f32, 1f32, 1f32), 10f32);
+    assert_eq!(point.attenuation(Vector3::zero()), 1f32);
+    assert!(point.attenuation(Vector3::new(5f32, 0f32, 0f32)) > 0f32);
+    assert_eq!(point.attenuation(Vector3::new(10f32, 0f32, 0f32)), 0f32);
+
+    let mut spot = LocalLight::new_spot(
+        Vector3::new(0f32, 10f32, 0f32),
+        -Vector3::unit_y(),
+        Vector3::new(1f32, 1f32, 1f32),
+        20f32,
+    );
+    spot.set_cone_angles(Rad(0.2f32), Rad(0.4f32));
+    let below = spot.attenuation(Vector3::zero());
+    assert!(below > 0f32);
+    assert_eq!(spot.attenuation(Vector3::new(10f32, 0f32, 0f32)), 0f32);
+    let edge = spot.attenuation(Vector3::new(10f32 * 0.3f32.tan(), 0f32, 0f32));
+    assert!(edge > 0f32 && edge < below);
+}
+
+#[test]
+fn test_spot_light_shadow_view_projection() {
+    let spot = LocalLight::new_spot(
+        Vector3::new(0f32, 10f32, 0f32),
+        -Vector3::unit_y(),
+        Vector3::new(1f32, 1f32, 1f32),
+        20f32,
+    );
+    let clip = spot.shadow_view_projection() * Vector3::zero().extend(1f32);
+    let ndc = clip.truncate() / clip.w;
+    assert!(ndc.x.abs() < 1e-5 && ndc.y.abs() < 1e-5);
+    assert!(ndc.z > 0f32 && ndc.z < 1f32);
+    let clip = spot.shadow_view_projection() * Vector3::new(0f32, -10f32, 0f32).extend(1f32);
+    assert!((clip.z / clip.w - 1f32).abs() < 1e-5);
+}
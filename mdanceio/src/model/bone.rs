@@ -1,12 +1,12 @@
 use std::collections::{HashMap, HashSet};
 
 use cgmath::{
-    AbsDiffEq, ElementWise, Euler, InnerSpace, Matrix4, One, Quaternion, Rad, Rotation3,
+    AbsDiffEq, ElementWise, Euler, InnerSpace, Matrix3, Matrix4, One, Quaternion, Rad, Rotation3,
     SquareMatrix, Vector3, Vector4, VectorSpace, Zero,
 };
 
 use crate::{
-    motion::{interpolation::BoneKeyframeInterpolation, Motion},
+    motion::{interpolation::BoneKeyframeInterpolation, layer::MotionLayer, Motion},
     physics_engine::PhysicsEngine,
     utils::{f128_to_quat, f128_to_vec3, mat4_truncate},
 };
@@ -204,6 +204,29 @@ impl Bone {
         }
     }
 
+    pub fn synchronize_motion_layers(
+        &mut self,
+        layers: &[MotionLayer],
+        frame_index: u32,
+        amount: f32,
+    ) {
+        for layer in layers {
+            if let Some(local_frame_index) = layer.local_frame_index(frame_index) {
+                if layer.affects_bone(&self.canonical_name) {
+                    let transform = layer.motion.find_bone_transform(
+                        &self.canonical_name,
+                        local_frame_index,
+                        amount,
+                    );
+                    self.local_user_translation =
+                        layer.blend_translation(self.local_user_translation, transform.translation);
+                    self.local_user_orientation =
+                        layer.blend_orientation(self.local_user_orientation, transform.orientation);
+                }
+            }
+        }
+    }
+
     pub fn update_matrices(&mut self, parent_bone: Option<&Self>) {
         if self
             .local_translation
@@ -378,6 +401,16 @@ impl Bone {
         self.matrices.world_transform[3].truncate()
     }
 
+    pub fn world_orientation(&self) -> Quaternion<f32> {
+        let transform = self.matrices.world_transform;
+        Quaternion::from(Matrix3::from_cols(
+            transform.x.truncate(),
+            transform.y.truncate(),
+            transform.z.truncate(),
+        ))
+        .normalize()
+    }
+
     pub fn has_unit_x_constraint(&self) -> bool {
         self.canonical_name == Self::LEFT_KNEE_IN_JAPANESE
             || self.canonical_name == Self::RIGHT_KNEE_IN_JAPANESE
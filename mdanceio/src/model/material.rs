@@ -75,6 +75,8 @@ pub struct MaterialStates {
     pub visible: bool,
     pub display_diffuse_texture_uv_mesh_enabled: bool,
     pub display_sphere_map_texture_uv_mesh_enabled: bool,
+    pub ambient_occlusion_excluded: bool,
+    pub outline_excluded: bool,
 }
 
 pub struct Material {
@@ -325,6 +327,22 @@ impl Material {
         self.states.visible
     }
 
+    pub fn is_ambient_occlusion_excluded(&self) -> bool {
+        self.states.ambient_occlusion_excluded
+    }
+
+    pub fn set_ambient_occlusion_excluded(&mut self, value: bool) {
+        self.states.ambient_occlusion_excluded = value;
+    }
+
+    pub fn is_outline_excluded(&self) -> bool {
+        self.states.outline_excluded
+    }
+
+    pub fn set_outline_excluded(&mut self, value: bool) {
+        self.states.outline_excluded = value;
+    }
+
     pub fn color(&self) -> MaterialColor {
         MaterialColor {
             ambient: self
@@ -3,8 +3,11 @@ use std::{
     sync::Arc,
 };
 
-use cgmath::{InnerSpace, Matrix4, Vector4, VectorSpace};
-use nanoem::motion::{MotionBoneKeyframe, MotionModelKeyframe, MotionTrackBundle};
+use cgmath::{InnerSpace, Matrix4, Vector4};
+use nanoem::motion::{
+    MotionBoneKeyframe, MotionEffectParameterValue, MotionModelKeyframeConstraintState,
+    MotionOutsideParent, MotionTrackBundle,
+};
 
 use crate::{
     bounding_box::BoundingBox,
@@ -12,9 +15,8 @@ use crate::{
     deformer::{CommonDeformer, Deformer, WgpuDeformer},
     error::MdanceioError,
     model::{material::MaterialContext, VertexUnit},
-    motion::{Motion, interpolation::coefficient},
+    motion::{layer::MotionSource, Motion},
     physics_engine::{PhysicsEngine, RigidBodyFollowBone, SimulationMode, SimulationTiming},
-    utils::{f128_to_vec4, lerp_f32},
 };
 
 use super::{
@@ -65,9 +67,14 @@ pub struct Model {
     bone_index_hash_map: HashMap<MaterialIndex, HashMap<BoneIndex, usize>>,
     /// Map from target bone to constraint containing it
     outside_parents: HashMap<BoneIndex, (String, String)>,
+    /// Outside parents bound at runtime, taking precedence over ones from model keyframes
+    bound_outside_parents: HashMap<BoneIndex, (String, String)>,
+    effect_parameters: HashMap<String, MotionEffectParameterValue>,
     pub shared_fallback_bone: Bone,
     bounding_box: BoundingBox,
     pub vertex_buffer: Arc<wgpu::Buffer>,
+    /// Vertices of the last drawn auxiliary targets for motion vectors
+    pub previous_vertex_buffer: Arc<wgpu::Buffer>,
     pub index_buffer: Arc<wgpu::Buffer>,
     edge_color: Vector4<f32>,
     name: String,
@@ -78,6 +85,7 @@ pub struct Model {
     opacity: f32,
     count_vertex_skinning_needed: i32,
     stage_vertex_buffer_index: usize,
+    vertex_weight_bone: Option<BoneIndex>,
 }
 
 impl Model {
@@ -141,9 +149,16 @@ impl Model {
                     size: (unpadded_size + padding) as u64,
                     usage: wgpu::BufferUsages::VERTEX
                         | wgpu::BufferUsages::STORAGE
+                        | wgpu::BufferUsages::COPY_SRC
                         | wgpu::BufferUsages::COPY_DST,
                     mapped_at_creation: false,
                 });
+                let previous_vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some(format!("Model/{}/PreviousVertexBuffer", canonical_name).as_str()),
+                    size: (unpadded_size + padding) as u64,
+                    usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                });
                 log::trace!("Len(index_buffer): {}", &opaque.vertex_indices.len());
                 let index_buffer = wgpu::util::DeviceExt::create_buffer_init(
                     device,
@@ -316,8 +331,11 @@ impl Model {
                     joints,
                     soft_bodies,
                     outside_parents: HashMap::new(),
+                    bound_outside_parents: HashMap::new(),
+                    effect_parameters: HashMap::new(),
                     // shared_fallback_bone,
                     vertex_buffer: Arc::new(vertex_buffer),
+                    previous_vertex_buffer: Arc::new(previous_vertex_buffer),
                     index_buffer: Arc::new(index_buffer),
                     shared_fallback_bone,
                     name,
@@ -330,6 +348,7 @@ impl Model {
                     edge_size_scale_factor,
                     bounding_box: BoundingBox::new(),
                     states: initial_states,
+                    vertex_weight_bone: None,
                 })
             }
             Err(status) => Err(MdanceioError::from_nanoem(
@@ -412,51 +431,34 @@ impl Model {
 
     pub fn synchronize_motion(
         &mut self,
-        motion: &Motion,
+        source: MotionSource,
         frame_index: u32,
         amount: f32,
         timing: SimulationTiming,
         physics_engine: &mut PhysicsEngine,
         outside_parent_bone_map: &HashMap<(String, String), Bone>,
     ) {
+        let motion = source.motion;
         let mut visible = true;
         if timing == SimulationTiming::Before {
-            if let Some(keyframe) = motion.find_model_keyframe(frame_index) {
-                self.edge_color = f128_to_vec4(keyframe.edge_color);
-                self.edge_size_scale_factor = keyframe.edge_scale_factor;
-                // TODO: do something if having active effect
-                visible = keyframe.visible;
+            if let Some(frame) = motion.find_model_frame(frame_index, amount) {
+                self.edge_color = frame.edge_color;
+                self.edge_size_scale_factor = frame.edge_scale_factor;
+                visible = frame.visible;
                 self.set_physics_simulation_enabled(
-                    keyframe.is_physics_simulation_enabled,
+                    frame.is_physics_simulation_enabled,
                     physics_engine,
                 );
                 self.set_visible(visible, physics_engine);
                 self.synchronize_all_constraint_states(
-                    keyframe,
+                    &frame.constraint_states,
                     &motion.opaque.local_bone_motion_track_bundle,
                 );
-                self.synchronize_all_outside_parents(
-                    keyframe,
+                self.synchronize_all_effect_parameters(
+                    &frame.effect_parameters,
                     &motion.opaque.global_motion_track_bundle,
                 );
             } else {
-                if let (Some(prev_frame), Some(next_frame)) =
-                    motion.opaque.search_closest_model_keyframes(frame_index)
-                {
-                    let coef = coefficient(
-                        prev_frame.base.frame_index,
-                        next_frame.base.frame_index,
-                        frame_index,
-                    );
-                    self.edge_color = f128_to_vec4(prev_frame.edge_color)
-                        .lerp(f128_to_vec4(next_frame.edge_color), coef);
-                    self.edge_size_scale_factor = lerp_f32(
-                        prev_frame.edge_scale_factor,
-                        next_frame.edge_scale_factor,
-                        coef,
-                    );
-                    // TODO: do something if having active effect
-                }
                 visible = self.states.visible
             }
         }
@@ -466,9 +468,9 @@ impl Model {
                     self.bounding_box.reset();
                     self.reset_materials();
                     self.bones.reset_local_transform();
-                    self.synchronize_morph_motion(motion, frame_index, amount);
+                    self.synchronize_morph_motion(source, frame_index, amount);
                     self.synchronize_bone_motion(
-                        motion,
+                        source,
                         frame_index,
                         amount,
                         timing,
@@ -478,7 +480,7 @@ impl Model {
                 }
                 SimulationTiming::After => {
                     self.synchronize_bone_motion(
-                        motion,
+                        source,
                         frame_index,
                         amount,
                         timing,
@@ -492,10 +494,10 @@ impl Model {
 
     fn synchronize_all_constraint_states(
         &mut self,
-        keyframe: &MotionModelKeyframe,
+        constraint_states: &[MotionModelKeyframeConstraintState],
         local_bone_motion_track_bundle: &MotionTrackBundle<MotionBoneKeyframe>,
     ) {
-        for state in &keyframe.constraint_states {
+        for state in constraint_states {
             if let Some(constraint) = local_bone_motion_track_bundle
                 .resolve_id(state.bone_id)
                 .and_then(|name| self.bones.find_mut_constraint(name))
@@ -505,13 +507,24 @@ impl Model {
         }
     }
 
+    /// Updates outside parents from model keyframes, called before synchronizing motion so that
+    /// the project can order models by their outside parent targets.
+    pub fn synchronize_outside_parents(&mut self, motion: &Motion, frame_index: u32) {
+        if let Some(frame) = motion.find_model_frame(frame_index, 0f32) {
+            self.synchronize_all_outside_parents(
+                &frame.outside_parents,
+                &motion.opaque.global_motion_track_bundle,
+            );
+        }
+    }
+
     fn synchronize_all_outside_parents(
         &mut self,
-        keyframe: &MotionModelKeyframe,
+        outside_parents: &[MotionOutsideParent],
         global_motion_track_bundle: &MotionTrackBundle<()>,
     ) {
         self.outside_parents.clear();
-        for op in &keyframe.outside_parents {
+        for op in outside_parents {
             if let Some(bound_bone) = global_motion_track_bundle
                 .resolve_id(op.local_bone_track_index)
                 .and_then(|subject_bone_name| self.bones.find(subject_bone_name))
@@ -522,7 +535,6 @@ impl Model {
                     if let Some(target_bone_name) =
                         global_motion_track_bundle.resolve_id(op.global_bone_track_index)
                     {
-                        // TODO: verify model and bone exists
                         self.outside_parents.insert(
                             bound_bone.origin.base.index,
                             (target_object_name.clone(), target_bone_name.clone()),
@@ -533,9 +545,22 @@ impl Model {
         }
     }
 
+    fn synchronize_all_effect_parameters(
+        &mut self,
+        effect_parameters: &HashMap<i32, MotionEffectParameterValue>,
+        global_motion_track_bundle: &MotionTrackBundle<()>,
+    ) {
+        self.effect_parameters.clear();
+        for (id, value) in effect_parameters {
+            if let Some(name) = global_motion_track_bundle.resolve_id(*id) {
+                self.effect_parameters.insert(name.clone(), *value);
+            }
+        }
+    }
+
     fn synchronize_bone_motion(
         &mut self,
-        motion: &Motion,
+        source: MotionSource,
         frame_index: u32,
         amount: f32,
         timing: SimulationTiming,
@@ -545,17 +570,25 @@ impl Model {
         if let SimulationTiming::Before = timing {
             for bone in self.bones.iter_mut() {
                 let rigid_body = self.rigid_bodies.find_mut_by_bone(bone.handle);
-                bone.synchronize_motion(motion, rigid_body, frame_index, amount, physics_engine);
+                bone.synchronize_motion(
+                    source.motion,
+                    rigid_body,
+                    frame_index,
+                    amount,
+                    physics_engine,
+                );
+                bone.synchronize_motion_layers(source.layers, frame_index, amount);
             }
         }
         self.apply_bones_transform(timing, outside_parent_bone_map);
     }
 
-    fn synchronize_morph_motion(&mut self, motion: &Motion, frame_index: u32, amount: f32) {
+    fn synchronize_morph_motion(&mut self, source: MotionSource, frame_index: u32, amount: f32) {
         if !self.states.dirty_morph {
             self.reset_morphs();
             for morph in self.morphs.iter_mut() {
-                morph.synchronize_motion(motion, frame_index, amount);
+                morph.synchronize_motion(source.motion, frame_index, amount);
+                morph.synchronize_motion_layers(source.layers, frame_index, amount);
             }
             self.deform_all_morphs(true);
             for morph in self.morphs.iter_mut() {
@@ -623,8 +656,7 @@ impl Model {
             {
                 self.bones.apply_local_transform(idx);
                 let outside_parent_bone = self
-                    .outside_parents
-                    .get(&idx)
+                    .outside_parent(idx)
                     .and_then(|op_path| outside_parent_bone_map.get(op_path));
                 let bone = self.bones.get_mut(idx).unwrap();
                 if let Some(outside_parent_bone) = outside_parent_bone {
@@ -667,6 +699,34 @@ impl Model {
         }
     }
 
+    pub fn set_show_all_bones(&mut self, value: bool) {
+        if self.states.show_all_bones != value {
+            self.states.show_all_bones = value;
+        }
+    }
+
+    pub fn set_show_all_vertex_faces(&mut self, value: bool) {
+        self.states.show_all_vertex_faces = value;
+    }
+
+    pub fn set_show_all_vertex_points(&mut self, value: bool) {
+        self.states.show_all_vertex_points = value;
+    }
+
+    pub fn set_show_all_vertex_weights(&mut self, value: bool) {
+        self.states.show_all_vertex_weights = value;
+    }
+
+    /// Bone whose weights are visualized when showing all vertex weights, active bone if not set
+    pub fn vertex_weight_bone(&self) -> Option<BoneIndex> {
+        self.vertex_weight_bone
+            .or_else(|| self.active_bone().map(|bone| bone.handle))
+    }
+
+    pub fn set_vertex_weight_bone(&mut self, value: Option<BoneIndex>) {
+        self.vertex_weight_bone = value;
+    }
+
     pub fn set_visible(&mut self, value: bool, physics_engine: &mut PhysicsEngine) {
         if self.states.visible != value {
             self.set_all_physics_objects_enabled(
@@ -707,6 +767,44 @@ impl Model {
         }
     }
 
+    pub fn remove_all_physics_objects(&mut self, physics_engine: &mut PhysicsEngine) {
+        for rigid_body in self.rigid_bodies.iter_mut() {
+            rigid_body.destroy(physics_engine);
+        }
+        for joint in self.joints.iter_mut() {
+            joint.disable();
+        }
+    }
+
+    /// Outside parent as pair of model name and bone name the bone is bound to.
+    pub fn outside_parent(&self, bone: BoneIndex) -> Option<&(String, String)> {
+        self.bound_outside_parents
+            .get(&bone)
+            .or_else(|| self.outside_parents.get(&bone))
+    }
+
+    pub fn outside_parent_targets(&self) -> impl Iterator<Item = &(String, String)> {
+        self.bones
+            .iter_idx()
+            .filter_map(|idx| self.outside_parent(idx))
+    }
+
+    /// Binds the bone to another model's bone, or clears the runtime binding if `None`.
+    pub fn set_outside_parent(&mut self, bone: BoneIndex, value: Option<(String, String)>) {
+        match value {
+            Some(value) => self.bound_outside_parents.insert(bone, value),
+            None => self.bound_outside_parents.remove(&bone),
+        };
+    }
+
+    /// Unbinds bones whose outside parent belongs to the model named `model_name`.
+    pub fn remove_outside_parents_of(&mut self, model_name: &str) {
+        self.outside_parents
+            .retain(|_, (target_model, _)| target_model != model_name);
+        self.bound_outside_parents
+            .retain(|_, (target_model, _)| target_model != model_name);
+    }
+
     pub fn deform_all_morphs(&mut self, check_dirty: bool) {
         self.morphs.deform_all(
             check_dirty,
@@ -829,6 +927,23 @@ impl Model {
         self.edge_size_scale_factor
     }
 
+    pub fn set_edge_size_scale_factor(&mut self, value: f32) {
+        self.edge_size_scale_factor = value;
+    }
+
+    /// Effect parameter values driven by model keyframes, keyed by parameter name.
+    pub fn effect_parameters(&self) -> &HashMap<String, MotionEffectParameterValue> {
+        &self.effect_parameters
+    }
+
+    pub fn edge_color(&self) -> Vector4<f32> {
+        self.edge_color
+    }
+
+    pub fn set_edge_color(&mut self, value: Vector4<f32>) {
+        self.edge_color = value;
+    }
+
     pub fn get_name(&self) -> &str {
         &self.name
     }
@@ -841,6 +956,10 @@ impl Model {
         self.states.enable_add_blend
     }
 
+    pub fn set_add_blend_enabled(&mut self, value: bool) {
+        self.states.enable_add_blend = value;
+    }
+
     pub fn is_physics_simulation_enabled(&self) -> bool {
         self.states.physics_simulation
     }
@@ -896,6 +1015,17 @@ impl Model {
             self.states.dirty_staging_buffer = false;
         }
     }
+
+    /// Keeps the current vertices as the previous ones of the next motion vectors.
+    pub fn store_previous_vertex_buffer(&self, encoder: &mut wgpu::CommandEncoder) {
+        encoder.copy_buffer_to_buffer(
+            &self.vertex_buffer,
+            0,
+            &self.previous_vertex_buffer,
+            0,
+            self.vertex_buffer.size(),
+        );
+    }
 }
 
 pub struct Label {
@@ -3,7 +3,11 @@ use std::collections::{HashMap, HashSet};
 use cgmath::{AbsDiffEq, Vector3, Zero};
 use nanoem::model::ModelMorphCategory;
 
-use crate::{motion::Motion, physics_engine::PhysicsEngine, utils::f128_to_vec3};
+use crate::{
+    motion::{layer::MotionLayer, Motion},
+    physics_engine::PhysicsEngine,
+    utils::f128_to_vec3,
+};
 
 use super::{
     bone::BoneSet, material::MaterialSet, rigid_body::RigidBodySet, vertex::VertexSet, BoneIndex,
@@ -64,6 +68,26 @@ impl Morph {
         let weight = motion.find_morph_weight(&self.canonical_name, frame_index, amount);
         self.set_weight(weight);
     }
+
+    pub fn synchronize_motion_layers(
+        &mut self,
+        layers: &[MotionLayer],
+        frame_index: u32,
+        amount: f32,
+    ) {
+        for layer in layers {
+            if let Some(local_frame_index) = layer.local_frame_index(frame_index) {
+                if layer.affects_morph(&self.canonical_name) {
+                    let weight = layer.motion.find_morph_weight(
+                        &self.canonical_name,
+                        local_frame_index,
+                        amount,
+                    );
+                    self.set_weight(layer.blend_weight(self.weight(), weight));
+                }
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -134,6 +158,32 @@ impl MorphSet {
         }
     }
 
+    pub fn set_active(&mut self, idx: MorphIndex) {
+        if let Some(morph) = self.morphs.get(idx) {
+            match morph.origin.category {
+                ModelMorphCategory::Eyebrow => self.active_morph.eyebrow = Some(idx),
+                ModelMorphCategory::Eye => self.active_morph.eye = Some(idx),
+                ModelMorphCategory::Lip => self.active_morph.lip = Some(idx),
+                ModelMorphCategory::Other => self.active_morph.other = Some(idx),
+                _ => {}
+            }
+        }
+    }
+
+    /// Whether the morph is the active one of its category.
+    pub fn is_active(&self, idx: MorphIndex) -> bool {
+        self.morphs.get(idx).is_some_and(|morph| {
+            let active = match morph.origin.category {
+                ModelMorphCategory::Eyebrow => self.active_morph.eyebrow,
+                ModelMorphCategory::Eye => self.active_morph.eye,
+                ModelMorphCategory::Lip => self.active_morph.lip,
+                ModelMorphCategory::Other => self.active_morph.other,
+                _ => None,
+            };
+            active == Some(idx)
+        })
+    }
+
     pub fn get(&self, idx: MorphIndex) -> Option<&Morph> {
         self.morphs.get(idx)
     }
@@ -136,6 +136,14 @@ impl RigidBody {
         }
     }
 
+    /// Removes the rigid body with its collider and attached joints from the physics engine.
+    pub fn destroy(&mut self, physics_engine: &mut PhysicsEngine) {
+        if let Some(handle) = self.physics_rb.take() {
+            physics_engine.remove_rb(handle);
+        }
+        self.states.enabled = false;
+    }
+
     pub fn mark_all_forces_reset(&mut self) {
         self.states.all_forces_should_reset = true;
     }
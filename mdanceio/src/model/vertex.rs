@@ -59,6 +59,7 @@ pub struct VertexSimd {
 pub struct VertexState {
     pub skinning_enabled: bool,
     pub editing: bool,
+    pub selected: bool,
 }
 
 #[derive(Clone)]
@@ -194,6 +195,23 @@ impl Vertex {
     pub fn set_skinning_enabled(&mut self, value: bool) {
         self.states.skinning_enabled = value;
     }
+
+    pub fn is_selected(&self) -> bool {
+        self.states.selected
+    }
+
+    pub fn set_selected(&mut self, value: bool) {
+        self.states.selected = value;
+    }
+
+    pub fn bone_weight(&self, bone_idx: BoneIndex) -> f32 {
+        self.bones
+            .iter()
+            .zip(Into::<[f32; 4]>::into(self.simd.weights))
+            .filter(|(bone, _)| **bone == Some(bone_idx))
+            .map(|(_, weight)| weight)
+            .sum()
+    }
 }
 
 pub struct VertexSet {
@@ -242,4 +260,20 @@ impl VertexSet {
     pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Vertex> {
         self.vertices.iter_mut()
     }
+
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+
+    pub fn set_selected(&mut self, idx: VertexIndex, value: bool) {
+        if let Some(vertex) = self.vertices.get_mut(idx) {
+            vertex.set_selected(value);
+        }
+    }
+
+    pub fn clear_selection(&mut self) {
+        for vertex in &mut self.vertices {
+            vertex.set_selected(false);
+        }
+    }
 }
@@ -0,0 +1,250 @@
+use cgmath::Vector4;
+use nanoem::motion::{
+    Keyframe, MotionBoneKeyframe, MotionBoneKeyframeInterpolation, MotionCameraKeyframe,
+    MotionCameraKeyframeInterpolation, MotionTrack,
+};
+
+use crate::bezier_curve::{BezierCurve, BezierCurveFactory};
+
+use super::{
+    interpolation::KeyframeInterpolationPoint,
+    resample::{BuildKeyframe, ResamplePoint},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BoneInterpolationChannel {
+    TranslationX,
+    TranslationY,
+    TranslationZ,
+    Orientation,
+}
+
+impl BoneInterpolationChannel {
+    pub const ALL: [Self; 4] = [
+        Self::TranslationX,
+        Self::TranslationY,
+        Self::TranslationZ,
+        Self::Orientation,
+    ];
+
+    pub fn parameters_mut(
+        self,
+        interpolation: &mut MotionBoneKeyframeInterpolation,
+    ) -> &mut [u8; 4] {
+        match self {
+            Self::TranslationX => &mut interpolation.translation_x,
+            Self::TranslationY => &mut interpolation.translation_y,
+            Self::TranslationZ => &mut interpolation.translation_z,
+            Self::Orientation => &mut interpolation.orientation,
+        }
+    }
+
+    pub fn from_index(value: u32) -> Option<Self> {
+        Self::ALL.get(value as usize).copied()
+    }
+
+    pub fn parameters(self, mut interpolation: MotionBoneKeyframeInterpolation) -> [u8; 4] {
+        *self.parameters_mut(&mut interpolation)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CameraInterpolationChannel {
+    LookAtX,
+    LookAtY,
+    LookAtZ,
+    Angle,
+    Fov,
+    Distance,
+}
+
+impl CameraInterpolationChannel {
+    pub const ALL: [Self; 6] = [
+        Self::LookAtX,
+        Self::LookAtY,
+        Self::LookAtZ,
+        Self::Angle,
+        Self::Fov,
+        Self::Distance,
+    ];
+
+    pub fn parameters_mut(
+        self,
+        interpolation: &mut MotionCameraKeyframeInterpolation,
+    ) -> &mut [u8; 4] {
+        match self {
+            Self::LookAtX => &mut interpolation.lookat_x,
+            Self::LookAtY => &mut interpolation.lookat_y,
+            Self::LookAtZ => &mut interpolation.lookat_z,
+            Self::Angle => &mut interpolation.angle,
+            Self::Fov => &mut interpolation.fov,
+            Self::Distance => &mut interpolation.distance,
+        }
+    }
+
+    pub fn from_index(value: u32) -> Option<Self> {
+        Self::ALL.get(value as usize).copied()
+    }
+
+    pub fn parameters(self, mut interpolation: MotionCameraKeyframeInterpolation) -> [u8; 4] {
+        *self.parameters_mut(&mut interpolation)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InterpolationPreset {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+impl InterpolationPreset {
+    pub const ALL: [Self; 4] = [Self::Linear, Self::EaseIn, Self::EaseOut, Self::EaseInOut];
+
+    pub fn from_index(value: u32) -> Option<Self> {
+        Self::ALL.get(value as usize).copied()
+    }
+
+    pub fn parameters(self) -> [u8; 4] {
+        match self {
+            Self::Linear => [20, 20, 107, 107],
+            Self::EaseIn => [53, 0, 127, 127],
+            Self::EaseOut => [0, 0, 74, 127],
+            Self::EaseInOut => [53, 0, 74, 127],
+        }
+    }
+}
+
+/// Clamps control points into the range VMD can store.
+pub fn normalize_parameters(parameters: [u8; 4]) -> [u8; 4] {
+    parameters.map(|v| v.min(127))
+}
+
+/// Samples `count` points of the curve in the unit square for drawing.
+pub fn sample_curve(parameters: [u8; 4], count: usize) -> Vec<[f32; 2]> {
+    if count < 2 {
+        return vec![];
+    }
+    let parameters = normalize_parameters(parameters);
+    if KeyframeInterpolationPoint::is_linear_interpolation(&parameters) {
+        let last = (count - 1) as f32;
+        return (0..count)
+            .map(|i| [i as f32 / last, i as f32 / last])
+            .collect();
+    }
+    BezierCurve::from_parameters(Vector4::from(parameters), (count - 1) as u32)
+        .points()
+        .iter()
+        .map(|point| [point.x, point.y])
+        .collect()
+}
+
+pub trait SplitInterval {
+    /// Inserts a keyframe at `frame_index` between two keyframes keeping the motion unchanged,
+    /// splitting interpolation curve of the interval at the frame.
+    ///
+    /// Returns `false` if a keyframe exists at the frame or the frame is not between keyframes.
+    fn split_interval(&mut self, frame_index: u32, curve_factory: &dyn BezierCurveFactory) -> bool;
+}
+
+fn split_point<K: Keyframe>(track: &MotionTrack<K>, frame_index: u32) -> Option<ResamplePoint> {
+    if track.keyframes.contains_key(&frame_index) {
+        return None;
+    }
+    match track.search_closest(frame_index) {
+        (Some(prev), Some(next))
+            if prev.frame_index() < frame_index && frame_index < next.frame_index() =>
+        {
+            let (prev, next) = (prev.frame_index(), next.frame_index());
+            let coef = (frame_index - prev) as f32 / (next - prev) as f32;
+            Some(ResamplePoint {
+                frame_index,
+                origin: prev,
+                position: (prev, next, coef),
+                interval: Some((next, 0f32, coef)),
+                span: None,
+            })
+        }
+        _ => None,
+    }
+}
+
+impl SplitInterval for MotionTrack<MotionBoneKeyframe> {
+    fn split_interval(&mut self, frame_index: u32, curve_factory: &dyn BezierCurveFactory) -> bool {
+        if let Some(point) = split_point(self, frame_index) {
+            let keyframe = self.build_keyframe(&point, curve_factory);
+            let (_, next, coef) = point.position;
+            if let Some(next_keyframe) = self.keyframes.get_mut(&next) {
+                for channel in BoneInterpolationChannel::ALL {
+                    let parameters = channel.parameters_mut(&mut next_keyframe.interpolation);
+                    *parameters = KeyframeInterpolationPoint::sub_range(parameters, coef, 1f32);
+                }
+            }
+            self.insert_keyframe(keyframe);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl SplitInterval for MotionTrack<MotionCameraKeyframe> {
+    fn split_interval(&mut self, frame_index: u32, curve_factory: &dyn BezierCurveFactory) -> bool {
+        if let Some(point) = split_point(self, frame_index) {
+            let keyframe = self.build_keyframe(&point, curve_factory);
+            let (_, next, coef) = point.position;
+            if let Some(next_keyframe) = self.keyframes.get_mut(&next) {
+                for channel in CameraInterpolationChannel::ALL {
+                    let parameters = channel.parameters_mut(&mut next_keyframe.interpolation);
+                    *parameters = KeyframeInterpolationPoint::sub_range(parameters, coef, 1f32);
+                }
+            }
+            self.insert_keyframe(keyframe);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[test]
+fn test_split_bone_interval() {
+    use crate::bezier_curve::BezierCurveCache;
+    use crate::motion::seek::Seek;
+    use nanoem::motion::MotionKeyframeBase;
+    use std::collections::HashMap;
+
+    let keyframe = |frame_index: u32, x: f32| MotionBoneKeyframe {
+        base: MotionKeyframeBase {
+            frame_index,
+            annotations: HashMap::new(),
+        },
+        translation: [x, 0f32, 0f32, 0f32],
+        orientation: [0f32, 0f32, 0f32, 1f32],
+        interpolation: MotionBoneKeyframeInterpolation {
+            translation_x: InterpolationPreset::EaseInOut.parameters(),
+            ..Default::default()
+        },
+        stage_index: 0,
+        is_physics_simulation_enabled: true,
+    };
+    let cache = BezierCurveCache::new();
+    let mut track = MotionTrack::new("bone", 1);
+    track.insert_keyframe(keyframe(0, 0f32));
+    track.insert_keyframe(keyframe(30, 30f32));
+    let before = (0..=30)
+        .map(|i| track.seek(i, &cache).translation.x)
+        .collect::<Vec<_>>();
+    assert!(track.split_interval(10, &cache));
+    assert!(!track.split_interval(10, &cache));
+    assert!(!track.split_interval(40, &cache));
+    assert_eq!(3, track.len());
+    for (i, value) in before.iter().enumerate() {
+        assert!((track.seek(i as u32, &cache).translation.x - value).abs() < 0.1f32);
+    }
+    assert_eq!(
+        vec![[0f32, 0f32], [0.5f32, 0.5f32], [1f32, 1f32]],
+        sample_curve(InterpolationPreset::Linear.parameters(), 3)
+    );
+}
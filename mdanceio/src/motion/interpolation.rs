@@ -1,7 +1,7 @@
-use cgmath::{Vector2, Vector3, VectorSpace};
+use cgmath::{Vector2, Vector3, Vector4, VectorSpace};
 use nanoem::motion::{MotionBoneKeyframeInterpolation, MotionCameraKeyframeInterpolation};
 
-use crate::bezier_curve::{BezierCurveFactory, Curve};
+use crate::bezier_curve::{BezierCurve, BezierCurveFactory, Curve};
 
 pub fn coefficient(prev_frame_index: u32, next_frame_index: u32, frame_index: u32) -> f32 {
     let interval = next_frame_index - prev_frame_index;
@@ -59,6 +59,18 @@ impl KeyframeInterpolationPoint {
         }
     }
 
+    /// Parameters of the part of `interpolation` between `from` and `to` of the interval.
+    pub fn sub_range(interpolation: &[u8; 4], from: f32, to: f32) -> [u8; 4] {
+        if Self::is_linear_interpolation(interpolation) || (from <= 0f32 && to >= 1f32) {
+            *interpolation
+        } else {
+            BezierCurve::from_parameters(Vector4::from(*interpolation), 1)
+                .sub_curve(from, to, 1)
+                .to_parameters()
+                .into()
+        }
+    }
+
     pub fn bezier_control_point(&self) -> [u8; 4] {
         [
             self.control_point1[0],
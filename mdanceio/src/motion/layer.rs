@@ -0,0 +1,149 @@
+use std::collections::HashSet;
+
+use cgmath::{InnerSpace, One, Quaternion, Vector3, VectorSpace};
+
+use super::Motion;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LayerBlendMode {
+    /// Blends toward values of the layer by weight
+    Override,
+    /// Adds values of the layer scaled by weight on top of lower layers
+    Additive,
+}
+
+/// Base motion of a model and the layers evaluated on top of it.
+#[derive(Clone, Copy)]
+pub struct MotionSource<'a> {
+    pub motion: &'a Motion,
+    pub layers: &'a [MotionLayer],
+}
+
+/// Motion evaluated on top of the base motion of a model.
+#[derive(Clone)]
+pub struct MotionLayer {
+    pub name: String,
+    pub motion: Motion,
+    pub weight: f32,
+    pub blend_mode: LayerBlendMode,
+    pub frame_offset: i32,
+    /// Canonical names of bones the layer affects, all bones having tracks if `None`
+    pub bone_mask: Option<HashSet<String>>,
+    /// Canonical names of morphs the layer affects, all morphs having tracks if `None`
+    pub morph_mask: Option<HashSet<String>>,
+    pub enabled: bool,
+    /// Asset path the motion was loaded from, recorded so the layer can be saved
+    pub source: Option<String>,
+}
+
+impl MotionLayer {
+    pub fn new(name: &str, motion: Motion) -> Self {
+        Self {
+            name: name.to_owned(),
+            motion,
+            weight: 1f32,
+            blend_mode: LayerBlendMode::Override,
+            frame_offset: 0,
+            bone_mask: None,
+            morph_mask: None,
+            enabled: true,
+            source: None,
+        }
+    }
+
+    /// Frame index in the layer motion, `None` before the layer starts.
+    pub fn local_frame_index(&self, frame_index: u32) -> Option<u32> {
+        u32::try_from(frame_index as i64 - self.frame_offset as i64).ok()
+    }
+
+    pub fn duration(&self) -> u32 {
+        (self.motion.duration() as i64 + self.frame_offset as i64).max(0) as u32
+    }
+
+    fn is_active(&self) -> bool {
+        self.enabled && self.weight > 0f32
+    }
+
+    pub fn affects_bone(&self, name: &str) -> bool {
+        self.is_active()
+            && self
+                .bone_mask
+                .as_ref()
+                .is_none_or(|mask| mask.contains(name))
+            && self
+                .motion
+                .opaque
+                .local_bone_motion_track_bundle
+                .tracks
+                .contains_key(name)
+    }
+
+    pub fn affects_morph(&self, name: &str) -> bool {
+        self.is_active()
+            && self
+                .morph_mask
+                .as_ref()
+                .is_none_or(|mask| mask.contains(name))
+            && self
+                .motion
+                .opaque
+                .local_morph_motion_track_bundle
+                .tracks
+                .contains_key(name)
+    }
+
+    pub fn blend_translation(&self, base: Vector3<f32>, value: Vector3<f32>) -> Vector3<f32> {
+        match self.blend_mode {
+            LayerBlendMode::Override => base.lerp(value, self.weight),
+            LayerBlendMode::Additive => base + value * self.weight,
+        }
+    }
+
+    pub fn blend_orientation(
+        &self,
+        base: Quaternion<f32>,
+        value: Quaternion<f32>,
+    ) -> Quaternion<f32> {
+        match self.blend_mode {
+            LayerBlendMode::Override => shortest_slerp(base, value, self.weight),
+            LayerBlendMode::Additive => {
+                (base * shortest_slerp(Quaternion::one(), value, self.weight)).normalize()
+            }
+        }
+    }
+
+    pub fn blend_weight(&self, base: f32, value: f32) -> f32 {
+        match self.blend_mode {
+            LayerBlendMode::Override => base + (value - base) * self.weight,
+            LayerBlendMode::Additive => base + value * self.weight,
+        }
+    }
+}
+
+/// Slerp taking the shorter arc, as `q` and `-q` represent the same rotation
+fn shortest_slerp(from: Quaternion<f32>, to: Quaternion<f32>, amount: f32) -> Quaternion<f32> {
+    let to = if from.dot(to) < 0f32 { -to } else { to };
+    from.slerp(to, amount)
+}
+
+#[test]
+fn test_motion_layer_blend() {
+    use cgmath::{Deg, Rotation3};
+
+    let mut layer = MotionLayer::new("layer", Motion::empty());
+    layer.weight = 0.5f32;
+    let base = Quaternion::from_angle_y(Deg(0f32));
+    let value = -Quaternion::from_angle_y(Deg(90f32));
+    let blended = layer.blend_orientation(base, value);
+    let expected = Quaternion::from_angle_y(Deg(45f32));
+    assert!(blended.dot(expected).abs() > 0.9999f32);
+    assert_eq!(0.75f32, layer.blend_weight(0.5f32, 1f32));
+    layer.blend_mode = LayerBlendMode::Additive;
+    let blended = layer.blend_orientation(Quaternion::from_angle_y(Deg(90f32)), value);
+    let expected = Quaternion::from_angle_y(Deg(135f32));
+    assert!(blended.dot(expected).abs() > 0.9999f32);
+    assert_eq!(1f32, layer.blend_weight(0.5f32, 1f32));
+    layer.frame_offset = 10;
+    assert_eq!(None, layer.local_frame_index(5));
+    assert_eq!(Some(5), layer.local_frame_index(15));
+}
@@ -1,5 +1,8 @@
+pub mod curve;
 pub mod interpolation;
+pub mod layer;
 mod motion;
+pub mod resample;
 pub mod seek;
 pub mod update;
 
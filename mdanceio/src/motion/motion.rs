@@ -21,8 +21,12 @@ use crate::{
 };
 
 use super::{
+    curve::{
+        normalize_parameters, BoneInterpolationChannel, CameraInterpolationChannel, SplitInterval,
+    },
     interpolation::KeyframeInterpolationPoint,
-    seek::{BoneFrameTransform, CameraTransform, LightFrame, SelfShadowParam, Seek},
+    resample::Resample,
+    seek::{BoneFrameTransform, CameraTransform, LightFrame, ModelFrame, SelfShadowParam, Seek},
 };
 
 pub type NanoemMotion = nanoem::motion::Motion;
@@ -363,6 +367,45 @@ impl Motion {
             .min(Project::MAXIMUM_BASE_DURATION)
     }
 
+    /// Converts keyframes authored at `from_fps` to frame indices at `to_fps`.
+    ///
+    /// Bone, morph and camera keyframes are re-evaluated at new frame indices and
+    /// their interpolation is refitted to the part of the original curve they cover.
+    /// Other keyframes are moved to the nearest frame index.
+    pub fn resample(&mut self, from_fps: f32, to_fps: f32) {
+        if from_fps <= 0f32 || to_fps <= 0f32 || from_fps == to_fps {
+            return;
+        }
+        let ratio = to_fps / from_fps;
+        for track in self
+            .opaque
+            .local_bone_motion_track_bundle
+            .tracks
+            .values_mut()
+        {
+            *track = track.resample(ratio, &self.bezier_cache);
+        }
+        for track in self
+            .opaque
+            .local_morph_motion_track_bundle
+            .tracks
+            .values_mut()
+        {
+            *track = track.resample(ratio, &self.bezier_cache);
+        }
+        self.opaque.camera_keyframes = self
+            .opaque
+            .camera_keyframes
+            .resample(ratio, &self.bezier_cache);
+        let to_nearest = |frame_index: u32| Some((frame_index as f32 * ratio).round() as u32);
+        self.opaque.accessory_keyframes.retime(to_nearest);
+        self.opaque.light_keyframes.retime(to_nearest);
+        self.opaque.model_keyframes.retime(to_nearest);
+        self.opaque.self_shadow_keyframes.retime(to_nearest);
+        self.opaque.preferred_fps = to_fps;
+        self.dirty = true;
+    }
+
     pub fn find_bone_transform(
         &self,
         name: &str,
@@ -376,6 +419,113 @@ impl Motion {
         }
     }
 
+    pub fn bone_keyframe_interpolation(
+        &self,
+        name: &str,
+        frame_index: u32,
+        channel: BoneInterpolationChannel,
+    ) -> Option<[u8; 4]> {
+        self.find_bone_keyframe(name, frame_index)
+            .map(|keyframe| channel.parameters(keyframe.interpolation))
+    }
+
+    pub fn set_bone_keyframe_interpolation(
+        &mut self,
+        name: &str,
+        frame_index: u32,
+        channel: BoneInterpolationChannel,
+        parameters: [u8; 4],
+    ) -> bool {
+        if let Some(keyframe) = self
+            .opaque
+            .local_bone_motion_track_bundle
+            .tracks
+            .get_mut(name)
+            .and_then(|track| track.keyframes.get_mut(&frame_index))
+        {
+            *channel.parameters_mut(&mut keyframe.interpolation) = normalize_parameters(parameters);
+            self.dirty = true;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn split_bone_interval(&mut self, name: &str, frame_index: u32) -> bool {
+        let split = self
+            .opaque
+            .local_bone_motion_track_bundle
+            .tracks
+            .get_mut(name)
+            .is_some_and(|track| track.split_interval(frame_index, &self.bezier_cache));
+        self.dirty |= split;
+        split
+    }
+
+    pub fn camera_keyframe_interpolation(
+        &self,
+        frame_index: u32,
+        channel: CameraInterpolationChannel,
+    ) -> Option<[u8; 4]> {
+        self.find_camera_keyframe(frame_index)
+            .map(|keyframe| channel.parameters(keyframe.interpolation))
+    }
+
+    pub fn set_camera_keyframe_interpolation(
+        &mut self,
+        frame_index: u32,
+        channel: CameraInterpolationChannel,
+        parameters: [u8; 4],
+    ) -> bool {
+        if let Some(keyframe) = self.opaque.camera_keyframes.keyframes.get_mut(&frame_index) {
+            *channel.parameters_mut(&mut keyframe.interpolation) = normalize_parameters(parameters);
+            self.dirty = true;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn camera_keyframe_outside_parent(&self, frame_index: u32) -> Option<(String, String)> {
+        self.opaque
+            .camera_keyframe_outside_parent(frame_index)
+            .map(|(model_name, bone_name)| (model_name.clone(), bone_name.clone()))
+    }
+
+    pub fn set_camera_keyframe_outside_parent(
+        &mut self,
+        frame_index: u32,
+        value: Option<(&str, &str)>,
+    ) -> bool {
+        let updated = self
+            .opaque
+            .set_camera_keyframe_outside_parent(frame_index, value);
+        self.dirty |= updated;
+        updated
+    }
+
+    /// Names of the model and the bone the seeked camera frame is bound to.
+    pub fn resolve_camera_outside_parent(
+        &self,
+        frame: &CameraTransform,
+    ) -> Option<(String, String)> {
+        let (model_id, bone_id) = frame.outside_parent?;
+        let bundle = &self.opaque.global_motion_track_bundle;
+        Some((
+            bundle.resolve_id(model_id)?.clone(),
+            bundle.resolve_id(bone_id)?.clone(),
+        ))
+    }
+
+    pub fn split_camera_interval(&mut self, frame_index: u32) -> bool {
+        let split = self
+            .opaque
+            .camera_keyframes
+            .split_interval(frame_index, &self.bezier_cache);
+        self.dirty |= split;
+        split
+    }
+
     pub fn find_bone_keyframe(&self, name: &str, frame_index: u32) -> Option<&MotionBoneKeyframe> {
         self.opaque.find_bone_keyframe_object(name, frame_index)
     }
@@ -384,6 +534,12 @@ impl Motion {
         self.opaque.find_model_keyframe_object(frame_index)
     }
 
+    pub fn find_model_frame(&self, frame_index: u32, amount: f32) -> Option<ModelFrame> {
+        self.opaque
+            .model_keyframes
+            .seek_precisely(frame_index, amount, &self.bezier_cache)
+    }
+
     pub fn find_morph_weight(&self, name: &str, frame_index: u32, amount: f32) -> f32 {
         if let Some(track) = self.opaque.local_morph_motion_track_bundle.tracks.get(name) {
             track.seek_precisely(frame_index, amount, &self.bezier_cache)
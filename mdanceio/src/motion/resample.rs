@@ -0,0 +1,485 @@
+use cgmath::{InnerSpace, Quaternion, Vector1, Vector2, Vector3, VectorSpace};
+use nanoem::motion::{
+    Keyframe, MotionBoneKeyframe, MotionCameraKeyframe, MotionMorphKeyframe, MotionTrack,
+};
+
+use crate::{
+    bezier_curve::{BezierCurve, BezierCurveFactory},
+    utils::{f128_to_quat, f128_to_vec3, lerp_element_wise, lerp_f32, quat_to_f128},
+};
+
+use super::interpolation::KeyframeInterpolationPoint;
+
+pub trait Resample {
+    /// Builds the track with frame indices multiplied by `ratio`,
+    /// evaluating values and interpolation of moved keyframes from original curves.
+    fn resample(&self, ratio: f32, curve_factory: &dyn BezierCurveFactory) -> Self;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResamplePoint {
+    pub frame_index: u32,
+    /// Original keyframe states other than values and interpolation are copied from
+    pub origin: u32,
+    /// Original interval and coefficient in it where values are evaluated
+    pub position: (u32, u32, f32),
+    /// Original keyframe holding interpolation of the new interval and the range of it covered
+    pub interval: Option<(u32, f32, f32)>,
+    /// Original time range of the new interval crossing keyframes, its interpolation is fitted
+    pub span: Option<(f32, f32)>,
+}
+
+/// Samples taken over a span to fit interpolation, ends included.
+const SPAN_SAMPLE_COUNT: usize = 17;
+/// Largest difference of a fitted curve from the samples in the unit square.
+const FIT_TOLERANCE: f32 = 0.02;
+const SPAN_EPSILON: f32 = 1e-4;
+
+fn locate(frames: &[u32], time: f32) -> (u32, u32, f32) {
+    let pos = frames.partition_point(|frame_index| *frame_index as f32 <= time);
+    if pos == 0 {
+        (frames[0], frames[0], 0f32)
+    } else if pos == frames.len() {
+        (frames[pos - 1], frames[pos - 1], 0f32)
+    } else {
+        let (prev, next) = (frames[pos - 1], frames[pos]);
+        (prev, next, (time - prev as f32) / (next - prev) as f32)
+    }
+}
+
+fn build_resample_points(frames: &[u32], ratio: f32) -> Vec<ResamplePoint> {
+    let to_source = |frame_index: u32| frame_index as f32 / ratio;
+    let mut points: Vec<ResamplePoint> = vec![];
+    for &origin in frames {
+        let frame_index = (origin as f32 * ratio).round() as u32;
+        match points.last_mut() {
+            Some(last) if last.frame_index == frame_index => last.origin = origin,
+            _ => points.push(ResamplePoint {
+                frame_index,
+                origin,
+                position: (origin, origin, 0f32),
+                interval: None,
+                span: None,
+            }),
+        }
+    }
+    for i in 0..points.len() {
+        let time = to_source(points[i].frame_index);
+        points[i].position = locate(frames, time);
+        if i > 0 {
+            let prev_time = to_source(points[i - 1].frame_index);
+            let (prev, next, _) = locate(frames, (prev_time + time) * 0.5f32);
+            if prev != next {
+                let length = (next - prev) as f32;
+                let from = (prev_time - prev as f32) / length;
+                let to = (time - prev as f32) / length;
+                if from > -SPAN_EPSILON && to < 1f32 + SPAN_EPSILON {
+                    points[i].interval = Some((next, from.max(0f32), to.min(1f32)));
+                } else {
+                    points[i].span = Some((prev_time, time));
+                }
+            }
+        }
+    }
+    points
+}
+
+/// Positions in the original track evenly sampled over `span`.
+fn span_positions(frames: &[u32], (from, to): (f32, f32)) -> Vec<(u32, u32, f32)> {
+    (0..SPAN_SAMPLE_COUNT)
+        .map(|i| locate(frames, from + (to - from) * sample_time(i)))
+        .collect()
+}
+
+fn sample_time(index: usize) -> f32 {
+    index as f32 / (SPAN_SAMPLE_COUNT - 1) as f32
+}
+
+/// Amounts between the first and the last of `values` interpolated linearly,
+/// `None` if any value leaves the path between them.
+fn path_amounts<V: InnerSpace<Scalar = f32>>(values: &[V]) -> Option<Vec<f32>> {
+    let (first, last) = (*values.first()?, *values.last()?);
+    let extent = last - first;
+    let length = extent.magnitude();
+    if length <= SPAN_EPSILON {
+        return values
+            .iter()
+            .all(|value| (*value - first).magnitude() <= SPAN_EPSILON)
+            .then(|| (0..values.len()).map(sample_time).collect());
+    }
+    values
+        .iter()
+        .map(|value| {
+            let amount = (*value - first).dot(extent) / (length * length);
+            let residual = (*value - first - extent * amount).magnitude();
+            (residual <= FIT_TOLERANCE * length).then_some(amount)
+        })
+        .collect()
+}
+
+/// Amounts between the first and the last of `values` interpolated spherically,
+/// `None` if any value leaves the arc between them.
+fn arc_amounts(values: &[Quaternion<f32>]) -> Option<Vec<f32>> {
+    let angle = |a: Quaternion<f32>, b: Quaternion<f32>| a.dot(b).abs().min(1f32).acos() * 2f32;
+    let (first, last) = (*values.first()?, *values.last()?);
+    let length = angle(first, last);
+    if length <= SPAN_EPSILON {
+        return values
+            .iter()
+            .all(|value| angle(first, *value) <= SPAN_EPSILON)
+            .then(|| (0..values.len()).map(sample_time).collect());
+    }
+    values
+        .iter()
+        .map(|value| {
+            let amount = angle(first, *value) / length;
+            let residual = angle(first.slerp(last, amount), *value);
+            (residual <= FIT_TOLERANCE * length).then_some(amount)
+        })
+        .collect()
+}
+
+/// Interpolation parameters of a curve through `amounts` sampled over a span.
+fn fit_interpolation(amounts: Option<Vec<f32>>) -> Option<[u8; 4]> {
+    let samples = amounts?
+        .into_iter()
+        .enumerate()
+        .map(|(i, amount)| Vector2::new(sample_time(i), amount))
+        .collect::<Vec<_>>();
+    BezierCurve::fit_parameters(&samples, FIT_TOLERANCE).map(Into::into)
+}
+
+fn replace_keyframes<K: Keyframe + Clone>(
+    track: &MotionTrack<K>,
+    keyframes: Vec<K>,
+) -> MotionTrack<K> {
+    let mut track = track.clone();
+    track.keyframes.clear();
+    track.ordered_frame_index.clear();
+    for keyframe in keyframes {
+        track.insert_keyframe(keyframe);
+    }
+    track
+}
+
+/// Builds a keyframe at a resample point from keyframes of the original track.
+pub trait BuildKeyframe {
+    type Keyframe;
+
+    fn build_keyframe(
+        &self,
+        point: &ResamplePoint,
+        curve_factory: &dyn BezierCurveFactory,
+    ) -> Self::Keyframe;
+}
+
+impl<K> Resample for MotionTrack<K>
+where
+    K: Keyframe + Clone,
+    MotionTrack<K>: BuildKeyframe<Keyframe = K>,
+{
+    fn resample(&self, ratio: f32, curve_factory: &dyn BezierCurveFactory) -> Self {
+        let keyframes = build_resample_points(&self.ordered_frame_index, ratio)
+            .iter()
+            .map(|point| self.build_keyframe(point, curve_factory))
+            .collect();
+        replace_keyframes(self, keyframes)
+    }
+}
+
+/// Translation and orientation of a bone track at a position in it.
+fn bone_values(
+    track: &MotionTrack<MotionBoneKeyframe>,
+    (prev, next, coef): (u32, u32, f32),
+    curve_factory: &dyn BezierCurveFactory,
+) -> (Vector3<f32>, Quaternion<f32>) {
+    let (prev_frame, next_frame) = (&track.keyframes[&prev], &track.keyframes[&next]);
+    let interval = next - prev;
+    let amounts = Vector3::new(
+        &next_frame.interpolation.translation_x,
+        &next_frame.interpolation.translation_y,
+        &next_frame.interpolation.translation_z,
+    )
+    .map(|interpolation| {
+        KeyframeInterpolationPoint::new(interpolation).curve_value(interval, coef, curve_factory)
+    });
+    let translation = lerp_element_wise(
+        f128_to_vec3(prev_frame.translation),
+        f128_to_vec3(next_frame.translation),
+        amounts,
+    );
+    let amount = KeyframeInterpolationPoint::new(&next_frame.interpolation.orientation)
+        .curve_value(interval, coef, curve_factory);
+    let orientation =
+        f128_to_quat(prev_frame.orientation).slerp(f128_to_quat(next_frame.orientation), amount);
+    (translation, orientation)
+}
+
+impl BuildKeyframe for MotionTrack<MotionBoneKeyframe> {
+    type Keyframe = MotionBoneKeyframe;
+
+    fn build_keyframe(
+        &self,
+        point: &ResamplePoint,
+        curve_factory: &dyn BezierCurveFactory,
+    ) -> Self::Keyframe {
+        let mut keyframe = self.keyframes[&point.origin].clone();
+        keyframe.set_frame_index(point.frame_index);
+        let (translation, orientation) = bone_values(self, point.position, curve_factory);
+        keyframe.translation = translation.extend(0f32).into();
+        keyframe.orientation = quat_to_f128(orientation);
+        if let Some((origin, from, to)) = point.interval {
+            let interpolation = self.keyframes[&origin].interpolation;
+            let sub_range =
+                |value: &[u8; 4]| KeyframeInterpolationPoint::sub_range(value, from, to);
+            keyframe.interpolation.translation_x = sub_range(&interpolation.translation_x);
+            keyframe.interpolation.translation_y = sub_range(&interpolation.translation_y);
+            keyframe.interpolation.translation_z = sub_range(&interpolation.translation_z);
+            keyframe.interpolation.orientation = sub_range(&interpolation.orientation);
+        } else if let Some(span) = point.span {
+            // the interpolation of the origin is kept where no curve follows the span
+            let (translations, orientations): (Vec<_>, Vec<_>) =
+                span_positions(&self.ordered_frame_index, span)
+                    .into_iter()
+                    .map(|position| bone_values(self, position, curve_factory))
+                    .unzip();
+            let interpolation = &mut keyframe.interpolation;
+            for (axis, parameters) in [
+                &mut interpolation.translation_x,
+                &mut interpolation.translation_y,
+                &mut interpolation.translation_z,
+            ]
+            .into_iter()
+            .enumerate()
+            {
+                let values = translations
+                    .iter()
+                    .map(|translation| Vector1::new(translation[axis]))
+                    .collect::<Vec<_>>();
+                if let Some(fitted) = fit_interpolation(path_amounts(&values)) {
+                    *parameters = fitted;
+                }
+            }
+            if let Some(fitted) = fit_interpolation(arc_amounts(&orientations)) {
+                interpolation.orientation = fitted;
+            }
+        }
+        keyframe
+    }
+}
+
+impl BuildKeyframe for MotionTrack<MotionMorphKeyframe> {
+    type Keyframe = MotionMorphKeyframe;
+
+    fn build_keyframe(
+        &self,
+        point: &ResamplePoint,
+        _curve_factory: &dyn BezierCurveFactory,
+    ) -> Self::Keyframe {
+        let mut keyframe = self.keyframes[&point.origin].clone();
+        keyframe.set_frame_index(point.frame_index);
+        let (prev, next, coef) = point.position;
+        keyframe.weight = lerp_f32(
+            self.keyframes[&prev].weight,
+            self.keyframes[&next].weight,
+            coef,
+        );
+        keyframe
+    }
+}
+
+struct CameraValues {
+    look_at: Vector3<f32>,
+    angle: Vector3<f32>,
+    distance: f32,
+    fov: f32,
+}
+
+/// Camera values of a camera track at a position in it, the field of view is not rounded.
+fn camera_values(
+    track: &MotionTrack<MotionCameraKeyframe>,
+    (prev, next, coef): (u32, u32, f32),
+    curve_factory: &dyn BezierCurveFactory,
+) -> CameraValues {
+    let (prev_frame, next_frame) = (&track.keyframes[&prev], &track.keyframes[&next]);
+    let interval = next - prev;
+    let curve_value = |interpolation: &[u8; 4]| {
+        KeyframeInterpolationPoint::new(interpolation).curve_value(interval, coef, curve_factory)
+    };
+    let lookat_amounts = Vector3::new(
+        curve_value(&next_frame.interpolation.lookat_x),
+        curve_value(&next_frame.interpolation.lookat_y),
+        curve_value(&next_frame.interpolation.lookat_z),
+    );
+    CameraValues {
+        look_at: lerp_element_wise(
+            f128_to_vec3(prev_frame.look_at),
+            f128_to_vec3(next_frame.look_at),
+            lookat_amounts,
+        ),
+        angle: f128_to_vec3(prev_frame.angle).lerp(
+            f128_to_vec3(next_frame.angle),
+            curve_value(&next_frame.interpolation.angle),
+        ),
+        distance: lerp_f32(
+            prev_frame.distance,
+            next_frame.distance,
+            curve_value(&next_frame.interpolation.distance),
+        ),
+        fov: lerp_f32(
+            prev_frame.fov as f32,
+            next_frame.fov as f32,
+            curve_value(&next_frame.interpolation.fov),
+        ),
+    }
+}
+
+impl BuildKeyframe for MotionTrack<MotionCameraKeyframe> {
+    type Keyframe = MotionCameraKeyframe;
+
+    fn build_keyframe(
+        &self,
+        point: &ResamplePoint,
+        curve_factory: &dyn BezierCurveFactory,
+    ) -> Self::Keyframe {
+        let mut keyframe = self.keyframes[&point.origin].clone();
+        keyframe.set_frame_index(point.frame_index);
+        let values = camera_values(self, point.position, curve_factory);
+        keyframe.look_at = values.look_at.extend(0f32).into();
+        keyframe.angle = values.angle.extend(0f32).into();
+        keyframe.distance = values.distance;
+        keyframe.fov = values.fov.round() as i32;
+        if let Some((origin, from, to)) = point.interval {
+            let interpolation = self.keyframes[&origin].interpolation;
+            let sub_range =
+                |value: &[u8; 4]| KeyframeInterpolationPoint::sub_range(value, from, to);
+            keyframe.interpolation.lookat_x = sub_range(&interpolation.lookat_x);
+            keyframe.interpolation.lookat_y = sub_range(&interpolation.lookat_y);
+            keyframe.interpolation.lookat_z = sub_range(&interpolation.lookat_z);
+            keyframe.interpolation.angle = sub_range(&interpolation.angle);
+            keyframe.interpolation.fov = sub_range(&interpolation.fov);
+            keyframe.interpolation.distance = sub_range(&interpolation.distance);
+        } else if let Some(span) = point.span {
+            // the interpolation of the origin is kept where no curve follows the span
+            let samples = span_positions(&self.ordered_frame_index, span)
+                .into_iter()
+                .map(|position| camera_values(self, position, curve_factory))
+                .collect::<Vec<_>>();
+            let scalars = |value: fn(&CameraValues) -> f32| {
+                samples
+                    .iter()
+                    .map(|values| Vector1::new(value(values)))
+                    .collect::<Vec<_>>()
+            };
+            let interpolation = &mut keyframe.interpolation;
+            for (parameters, values) in [
+                (&mut interpolation.lookat_x, scalars(|v| v.look_at.x)),
+                (&mut interpolation.lookat_y, scalars(|v| v.look_at.y)),
+                (&mut interpolation.lookat_z, scalars(|v| v.look_at.z)),
+                (&mut interpolation.fov, scalars(|v| v.fov)),
+                (&mut interpolation.distance, scalars(|v| v.distance)),
+            ] {
+                if let Some(fitted) = fit_interpolation(path_amounts(&values)) {
+                    *parameters = fitted;
+                }
+            }
+            let angles = samples
+                .iter()
+                .map(|values| values.angle)
+                .collect::<Vec<_>>();
+            if let Some(fitted) = fit_interpolation(path_amounts(&angles)) {
+                interpolation.angle = fitted;
+            }
+        }
+        keyframe
+    }
+}
+
+#[test]
+fn test_build_resample_points() {
+    let points = build_resample_points(&[0, 10, 20], 2f32);
+    assert_eq!(
+        vec![0, 20, 40],
+        points.iter().map(|p| p.frame_index).collect::<Vec<_>>()
+    );
+    assert_eq!((10, 20, 0f32), points[1].position);
+    assert_eq!(Some((10, 0f32, 1f32)), points[1].interval);
+    let points = build_resample_points(&[0, 1, 2, 5], 0.25f32);
+    assert_eq!(
+        vec![0, 1],
+        points.iter().map(|p| p.frame_index).collect::<Vec<_>>()
+    );
+    assert_eq!(1, points[0].origin);
+    assert_eq!(5, points[1].origin);
+    assert_eq!((2, 5, 2f32 / 3f32), points[1].position);
+    assert_eq!(None, points[1].interval);
+    assert_eq!(Some((0f32, 4f32)), points[1].span);
+}
+
+#[test]
+fn test_resample_fits_merged_interval() {
+    use crate::bezier_curve::{BezierCurveCache, Curve};
+    use crate::motion::{
+        curve::{InterpolationPreset, SplitInterval},
+        seek::Seek,
+    };
+    use cgmath::Vector4;
+    use nanoem::motion::{MotionBoneKeyframeInterpolation, MotionKeyframeBase};
+    use std::collections::HashMap;
+
+    let keyframe = |frame_index: u32, x: f32| MotionBoneKeyframe {
+        base: MotionKeyframeBase {
+            frame_index,
+            annotations: HashMap::new(),
+        },
+        translation: [x, 0f32, 0f32, 0f32],
+        orientation: [0f32, 0f32, 0f32, 1f32],
+        interpolation: MotionBoneKeyframeInterpolation {
+            translation_x: InterpolationPreset::EaseInOut.parameters(),
+            ..Default::default()
+        },
+        stage_index: 0,
+        is_physics_simulation_enabled: true,
+    };
+    let cache = BezierCurveCache::new();
+    let mut track = MotionTrack::new("bone", 1);
+    track.insert_keyframe(keyframe(0, 0f32));
+    track.insert_keyframe(keyframe(4, 8f32));
+    // keyframes at 1 and 2 are merged into the new interval from 0 to 1
+    assert!(track.split_interval(1, &cache));
+    assert!(track.split_interval(2, &cache));
+    let resampled = track.resample(0.25f32, &cache);
+    assert_eq!(vec![0, 1], resampled.ordered_frame_index);
+    let curve = BezierCurve::from_parameters(
+        Vector4::from(resampled.keyframes[&1].interpolation.translation_x),
+        1000,
+    );
+    for frame_index in 0..=4 {
+        let expected = track.seek(frame_index, &cache).translation.x / 8f32;
+        assert!((curve.value(frame_index as f32 / 4f32) - expected).abs() < 0.03f32);
+    }
+}
+
+#[test]
+fn test_sub_range_interpolation() {
+    use crate::bezier_curve::{BezierCurve, Curve};
+    use cgmath::Vector4;
+
+    let linear = [20u8, 20, 107, 107];
+    assert_eq!(
+        linear,
+        KeyframeInterpolationPoint::sub_range(&linear, 0.2f32, 0.7f32)
+    );
+    let interpolation = [64u8, 0, 64, 127];
+    let curve = BezierCurve::from_parameters(Vector4::from(interpolation), 1000);
+    let sub_curve = BezierCurve::from_parameters(
+        Vector4::from(KeyframeInterpolationPoint::sub_range(
+            &interpolation,
+            0f32,
+            0.5f32,
+        )),
+        1000,
+    );
+    let expected = curve.value(0.25f32) / curve.value(0.5f32);
+    assert!((sub_curve.value(0.5f32) - expected).abs() < 0.02f32);
+}
@@ -1,13 +1,16 @@
-use cgmath::{Deg, One, Quaternion, Rad, Vector3, VectorSpace, Zero};
+use std::collections::HashMap;
+
+use cgmath::{Deg, One, Quaternion, Rad, Vector3, Vector4, VectorSpace, Zero};
 use nanoem::motion::{
-    MotionBoneKeyframe, MotionCameraKeyframe, MotionLightKeyframe, MotionMorphKeyframe,
-    MotionSelfShadowKeyframe, MotionTrack,
+    MotionBoneKeyframe, MotionCameraKeyframe, MotionEffectParameterValue, MotionLightKeyframe,
+    MotionModelKeyframe, MotionModelKeyframeConstraintState, MotionMorphKeyframe,
+    MotionOutsideParent, MotionSelfShadowKeyframe, MotionTrack,
 };
 
 use crate::{
     bezier_curve::BezierCurveFactory,
     shadow_camera::CoverageMode,
-    utils::{f128_to_quat, f128_to_vec3, lerp_element_wise, lerp_f32, lerp_rad},
+    utils::{f128_to_quat, f128_to_vec3, f128_to_vec4, lerp_element_wise, lerp_f32, lerp_rad},
 };
 
 use super::interpolation::{
@@ -29,166 +32,105 @@ pub trait Seek {
     ) -> Self::Frame;
 }
 
-// Seek for MotionTrack<MotionBoneKeyframe> would be implemented in future
-
-// #[derive(Debug, Clone)]
-// pub struct ModelFrame {
-//     pub visible: bool,
-//     pub constraint_states: HashMap<BoneIndex, bool>,
-//     pub effect_parameters: HashMap<i32, MotionEffectParameterValue>,
-//     pub outside_parents: Vec<MotionOutsideParent>,
-//     pub has_edge_option: bool,
-//     pub edge_scale_factor: f32,
-//     pub edge_color: [f32; 4],
-//     pub is_add_blending_enabled: bool,
-//     pub is_physics_simulation_enabled: bool,
-// }
-
-// impl Default for ModelFrame {
-//     fn default() -> Self {
-//         Self {
-//             visible: true,
-//             constraint_states: HashMap::new(),
-//             effect_parameters: HashMap::new(),
-//             outside_parents: Vec::new(),
-//             has_edge_option: false,
-//             edge_scale_factor: 1f32,
-//             edge_color: [0f32; 4],
-//             is_add_blending_enabled: false,
-//             is_physics_simulation_enabled: false,
-//         }
-//     }
-// }
-
-// impl From<&MotionModelKeyframe> for ModelFrame {
-//     fn from(v: &MotionModelKeyframe) -> Self {
-//         Self {
-//             visible: v.visible,
-//             constraint_states: v
-//                 .constraint_states
-//                 .iter()
-//                 .filter(|state| state.bone_id >= 0)
-//                 .map(|state| (state.bone_id as usize, state.enabled))
-//                 .collect(),
-//             effect_parameters: v
-//                 .effect_parameters
-//                 .iter()
-//                 .map(|p| (p.parameter_id, p.value))
-//                 .collect(),
-//             outside_parents: v.outside_parents,
-//             has_edge_option: v.has_edge_option,
-//             edge_scale_factor: v.edge_scale_factor,
-//             edge_color: v.edge_color,
-//             is_add_blending_enabled: v.is_add_blending_enabled,
-//             is_physics_simulation_enabled: v.is_physics_simulation_enabled,
-//         }
-//     }
-// }
-
-// impl Seek for MotionTrack<MotionModelKeyframe> {
-//     type Frame = ModelFrame;
-
-//     fn find(&self, frame_index: u32) -> Option<Self::Frame> {
-//         self.keyframes
-//             .get(&frame_index)
-//             .map(|keyframe| keyframe.into())
-//     }
-
-//     fn seek(&self, frame_index: u32, _curve_factory: &dyn BezierCurveFactory) -> Self::Frame {
-//         if let Some(frame) = self.find(frame_index) {
-//             frame
-//         } else {
-//             match self.search_closest(frame_index) {
-//                 (Some(prev), Some(next)) => {
-//                     let coef = super::interpolation::coefficient(
-//                         prev.base.frame_index,
-//                         next.base.frame_index,
-//                         frame_index,
-//                     );
-//                     ModelFrame {
-//                         visible: prev.visible,
-//                         constraint_states: prev
-//                             .constraint_states
-//                             .iter()
-//                             .filter(|state| state.bone_id >= 0)
-//                             .map(|state| (state.bone_id as usize, state.enabled))
-//                             .collect(),
-//                         effect_parameters: prev
-//                             .effect_parameters
-//                             .iter()
-//                             .map(|p| (p.parameter_id, p.value))
-//                             .collect(),
-//                         outside_parents: prev.outside_parents.clone(),
-//                         has_edge_option: prev.has_edge_option,
-//                         edge_scale_factor: prev.edge_scale_factor,
-//                         edge_color: prev.edge_color,
-//                         is_add_blending_enabled: prev.is_add_blending_enabled,
-//                         is_physics_simulation_enabled: prev.is_physics_simulation_enabled,
-//                     }
-//                 }
-//                 (Some(prev_frame), None) => prev_frame.into(),
-//                 _ => ModelFrame::default(),
-//             }
-//         }
-//     }
-
-//     fn seek_precisely(
-//         &self,
-//         frame_index: u32,
-//         amount: f32,
-//         curve_factory: &dyn BezierCurveFactory,
-//     ) -> Self::Frame {
-//         if let Some(frame0) = self.seek(frame_index, curve_factory) {
-//             if amount > 0f32 {
-//                 if let Some(frame1) = self.seek(frame_index, curve_factory) {
-//                     Some(ModelFrame {
-//                         visible: frame0.visible,
-//                         constraint_states: frame0.constraint_states,
-//                         effect_parameters: frame0.effect_parameters,
-//                         outside_parents: frame0.outside_parents,
-//                         has_edge_option: frame0.has_edge_option,
-//                         edge_scale_factor: frame0.edge_scale_factor,
-//                         edge_color: frame0.edge_color,
-//                         is_add_blending_enabled: frame0.is_add_blending_enabled,
-//                         is_physics_simulation_enabled: frame0.is_physics_simulation_enabled,
-//                     })
-//                 } else {
-//                     Some(frame0)
-//                 }
-//             } else {
-//                 Some(frame0)
-//             }
-//         } else {
-//             None
-//         }
-
-//         fn lerp_effect_parameters(
-//             prev: &Vec<MotionEffectParameter>,
-//             next: &Vec<MotionEffectParameter>,
-//             coef: f32,
-//         ) -> HashMap<i32, MotionEffectParameterValue> {
-//             let build_map = |vec: &Vec<MotionEffectParameter>| {vec
-
-//                 .iter()
-//                 .map(|p| (p.parameter_id, p.value))
-//                 .collect()};
-
-//             let next: HashMap<_, _> = build_map(next);
-//             let mut result = HashMap::new();
-//             for prev_param in prev {
-//                 if let Some(next_param) = next.get(&prev_param.parameter_id) {
-//                     result.insert(
-//                         prev_param.parameter_id,
-//                         prev_param.value.lerp_or_first(*next_param, coef),
-//                     );
-//                 } else {
-//                     result.insert(prev_param.parameter_id, prev_param.value);
-//                 }
-//             }
-//             result
-//         }
-//     }
-// }
+#[derive(Debug, Clone)]
+pub struct ModelFrame {
+    pub visible: bool,
+    pub constraint_states: Vec<MotionModelKeyframeConstraintState>,
+    pub effect_parameters: HashMap<i32, MotionEffectParameterValue>,
+    pub outside_parents: Vec<MotionOutsideParent>,
+    pub has_edge_option: bool,
+    pub edge_scale_factor: f32,
+    pub edge_color: Vector4<f32>,
+    pub is_add_blending_enabled: bool,
+    pub is_physics_simulation_enabled: bool,
+}
+
+impl ModelFrame {
+    fn lerp(&self, next: &Self, coef: f32) -> Self {
+        Self {
+            edge_scale_factor: lerp_f32(self.edge_scale_factor, next.edge_scale_factor, coef),
+            edge_color: self.edge_color.lerp(next.edge_color, coef),
+            effect_parameters: self
+                .effect_parameters
+                .iter()
+                .map(|(id, value)| {
+                    let value = match next.effect_parameters.get(id) {
+                        Some(next_value) => value.lerp_or_first(*next_value, coef),
+                        None => *value,
+                    };
+                    (*id, value)
+                })
+                .collect(),
+            ..self.clone()
+        }
+    }
+}
+
+impl From<&MotionModelKeyframe> for ModelFrame {
+    fn from(v: &MotionModelKeyframe) -> Self {
+        Self {
+            visible: v.visible,
+            constraint_states: v.constraint_states.clone(),
+            effect_parameters: v
+                .effect_parameters
+                .iter()
+                .map(|p| (p.parameter_id, p.value))
+                .collect(),
+            outside_parents: v.outside_parents.clone(),
+            has_edge_option: v.has_edge_option,
+            edge_scale_factor: v.edge_scale_factor,
+            edge_color: f128_to_vec4(v.edge_color),
+            is_add_blending_enabled: v.is_add_blending_enabled,
+            is_physics_simulation_enabled: v.is_physics_simulation_enabled,
+        }
+    }
+}
+
+impl Seek for MotionTrack<MotionModelKeyframe> {
+    type Frame = Option<ModelFrame>;
+
+    fn find(&self, frame_index: u32) -> Option<Self::Frame> {
+        self.keyframes
+            .get(&frame_index)
+            .map(|keyframe| Some(keyframe.into()))
+    }
+
+    fn seek(&self, frame_index: u32, _curve_factory: &dyn BezierCurveFactory) -> Self::Frame {
+        if let Some(frame) = self.find(frame_index) {
+            return frame;
+        }
+        // States other than edge and effect parameters are held until next keyframe
+        match self.search_closest(frame_index) {
+            (Some(prev_frame), Some(next_frame)) => {
+                let coef = super::interpolation::coefficient(
+                    prev_frame.base.frame_index,
+                    next_frame.base.frame_index,
+                    frame_index,
+                );
+                let prev: ModelFrame = prev_frame.into();
+                Some(prev.lerp(&next_frame.into(), coef))
+            }
+            (None, Some(next_frame)) => Some(next_frame.into()),
+            (Some(prev_frame), None) => Some(prev_frame.into()),
+            (None, None) => None,
+        }
+    }
+
+    fn seek_precisely(
+        &self,
+        frame_index: u32,
+        amount: f32,
+        curve_factory: &dyn BezierCurveFactory,
+    ) -> Self::Frame {
+        let frame0 = self.seek(frame_index, curve_factory)?;
+        if amount > 0f32 {
+            if let Some(frame1) = self.seek(frame_index + 1, curve_factory) {
+                return Some(frame0.lerp(&frame1, amount));
+            }
+        }
+        Some(frame0)
+    }
+}
 
 #[derive(Debug, Clone, Copy)]
 pub struct BoneFrameTransform {
@@ -361,7 +303,7 @@ impl Seek for MotionTrack<MotionMorphKeyframe> {
     ) -> Self::Frame {
         let w0 = self.seek(frame_index, curve_factory);
         if amount > 0f32 {
-            let w1 = self.seek(frame_index, curve_factory);
+            let w1 = self.seek(frame_index + 1, curve_factory);
             lerp_f32(w0, w1, amount)
         } else {
             w0
@@ -467,7 +409,7 @@ impl Seek for MotionTrack<MotionCameraKeyframe> {
     ) -> Self::Frame {
         if let Some(frame0) = self.seek(frame_index, curve_factory) {
             if amount > 0f32 {
-                if let Some(frame1) = self.seek(frame_index, curve_factory) {
+                if let Some(frame1) = self.seek(frame_index + 1, curve_factory) {
                     Some(CameraTransform {
                         lookat: frame0.lookat.lerp(frame1.lookat, amount),
                         angle: frame0.angle.lerp(frame1.angle, amount),
@@ -541,7 +483,7 @@ impl Seek for MotionTrack<MotionLightKeyframe> {
     ) -> Self::Frame {
         if let Some(frame0) = self.seek(frame_index, curve_factory) {
             if amount > 0f32 {
-                if let Some(frame1) = self.seek(frame_index, curve_factory) {
+                if let Some(frame1) = self.seek(frame_index + 1, curve_factory) {
                     Some(LightFrame {
                         color: frame0.color.lerp(frame1.color, amount),
                         direction: frame0.direction.lerp(frame1.direction, amount),
@@ -608,7 +550,7 @@ impl Seek for MotionTrack<MotionSelfShadowKeyframe> {
     ) -> Self::Frame {
         if let Some(frame0) = self.seek(frame_index, curve_factory) {
             if amount > 0f32 {
-                if let Some(frame1) = self.seek(frame_index, curve_factory) {
+                if let Some(frame1) = self.seek(frame_index + 1, curve_factory) {
                     Some(SelfShadowParam {
                         distance: lerp_f32(frame0.distance, frame1.distance, amount),
                         coverage: frame0.coverage,
@@ -624,3 +566,39 @@ impl Seek for MotionTrack<MotionSelfShadowKeyframe> {
         }
     }
 }
+
+#[test]
+fn test_seek_model_keyframe_holds_states() {
+    use nanoem::motion::MotionKeyframeBase;
+
+    let keyframe = |frame_index: u32, visible: bool, edge_scale_factor: f32| MotionModelKeyframe {
+        base: MotionKeyframeBase {
+            frame_index,
+            annotations: HashMap::new(),
+        },
+        visible,
+        constraint_states: vec![MotionModelKeyframeConstraintState {
+            bone_id: 0,
+            enabled: visible,
+        }],
+        effect_parameters: vec![],
+        outside_parents: vec![],
+        has_edge_option: true,
+        edge_scale_factor,
+        edge_color: [0f32, 0f32, 0f32, 1f32],
+        is_add_blending_enabled: false,
+        is_physics_simulation_enabled: true,
+    };
+    let mut track = MotionTrack::new("model", 0);
+    track.insert_keyframe(keyframe(0, true, 1f32));
+    track.insert_keyframe(keyframe(10, false, 3f32));
+    let cache = crate::bezier_curve::BezierCurveCache::new();
+    let frame = track.seek(5, &cache).unwrap();
+    assert!(frame.visible);
+    assert!(frame.constraint_states[0].enabled);
+    assert_eq!(2f32, frame.edge_scale_factor);
+    let frame = track.seek(20, &cache).unwrap();
+    assert!(!frame.visible);
+    assert!(!frame.constraint_states[0].enabled);
+    assert_eq!(3f32, frame.edge_scale_factor);
+}
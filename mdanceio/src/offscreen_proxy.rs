@@ -1,10 +1,16 @@
 use crate::{
-    base_application_service::BaseApplicationService, error::MdanceioError, injector::Injector,
-    project::ModelHandle,
+    base_application_service::BaseApplicationService,
+    error::MdanceioError,
+    injector::Injector,
+    project::{
+        AuxiliaryTarget, EnvironmentSettings, ModelHandle, MotionBlurSettings, PostProcessSettings,
+        SupersamplingSettings, ViewportAlphaMode,
+    },
 };
 
 pub struct OffscreenProxy {
     texture: wgpu::Texture,
+    texture_format: wgpu::TextureFormat,
     target: wgpu::TextureView,
     buffer_dimensions: BufferDimensions,
     target_buffer: wgpu::Buffer,
@@ -17,8 +23,18 @@ pub struct OffscreenProxy {
 
 impl OffscreenProxy {
     pub async fn init(width: u32, height: u32) -> Self {
-        let texture_format = wgpu::TextureFormat::Bgra8UnormSrgb;
+        Self::init_with_format(width, height, wgpu::TextureFormat::Bgra8UnormSrgb)
+            .await
+            .unwrap()
+    }
 
+    /// Renders into a texture of `texture_format`, e.g. `Rgba16Float` to capture HDR frames
+    /// without clipping. Formats the adapter can't render and blend into are rejected.
+    pub async fn init_with_format(
+        width: u32,
+        height: u32,
+        texture_format: wgpu::TextureFormat,
+    ) -> Result<Self, MdanceioError> {
         let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
             backends: wgpu::Backends::PRIMARY,
             ..Default::default()
@@ -31,10 +47,33 @@ impl OffscreenProxy {
             })
             .await
             .unwrap();
+        // frames are drawn with blending, so integer and unfilterable float formats are rejected
+        // along with depth and compressed ones
+        let device_features =
+            adapter.features() & wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES;
+        let format_features = if device_features.is_empty() {
+            texture_format.guaranteed_format_features(device_features)
+        } else {
+            adapter.get_texture_format_features(texture_format)
+        };
+        if !format_features
+            .allowed_usages
+            .contains(wgpu::TextureUsages::RENDER_ATTACHMENT)
+            || !format_features
+                .flags
+                .contains(wgpu::TextureFormatFeatureFlags::BLENDABLE)
+        {
+            return Err(MdanceioError::unsupported_offscreen_format(&format!(
+                "{:?}",
+                texture_format
+            )));
+        }
         let (device, queue) = adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
                     label: Some("OffscreenRendererDevice"),
+                    // allows MSAA sample counts beyond 4 where the adapter supports them
+                    features: device_features,
                     ..Default::default()
                 },
                 None,
@@ -59,7 +98,7 @@ impl OffscreenProxy {
         let texture = device.create_texture(&texture_desc);
         let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
 
-        let buffer_dimensions = BufferDimensions::new(width, height);
+        let buffer_dimensions = BufferDimensions::new(width, height, texture_format);
         let target_buffer_desc = wgpu::BufferDescriptor {
             label: Some("OffscreenTargetBuffer"),
             size: (buffer_dimensions.padded_bytes_per_row * buffer_dimensions.height)
@@ -79,15 +118,16 @@ impl OffscreenProxy {
             },
         );
 
-        Self {
+        Ok(Self {
             texture,
+            texture_format,
             target: view,
             buffer_dimensions,
             target_buffer,
             device,
             queue,
             application: service,
-        }
+        })
     }
 
     pub fn load_model(&mut self, data: &[u8]) -> Result<ModelHandle, MdanceioError> {
@@ -111,45 +151,183 @@ impl OffscreenProxy {
         self.application.load_model_motion(data)
     }
 
+    pub fn load_camera_motion(&mut self, data: &[u8]) -> Result<(), MdanceioError> {
+        self.application.load_camera_motion(data)
+    }
+
+    pub fn add_camera(&mut self, name: &str) -> Result<(), MdanceioError> {
+        self.application.add_camera(name)
+    }
+
+    /// Selects the named camera frames are rendered from.
+    pub fn set_camera(&mut self, name: &str) -> Result<(), MdanceioError> {
+        self.application.set_active_camera(name)
+    }
+
+    pub fn post_process_settings(&self) -> PostProcessSettings {
+        self.application.post_process_settings()
+    }
+
+    pub fn set_post_process_settings(&mut self, settings: PostProcessSettings) {
+        self.application.set_post_process_settings(settings);
+    }
+
+    pub fn motion_blur_settings(&self) -> MotionBlurSettings {
+        self.application.motion_blur_settings()
+    }
+
+    pub fn set_motion_blur_settings(&mut self, settings: MotionBlurSettings) {
+        self.application.set_motion_blur_settings(settings);
+    }
+
+    /// Sets the MSAA sample count to `1 << value`, e.g. 2 for 4x.
+    pub fn set_sample_level(&mut self, value: u32) -> Result<(), MdanceioError> {
+        self.application.set_sample_level(value)
+    }
+
+    pub fn supersampling_settings(&self) -> SupersamplingSettings {
+        self.application.supersampling_settings()
+    }
+
+    /// Renders at `factor` times the size and filters frames down, combined with MSAA for
+    /// high quality offline renders.
+    pub fn set_supersampling_settings(&mut self, settings: SupersamplingSettings) {
+        self.application.set_supersampling_settings(settings);
+    }
+
+    pub fn alpha_mode(&self) -> ViewportAlphaMode {
+        self.application.viewport_alpha_mode()
+    }
+
+    /// Renders frames over a transparent background for compositing, with straight or
+    /// premultiplied colors in the returned buffers.
+    pub fn set_alpha_mode(&mut self, value: ViewportAlphaMode) {
+        self.application.set_viewport_alpha_mode(value);
+    }
+
+    pub fn is_auxiliary_targets_enabled(&self) -> bool {
+        self.application.is_auxiliary_targets_enabled()
+    }
+
+    /// Draws the auxiliary targets with every rendered frame to be read by
+    /// `read_auxiliary_target`. With motion blur they hold the last sub-frame.
+    pub fn set_auxiliary_targets_enabled(&mut self, value: bool) {
+        self.application.set_auxiliary_targets_enabled(value);
+    }
+
+    pub fn environment_settings(&self) -> EnvironmentSettings {
+        self.application.environment_settings()
+    }
+
+    pub fn set_environment_settings(&mut self, settings: EnvironmentSettings) {
+        self.application.set_environment_settings(settings);
+    }
+
+    pub fn load_environment_image(&mut self, data: &[u8]) -> Result<(), MdanceioError> {
+        self.application
+            .load_environment_image(data, &self.device, &self.queue)
+    }
+
+    pub fn load_color_grading_lut(&mut self, data: &[u8]) -> Result<(), MdanceioError> {
+        self.application
+            .load_color_grading_lut(data, &self.device, &self.queue)
+    }
+
+    pub fn set_material_ambient_occlusion_excluded(
+        &mut self,
+        model_handle: ModelHandle,
+        material_idx: usize,
+        value: bool,
+    ) -> Result<(), MdanceioError> {
+        self.application
+            .set_material_ambient_occlusion_excluded(model_handle, material_idx, value)
+    }
+
+    pub fn set_material_outline_excluded(
+        &mut self,
+        model_handle: ModelHandle,
+        material_idx: usize,
+        value: bool,
+    ) -> Result<(), MdanceioError> {
+        self.application
+            .set_material_outline_excluded(model_handle, material_idx, value)
+    }
+
     pub fn redraw(&mut self) -> Vec<u8> {
         self.application
             .draw_default_pass(&self.target, &self.device, &self.queue);
+        self.read_target()
+    }
 
+    /// Renders the frame `frame_index` at the preferred motion fps with motion blur if enabled.
+    /// Frames should be rendered in order as physics is simulated through the sub-frames.
+    pub fn render_frame(&mut self, frame_index: u32) -> Vec<u8> {
+        self.application
+            .draw_frame(frame_index, &self.target, &self.device, &self.queue);
+        self.read_target()
+    }
+
+    /// Reads `target` of the last rendered frame as rows of raw texels in the format of
+    /// `AuxiliaryTarget::format`, `None` unless auxiliary targets were enabled while rendering.
+    pub fn read_auxiliary_target(&self, target: AuxiliaryTarget) -> Option<Vec<u8>> {
+        let targets = self.application.auxiliary_targets()?;
+        let size = targets.size();
+        let buffer_dimensions = BufferDimensions::new(size.x, size.y, target.format());
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("OffscreenAuxiliaryBuffer"),
+            size: (buffer_dimensions.padded_bytes_per_row * buffer_dimensions.height)
+                as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        Some(self.read_texture(targets.texture(target), &buffer, &buffer_dimensions))
+    }
+
+    fn read_target(&self) -> Vec<u8> {
+        self.read_texture(&self.texture, &self.target_buffer, &self.buffer_dimensions)
+    }
+
+    fn read_texture(
+        &self,
+        texture: &wgpu::Texture,
+        buffer: &wgpu::Buffer,
+        buffer_dimensions: &BufferDimensions,
+    ) -> Vec<u8> {
         let mut encoder = self
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                 label: Some("OffscreenBufferEncoder"),
             });
         encoder.copy_texture_to_buffer(
-            self.texture.as_image_copy(),
+            texture.as_image_copy(),
             wgpu::ImageCopyBuffer {
-                buffer: &self.target_buffer,
+                buffer,
                 layout: wgpu::ImageDataLayout {
                     offset: 0,
-                    bytes_per_row: Some(self.buffer_dimensions.padded_bytes_per_row),
-                    rows_per_image: Some(self.buffer_dimensions.height),
+                    bytes_per_row: Some(buffer_dimensions.padded_bytes_per_row),
+                    rows_per_image: Some(buffer_dimensions.height),
                 },
             },
             wgpu::Extent3d {
-                width: self.buffer_dimensions.width,
-                height: self.buffer_dimensions.height,
+                width: buffer_dimensions.width,
+                height: buffer_dimensions.height,
                 depth_or_array_layers: 1,
             },
         );
         let submission_index = self.queue.submit(Some(encoder.finish()));
 
-        let buffer_slice = self.target_buffer.slice(..);
+        let buffer_slice = buffer.slice(..);
         buffer_slice.map_async(wgpu::MapMode::Read, |_| ());
         self.device
             .poll(wgpu::Maintain::WaitForSubmissionIndex(submission_index));
 
         let mut result = vec![];
         let mapped_buffer = buffer_slice.get_mapped_range();
-        for chunk in mapped_buffer.chunks(self.buffer_dimensions.padded_bytes_per_row as usize) {
-            result.extend(&chunk[..self.buffer_dimensions.unpadded_bytes_per_row as usize]);
+        for chunk in mapped_buffer.chunks(buffer_dimensions.padded_bytes_per_row as usize) {
+            result.extend(&chunk[..buffer_dimensions.unpadded_bytes_per_row as usize]);
         }
         drop(mapped_buffer);
-        self.target_buffer.unmap();
+        buffer.unmap();
         result
     }
 
@@ -160,6 +338,11 @@ impl OffscreenProxy {
     pub fn viewport_size(&self) -> (u32, u32) {
         (self.buffer_dimensions.width, self.buffer_dimensions.height)
     }
+
+    /// Format of the pixels returned by `redraw`.
+    pub fn texture_format(&self) -> wgpu::TextureFormat {
+        self.texture_format
+    }
 }
 
 struct BufferDimensions {
@@ -170,8 +353,10 @@ struct BufferDimensions {
 }
 
 impl BufferDimensions {
-    fn new(width: u32, height: u32) -> Self {
-        let bytes_per_pixel = std::mem::size_of::<u32>() as u32;
+    fn new(width: u32, height: u32, format: wgpu::TextureFormat) -> Self {
+        let bytes_per_pixel = format
+            .block_size(None)
+            .expect("offscreen formats are checked to be color formats");
         let unpadded_bytes_per_row = width * bytes_per_pixel;
         let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
         let padded_bytes_per_row_padding = (align - unpadded_bytes_per_row % align) % align;
@@ -184,3 +369,21 @@ impl BufferDimensions {
         }
     }
 }
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_init_with_integer_format() {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::PRIMARY,
+        ..Default::default()
+    });
+    if instance
+        .request_adapter(&wgpu::RequestAdapterOptions::default())
+        .await
+        .is_none()
+    {
+        return;
+    }
+    let result = OffscreenProxy::init_with_format(64, 64, wgpu::TextureFormat::R32Uint).await;
+    assert!(result.is_err());
+}
@@ -27,6 +27,28 @@ pub enum SimulationMode {
     EnableTracing,
 }
 
+impl From<SimulationMode> for u32 {
+    fn from(mode: SimulationMode) -> Self {
+        match mode {
+            SimulationMode::Disable => 0,
+            SimulationMode::EnableAnytime => 1,
+            SimulationMode::EnablePlaying => 2,
+            SimulationMode::EnableTracing => 3,
+        }
+    }
+}
+
+impl From<u32> for SimulationMode {
+    fn from(value: u32) -> Self {
+        match value {
+            1 => SimulationMode::EnableAnytime,
+            2 => SimulationMode::EnablePlaying,
+            3 => SimulationMode::EnableTracing,
+            _ => SimulationMode::Disable,
+        }
+    }
+}
+
 pub struct PhysicsEngine {
     pub rigid_body_set: RigidBodySet,
     pub collider_set: ColliderSet,
@@ -81,10 +103,20 @@ impl PhysicsEngine {
     }
 
     pub fn step(&mut self, delta: f32, before_step: impl Fn(&mut Self, f32)) {
+        self.internal_step(delta, true, before_step);
+    }
+
+    /// Steps only whole time steps and carries the rest to the next call, so that `delta` split
+    /// across sub-frames simulates the same as stepping it at once.
+    pub fn step_exact(&mut self, delta: f32, before_step: impl Fn(&mut Self, f32)) {
+        self.internal_step(delta, false, before_step);
+    }
+
+    fn internal_step(&mut self, delta: f32, force: bool, before_step: impl Fn(&mut Self, f32)) {
         let dt = self.integration_parameters.dt;
         let mut it_count = (delta + self.dt_residual).div_euclid(dt) as u32;
         self.dt_residual = (delta + self.dt_residual).rem_euclid(dt);
-        if it_count == 0 {
+        if it_count == 0 && force {
             it_count += 1;
             self.dt_residual = 0f32;
         }
@@ -3,31 +3,51 @@ use std::{
     sync::Arc,
 };
 
-use cgmath::{ElementWise, Matrix4, Vector2, Vector3, Vector4};
+use cgmath::{ElementWise, Matrix4, One, Quaternion, Rad, Vector2, Vector3, Vector4};
 
 use crate::{
     audio_player::{AudioPlayer, ClockAudioPlayer},
-    camera::{Camera, PerspectiveCamera},
+    camera::{Camera, CameraFollowMode, PerspectiveCamera},
+    effect_package::{EffectManifest, EffectPackage},
+    environment::Environment,
     error::MdanceioError,
+    event_publisher::{Event, EventPublisher, MotionTarget},
     graphics::effect::{
         render_target::{DrawType, RenderTargetBuilder, RendererConfig, ScreenRenderTarget},
         technique::TechniqueType,
         Effect, RenderFormat,
     },
     graphics::physics_debug::PhysicsDrawerBuilder,
-    graphics::ClearPass,
+    graphics::{
+        AuxiliaryTargets, BoneDrawer, ClearPass, ModelOverlayDrawer, MotionBlur, PostProcess,
+        Supersampler, Unpremultiplier,
+    },
     grid::Grid,
     injector::Injector,
-    light::{DirectionalLight, Light},
-    model::{Bone, Model},
-    motion::Motion,
+    light::{DirectionalLight, Light, LocalLight, LocalLightType, MAX_LOCAL_LIGHTS},
+    model::{Bone, Material, Model},
+    motion::{
+        curve::{BoneInterpolationChannel, CameraInterpolationChannel},
+        layer::{LayerBlendMode, MotionLayer, MotionSource},
+        Motion,
+    },
     physics_engine::{PhysicsEngine, RigidBodyFollowBone, SimulationMode, SimulationTiming},
-    shadow_camera::ShadowCamera,
+    project_file::{
+        copy_persistent_flags, resolve_relative_path, AssetLoader, AssetSources, CameraEntry,
+        EffectEntry, LightEntry, ModelEntry, MotionLayerEntry, ProjectFile,
+    },
+    shadow_camera::{ShadowCamera, ShadowCascade, ShadowMapSettings},
     time_line_segment::TimeLineSegment,
     translator::LanguageType,
     utils::f32_array_to_mat4_col_major_order,
 };
 
+pub use crate::environment::EnvironmentSettings;
+pub use crate::graphics::{
+    AuxiliaryTarget, DownsampleFilter, MotionBlurSettings, PostProcessSettings,
+    SupersamplingSettings, ToneMappingOperator, ViewportAlphaMode,
+};
+
 #[derive(Debug, Clone, Copy)]
 struct SaveState {
     active_model: Option<ModelHandle>,
@@ -89,10 +109,15 @@ impl HandleAllocator {
 
 struct Pass {
     name: String,
-    // color_texture: wgpu::Texture,
-    // color_view: wgpu::TextureView,
+    /// Multisampled color resolved into the viewport by `resolve`, `None` without MSAA
+    color_view: Option<wgpu::TextureView>,
     depth_texture: wgpu::Texture,
     depth_view: wgpu::TextureView,
+    /// Multisampled depth can't be read by the post process, so the normal prepass writes
+    /// a single sampled copy here, `None` without MSAA
+    geometry_depth_view: Option<wgpu::TextureView>,
+    size: Vector2<u32>,
+    sample_count: u32,
     color_texture_format: wgpu::TextureFormat,
     depth_texture_format: wgpu::TextureFormat,
     sampler: wgpu::Sampler,
@@ -107,20 +132,56 @@ impl Pass {
         device: &wgpu::Device,
     ) -> Self {
         let depth_texture_format = wgpu::TextureFormat::Depth16Unorm;
-        let (/*color_texture, color_view, */ depth_texture, depth_view, sampler) = Self::_update(
+        let multisampled = sample_count > 1;
+        let depth_texture = Self::create_texture(
             name,
+            "DepthTexture",
             size,
-            color_texture_format,
             depth_texture_format,
             sample_count,
             device,
         );
+        let depth_view = depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let color_view = multisampled.then(|| {
+            Self::create_texture(
+                name,
+                "ColorTexture",
+                size,
+                color_texture_format,
+                sample_count,
+                device,
+            )
+            .create_view(&wgpu::TextureViewDescriptor::default())
+        });
+        let geometry_depth_view = multisampled.then(|| {
+            Self::create_texture(
+                name,
+                "GeometryDepthTexture",
+                size,
+                depth_texture_format,
+                1,
+                device,
+            )
+            .create_view(&wgpu::TextureViewDescriptor::default())
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some(format!("{}/Sampler", name).as_str()),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::default(),
+            ..Default::default()
+        });
         Self {
             name: name.to_owned(),
-            // color_texture,
-            // color_view,
+            color_view,
             depth_texture,
             depth_view,
+            geometry_depth_view,
+            size,
+            sample_count,
             color_texture_format,
             depth_texture_format,
             sampler,
@@ -134,83 +195,91 @@ impl Pass {
         sample_count: u32,
         device: &wgpu::Device,
     ) {
-        let (/*color_texture, color_view, */ depth_texture, depth_view, sampler) = Self::_update(
-            self.name.as_str(),
-            size,
-            color_texture_format,
-            self.depth_texture_format,
-            sample_count,
-            device,
-        );
-        // self.color_texture = color_texture;
-        // self.color_view = color_view;
+        *self = Self::new(&self.name, size, color_texture_format, sample_count, device);
+    }
+
+    /// Recreates the multisampled color only, keeping the depth bound by the post process.
+    pub fn update_color_format(
+        &mut self,
+        color_texture_format: wgpu::TextureFormat,
+        device: &wgpu::Device,
+    ) {
+        if self.color_texture_format == color_texture_format {
+            return;
+        }
         self.color_texture_format = color_texture_format;
-        self.depth_texture = depth_texture;
-        self.depth_view = depth_view;
-        self.sampler = sampler;
+        if self.color_view.is_some() {
+            self.color_view = Some(
+                Self::create_texture(
+                    &self.name,
+                    "ColorTexture",
+                    self.size,
+                    color_texture_format,
+                    self.sample_count,
+                    device,
+                )
+                .create_view(&wgpu::TextureViewDescriptor::default()),
+            );
+        }
+    }
+
+    /// Depth read by the post process.
+    pub fn geometry_depth_view(&self) -> &wgpu::TextureView {
+        self.geometry_depth_view
+            .as_ref()
+            .unwrap_or(&self.depth_view)
     }
 
-    fn _update(
+    /// Resolves the multisampled color into `target`, does nothing without MSAA.
+    pub fn resolve(&self, target: &wgpu::TextureView, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if let Some(color_view) = &self.color_view {
+            let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some(format!("{}/Resolve/CommandEncoder", self.name).as_str()),
+            });
+            encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some(format!("{}/Resolve/RenderPass", self.name).as_str()),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: color_view,
+                    resolve_target: Some(target),
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            queue.submit(Some(encoder.finish()));
+        }
+    }
+
+    fn create_texture(
         name: &str,
+        label: &str,
         size: Vector2<u32>,
-        color_texture_format: wgpu::TextureFormat,
-        depth_texture_format: wgpu::TextureFormat,
+        format: wgpu::TextureFormat,
         sample_count: u32,
         device: &wgpu::Device,
-    ) -> (
-        // wgpu::Texture,
-        // wgpu::TextureView,
-        wgpu::Texture,
-        wgpu::TextureView,
-        wgpu::Sampler,
-    ) {
-        // TODO: Feature Query For msaa?
-        // let color_texture = device.create_texture(&wgpu::TextureDescriptor {
-        //     label: Some(format!("{}/ColorTexture", name).as_str()),
-        //     size: wgpu::Extent3d {
-        //         width: size.x as u32,
-        //         height: size.y as u32,
-        //         depth_or_array_layers: 1,
-        //     },
-        //     mip_level_count: 1,
-        //     sample_count,
-        //     dimension: wgpu::TextureDimension::D2,
-        //     format: color_texture_format,
-        //     usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-        // });
-        // let color_view = color_texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let depth_texture = device.create_texture(&wgpu::TextureDescriptor {
-            label: Some(format!("{}/DepthTexture", name).as_str()),
+    ) -> wgpu::Texture {
+        // multisampled textures are only attached, the post process reads single sampled ones
+        let usage = if sample_count > 1 {
+            wgpu::TextureUsages::RENDER_ATTACHMENT
+        } else {
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING
+        };
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(format!("{}/{}", name, label).as_str()),
             size: wgpu::Extent3d {
-                width: size.x as u32,
-                height: size.y as u32,
+                width: size.x.max(1),
+                height: size.y.max(1),
                 depth_or_array_layers: 1,
             },
             mip_level_count: 1,
             sample_count,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Depth16Unorm,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format,
+            usage,
             view_formats: &[],
-        });
-        let depth_view = depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let common_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            label: Some(format!("{}/Sampler", name).as_str()),
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Linear,
-            mipmap_filter: wgpu::FilterMode::default(),
-            ..Default::default()
-        });
-        (
-            // color_texture,
-            // color_view,
-            depth_texture,
-            depth_view,
-            common_sampler,
-        )
+        })
     }
 }
 
@@ -227,8 +296,9 @@ impl FpsUnit {
     pub const HALF_BASE_FPS_F32: f32 = Self::HALF_BASE_FPS as f32;
 
     pub fn new(value: u32) -> Self {
+        let value = value.max(1);
         Self {
-            value: value.max(Self::HALF_BASE_FPS),
+            value,
             scale_factor: (value as f32) / Self::HALF_BASE_FPS_F32,
             inverted_value: 1f32 / (value as f32),
             inverted_scale_factor: Self::HALF_BASE_FPS_F32 / (value as f32),
@@ -238,9 +308,18 @@ impl FpsUnit {
     pub fn value(&self) -> u32 {
         self.value
     }
+
+    /// Converts frame index at this rate to base frame index and amount to the next base frame.
+    pub fn split_base_frame(&self, frame_index: u32) -> (u32, f32) {
+        let base_frame_index = frame_index as f64 * Self::HALF_BASE_FPS as f64 / self.value as f64;
+        (
+            base_frame_index.floor() as u32,
+            base_frame_index.fract() as f32,
+        )
+    }
 }
 
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub struct ProjectStates {
     pub disable_hidden_bone_bounds_rigid_body: bool,
     pub display_user_interface: bool,
@@ -258,6 +337,7 @@ pub struct ProjectStates {
     pub loading_redo_file: bool,
     pub enable_playing_audio_part: bool,
     pub enable_viewport_with_transparent: bool,
+    pub enable_viewport_premultiplied_alpha: bool,
     pub enable_compiled_effect_cache: bool,
     pub reset_all_passes: bool,
     pub cancel_requested: bool,
@@ -289,6 +369,20 @@ pub struct ConfirmSeekFlags {
 
 pub type ModelHandle = u32;
 
+/// Named camera kept with its motion while another camera is active.
+#[derive(Clone)]
+struct InactiveCamera {
+    camera: PerspectiveCamera,
+    motion: Motion,
+    motion_source: Option<String>,
+}
+
+/// Camera and model world transform a viewport is drawn from instead of the project camera.
+struct Viewpoint {
+    camera: PerspectiveCamera,
+    world: Matrix4<f32>,
+}
+
 pub struct Project {
     transform_model_order_list: Vec<ModelHandle>,
     active_model_pair: (Option<ModelHandle>, Option<ModelHandle>),
@@ -296,16 +390,37 @@ pub struct Project {
     physics_engine: Box<PhysicsEngine>,
     camera: PerspectiveCamera,
     light: DirectionalLight,
+    local_lights: Vec<LocalLight>,
     shadow_camera: ShadowCamera,
     grid: Box<Grid>,
+    environment: Box<Environment>,
+    bone_drawer: Box<BoneDrawer>,
+    model_overlay_drawer: Box<ModelOverlayDrawer>,
     camera_motion: Motion,
+    /// Names of all cameras in creation order, the active one lives in `camera` and `camera_motion`
+    camera_names: Vec<String>,
+    active_camera_name: String,
+    inactive_cameras: HashMap<String, InactiveCamera>,
     light_motion: Motion,
     self_shadow_motion: Motion,
     model_to_motion: HashMap<ModelHandle, Motion>,
+    model_to_motion_layers: HashMap<ModelHandle, Vec<MotionLayer>>,
     last_save_state: Option<SaveState>,
     // model_program_bundle: Box<ModelProgramBundle>,
     main_render_target: Box<ScreenRenderTarget>,
     clear_pass: Box<ClearPass>,
+    post_process: Box<PostProcess>,
+    motion_blur_settings: MotionBlurSettings,
+    /// Created on the first motion blurred frame
+    motion_blur: Option<Box<MotionBlur>>,
+    /// Position in base frames the last sub-frame was seeked to
+    sub_frame_position: Option<f64>,
+    supersampling_settings: SupersamplingSettings,
+    /// Applied by `reset_all_passes` if supersampling is active
+    supersampler: Option<Box<Supersampler>>,
+    unpremultiplier: Option<Box<Unpremultiplier>>,
+    auxiliary_targets_enabled: bool,
+    auxiliary_targets: Option<Box<AuxiliaryTargets>>,
     viewport_texture_format: (wgpu::TextureFormat, wgpu::TextureFormat),
     editing_mode: EditingMode,
     playing_segment: TimeLineSegment,
@@ -321,6 +436,11 @@ pub struct Project {
     shadow_bind_group_layout: wgpu::BindGroupLayout,
     fallback_texture_bind: wgpu::BindGroup,
     fallback_shadow_bind: Arc<wgpu::BindGroup>,
+    /// Built-in effect used when no user effect applies
+    default_effect: Arc<Effect>,
+    effects: HashMap<String, Arc<Effect>>,
+    model_effects: HashMap<ModelHandle, String>,
+    material_effects: HashMap<(ModelHandle, usize), String>,
     object_handler_allocator: HandleAllocator,
     model_handle_map: HashMap<ModelHandle, Model>,
     viewport_primary_pass: Pass,
@@ -330,9 +450,12 @@ pub struct Project {
     local_frame_index: (u32, u32),
     time_step_factor: f32,
     sample_level: (u32, u32),
+    supported_sample_counts: Vec<u32>,
     state_flags: ProjectStates,
     confirm_seek_flags: ConfirmSeekFlags,
     loaded_texture_map: HashMap<String, wgpu::Texture>,
+    asset_sources: AssetSources,
+    event_publisher: EventPublisher,
 }
 
 impl Project {
@@ -341,6 +464,9 @@ impl Project {
     pub const DEFAULT_CIRCLE_RADIUS_SIZE: f32 = 7.5f32;
 
     pub const DEFAULT_VIEWPORT_IMAGE_SIZE: [u32; 2] = [640, 360];
+    /// Sample level of 16x MSAA
+    pub const MAX_SAMPLE_LEVEL: u32 = 4;
+    pub const DEFAULT_CAMERA_NAME: &'static str = "Camera";
     pub const TIME_BASED_AUDIO_SOURCE_DEFAULT_SAMPLE_RATE: u32 = 1440;
 
     pub const REDO_LOG_FILE_EXTENSION: &'static str = "redo";
@@ -376,6 +502,20 @@ impl Project {
         );
         log::trace!("Finish Primary and Secondary Pass");
 
+        let post_process = PostProcess::new(
+            viewport_size,
+            injector.texture_format(),
+            viewport_primary_pass.geometry_depth_view(),
+            device,
+            queue,
+        );
+        let supported_sample_counts = Self::find_supported_sample_counts(
+            &[injector.texture_format(), PostProcess::HDR_TEXTURE_FORMAT],
+            wgpu::TextureFormat::Depth16Unorm,
+            adapter,
+            device,
+        );
+
         let fallback_texture = Self::create_white_fallback_image(&device, &queue)
             .create_view(&wgpu::TextureViewDescriptor::default());
         let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
@@ -489,7 +629,7 @@ impl Project {
                         visibility: wgpu::ShaderStages::FRAGMENT,
                         ty: wgpu::BindingType::Texture {
                             sample_type: wgpu::TextureSampleType::Float { filterable: false },
-                            view_dimension: wgpu::TextureViewDimension::D2,
+                            view_dimension: wgpu::TextureViewDimension::D2Array,
                             multisampled: false,
                         },
                         count: None,
@@ -500,26 +640,46 @@ impl Project {
                         ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::NonFiltering),
                         count: None,
                     },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                            view_dimension: wgpu::TextureViewDimension::D2Array,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
                 ],
             });
+        let fallback_array_texture = Self::create_white_fallback_image(device, queue).create_view(
+            &wgpu::TextureViewDescriptor {
+                dimension: Some(wgpu::TextureViewDimension::D2Array),
+                ..Default::default()
+            },
+        );
         let shadow_fallback_bind = Arc::new(device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("BindGroup/ShadowFallbackBindGroup"),
             layout: &shadow_bind_group_layout,
             entries: &[
                 wgpu::BindGroupEntry {
                     binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&fallback_texture),
+                    resource: wgpu::BindingResource::TextureView(&fallback_array_texture),
                 },
                 wgpu::BindGroupEntry {
                     binding: 1,
                     resource: wgpu::BindingResource::Sampler(&shadow_sampler),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&fallback_array_texture),
+                },
             ],
         }));
 
         let mut camera = PerspectiveCamera::new();
 
-        camera.update(viewport_size, camera.look_at(None));
+        camera.update(viewport_size, camera.look_at(None), Quaternion::one());
         camera.set_dirty(false);
         let mut shadow_camera =
             ShadowCamera::new(&shadow_bind_group_layout, &shadow_sampler, device);
@@ -551,6 +711,14 @@ impl Project {
                 TechniqueType::Zplot,
                 include_str!("../resources/shaders/model_zplot.wgsl"),
             ),
+            (
+                TechniqueType::Normal,
+                include_str!("../resources/shaders/model_normal.wgsl"),
+            ),
+            (
+                TechniqueType::Auxiliary,
+                include_str!("../resources/shaders/model_auxiliary.wgsl"),
+            ),
         ]);
         let model_effect = Arc::new(Effect::new(shaders, true, &shadow_fallback_bind, device));
         let main_render_target = ScreenRenderTarget::new(
@@ -561,6 +729,7 @@ impl Project {
                     format: RenderFormat {
                         color: injector.texture_format(),
                         depth: Some(wgpu::TextureFormat::Depth16Unorm),
+                        sample_count: 1,
                     },
                     size: wgpu::Extent3d {
                         width: injector.viewport_size[0],
@@ -572,6 +741,8 @@ impl Project {
                         DrawType::Edge,
                         DrawType::GroundShadow,
                         DrawType::ShadowMap,
+                        DrawType::Normal,
+                        DrawType::Auxiliary,
                     ]),
                 },
             },
@@ -602,10 +773,20 @@ impl Project {
             viewport_size: (viewport_size, viewport_size),
             active_model_pair: (None, None),
             grid: Box::new(Grid::new(injector.texture_format(), device)),
+            environment: Box::new(Environment::new(injector.texture_format(), device)),
+            bone_drawer: Box::new(BoneDrawer::new(injector.texture_format())),
+            model_overlay_drawer: Box::new(ModelOverlayDrawer::new(
+                injector.texture_format(),
+                device,
+            )),
             camera_motion,
+            camera_names: vec![Self::DEFAULT_CAMERA_NAME.to_owned()],
+            active_camera_name: Self::DEFAULT_CAMERA_NAME.to_owned(),
+            inactive_cameras: HashMap::new(),
             light_motion,
             self_shadow_motion,
             model_to_motion: HashMap::new(),
+            model_to_motion_layers: HashMap::new(),
             // model_program_bundle: Box::new(ModelProgramBundle::new(
             //     injector.texture_format(),
             //     wgpu::TextureFormat::Depth16Unorm,
@@ -615,16 +796,28 @@ impl Project {
             clear_pass: Box::new(ClearPass::new(
                 &[Some(injector.texture_format())],
                 Some(wgpu::TextureFormat::Depth16Unorm),
+                1,
                 device,
             )),
+            post_process: Box::new(post_process),
+            motion_blur_settings: MotionBlurSettings::default(),
+            motion_blur: None,
+            sub_frame_position: None,
+            supersampling_settings: SupersamplingSettings::default(),
+            supersampler: None,
+            unpremultiplier: None,
+            auxiliary_targets_enabled: false,
+            auxiliary_targets: None,
             viewport_texture_format: (injector.texture_format(), injector.texture_format()),
             viewport_background_color: Vector4::new(0f32, 0f32, 0f32, 1f32),
             local_frame_index: (0, 0),
             transform_performed_at: (Motion::MAX_KEYFRAME_INDEX, 0),
             sample_level: (0u32, 0u32),
+            supported_sample_counts,
             camera,
             shadow_camera,
             light: directional_light,
+            local_lights: vec![],
             fallback_texture,
             shared_sampler: sampler,
             shadow_sampler,
@@ -632,6 +825,10 @@ impl Project {
             shadow_bind_group_layout,
             fallback_texture_bind: texture_fallback_bind,
             fallback_shadow_bind: shadow_fallback_bind,
+            default_effect: model_effect,
+            effects: HashMap::new(),
+            model_effects: HashMap::new(),
+            material_effects: HashMap::new(),
             object_handler_allocator,
             model_handle_map: HashMap::new(),
             transform_model_order_list: vec![],
@@ -639,6 +836,7 @@ impl Project {
             viewport_secondary_pass,
             physics_engine,
             loaded_texture_map: HashMap::new(),
+            asset_sources: AssetSources::default(),
             state_flags: ProjectStates {
                 display_transform_handle: true,
                 display_user_interface: true,
@@ -653,6 +851,7 @@ impl Project {
                 ..Default::default()
             },
             confirm_seek_flags: ConfirmSeekFlags::default(),
+            event_publisher: EventPublisher::default(),
             last_save_state: None,
         }
         // TODO: may need to publish set fps event
@@ -676,6 +875,57 @@ impl Project {
         self.sample_level.0
     }
 
+    /// Sample counts of MSAA supported by the adapter for all viewport formats.
+    pub fn supported_sample_counts(&self) -> &[u32] {
+        &self.supported_sample_counts
+    }
+
+    /// Sets the MSAA sample count to `1 << value`, applied before the next viewport is drawn.
+    pub fn set_sample_level(&mut self, value: u32) -> Result<(), MdanceioError> {
+        let sample_count = 1u32.checked_shl(value).unwrap_or(0);
+        if !self.supported_sample_counts.contains(&sample_count) {
+            return Err(MdanceioError::unsupported_sample_count(sample_count));
+        }
+        if self.sample_level.1 != value {
+            self.sample_level.1 = value;
+            self.state_flags.reset_all_passes = true;
+            self.publish_event(Event::SetSampleLevel(value));
+        }
+        Ok(())
+    }
+
+    fn find_supported_sample_counts(
+        color_formats: &[wgpu::TextureFormat],
+        depth_format: wgpu::TextureFormat,
+        adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+    ) -> Vec<u32> {
+        // counts other than 1 and 4 depend on the adapter and have to be enabled as a feature
+        let features = |format: wgpu::TextureFormat| {
+            if device
+                .features()
+                .contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
+            {
+                adapter.get_texture_format_features(format).flags
+            } else {
+                format.guaranteed_format_features(device.features()).flags
+            }
+        };
+        (0..=Self::MAX_SAMPLE_LEVEL)
+            .map(|level| 1u32 << level)
+            .filter(|&count| {
+                count == 1
+                    || (features(depth_format).sample_count_supported(count)
+                        && color_formats.iter().all(|&format| {
+                            let flags = features(format);
+                            flags.sample_count_supported(count)
+                                && flags
+                                    .contains(wgpu::TextureFormatFeatureFlags::MULTISAMPLE_RESOLVE)
+                        }))
+            })
+            .collect()
+    }
+
     pub fn current_frame_index(&self) -> u32 {
         self.local_frame_index.0
     }
@@ -693,144 +943,843 @@ impl Project {
         &self.camera
     }
 
-    pub fn shadow_camera(&self) -> &ShadowCamera {
-        &self.shadow_camera
-    }
-
-    pub fn global_light(&self) -> &dyn Light {
-        &self.light
+    pub fn camera_names(&self) -> &[String] {
+        &self.camera_names
     }
 
-    pub fn global_light_mut(&mut self) -> &mut DirectionalLight {
-        &mut self.light
+    pub fn active_camera_name(&self) -> &str {
+        &self.active_camera_name
     }
 
-    pub fn shared_fallback_image(&self) -> &wgpu::TextureView {
-        &self.fallback_texture
+    /// Adds a camera with an empty motion, the active camera is kept.
+    pub fn add_camera(&mut self, name: &str) -> Result<(), MdanceioError> {
+        if self
+            .camera_names
+            .iter()
+            .any(|camera_name| camera_name == name)
+        {
+            return Err(MdanceioError::camera_already_exists(name));
+        }
+        let mut camera = PerspectiveCamera::new();
+        camera.update(
+            self.viewport_size.0,
+            camera.look_at(None),
+            Quaternion::one(),
+        );
+        camera.set_dirty(false);
+        let mut motion = Motion::empty();
+        motion.initialize_camera_frame_0(&camera, None);
+        self.camera_names.push(name.to_owned());
+        self.inactive_cameras.insert(
+            name.to_owned(),
+            InactiveCamera {
+                camera,
+                motion,
+                motion_source: None,
+            },
+        );
+        Ok(())
     }
 
-    pub fn viewport_texture_format(&self) -> wgpu::TextureFormat {
-        self.viewport_texture_format.0
+    /// Removes the camera, activating the previous one in order if it is active.
+    pub fn remove_camera(&mut self, name: &str) -> Result<(), MdanceioError> {
+        let index = self
+            .camera_names
+            .iter()
+            .position(|camera_name| camera_name == name)
+            .ok_or_else(|| MdanceioError::camera_not_found(name))?;
+        if self.camera_names.len() == 1 {
+            return Err(MdanceioError::last_camera_not_removable());
+        }
+        if name == self.active_camera_name {
+            let next_index = if index == 0 { 1 } else { index - 1 };
+            let next_name = self.camera_names[next_index].clone();
+            self.set_active_camera(&next_name)?;
+        }
+        self.camera_names.remove(index);
+        self.inactive_cameras.remove(name);
+        Ok(())
     }
 
-    pub fn viewport_primary_depth_view(&self) -> &wgpu::TextureView {
-        &self.viewport_primary_pass.depth_view
+    pub fn rename_camera(&mut self, name: &str, new_name: &str) -> Result<(), MdanceioError> {
+        if name == new_name {
+            return Ok(());
+        }
+        if self
+            .camera_names
+            .iter()
+            .any(|camera_name| camera_name == new_name)
+        {
+            return Err(MdanceioError::camera_already_exists(new_name));
+        }
+        let camera_name = self
+            .camera_names
+            .iter_mut()
+            .find(|camera_name| *camera_name == name)
+            .ok_or_else(|| MdanceioError::camera_not_found(name))?;
+        *camera_name = new_name.to_owned();
+        if name == self.active_camera_name {
+            self.active_camera_name = new_name.to_owned();
+        } else if let Some(camera) = self.inactive_cameras.remove(name) {
+            self.inactive_cameras.insert(new_name.to_owned(), camera);
+        }
+        Ok(())
     }
 
-    pub fn is_render_pass_viewport(&self) -> bool {
-        // TODO
-        true
+    /// Makes the camera used for rendering, camera edits and camera motion operations.
+    pub fn set_active_camera(&mut self, name: &str) -> Result<(), MdanceioError> {
+        if name == self.active_camera_name {
+            return Ok(());
+        }
+        let next = self
+            .inactive_cameras
+            .remove(name)
+            .ok_or_else(|| MdanceioError::camera_not_found(name))?;
+        let last = InactiveCamera {
+            camera: std::mem::replace(&mut self.camera, next.camera),
+            motion: std::mem::replace(&mut self.camera_motion, next.motion),
+            motion_source: std::mem::replace(
+                &mut self.asset_sources.camera_motion,
+                next.motion_source,
+            ),
+        };
+        let last_name = std::mem::replace(&mut self.active_camera_name, name.to_owned());
+        self.inactive_cameras.insert(last_name, last);
+        self.synchronize_camera(self.local_frame_index.0, 0f32);
+        self.event_publisher
+            .publish(Event::SetActiveCamera(name.to_owned()));
+        Ok(())
     }
 
-    pub fn current_color_attachment_texture(&self) -> Option<&wgpu::TextureView> {
-        None
+    pub fn shadow_camera(&self) -> &ShadowCamera {
+        &self.shadow_camera
     }
 
-    pub fn set_transform_performed_at(&mut self, value: (u32, i32)) {
-        self.transform_performed_at = value
+    pub fn shadow_camera_mut(&mut self) -> &mut ShadowCamera {
+        &mut self.shadow_camera
     }
 
-    pub fn reset_transform_performed_at(&mut self) {
-        self.set_transform_performed_at((Motion::MAX_KEYFRAME_INDEX, 0))
+    /// Recreates the shadow maps and rebinds them to every model renderer.
+    pub fn set_shadow_map_resolution(
+        &mut self,
+        texture_size: u32,
+        cascade_count: usize,
+        device: &wgpu::Device,
+    ) {
+        self.shadow_camera.resize(
+            texture_size,
+            cascade_count,
+            &self.shadow_bind_group_layout,
+            &self.shadow_sampler,
+            device,
+        );
+        self.main_render_target.set_shadow_bind(
+            &self.model_handle_map,
+            self.shadow_camera.bind_group(),
+            device,
+        );
     }
 
-    pub fn duration(&self, base_duration: u32) -> u32 {
-        let mut duration =
-            base_duration.clamp(Self::MINIMUM_BASE_DURATION, Self::MAXIMUM_BASE_DURATION);
-        duration = duration.max(self.camera_motion.duration());
-        duration = duration.max(self.light_motion.duration());
-        for motion in self.model_to_motion.values() {
-            duration = duration.max(motion.duration());
+    /// Resizes the shadow maps only if the resolution or the cascade count changes.
+    pub fn set_shadow_map_settings(&mut self, settings: ShadowMapSettings, device: &wgpu::Device) {
+        let current = self.shadow_camera.settings();
+        if settings.resolution != current.resolution
+            || settings.cascade_count != current.cascade_count
+        {
+            self.set_shadow_map_resolution(settings.resolution, settings.cascade_count, device);
         }
-        duration
+        self.shadow_camera.set_split_lambda(settings.split_lambda);
+        self.shadow_camera.set_filter_radius(settings.filter_radius);
+        self.shadow_camera.set_depth_bias(settings.depth_bias);
     }
 
-    pub fn project_duration(&self) -> u32 {
-        self.duration(self.base_duration)
+    pub fn post_process_settings(&self) -> &PostProcessSettings {
+        self.post_process.settings()
     }
 
-    pub fn base_duration(&self) -> u32 {
-        self.base_duration
+    pub fn set_post_process_settings(&mut self, settings: PostProcessSettings) {
+        self.post_process.set_settings(settings);
     }
 
-    pub fn set_base_duration(&mut self, value: u32) {
-        self.base_duration = value
-            .clamp(Self::MINIMUM_BASE_DURATION, Self::MAXIMUM_BASE_DURATION)
-            .max(self.base_duration);
-        let new_duration = self.duration(value);
-        self.playing_segment.to = self.playing_segment.to.max(new_duration);
-        self.selection_segment.to = self.selection_segment.to.max(new_duration);
+    pub fn set_material_ambient_occlusion_excluded(
+        &mut self,
+        handle: ModelHandle,
+        material_idx: usize,
+        value: bool,
+    ) -> Result<(), MdanceioError> {
+        self.material_mut(handle, material_idx)?
+            .set_ambient_occlusion_excluded(value);
+        Ok(())
     }
 
-    pub fn set_playing_segment(&mut self, value: &TimeLineSegment) {
-        self.playing_segment = value.normalized(self.project_duration());
+    /// The material keeps drawing the edge of the model while the screen space outline is off.
+    pub fn set_material_outline_excluded(
+        &mut self,
+        handle: ModelHandle,
+        material_idx: usize,
+        value: bool,
+    ) -> Result<(), MdanceioError> {
+        self.material_mut(handle, material_idx)?
+            .set_outline_excluded(value);
+        Ok(())
     }
 
-    pub fn set_selection_segment(&mut self, value: &TimeLineSegment) {
-        self.selection_segment = value.normalized(self.project_duration());
+    fn material_mut(
+        &mut self,
+        handle: ModelHandle,
+        material_idx: usize,
+    ) -> Result<&mut Material, MdanceioError> {
+        self.model_handle_map
+            .get_mut(&handle)
+            .ok_or_else(MdanceioError::model_not_found)?
+            .materials
+            .get_mut(material_idx)
+            .ok_or_else(|| MdanceioError::material_not_found(material_idx))
     }
 
-    pub fn set_physics_simulation_mode(&mut self, value: SimulationMode) {
-        if self.physics_engine.simulation_mode != value {
-            self.physics_engine.simulation_mode = value;
-            self.reset_physics_simulation();
+    /// Decodes a color grading LUT image laid out as a strip of N slices of N x N texels.
+    pub fn load_color_grading_lut(
+        &mut self,
+        data: &[u8],
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<(), MdanceioError> {
+        let img = image::load_from_memory(data)
+            .map_err(|err| MdanceioError::asset_not_loaded("color grading LUT", &err.to_string()))?
+            .to_rgba8();
+        let (width, height) = img.dimensions();
+        if !self
+            .post_process
+            .set_color_grading_lut(&img, (width, height), device, queue)
+        {
+            return Err(MdanceioError::invalid_color_grading_lut(width, height));
         }
+        Ok(())
     }
 
-    pub fn active_model(&self) -> Option<&Model> {
-        self.active_model_pair
-            .0
-            .and_then(|idx| self.model_handle_map.get(&idx))
+    pub fn environment_settings(&self) -> &EnvironmentSettings {
+        self.environment.settings()
     }
 
-    pub fn active_model_mut(&mut self) -> Option<&mut Model> {
-        self.active_model_pair
-            .0
-            .and_then(|idx| self.model_handle_map.get_mut(&idx))
+    pub fn set_environment_settings(&mut self, settings: EnvironmentSettings) {
+        self.environment.set_settings(settings);
     }
 
-    pub fn set_active_model(&mut self, model: Option<ModelHandle>) {
-        let last_active_model = self.active_model_pair.0;
-        if last_active_model != model && !self.state_flags.enable_model_editing {
-            self.active_model_pair = (model, last_active_model);
-            if self.editing_mode == EditingMode::None {
-                self.editing_mode = EditingMode::Select;
-            } else if model.is_none() {
-                self.editing_mode = EditingMode::None;
-            }
-            // TODO: publish event
-            // TODO: rebuild tracks
-            self.internal_seek(self.local_frame_index.0);
-        }
+    pub fn load_environment_image(
+        &mut self,
+        data: &[u8],
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<(), MdanceioError> {
+        self.environment.load(data, device, queue)?;
+        self.asset_sources.environment_image = None;
+        Ok(())
     }
 
-    pub fn model(&self, handle: ModelHandle) -> Option<&Model> {
-        self.model_handle_map.get(&handle)
+    pub fn clear_environment_image(&mut self) {
+        self.environment.clear();
+        self.asset_sources.environment_image = None;
     }
 
-    pub fn model_mut(&mut self, handle: ModelHandle) -> Option<&mut Model> {
-        self.model_handle_map.get_mut(&handle)
+    pub fn motion_blur_settings(&self) -> &MotionBlurSettings {
+        &self.motion_blur_settings
     }
 
-    pub fn find_model_by_name(&self, name: &str) -> Option<&Model> {
-        for (idx, model) in &self.model_handle_map {
-            if model.get_name() == name || model.get_canonical_name() == name {
-                return Some(model);
-            }
+    pub fn set_motion_blur_settings(&mut self, settings: MotionBlurSettings) {
+        self.motion_blur_settings = settings;
+    }
+
+    pub fn supersampling_settings(&self) -> &SupersamplingSettings {
+        &self.supersampling_settings
+    }
+
+    /// Changing the factor is applied before the next viewport is drawn.
+    pub fn set_supersampling_settings(&mut self, settings: SupersamplingSettings) {
+        if settings.factor() != self.supersampling_settings.factor() {
+            self.state_flags.reset_all_passes = true;
         }
-        return None;
+        self.supersampling_settings = settings;
     }
 
-    pub fn resolve_bone(&self, value: (&str, &str)) -> Option<&Bone> {
-        self.find_model_by_name(value.0)
-            .and_then(|model| model.find_bone(value.1))
+    pub fn viewport_alpha_mode(&self) -> ViewportAlphaMode {
+        match (
+            self.state_flags.enable_viewport_with_transparent,
+            self.state_flags.enable_viewport_premultiplied_alpha,
+        ) {
+            (false, _) => ViewportAlphaMode::Opaque,
+            (true, false) => ViewportAlphaMode::Straight,
+            (true, true) => ViewportAlphaMode::Premultiplied,
+        }
     }
 
-    pub fn resolve_model_motion(&self, model: ModelHandle) -> Option<&Motion> {
-        self.model_to_motion.get(&model)
+    /// Transparent viewports are drawn without the environment and the grid.
+    pub fn set_viewport_alpha_mode(&mut self, value: ViewportAlphaMode) {
+        self.state_flags.enable_viewport_with_transparent = value.is_transparent();
+        self.state_flags.enable_viewport_premultiplied_alpha =
+            value == ViewportAlphaMode::Premultiplied;
     }
 
-    pub fn physics_simulation_time_step(&self) -> f32 {
+    pub fn is_auxiliary_targets_enabled(&self) -> bool {
+        self.auxiliary_targets_enabled
+    }
+
+    /// Enables drawing the auxiliary targets through `draw_auxiliary_targets`.
+    pub fn set_auxiliary_targets_enabled(&mut self, value: bool) {
+        self.auxiliary_targets_enabled = value;
+        if !value {
+            self.auxiliary_targets = None;
+        }
+    }
+
+    /// Targets of the last drawn frame, `None` until drawn once.
+    pub fn auxiliary_targets(&self) -> Option<&AuxiliaryTargets> {
+        self.auxiliary_targets.as_deref()
+    }
+
+    fn viewport_clear_color(&self) -> wgpu::Color {
+        if self.state_flags.enable_viewport_with_transparent {
+            wgpu::Color::TRANSPARENT
+        } else {
+            wgpu::Color::WHITE
+        }
+    }
+
+    /// Makes sure the unpremultiplier matches the viewport size before drawing into it.
+    fn prepare_unpremultiplier(&mut self, device: &wgpu::Device) {
+        let size = self.viewport_size.0;
+        if self
+            .unpremultiplier
+            .as_ref()
+            .map(|unpremultiplier| unpremultiplier.size())
+            != Some(size)
+        {
+            self.unpremultiplier = Some(Box::new(Unpremultiplier::new(
+                size,
+                self.viewport_texture_format.1,
+                device,
+            )));
+        }
+    }
+
+    /// Size the viewport passes are drawn at, enlarged while supersampling.
+    fn render_size(&self) -> Vector2<u32> {
+        self.supersampler
+            .as_ref()
+            .map_or(self.viewport_size.0, |supersampler| supersampler.size())
+    }
+
+    pub fn global_light(&self) -> &dyn Light {
+        &self.light
+    }
+
+    pub fn global_light_mut(&mut self) -> &mut DirectionalLight {
+        &mut self.light
+    }
+
+    pub fn local_lights(&self) -> &[LocalLight] {
+        &self.local_lights
+    }
+
+    pub fn add_local_light(&mut self, light: LocalLight) -> Result<usize, MdanceioError> {
+        if self.local_lights.len() >= MAX_LOCAL_LIGHTS {
+            return Err(MdanceioError::too_many_local_lights(MAX_LOCAL_LIGHTS));
+        }
+        self.local_lights.push(light);
+        Ok(self.local_lights.len() - 1)
+    }
+
+    /// Lights after the removed one shift down by one index.
+    pub fn remove_local_light(&mut self, index: usize) -> Result<LocalLight, MdanceioError> {
+        if index < self.local_lights.len() {
+            Ok(self.local_lights.remove(index))
+        } else {
+            Err(MdanceioError::local_light_not_found(index))
+        }
+    }
+
+    pub fn local_light_mut(&mut self, index: usize) -> Result<&mut LocalLight, MdanceioError> {
+        self.local_lights
+            .get_mut(index)
+            .ok_or_else(|| MdanceioError::local_light_not_found(index))
+    }
+
+    /// Loads the effect package described by `manifest`, replacing a loaded effect of the same name.
+    pub fn load_effect(
+        &mut self,
+        manifest: &str,
+        loader: &mut dyn AssetLoader,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<String, MdanceioError> {
+        let manifest = EffectManifest::parse(manifest)?;
+        let package = EffectPackage::load(manifest, loader)?;
+        Ok(self.insert_effect(package, device, queue))
+    }
+
+    /// Loads the effect package whose manifest is at `path`, returning its name.
+    pub fn load_effect_from(
+        &mut self,
+        path: &str,
+        loader: &mut dyn AssetLoader,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<String, MdanceioError> {
+        let data = loader.load(path)?;
+        let manifest = String::from_utf8(data)
+            .map_err(|_| MdanceioError::asset_not_loaded(path, "manifest is not UTF-8"))?;
+        let name = self.load_effect(&manifest, loader, device, queue)?;
+        self.asset_sources.effects.insert(
+            name.clone(),
+            EffectEntry {
+                source: path.to_owned(),
+                fx: false,
+            },
+        );
+        Ok(name)
+    }
+
+    /// Loads an MME `.fx` effect, returning its name like `load_effect`.
+    pub fn load_fx_effect(
+        &mut self,
+        path: &str,
+        loader: &mut dyn AssetLoader,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<String, MdanceioError> {
+        let package = EffectPackage::load_fx(path, loader)?;
+        let name = self.insert_effect(package, device, queue);
+        self.asset_sources.effects.insert(
+            name.clone(),
+            EffectEntry {
+                source: path.to_owned(),
+                fx: true,
+            },
+        );
+        Ok(name)
+    }
+
+    fn insert_effect(
+        &mut self,
+        package: EffectPackage,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> String {
+        let effect = Effect::from_package(
+            &package,
+            &self.default_effect,
+            &self.fallback_shadow_bind,
+            device,
+            queue,
+        );
+        let name = package.manifest.name;
+        self.effects.insert(name.clone(), Arc::new(effect));
+        self.asset_sources.effects.remove(&name);
+        self.apply_all_effects(device);
+        name
+    }
+
+    pub fn effect_names(&self) -> Vec<String> {
+        let mut names = self.effects.keys().cloned().collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    /// Models and materials using the effect go back to the default effect.
+    pub fn remove_effect(
+        &mut self,
+        name: &str,
+        device: &wgpu::Device,
+    ) -> Result<(), MdanceioError> {
+        self.effects
+            .remove(name)
+            .ok_or_else(|| MdanceioError::effect_not_found(name))?;
+        self.asset_sources.effects.remove(name);
+        self.model_effects.retain(|_, effect| effect != name);
+        self.material_effects.retain(|_, effect| effect != name);
+        self.apply_all_effects(device);
+        Ok(())
+    }
+
+    /// `None` draws the model with the default effect, material assignments take precedence.
+    pub fn set_model_effect(
+        &mut self,
+        handle: ModelHandle,
+        name: Option<&str>,
+        device: &wgpu::Device,
+    ) -> Result<(), MdanceioError> {
+        if !self.model_handle_map.contains_key(&handle) {
+            return Err(MdanceioError::model_not_found());
+        }
+        match name {
+            Some(name) => {
+                if !self.effects.contains_key(name) {
+                    return Err(MdanceioError::effect_not_found(name));
+                }
+                self.model_effects.insert(handle, name.to_owned());
+            }
+            None => {
+                self.model_effects.remove(&handle);
+            }
+        }
+        self.apply_model_effects(handle, device);
+        Ok(())
+    }
+
+    /// `None` draws the material with the effect of its model.
+    pub fn set_material_effect(
+        &mut self,
+        handle: ModelHandle,
+        material_idx: usize,
+        name: Option<&str>,
+        device: &wgpu::Device,
+    ) -> Result<(), MdanceioError> {
+        let model = self
+            .model_handle_map
+            .get(&handle)
+            .ok_or_else(MdanceioError::model_not_found)?;
+        if material_idx >= model.materials.len() {
+            return Err(MdanceioError::material_not_found(material_idx));
+        }
+        match name {
+            Some(name) => {
+                if !self.effects.contains_key(name) {
+                    return Err(MdanceioError::effect_not_found(name));
+                }
+                self.material_effects
+                    .insert((handle, material_idx), name.to_owned());
+            }
+            None => {
+                self.material_effects.remove(&(handle, material_idx));
+            }
+        }
+        self.apply_model_effects(handle, device);
+        Ok(())
+    }
+
+    pub fn is_effect_plugin_enabled(&self) -> bool {
+        self.state_flags.enable_effect_plugin
+    }
+
+    /// Assignments are kept while disabled and every model is drawn with the default effect.
+    pub fn set_effect_plugin_enabled(&mut self, value: bool, device: &wgpu::Device) {
+        if self.state_flags.enable_effect_plugin != value {
+            self.state_flags.enable_effect_plugin = value;
+            self.apply_all_effects(device);
+        }
+    }
+
+    fn apply_all_effects(&mut self, device: &wgpu::Device) {
+        let handles = self.model_handle_map.keys().copied().collect::<Vec<_>>();
+        for handle in handles {
+            self.apply_model_effects(handle, device);
+        }
+    }
+
+    fn apply_model_effects(&mut self, handle: ModelHandle, device: &wgpu::Device) {
+        let enabled = self.state_flags.enable_effect_plugin;
+        let resolve = |name: &String| {
+            self.effects
+                .get(name)
+                .filter(|_| enabled)
+                .unwrap_or(&self.default_effect)
+                .clone()
+        };
+        if let Some(model) = self.model_handle_map.get(&handle) {
+            let effect = self
+                .model_effects
+                .get(&handle)
+                .map(resolve)
+                .unwrap_or_else(|| self.default_effect.clone());
+            let material_effects = (0..model.materials.len())
+                .filter_map(|idx| {
+                    self.material_effects
+                        .get(&(handle, idx))
+                        .map(|name| (idx, resolve(name)))
+                })
+                .collect::<Vec<_>>();
+            self.main_render_target
+                .set_model_effect(handle, model, &effect, device);
+            for (idx, effect) in material_effects {
+                self.main_render_target
+                    .set_material_effect(handle, model, idx, &effect, device);
+            }
+        }
+    }
+
+    pub fn shared_fallback_image(&self) -> &wgpu::TextureView {
+        &self.fallback_texture
+    }
+
+    pub fn viewport_texture_format(&self) -> wgpu::TextureFormat {
+        self.viewport_texture_format.0
+    }
+
+    pub fn viewport_primary_depth_view(&self) -> &wgpu::TextureView {
+        &self.viewport_primary_pass.depth_view
+    }
+
+    pub fn is_render_pass_viewport(&self) -> bool {
+        // TODO
+        true
+    }
+
+    pub fn current_color_attachment_texture(&self) -> Option<&wgpu::TextureView> {
+        None
+    }
+
+    pub fn set_transform_performed_at(&mut self, value: (u32, i32)) {
+        self.transform_performed_at = value
+    }
+
+    pub fn reset_transform_performed_at(&mut self) {
+        self.set_transform_performed_at((Motion::MAX_KEYFRAME_INDEX, 0))
+    }
+
+    pub fn duration(&self, base_duration: u32) -> u32 {
+        let mut duration =
+            base_duration.clamp(Self::MINIMUM_BASE_DURATION, Self::MAXIMUM_BASE_DURATION);
+        duration = duration.max(self.camera_motion.duration());
+        for camera in self.inactive_cameras.values() {
+            duration = duration.max(camera.motion.duration());
+        }
+        duration = duration.max(self.light_motion.duration());
+        for motion in self.model_to_motion.values() {
+            duration = duration.max(motion.duration());
+        }
+        for layer in self.model_to_motion_layers.values().flatten() {
+            duration = duration.max(layer.duration());
+        }
+        duration
+    }
+
+    pub fn project_duration(&self) -> u32 {
+        self.duration(self.base_duration)
+    }
+
+    pub fn base_duration(&self) -> u32 {
+        self.base_duration
+    }
+
+    pub fn set_base_duration(&mut self, value: u32) {
+        let last_duration = self.project_duration();
+        self.base_duration = value
+            .clamp(Self::MINIMUM_BASE_DURATION, Self::MAXIMUM_BASE_DURATION)
+            .max(self.base_duration);
+        let new_duration = self.duration(value);
+        self.playing_segment.to = self.playing_segment.to.max(new_duration);
+        self.selection_segment.to = self.selection_segment.to.max(new_duration);
+        let duration = self.project_duration();
+        if duration != last_duration {
+            self.publish_event(Event::UpdateDuration {
+                duration,
+                last_duration,
+            });
+        }
+    }
+
+    pub fn set_playing_segment(&mut self, value: &TimeLineSegment) {
+        self.playing_segment = value.normalized(self.project_duration());
+    }
+
+    pub fn set_selection_segment(&mut self, value: &TimeLineSegment) {
+        self.selection_segment = value.normalized(self.project_duration());
+    }
+
+    pub fn set_physics_simulation_mode(&mut self, value: SimulationMode) {
+        if self.physics_engine.simulation_mode != value {
+            self.physics_engine.simulation_mode = value;
+            self.reset_physics_simulation();
+            self.publish_event(Event::SetPhysicsSimulationMode(value.into()));
+        }
+    }
+
+    pub fn active_model(&self) -> Option<&Model> {
+        self.active_model_pair
+            .0
+            .and_then(|idx| self.model_handle_map.get(&idx))
+    }
+
+    pub fn active_model_mut(&mut self) -> Option<&mut Model> {
+        self.active_model_pair
+            .0
+            .and_then(|idx| self.model_handle_map.get_mut(&idx))
+    }
+
+    pub fn set_active_model(&mut self, model: Option<ModelHandle>) {
+        let last_active_model = self.active_model_pair.0;
+        if last_active_model != model && !self.state_flags.enable_model_editing {
+            self.active_model_pair = (model, last_active_model);
+            if self.editing_mode == EditingMode::None {
+                self.editing_mode = EditingMode::Select;
+            } else if model.is_none() {
+                self.editing_mode = EditingMode::None;
+            }
+            self.publish_event(Event::SetActiveModel(model));
+            self.publish_undo_availability();
+            // TODO: rebuild tracks
+            self.internal_seek(self.local_frame_index.0);
+        }
+    }
+
+    pub fn active_model_handle(&self) -> Option<ModelHandle> {
+        self.active_model_pair.0
+    }
+
+    pub fn set_active_bone(&mut self, handle: ModelHandle, bone_name: Option<&str>) {
+        if let Some(model) = self.model_handle_map.get_mut(&handle) {
+            let bone = bone_name.and_then(|name| model.find_bone(name).map(|bone| bone.handle));
+            if model.active_bone().map(|bone| bone.handle) != bone {
+                model.bones.set_active(bone);
+                let bone = model.active_bone().map(|bone| bone.name.clone());
+                self.publish_event(Event::SetActiveBone {
+                    model: handle,
+                    bone,
+                });
+            }
+        }
+    }
+
+    pub fn set_active_morph(&mut self, handle: ModelHandle, morph_name: &str) {
+        if let Some(model) = self.model_handle_map.get_mut(&handle) {
+            if let Some(morph) = model
+                .find_morph(morph_name)
+                .map(|morph| morph.origin.base.index)
+                .filter(|morph| !model.morphs.is_active(*morph))
+            {
+                model.morphs.set_active(morph);
+                self.publish_event(Event::SetActiveMorph {
+                    model: handle,
+                    morph: Some(morph_name.to_owned()),
+                });
+            }
+        }
+    }
+
+    pub fn set_model_visible(&mut self, handle: ModelHandle, value: bool) {
+        if let Some(model) = self.model_handle_map.get_mut(&handle) {
+            if model.is_visible() != value {
+                model.set_visible(value, &mut self.physics_engine);
+                self.publish_event(Event::ToggleModelVisible {
+                    model: handle,
+                    value,
+                });
+            }
+        }
+    }
+
+    pub fn set_model_shadow_map_enabled(&mut self, handle: ModelHandle, value: bool) {
+        if let Some(model) = self.model_handle_map.get_mut(&handle) {
+            if model.states.enable_shadow_map != value {
+                model.set_shadow_map_enabled(value);
+                self.publish_event(Event::ToggleModelShadowMapEnabled {
+                    model: handle,
+                    value,
+                });
+            }
+        }
+    }
+
+    pub fn set_model_show_all_bones(&mut self, handle: ModelHandle, value: bool) {
+        if let Some(model) = self.model_handle_map.get_mut(&handle) {
+            if model.states.show_all_bones != value {
+                model.set_show_all_bones(value);
+                self.publish_event(Event::ToggleModelShowAllBones {
+                    model: handle,
+                    value,
+                });
+            }
+        }
+    }
+
+    pub fn set_model_show_all_vertex_faces(&mut self, handle: ModelHandle, value: bool) {
+        if let Some(model) = self.model_handle_map.get_mut(&handle) {
+            if model.states.show_all_vertex_faces != value {
+                model.set_show_all_vertex_faces(value);
+                self.publish_event(Event::ToggleModelShowAllVertexFaces {
+                    model: handle,
+                    value,
+                });
+            }
+        }
+    }
+
+    pub fn set_model_show_all_vertex_points(&mut self, handle: ModelHandle, value: bool) {
+        if let Some(model) = self.model_handle_map.get_mut(&handle) {
+            if model.states.show_all_vertex_points != value {
+                model.set_show_all_vertex_points(value);
+                self.publish_event(Event::ToggleModelShowAllVertexPoints {
+                    model: handle,
+                    value,
+                });
+            }
+        }
+    }
+
+    pub fn preferred_motion_fps(&self) -> u32 {
+        self.preferred_motion_fps.value()
+    }
+
+    pub fn set_preferred_motion_fps(&mut self, value: u32) {
+        if self.preferred_motion_fps.value() != value {
+            self.preferred_motion_fps = FpsUnit::new(value);
+            self.publish_event(Event::SetPreferredMotionFps {
+                value: self.preferred_motion_fps.value(),
+                unlimited: false,
+            });
+        }
+    }
+
+    pub fn set_grid_visible(&mut self, value: bool) {
+        if self.grid.visible() != value {
+            self.grid.set_visible(value);
+            self.publish_event(Event::ToggleGridEnabled(value));
+        }
+    }
+
+    pub fn publish_event(&mut self, event: Event) {
+        self.event_publisher.publish(event);
+    }
+
+    /// Undo stacks follow the active model and its motions, nothing can be undone until they
+    /// are kept.
+    fn publish_undo_availability(&mut self) {
+        self.publish_event(Event::UpdateUndoAvailability {
+            can_undo: false,
+            can_redo: false,
+        });
+    }
+
+    pub fn take_events(&mut self) -> Vec<Event> {
+        self.event_publisher.take_events()
+    }
+
+    pub fn model(&self, handle: ModelHandle) -> Option<&Model> {
+        self.model_handle_map.get(&handle)
+    }
+
+    pub fn model_mut(&mut self, handle: ModelHandle) -> Option<&mut Model> {
+        self.model_handle_map.get_mut(&handle)
+    }
+
+    pub fn find_model_by_name(&self, name: &str) -> Option<&Model> {
+        for (idx, model) in &self.model_handle_map {
+            if model.get_name() == name || model.get_canonical_name() == name {
+                return Some(model);
+            }
+        }
+        return None;
+    }
+
+    pub fn resolve_bone(&self, value: (&str, &str)) -> Option<&Bone> {
+        self.find_model_by_name(value.0)
+            .and_then(|model| model.find_bone(value.1))
+    }
+
+    pub fn resolve_model_motion(&self, model: ModelHandle) -> Option<&Motion> {
+        self.model_to_motion.get(&model)
+    }
+
+    pub fn physics_simulation_time_step(&self) -> f32 {
         self.inverted_preferred_motion_fps()
             * self.preferred_motion_fps.scale_factor
             * self.time_step_factor
@@ -877,6 +1826,30 @@ impl Project {
             self.synchronize_all_motions(self.playing_segment.from, 0f32, SimulationTiming::Before);
             self.reset_physics_simulation();
             self.audio_player.play();
+            self.publish_event(Event::Play {
+                frame_index: local_frame_index_at,
+                duration: duration_at,
+            });
+        }
+    }
+
+    pub fn pause(&mut self) {
+        if self.audio_player.is_playing() {
+            self.audio_player.pause();
+            self.publish_event(Event::Pause {
+                frame_index: self.current_frame_index(),
+                duration: self.project_duration(),
+            });
+        }
+    }
+
+    pub fn resume(&mut self) {
+        if self.audio_player.is_paused() {
+            self.audio_player.resume();
+            self.publish_event(Event::Resume {
+                frame_index: self.current_frame_index(),
+                duration: self.project_duration(),
+            });
         }
     }
 
@@ -891,6 +1864,10 @@ impl Project {
         self.synchronize_all_motions(0, 0f32, SimulationTiming::After);
         self.mark_all_models_dirty();
         self.local_frame_index = (0, 0);
+        self.publish_event(Event::Stop {
+            frame_index: last_local_frame_index,
+            duration: last_duration,
+        });
     }
 
     fn prepare_playing(&mut self) {
@@ -913,37 +1890,35 @@ impl Project {
         if self.is_playing() && self.continue_playing() {
             self.audio_player.update();
             let fps = self.preferred_motion_fps.value;
-            let base = FpsUnit::HALF_BASE_FPS;
-            let fps_rate = fps / base;
             let frame_index =
                 (self.audio_player.current_rational().subdivide() * (fps as f64)) as u32;
             let last_frame_index = self
                 .audio_player
                 .last_rational()
                 .map(|rational| (rational.subdivide() * (fps as f64)) as u32);
-            let inverted_fpx_rate = 1f32 / fps_rate as f32;
-            let amount = (frame_index % fps_rate) as f32 * inverted_fpx_rate;
             let delta = last_frame_index.map_or(0f32, |last_frame_index| {
                 if frame_index > last_frame_index {
                     (frame_index - last_frame_index).min(0xffff) as f32
-                        * inverted_fpx_rate
+                        * self.preferred_motion_fps.inverted_scale_factor
                         * self.physics_simulation_time_step()
                 } else {
                     0f32
                 }
             });
-            self.internal_seek_precisely(
-                (frame_index as f32 * inverted_fpx_rate) as u32,
-                amount,
-                delta,
-            );
+            let (base_frame_index, amount) =
+                self.preferred_motion_fps.split_base_frame(frame_index);
+            self.internal_seek_precisely(base_frame_index, amount, delta);
         }
         // TODO: simulate if simulation anytime
+        self.update_all_staging_vertex_buffers(device, queue);
+        // TODO: mark all animated images updatable
+        // TODO: render background video
+    }
+
+    fn update_all_staging_vertex_buffers(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
         for (_, model) in &mut self.model_handle_map {
             model.update_staging_vertex_buffer(&self.camera, device, queue);
         }
-        // TODO: mark all animated images updatable
-        // TODO: render background video
     }
 
     fn restore_state(&mut self, state: &SaveState, force_seek: bool) {
@@ -952,8 +1927,9 @@ impl Project {
         self.camera.set_look_at(state.camera_look_at);
         self.camera.set_distance(state.camera_distance);
         self.camera.set_fov(state.camera_fov);
-        let bound_look_at = self.camera.bound_look_at(self);
-        self.camera.update(self.viewport_size.0, bound_look_at);
+        let (bound_look_at, bound_orientation) = self.camera.bound_transform(self);
+        self.camera
+            .update(self.viewport_size.0, bound_look_at, bound_orientation);
         self.light.set_color(state.light_color);
         self.light.set_direction(state.light_direction);
         if force_seek {
@@ -972,29 +1948,46 @@ impl Project {
         }
         self.viewport_size.0 = self.viewport_size.1;
         self.sample_level.0 = self.sample_level.1;
+        let factor = self.supersampling_settings.factor();
+        self.supersampler = (factor > 1).then(|| {
+            Box::new(Supersampler::new(
+                self.viewport_size.0,
+                factor,
+                self.viewport_texture_format.1,
+                device,
+            ))
+        });
+        let render_size = self.render_size();
         self.viewport_primary_pass.update(
-            self.viewport_size.0,
+            render_size,
             self.viewport_texture_format(),
             self.sample_count(),
             device,
         );
         self.viewport_secondary_pass.update(
-            self.viewport_size.0,
+            render_size,
             self.viewport_texture_format(),
             self.sample_count(),
             device,
         );
-        let bound_look_at = self.camera.bound_look_at(self);
-        self.camera.update(self.viewport_size.0, bound_look_at);
+        self.post_process.resize(
+            render_size,
+            self.viewport_primary_pass.geometry_depth_view(),
+            device,
+        );
+        self.update_viewport_pipelines(device);
+        let (bound_look_at, bound_orientation) = self.camera.bound_transform(self);
+        self.camera
+            .update(self.viewport_size.0, bound_look_at, bound_orientation);
         self.state_flags.reset_all_passes = false;
         return true;
     }
 
     pub fn update_global_camera(&mut self) {
-        let bound_look_at = self.global_camera().bound_look_at(self);
+        let (bound_look_at, bound_orientation) = self.global_camera().bound_transform(self);
         let viewport_image_size = self.viewport_size.0;
         self.global_camera_mut()
-            .update(viewport_image_size, bound_look_at);
+            .update(viewport_image_size, bound_look_at, bound_orientation);
     }
 
     pub fn seek(&mut self, frame_index: u32, force_seek: bool) {
@@ -1019,7 +2012,11 @@ impl Project {
             self.set_base_duration(frame_index);
             // TODO: seek audio player
             self.internal_seek_precisely(frame_index, amount, delta);
-            // TODO: publish event
+            self.publish_event(Event::Seek {
+                frame_index,
+                last_frame_index: seek_from,
+                duration: last_duration,
+            });
         }
     }
 
@@ -1040,6 +2037,18 @@ impl Project {
     }
 
     fn internal_seek_precisely(&mut self, frame_index: u32, amount: f32, delta: f32) {
+        self.internal_seek_sub_frame(frame_index, amount, delta, false);
+    }
+
+    /// Seeks like `internal_seek_precisely`, stepping physics by whole time steps only if
+    /// `exact_physics` so that sub-frames split the frame interval without extra steps.
+    fn internal_seek_sub_frame(
+        &mut self,
+        frame_index: u32,
+        amount: f32,
+        delta: f32,
+        exact_physics: bool,
+    ) {
         log::debug!("Before Internal seek: {:?}", self.local_frame_index);
         log::debug!("Seek to {:?}", frame_index);
         if self.transform_performed_at.0 != Motion::MAX_KEYFRAME_INDEX
@@ -1052,7 +2061,7 @@ impl Project {
             self.restart(frame_index);
         }
         self.synchronize_all_motions(frame_index, amount, SimulationTiming::Before);
-        self.internal_perform_physics_simulation(delta);
+        self.internal_perform_physics_simulation(delta, exact_physics);
         self.synchronize_all_motions(frame_index, amount, SimulationTiming::After);
         self.mark_all_models_dirty();
         self.light.set_dirty(false);
@@ -1077,11 +2086,9 @@ impl Project {
         self.restart(self.current_frame_index());
     }
 
-    pub fn reset_all_model_edges(
-        &mut self,
-        outside_parent_bone_map: &HashMap<(String, String), Bone>,
-    ) {
+    pub fn reset_all_model_edges(&mut self) {
         let physics_simulation_time_step = self.physics_simulation_time_step();
+        let outside_parent_bone_map = self.build_outside_parent_bone_map();
         for (handle, model) in &mut self.model_handle_map {
             if model.edge_size_scale_factor() > 0f32 && !model.is_staging_vertex_buffer_dirty() {
                 model.reset_morphs_deform_state(
@@ -1093,7 +2100,7 @@ impl Project {
                 model.perform_all_bones_transform(
                     &mut self.physics_engine,
                     physics_simulation_time_step,
-                    outside_parent_bone_map,
+                    &outside_parent_bone_map,
                 );
                 model.mark_staging_vertex_buffer_dirty();
             }
@@ -1106,13 +2113,27 @@ impl Project {
         amount: f32,
         timing: SimulationTiming,
     ) {
-        // TODO: for model motions
-        for handle in &self.transform_model_order_list {
-            if let Some(model) = self.model_handle_map.get_mut(handle) {
-                let outside_parent_bone_map = HashMap::new();
-                if let Some(motion) = self.model_to_motion.get(handle) {
+        if timing == SimulationTiming::Before {
+            for handle in &self.transform_model_order_list {
+                if let (Some(model), Some(motion)) = (
+                    self.model_handle_map.get_mut(handle),
+                    self.model_to_motion.get(handle),
+                ) {
+                    model.synchronize_outside_parents(motion, frame_index);
+                }
+            }
+        }
+        let targets = self.outside_parent_targets();
+        let mut outside_parent_bone_map = HashMap::new();
+        for handle in self.outside_parent_transform_order() {
+            if let Some(model) = self.model_handle_map.get_mut(&handle) {
+                if let Some(motion) = self.model_to_motion.get(&handle) {
+                    let layers = self
+                        .model_to_motion_layers
+                        .get(&handle)
+                        .map_or(&[][..], |layers| layers.as_slice());
                     model.synchronize_motion(
-                        motion,
+                        MotionSource { motion, layers },
                         frame_index,
                         amount,
                         timing,
@@ -1121,6 +2142,7 @@ impl Project {
                     );
                 }
             }
+            self.collect_outside_parent_bones(handle, &targets, &mut outside_parent_bone_map);
         }
         if timing == SimulationTiming::After {
             // TODO: for accessory motions
@@ -1138,16 +2160,19 @@ impl Project {
             .find_camera_transform(frame_index, amount)
         {
             self.camera.set_look_at(frame.lookat);
-            let look_at = self.camera.look_at(self.active_model());
-            self.camera.set_look_at(look_at);
+            self.camera.outside_parent = self
+                .camera_motion
+                .resolve_camera_outside_parent(&frame)
+                .unwrap_or_default();
             self.camera
                 .set_angle(frame.angle.mul_element_wise(CAMERA_DIRECTION));
             self.camera.set_distance(frame.distance * DISTANCE_FACTOR);
             self.camera.set_fov_radians(frame.fov.0);
             self.camera.set_perspective(frame.perspective);
         }
-        let bound_look_at = self.camera.bound_look_at(self);
-        self.camera.update(self.viewport_size.0, bound_look_at);
+        let (bound_look_at, bound_orientation) = self.camera.bound_transform(self);
+        self.camera
+            .update(self.viewport_size.0, bound_look_at, bound_orientation);
         self.camera.set_dirty(false);
     }
 
@@ -1164,9 +2189,21 @@ impl Project {
             .self_shadow_motion
             .find_self_shadow_frame(frame_index, amount)
         {
+            let (last_distance, last_coverage) = (
+                self.shadow_camera.distance(),
+                self.shadow_camera.coverage_mode(),
+            );
             self.shadow_camera.set_distance(frame.distance);
             self.shadow_camera.set_coverage_mode(frame.coverage);
             self.shadow_camera.set_dirty(false);
+            if self.shadow_camera.distance() != last_distance {
+                self.publish_event(Event::SetShadowMapDistance(self.shadow_camera.distance()));
+            }
+            if self.shadow_camera.coverage_mode() != last_coverage {
+                self.publish_event(Event::SetShadowMapMode(
+                    self.shadow_camera.coverage_mode().into(),
+                ));
+            }
         }
     }
 
@@ -1179,9 +2216,134 @@ impl Project {
 }
 
 impl Project {
+    /// Binds a bone of the model to a bone of another model, or clears the binding if `target` is
+    /// `None`. Target is pair of model name and bone name.
+    pub fn set_outside_parent(
+        &mut self,
+        handle: ModelHandle,
+        bone_name: &str,
+        target: Option<(&str, &str)>,
+    ) -> Result<(), MdanceioError> {
+        let bone = self
+            .model_handle_map
+            .get(&handle)
+            .ok_or_else(MdanceioError::model_not_found)?
+            .find_bone(bone_name)
+            .ok_or_else(MdanceioError::bone_not_found)?
+            .handle;
+        let target = match target {
+            Some((target_model, target_bone)) => {
+                let target_handle = self
+                    .find_model_handle_by_name(target_model)
+                    .ok_or_else(MdanceioError::model_not_found)?;
+                if self.resolve_bone((target_model, target_bone)).is_none() {
+                    return Err(MdanceioError::bone_not_found());
+                }
+                if target_handle == handle || self.depends_on_outside_parent(target_handle, handle)
+                {
+                    return Err(MdanceioError::outside_parent_cycle());
+                }
+                Some((target_model.to_owned(), target_bone.to_owned()))
+            }
+            None => None,
+        };
+        if let Some(model) = self.model_handle_map.get_mut(&handle) {
+            model.set_outside_parent(bone, target);
+        }
+        self.restart_from_current();
+        Ok(())
+    }
+
+    fn find_model_handle_by_name(&self, name: &str) -> Option<ModelHandle> {
+        self.transform_model_order_list
+            .iter()
+            .copied()
+            .find(|handle| {
+                self.model_handle_map.get(handle).is_some_and(|model| {
+                    model.get_name() == name || model.get_canonical_name() == name
+                })
+            })
+    }
+
+    fn outside_parent_dependencies(&self) -> HashMap<ModelHandle, HashSet<ModelHandle>> {
+        self.model_handle_map
+            .iter()
+            .map(|(handle, model)| {
+                let dependencies = model
+                    .outside_parent_targets()
+                    .filter_map(|(model_name, _)| self.find_model_handle_by_name(model_name))
+                    .collect();
+                (*handle, dependencies)
+            })
+            .collect()
+    }
+
+    fn depends_on_outside_parent(&self, from: ModelHandle, to: ModelHandle) -> bool {
+        let dependencies = self.outside_parent_dependencies();
+        let mut visited = HashSet::new();
+        let mut stack = vec![from];
+        while let Some(handle) = stack.pop() {
+            if handle == to {
+                return true;
+            }
+            if visited.insert(handle) {
+                if let Some(next) = dependencies.get(&handle) {
+                    stack.extend(next.iter().copied());
+                }
+            }
+        }
+        false
+    }
+
+    fn outside_parent_transform_order(&self) -> Vec<ModelHandle> {
+        let (mut order, cyclic) = sort_by_outside_parents(
+            &self.transform_model_order_list,
+            &self.outside_parent_dependencies(),
+        );
+        if !cyclic.is_empty() {
+            // the first model on a cycle is transformed without its outside parents
+            log::debug!("Outside parents of models {:?} make a cycle", cyclic);
+            order.extend(cyclic);
+        }
+        order
+    }
+
+    fn outside_parent_targets(&self) -> HashSet<(String, String)> {
+        self.model_handle_map
+            .values()
+            .flat_map(|model| model.outside_parent_targets().cloned())
+            .collect()
+    }
+
+    fn collect_outside_parent_bones(
+        &self,
+        handle: ModelHandle,
+        targets: &HashSet<(String, String)>,
+        outside_parent_bone_map: &mut HashMap<(String, String), Bone>,
+    ) {
+        if let Some(model) = self.model_handle_map.get(&handle) {
+            for target in targets {
+                if target.0 == model.get_name() || target.0 == model.get_canonical_name() {
+                    if let Some(bone) = model.find_bone(&target.1) {
+                        outside_parent_bone_map.insert(target.clone(), bone.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn build_outside_parent_bone_map(&self) -> HashMap<(String, String), Bone> {
+        let targets = self.outside_parent_targets();
+        let mut outside_parent_bone_map = HashMap::new();
+        for handle in &self.transform_model_order_list {
+            self.collect_outside_parent_bones(*handle, &targets, &mut outside_parent_bone_map);
+        }
+        outside_parent_bone_map
+    }
+
     pub fn perform_model_bones_transform(&mut self, model: Option<ModelHandle>) {
         let physics_simulation_time_step = self.physics_simulation_time_step();
-        let outside_parent_bone_map = HashMap::new();
+        let outside_parent_bone_map = self.build_outside_parent_bone_map();
         if let Some(model) = model
             .or(self.active_model_pair.0)
             .and_then(|handle| self.model_handle_map.get_mut(&handle))
@@ -1252,7 +2414,7 @@ impl Project {
         self.model_handle_map.insert(model_handle, model);
         self.transform_model_order_list.push(model_handle);
         // TODO: add effect to kScriptOrderTypeStandard
-        // TODO: publish event
+        self.publish_event(Event::AddModel(model_handle));
         let motion = Motion::empty();
         // TODO: clear model undo stack
         self.add_model_motion(motion, model_handle);
@@ -1333,7 +2495,8 @@ impl Project {
             // TODO: clear model undo stack
             self.model_to_motion.insert(model, motion);
             self.set_base_duration(self.project_duration());
-            // TODO: publish add motion event
+            self.publish_event(Event::AddMotion(MotionTarget::Model(model)));
+            self.publish_undo_availability();
             return last_model_motion;
         }
         None
@@ -1350,7 +2513,8 @@ impl Project {
         self.camera_motion
             .initialize_camera_frame_0(&self.camera, active_model);
         self.synchronize_camera(self.local_frame_index.0, 0f32);
-        // TODO: publish motion event
+        self.publish_event(Event::AddMotion(MotionTarget::Camera));
+        self.publish_undo_availability();
         last_motion
     }
 
@@ -1360,7 +2524,347 @@ impl Project {
         self.set_base_duration(self.project_duration());
         self.light_motion.initialize_light_frame_0(&self.light);
         self.synchronize_light(self.local_frame_index.0, 0f32);
-        // TODO: publish motion event
+        self.publish_event(Event::AddMotion(MotionTarget::Light));
+        self.publish_undo_availability();
+        last_motion
+    }
+
+    pub fn remove_model(&mut self, handle: ModelHandle) -> Result<(), MdanceioError> {
+        let mut model = self
+            .model_handle_map
+            .remove(&handle)
+            .ok_or_else(MdanceioError::model_not_found)?;
+        model.remove_all_physics_objects(&mut self.physics_engine);
+        self.transform_model_order_list.retain(|idx| *idx != handle);
+        self.main_render_target.remove_model(handle);
+        self.model_effects.remove(&handle);
+        self.material_effects
+            .retain(|(model_handle, _), _| *model_handle != handle);
+        self.model_overlay_drawer.remove_model(handle);
+        self.model_to_motion_layers.remove(&handle);
+        if self.model_to_motion.remove(&handle).is_some() {
+            self.publish_event(Event::RemoveMotion(MotionTarget::Model(handle)));
+            self.publish_undo_availability();
+        }
+        self.asset_sources.models.remove(&handle);
+        self.asset_sources.model_motions.remove(&handle);
+        for name in [model.get_name(), model.get_canonical_name()] {
+            for other in self.model_handle_map.values_mut() {
+                other.remove_outside_parents_of(name);
+            }
+            for camera in std::iter::once(&mut self.camera).chain(
+                self.inactive_cameras
+                    .values_mut()
+                    .map(|inactive| &mut inactive.camera),
+            ) {
+                if camera.outside_parent.0 == name {
+                    camera.outside_parent = (String::default(), String::default());
+                }
+            }
+        }
+        let referenced_textures = self
+            .model_handle_map
+            .values()
+            .flat_map(|model| model.textures().iter().map(|texture| texture.path.clone()))
+            .collect::<HashSet<_>>();
+        self.loaded_texture_map
+            .retain(|key, _| referenced_textures.contains(key));
+        if self.active_model_pair.1 == Some(handle) {
+            self.active_model_pair.1 = None;
+        }
+        if self.active_model_pair.0 == Some(handle) {
+            self.active_model_pair.0 = None;
+            self.editing_mode = EditingMode::None;
+            self.publish_event(Event::SetActiveModel(None));
+            self.publish_undo_availability();
+        }
+        self.publish_event(Event::RemoveModel(handle));
+        self.restart_from_current();
+        Ok(())
+    }
+
+    pub fn model_motion_layers(&self, handle: ModelHandle) -> &[MotionLayer] {
+        self.model_to_motion_layers
+            .get(&handle)
+            .map_or(&[][..], |layers| layers.as_slice())
+    }
+
+    /// Adds the layer on top of the base motion and existing layers, returning its index.
+    pub fn add_model_motion_layer(
+        &mut self,
+        handle: ModelHandle,
+        layer: MotionLayer,
+    ) -> Result<usize, MdanceioError> {
+        if !self.model_handle_map.contains_key(&handle) {
+            return Err(MdanceioError::model_not_found());
+        }
+        let layers = self.model_to_motion_layers.entry(handle).or_default();
+        layers.push(layer);
+        let index = layers.len() - 1;
+        self.set_base_duration(self.project_duration());
+        self.publish_event(Event::AddMotion(MotionTarget::Model(handle)));
+        self.publish_undo_availability();
+        self.restart_from_current();
+        Ok(index)
+    }
+
+    pub fn load_model_motion_layer(
+        &mut self,
+        handle: ModelHandle,
+        name: &str,
+        motion_data: &[u8],
+    ) -> Result<usize, MdanceioError> {
+        let motion = Motion::new_from_bytes(motion_data, 0)?;
+        if motion.opaque.target_model_name == Motion::CAMERA_AND_LIGHT_TARGET_MODEL_NAME {
+            return Err(MdanceioError::not_intended_model());
+        }
+        self.add_model_motion_layer(handle, MotionLayer::new(name, motion))
+    }
+
+    pub fn remove_model_motion_layer(
+        &mut self,
+        handle: ModelHandle,
+        index: usize,
+    ) -> Option<MotionLayer> {
+        let layers = self.model_to_motion_layers.get_mut(&handle)?;
+        if index >= layers.len() {
+            return None;
+        }
+        let layer = layers.remove(index);
+        self.publish_event(Event::RemoveMotion(MotionTarget::Model(handle)));
+        self.publish_undo_availability();
+        self.restart_from_current();
+        Some(layer)
+    }
+
+    /// Modifies the layer and reevaluates motions at current frame.
+    pub fn update_model_motion_layer(
+        &mut self,
+        handle: ModelHandle,
+        index: usize,
+        update: impl FnOnce(&mut MotionLayer),
+    ) -> bool {
+        if let Some(layer) = self
+            .model_to_motion_layers
+            .get_mut(&handle)
+            .and_then(|layers| layers.get_mut(index))
+        {
+            update(layer);
+            self.set_base_duration(self.project_duration());
+            self.restart_from_current();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Converts keyframes of the motion authored at `from_fps` to the base frame rate of the project.
+    pub fn resample_motion(
+        &mut self,
+        target: MotionTarget,
+        from_fps: f32,
+    ) -> Result<(), MdanceioError> {
+        let to_fps = FpsUnit::HALF_BASE_FPS_F32;
+        match target {
+            MotionTarget::Model(handle) => self
+                .model_to_motion
+                .get_mut(&handle)
+                .ok_or_else(MdanceioError::model_not_found)?
+                .resample(from_fps, to_fps),
+            MotionTarget::Camera => self.camera_motion.resample(from_fps, to_fps),
+            MotionTarget::Light => self.light_motion.resample(from_fps, to_fps),
+            MotionTarget::SelfShadow => self.self_shadow_motion.resample(from_fps, to_fps),
+        }
+        self.set_base_duration(self.project_duration());
+        self.restart_from_current();
+        Ok(())
+    }
+
+    fn resolve_bone_track_name(
+        &self,
+        handle: ModelHandle,
+        bone_name: &str,
+    ) -> Result<String, MdanceioError> {
+        let model = self
+            .model_handle_map
+            .get(&handle)
+            .ok_or_else(MdanceioError::model_not_found)?;
+        model
+            .find_bone(bone_name)
+            .map(|bone| bone.canonical_name.clone())
+            .ok_or_else(MdanceioError::bone_not_found)
+    }
+
+    pub fn bone_keyframe_interpolation(
+        &self,
+        handle: ModelHandle,
+        bone_name: &str,
+        frame_index: u32,
+        channel: BoneInterpolationChannel,
+    ) -> Option<[u8; 4]> {
+        let name = self.resolve_bone_track_name(handle, bone_name).ok()?;
+        self.model_to_motion
+            .get(&handle)?
+            .bone_keyframe_interpolation(&name, frame_index, channel)
+    }
+
+    pub fn set_bone_keyframe_interpolation(
+        &mut self,
+        handle: ModelHandle,
+        bone_name: &str,
+        frame_index: u32,
+        channel: BoneInterpolationChannel,
+        parameters: [u8; 4],
+    ) -> Result<(), MdanceioError> {
+        let name = self.resolve_bone_track_name(handle, bone_name)?;
+        let updated = self.model_to_motion.get_mut(&handle).is_some_and(|motion| {
+            motion.set_bone_keyframe_interpolation(&name, frame_index, channel, parameters)
+        });
+        if !updated {
+            return Err(MdanceioError::keyframe_not_found());
+        }
+        self.restart_from_current();
+        Ok(())
+    }
+
+    /// Inserts a bone keyframe between two keyframes without changing the motion shape.
+    pub fn split_bone_keyframe_interval(
+        &mut self,
+        handle: ModelHandle,
+        bone_name: &str,
+        frame_index: u32,
+    ) -> Result<(), MdanceioError> {
+        let name = self.resolve_bone_track_name(handle, bone_name)?;
+        let split = self
+            .model_to_motion
+            .get_mut(&handle)
+            .is_some_and(|motion| motion.split_bone_interval(&name, frame_index));
+        if !split {
+            return Err(MdanceioError::no_keyframe_interval());
+        }
+        self.restart_from_current();
+        Ok(())
+    }
+
+    pub fn camera_keyframe_interpolation(
+        &self,
+        frame_index: u32,
+        channel: CameraInterpolationChannel,
+    ) -> Option<[u8; 4]> {
+        self.camera_motion
+            .camera_keyframe_interpolation(frame_index, channel)
+    }
+
+    pub fn set_camera_keyframe_interpolation(
+        &mut self,
+        frame_index: u32,
+        channel: CameraInterpolationChannel,
+        parameters: [u8; 4],
+    ) -> Result<(), MdanceioError> {
+        if !self
+            .camera_motion
+            .set_camera_keyframe_interpolation(frame_index, channel, parameters)
+        {
+            return Err(MdanceioError::keyframe_not_found());
+        }
+        self.synchronize_camera(self.local_frame_index.0, 0f32);
+        Ok(())
+    }
+
+    /// Inserts a camera keyframe between two keyframes without changing the motion shape.
+    pub fn split_camera_keyframe_interval(
+        &mut self,
+        frame_index: u32,
+    ) -> Result<(), MdanceioError> {
+        if !self.camera_motion.split_camera_interval(frame_index) {
+            return Err(MdanceioError::no_keyframe_interval());
+        }
+        self.synchronize_camera(self.local_frame_index.0, 0f32);
+        Ok(())
+    }
+
+    /// Model handle and bone name the camera keyframe follows, the bone name is empty when following the model.
+    pub fn camera_keyframe_follow_target(&self, frame_index: u32) -> Option<(ModelHandle, String)> {
+        let (model_name, bone_name) = self
+            .camera_motion
+            .camera_keyframe_outside_parent(frame_index)?;
+        Some((self.find_model_handle_by_name(&model_name)?, bone_name))
+    }
+
+    /// Binds look at of the camera keyframe to the bone of the model, or unbinds it if `target` is `None`.
+    pub fn set_camera_keyframe_follow_target(
+        &mut self,
+        frame_index: u32,
+        target: Option<(ModelHandle, &str)>,
+    ) -> Result<(), MdanceioError> {
+        let target = match target {
+            Some((handle, bone_name)) => {
+                let model = self
+                    .model_handle_map
+                    .get(&handle)
+                    .ok_or_else(MdanceioError::model_not_found)?;
+                let bone_name = if bone_name.is_empty() {
+                    String::default()
+                } else {
+                    model
+                        .find_bone(bone_name)
+                        .ok_or_else(MdanceioError::bone_not_found)?
+                        .canonical_name
+                        .clone()
+                };
+                Some((model.get_name().to_owned(), bone_name))
+            }
+            None => None,
+        };
+        if !self.camera_motion.set_camera_keyframe_outside_parent(
+            frame_index,
+            target
+                .as_ref()
+                .map(|(model_name, bone_name)| (model_name.as_str(), bone_name.as_str())),
+        ) {
+            return Err(MdanceioError::keyframe_not_found());
+        }
+        self.synchronize_camera(self.local_frame_index.0, 0f32);
+        Ok(())
+    }
+
+    pub fn set_camera_follow_mode(&mut self, value: CameraFollowMode) {
+        self.camera.set_follow_mode(value);
+        self.update_global_camera();
+    }
+
+    /// Replaces motion of the model with an empty one, returning the removed motion.
+    pub fn remove_model_motion(&mut self, handle: ModelHandle) -> Option<Motion> {
+        let model = self.model_handle_map.get(&handle)?;
+        let mut motion = Motion::empty();
+        motion.initialize_model_frame_0(model);
+        let last_motion = self.model_to_motion.insert(handle, motion);
+        self.asset_sources.model_motions.remove(&handle);
+        self.publish_event(Event::RemoveMotion(MotionTarget::Model(handle)));
+        self.publish_undo_availability();
+        self.restart_from_current();
+        last_motion
+    }
+
+    pub fn remove_camera_motion(&mut self) -> Motion {
+        let mut motion = Motion::empty();
+        motion.initialize_camera_frame_0(&self.camera, None);
+        let last_motion = std::mem::replace(&mut self.camera_motion, motion);
+        self.asset_sources.camera_motion = None;
+        self.synchronize_camera(self.local_frame_index.0, 0f32);
+        self.publish_event(Event::RemoveMotion(MotionTarget::Camera));
+        self.publish_undo_availability();
+        last_motion
+    }
+
+    pub fn remove_light_motion(&mut self) -> Motion {
+        let mut motion = Motion::empty();
+        motion.initialize_light_frame_0(&self.light);
+        let last_motion = std::mem::replace(&mut self.light_motion, motion);
+        self.asset_sources.light_motion = None;
+        self.synchronize_light(self.local_frame_index.0, 0f32);
+        self.publish_event(Event::RemoveMotion(MotionTarget::Light));
+        self.publish_undo_availability();
         last_motion
     }
 
@@ -1374,21 +2878,26 @@ impl Project {
             model.initialize_rigid_bodies_simulation(&mut self.physics_engine);
             // TODO: soft_bodies
         }
-        self.internal_perform_physics_simulation(self.physics_simulation_time_step());
+        self.internal_perform_physics_simulation(self.physics_simulation_time_step(), false);
         self.synchronize_all_motions(frame_index, 0f32, SimulationTiming::After);
         self.mark_all_models_dirty();
     }
 
-    fn internal_perform_physics_simulation(&mut self, delta: f32) {
+    fn internal_perform_physics_simulation(&mut self, delta: f32, exact: bool) {
         if self.is_physics_simulation_enabled() {
             for model in self.model_handle_map.values_mut() {
                 model.apply_forces(&mut self.physics_engine);
             }
-            self.physics_engine.step(delta, |physics_engine, amount| {
+            let before_step = |physics_engine: &mut PhysicsEngine, amount: f32| {
                 for model in self.model_handle_map.values() {
                     model.synchronize_to_simulation_by_lerp(physics_engine, amount);
                 }
-            });
+            };
+            if exact {
+                self.physics_engine.step_exact(delta, before_step);
+            } else {
+                self.physics_engine.step(delta, before_step);
+            }
             for model in self.model_handle_map.values_mut() {
                 model.synchronize_from_simulation(
                     RigidBodyFollowBone::Perform,
@@ -1468,6 +2977,27 @@ impl Project {
         }
     }
 
+    /// Decodes an encoded image whose format is inferred from the extension of `key`.
+    pub fn load_image_texture(
+        &mut self,
+        key: &str,
+        data: &[u8],
+        update_bind: bool,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<(), MdanceioError> {
+        let format = key
+            .rsplit('.')
+            .next()
+            .and_then(image::ImageFormat::from_extension)
+            .ok_or_else(|| MdanceioError::asset_not_loaded(key, "unsupported image format"))?;
+        let img = image::load_from_memory_with_format(data, format)
+            .map_err(|err| MdanceioError::asset_not_loaded(key, &err.to_string()))?;
+        let rgba = img.to_rgba8();
+        self.load_texture(key, &rgba, rgba.dimensions(), update_bind, device, queue);
+        Ok(())
+    }
+
     pub fn update_bind_texture(&mut self, device: &wgpu::Device) {
         for (handle, model) in &mut self.model_handle_map {
             model.create_all_images(
@@ -1531,6 +3061,45 @@ impl Project {
         fallback_texture
     }
 
+    /// Switches the viewport passes to the scene format of the post process when HDR is toggled.
+    fn update_viewport_format(&mut self, device: &wgpu::Device) {
+        if !self.post_process.update_format(
+            self.render_size(),
+            self.viewport_primary_pass.geometry_depth_view(),
+            device,
+        ) {
+            return;
+        }
+        let format = self.post_process.scene_format();
+        self.viewport_texture_format.0 = format;
+        self.viewport_primary_pass
+            .update_color_format(format, device);
+        self.viewport_secondary_pass
+            .update_color_format(format, device);
+        self.update_viewport_pipelines(device);
+    }
+
+    /// Rebuilds everything drawn into the viewport passes for their format and sample count.
+    fn update_viewport_pipelines(&mut self, device: &wgpu::Device) {
+        let format = RenderFormat {
+            color: self.viewport_texture_format.0,
+            depth: Some(wgpu::TextureFormat::Depth16Unorm),
+            sample_count: self.sample_count(),
+        };
+        *self.clear_pass = ClearPass::new(
+            &[Some(format.color)],
+            format.depth,
+            format.sample_count,
+            device,
+        );
+        self.grid
+            .update_texture_format(format.color, format.sample_count, device);
+        self.environment
+            .update_texture_format(format.color, format.sample_count, device);
+        self.main_render_target
+            .update_format(format, &self.model_handle_map, device);
+    }
+
     fn clear_view_port_primary_pass(
         &self,
         view: &wgpu::TextureView,
@@ -1541,11 +3110,27 @@ impl Project {
         self.clear_pass.draw(
             &[Some(view)],
             Some(depth_stencil_attachment_view),
+            self.viewport_clear_color(),
             device,
             queue,
         );
     }
 
+    /// Draws the skybox of the loaded environment image as seen from `camera`.
+    fn draw_environment(
+        &self,
+        view: &wgpu::TextureView,
+        camera: &PerspectiveCamera,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        if self.environment.is_loaded() {
+            let (view_matrix, projection_matrix) = camera.get_view_transform();
+            self.environment
+                .draw(view, projection_matrix * view_matrix, device, queue);
+        }
+    }
+
     pub fn draw_grid(&self, view: &wgpu::TextureView, device: &wgpu::Device, queue: &wgpu::Queue) {
         self.grid.draw(
             view,
@@ -1556,18 +3141,125 @@ impl Project {
         );
     }
 
+    pub fn draw_all_bones(
+        &mut self,
+        view: &wgpu::TextureView,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        let (view_matrix, projection_matrix) = self.camera.get_view_transform();
+        let models = self
+            .transform_model_order_list
+            .iter()
+            .filter_map(|handle| self.model_handle_map.get(handle));
+        self.bone_drawer
+            .draw(models, projection_matrix * view_matrix, view, device, queue);
+    }
+
+    pub fn draw_all_model_overlays(
+        &mut self,
+        view: &wgpu::TextureView,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        let (view_matrix, projection_matrix) = self.camera.get_view_transform();
+        let models = self.transform_model_order_list.iter().filter_map(|handle| {
+            self.model_handle_map
+                .get(handle)
+                .map(|model| (*handle, model))
+        });
+        self.model_overlay_drawer.draw(
+            models,
+            projection_matrix * view_matrix,
+            view,
+            device,
+            queue,
+        );
+    }
+
+    pub fn model_overlay_drawer_mut(&mut self) -> &mut ModelOverlayDrawer {
+        &mut self.model_overlay_drawer
+    }
+
+    pub fn bone_drawer(&self) -> &BoneDrawer {
+        &self.bone_drawer
+    }
+
+    pub fn bone_drawer_mut(&mut self) -> &mut BoneDrawer {
+        &mut self.bone_drawer
+    }
+
     pub fn draw_shadow_map(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
         if self.shadow_camera.is_enabled() {
-            self.shadow_camera.clear(device, queue);
-            let color_view = self.shadow_camera.color_image();
-            let depth_view = self.shadow_camera.depth_image();
-            self._draw_viewport(
-                DrawType::ShadowMap,
-                color_view,
-                Some(depth_view),
-                device,
-                queue,
-            );
+            let cascades = self.shadow_camera.get_cascades(&self.camera, &self.light);
+            self.draw_shadow_map_cascades(&cascades, &Model::INITIAL_WORLD_MATRIX, device, queue);
+            self.draw_local_shadow_maps(device, queue);
+        }
+    }
+
+    fn draw_shadow_map_cascades(
+        &mut self,
+        cascades: &[ShadowCascade],
+        world: &Matrix4<f32>,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        for (idx, cascade) in cascades.iter().enumerate() {
+            self.shadow_camera.clear(idx, device, queue);
+            if let Some(color_view) = self.shadow_camera.color_image(idx) {
+                self.main_render_target.draw(
+                    DrawType::ShadowMap,
+                    &|model_handle, uniform_data| {
+                        if let Some(model) = self.model_handle_map.get(&model_handle) {
+                            let world = model.world_transform(world);
+                            uniform_data
+                                .set_all_model_parameters(model, &self.model_handle_map.values());
+                            for (idx, material) in model.materials.iter().enumerate() {
+                                uniform_data.set_material_parameters(idx, material);
+                            }
+                            uniform_data.set_shadow_map_cascade_parameters(
+                                cascade,
+                                &world,
+                                &self.shadow_camera,
+                            );
+                        }
+                    },
+                    color_view,
+                    Some(self.shadow_camera.depth_image()),
+                    device,
+                    queue,
+                );
+            }
+        }
+    }
+
+    fn draw_local_shadow_maps(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        for idx in 0..self.local_lights.len().min(MAX_LOCAL_LIGHTS) {
+            if !self.local_lights[idx].casts_shadow() {
+                continue;
+            }
+            self.shadow_camera.clear_local(idx, device, queue);
+            if let Some(color_view) = self.shadow_camera.local_color_image(idx) {
+                let light = &self.local_lights[idx];
+                self.main_render_target.draw(
+                    DrawType::ShadowMap,
+                    &|model_handle, uniform_data| {
+                        if let Some(model) = self.model_handle_map.get(&model_handle) {
+                            let world = model.world_transform(&Model::INITIAL_WORLD_MATRIX);
+                            uniform_data
+                                .set_all_model_parameters(model, &self.model_handle_map.values());
+                            for (idx, material) in model.materials.iter().enumerate() {
+                                uniform_data.set_material_parameters(idx, material);
+                            }
+                            uniform_data.set_local_shadow_map_parameters(light, &world);
+                        }
+                    },
+                    color_view,
+                    Some(self.shadow_camera.local_depth_image()),
+                    device,
+                    queue,
+                );
+            }
         }
     }
 
@@ -1580,24 +3272,19 @@ impl Project {
         queue: &wgpu::Queue,
     ) {
         if self.shadow_camera.is_enabled() {
-            self.shadow_camera.clear(device, queue);
             let mut new_camera = self.camera.clone();
             new_camera.update_view_projection(
                 f32_array_to_mat4_col_major_order(camera_view),
                 f32_array_to_mat4_col_major_order(camera_projection),
             );
-            let color_view = self.shadow_camera.color_image();
-            let depth_view = self.shadow_camera.depth_image();
-            self._draw_viewport_from(
-                world,
-                camera_view,
-                camera_projection,
-                DrawType::ShadowMap,
-                color_view,
-                Some(depth_view),
+            let cascades = self.shadow_camera.get_cascades(&new_camera, &self.light);
+            self.draw_shadow_map_cascades(
+                &cascades,
+                &f32_array_to_mat4_col_major_order(world),
                 device,
                 queue,
             );
+            self.draw_local_shadow_maps(device, queue);
         }
     }
 
@@ -1606,36 +3293,137 @@ impl Project {
         view: &wgpu::TextureView,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
+    ) {
+        let unpremultiply = self.viewport_alpha_mode() == ViewportAlphaMode::Straight;
+        self.draw_viewport_with_alpha(view, unpremultiply, None, device, queue);
+    }
+
+    /// Draws the viewport into `view`, transparent colors are kept premultiplied by alpha unless
+    /// `unpremultiply` is set. With a `viewpoint` the editor grid, overlays and bones are left
+    /// out and the scene is drawn over `view` when no intermediate target is needed.
+    fn draw_viewport_with_alpha(
+        &mut self,
+        view: &wgpu::TextureView,
+        unpremultiply: bool,
+        viewpoint: Option<&Viewpoint>,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
     ) {
         log::debug!("Start drawing viewport");
+        self.reset_all_passes(device);
+        self.update_viewport_format(device);
+        if unpremultiply {
+            self.prepare_unpremultiplier(device);
+        }
         let shadow_map_enabled = self.shadow_camera.is_enabled();
+        let transparent = self.state_flags.enable_viewport_with_transparent;
         let mut encoder =
             device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
         encoder.push_debug_group("Project::draw_viewport");
-        self.clear_view_port_primary_pass(view, device, queue);
-        self.draw_grid(view, device, queue);
+        let post_process_active = self.post_process.is_active();
+        let output_view = self
+            .unpremultiplier
+            .as_ref()
+            .filter(|_| unpremultiply)
+            .map_or(view, |unpremultiplier| unpremultiplier.view());
+        // supersampled frames are drawn at the render size and filtered into the view
+        let target_view = self
+            .supersampler
+            .as_ref()
+            .map_or(output_view, |supersampler| supersampler.view());
+        let scene_view = if post_process_active {
+            self.post_process.scene_view()
+        } else {
+            target_view
+        };
+        let color_view = self
+            .viewport_primary_pass
+            .color_view
+            .as_ref()
+            .unwrap_or(scene_view);
+        let post_process_settings = self.post_process.settings();
+        let camera = viewpoint.map_or(&self.camera, |viewpoint| &viewpoint.camera);
+        if viewpoint.is_none()
+            || post_process_active
+            || self.viewport_primary_pass.color_view.is_some()
+            || self.supersampler.is_some()
+            || unpremultiply
+        {
+            self.clear_view_port_primary_pass(color_view, device, queue);
+        }
+        // the background stays empty to be composited over
+        if !transparent {
+            self.draw_environment(color_view, camera, device, queue);
+            if viewpoint.is_none() {
+                self.draw_grid(color_view, device, queue);
+            }
+        }
         // Draw PreProcess Color
+        if !post_process_settings.is_outline_enabled() {
+            self._draw_viewport(
+                viewpoint,
+                DrawType::Edge,
+                color_view,
+                Some(&self.viewport_primary_pass.depth_view),
+                device,
+                queue,
+            );
+        }
         self._draw_viewport(
-            DrawType::Edge,
-            view,
-            Some(&self.viewport_primary_pass.depth_view),
-            device,
-            queue,
-        );
-        self._draw_viewport(
+            viewpoint,
             DrawType::Color(shadow_map_enabled),
-            view,
+            color_view,
             Some(&self.viewport_primary_pass.depth_view),
             device,
             queue,
         );
+        // multisampled depth can't be read, so the prepass also draws the depth of the scene
+        let geometry_depth_view = self.viewport_primary_pass.geometry_depth_view.as_ref();
+        if post_process_settings.requires_normal()
+            || (geometry_depth_view.is_some() && post_process_settings.requires_depth())
+        {
+            self.post_process
+                .clear_normal(geometry_depth_view, device, queue);
+            self._draw_viewport(
+                viewpoint,
+                DrawType::Normal,
+                self.post_process.normal_view(),
+                Some(self.viewport_primary_pass.geometry_depth_view()),
+                device,
+                queue,
+            );
+        }
         self._draw_viewport(
+            viewpoint,
             DrawType::GroundShadow,
-            view,
+            color_view,
             Some(&self.viewport_primary_pass.depth_view),
             device,
             queue,
         );
+        self.viewport_primary_pass
+            .resolve(scene_view, device, queue);
+        if post_process_active {
+            let (_, projection_matrix) = camera.get_view_transform();
+            self.post_process
+                .draw(target_view, projection_matrix, device, queue);
+        }
+        if let Some(supersampler) = &self.supersampler {
+            supersampler.downsample(
+                output_view,
+                self.supersampling_settings.filter,
+                device,
+                queue,
+            );
+        }
+        if let Some(unpremultiplier) = self.unpremultiplier.as_ref().filter(|_| unpremultiply) {
+            unpremultiplier.draw(view, device, queue);
+        }
+        // overlays are drawn after post-processing to keep them sharp
+        if viewpoint.is_none() {
+            self.draw_all_model_overlays(view, device, queue);
+            self.draw_all_bones(view, device, queue);
+        }
         self.local_frame_index.1 = 0;
         // self.physics_engine.debug_draw(projection_matrix*view_matrix, view, device, queue);
         encoder.pop_debug_group();
@@ -1652,104 +3440,185 @@ impl Project {
         device: &wgpu::Device,
         queue: &wgpu::Queue,
     ) {
-        log::debug!("Start drawing viewport from");
-        let shadow_map_enabled = self.shadow_camera.is_enabled();
-        let mut encoder =
-            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
-        encoder.push_debug_group("Project::draw_viewport_from");
-        self._draw_viewport_from(
-            world,
-            camera_view,
-            camera_projection,
-            DrawType::Edge,
-            view,
-            Some(&self.viewport_primary_pass.depth_view),
-            device,
-            queue,
-        );
-        self._draw_viewport_from(
-            world,
-            camera_view,
-            camera_projection,
-            DrawType::Color(shadow_map_enabled),
-            view,
-            Some(&self.viewport_primary_pass.depth_view),
-            device,
-            queue,
-        );
-        self._draw_viewport_from(
-            world,
-            camera_view,
-            camera_projection,
-            DrawType::GroundShadow,
-            view,
-            Some(&self.viewport_primary_pass.depth_view),
-            device,
-            queue,
+        let mut camera = self.camera.clone();
+        camera.update_view_projection(
+            f32_array_to_mat4_col_major_order(camera_view),
+            f32_array_to_mat4_col_major_order(camera_projection),
         );
-        self.local_frame_index.1 = 0;
-        encoder.pop_debug_group();
-        queue.submit(Some(encoder.finish()));
+        let viewpoint = Viewpoint {
+            camera,
+            world: f32_array_to_mat4_col_major_order(world),
+        };
+        let unpremultiply = self.viewport_alpha_mode() == ViewportAlphaMode::Straight;
+        self.draw_viewport_with_alpha(view, unpremultiply, Some(&viewpoint), device, queue);
     }
 
-    fn _draw_viewport(
-        &self,
-        draw_type: DrawType,
+    /// Draws the frame `frame_index` at the preferred motion fps, averaging sub-frames across
+    /// the open shutter if motion blur is enabled. Physics is stepped through every sub-frame,
+    /// so frames are expected to be drawn in order like a playback.
+    pub fn draw_motion_blurred_frame(
+        &mut self,
+        frame_index: u32,
         view: &wgpu::TextureView,
-        depth: Option<&wgpu::TextureView>,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
     ) {
-        log::debug!("Start internal drawing viewport");
-        self.main_render_target.draw(
-            draw_type,
+        if !self.can_seek() {
+            self.draw_shadow_map(device, queue);
+            self.draw_viewport(view, device, queue);
+            return;
+        }
+        let offsets = self.motion_blur_settings.sample_offsets();
+        let motion_blur = (offsets.len() > 1).then(|| {
+            let mut motion_blur = self.motion_blur.take().unwrap_or_else(|| {
+                Box::new(MotionBlur::new(self.viewport_texture_format.1, device))
+            });
+            motion_blur.prepare(self.viewport_size.0, device);
+            motion_blur.clear(device, queue);
+            motion_blur
+        });
+        let last_duration = self.project_duration();
+        let seek_from = self.local_frame_index.0;
+        let mut last_position = self
+            .sub_frame_position
+            .filter(|position| position.floor() as u32 == seek_from)
+            .unwrap_or(seek_from as f64);
+        let base_frames_per_frame =
+            FpsUnit::HALF_BASE_FPS as f64 / self.preferred_motion_fps.value() as f64;
+        let weight = 1f32 / offsets.len() as f32;
+        for offset in offsets {
+            let position = ((frame_index as f64 + offset as f64) * base_frames_per_frame).max(0f64);
+            let delta =
+                (position - last_position).max(0f64) as f32 * self.physics_simulation_time_step();
+            self.internal_seek_sub_frame(
+                position.floor() as u32,
+                position.fract() as f32,
+                delta,
+                true,
+            );
+            last_position = position;
+            self.update_all_staging_vertex_buffers(device, queue);
+            self.draw_shadow_map(device, queue);
+            match motion_blur.as_ref().and_then(|motion_blur| {
+                motion_blur
+                    .sample_view()
+                    .map(|sample_view| (motion_blur, sample_view))
+            }) {
+                Some((motion_blur, sample_view)) => {
+                    // sub-frames are averaged premultiplied like the samples of a pixel
+                    self.draw_viewport_with_alpha(sample_view, false, None, device, queue);
+                    motion_blur.accumulate(weight, device, queue);
+                }
+                None => self.draw_viewport(view, device, queue),
+            }
+        }
+        if let Some(motion_blur) = motion_blur {
+            if self.viewport_alpha_mode() == ViewportAlphaMode::Straight {
+                self.prepare_unpremultiplier(device);
+                if let Some(unpremultiplier) = &self.unpremultiplier {
+                    motion_blur.resolve(unpremultiplier.view(), device, queue);
+                    unpremultiplier.draw(view, device, queue);
+                }
+            } else {
+                motion_blur.resolve(view, device, queue);
+            }
+            self.motion_blur = Some(motion_blur);
+        }
+        self.sub_frame_position = Some(last_position);
+        let frame_index = self.local_frame_index.0;
+        self.set_base_duration(frame_index);
+        self.publish_event(Event::Seek {
+            frame_index,
+            last_frame_index: seek_from,
+            duration: last_duration,
+        });
+    }
+
+    /// Draws the auxiliary targets of the current frame at the viewport size if enabled. Motion
+    /// vectors are relative to the previous call, so frames are expected to be drawn in order.
+    pub fn draw_auxiliary_targets(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if !self.auxiliary_targets_enabled {
+            return;
+        }
+        let size = self.viewport_size.0;
+        if self
+            .auxiliary_targets
+            .as_ref()
+            .map(|targets| targets.size())
+            != Some(size)
+        {
+            self.auxiliary_targets = Some(Box::new(AuxiliaryTargets::new(
+                size,
+                wgpu::TextureFormat::Depth16Unorm,
+                device,
+            )));
+        }
+        let targets = match &self.auxiliary_targets {
+            Some(targets) => targets,
+            None => return,
+        };
+        let (view_matrix, projection_matrix) = self.camera.get_view_transform();
+        let transforms = self
+            .model_handle_map
+            .iter()
+            .map(|(handle, model)| {
+                let world = model.world_transform(&Model::INITIAL_WORLD_MATRIX);
+                (*handle, projection_matrix * view_matrix * world)
+            })
+            .collect::<HashMap<_, _>>();
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Project/AuxiliaryTargets/CommandEncoder"),
+        });
+        // models drawn for the first time start without motion
+        for (handle, model) in &self.model_handle_map {
+            if targets.previous_transform(*handle).is_none() {
+                model.store_previous_vertex_buffer(&mut encoder);
+            }
+        }
+        queue.submit(Some(encoder.finish()));
+        targets.clear(device, queue);
+        self.main_render_target.draw_multiple(
+            DrawType::Auxiliary,
             &|model_handle, uniform_data| {
-                if let Some(model) = self.model_handle_map.get(&model_handle) {
-                    let world: Matrix4<f32> = match draw_type {
-                        DrawType::GroundShadow | DrawType::ShadowMap => {
-                            self.light.get_shadow_transform()
-                        }
-                        _ => Model::INITIAL_WORLD_MATRIX,
-                    };
-                    uniform_data.set_camera_parameters(&self.camera, &world, model);
-                    uniform_data.set_light_parameters(&self.light);
+                if let (Some(model), Some(transform)) = (
+                    self.model_handle_map.get(&model_handle),
+                    transforms.get(&model_handle),
+                ) {
+                    uniform_data.set_camera_parameters(
+                        &self.camera,
+                        &Model::INITIAL_WORLD_MATRIX,
+                        model,
+                    );
                     uniform_data.set_all_model_parameters(model, &self.model_handle_map.values());
                     for (idx, material) in model.materials.iter().enumerate() {
                         uniform_data.set_material_parameters(idx, material);
-                        if let DrawType::Edge = draw_type {
-                            let edge_size_scale_factor = model.edge_size(&self.camera);
-                            uniform_data.set_edge_parameters(idx, material, edge_size_scale_factor);
-                        }
-                    }
-                    if let DrawType::GroundShadow = draw_type {
-                        uniform_data.set_ground_shadow_parameters(
-                            &self.light,
-                            &self.camera,
-                            &world,
-                        );
-                    }
-                    if matches!(draw_type, DrawType::Color(_) | DrawType::ShadowMap) {
-                        uniform_data.set_shadow_map_parameters(
-                            &self.shadow_camera,
-                            &world,
-                            &self.camera,
-                            &self.light,
-                        );
                     }
+                    let previous = targets
+                        .previous_transform(model_handle)
+                        .unwrap_or(transform);
+                    uniform_data.set_auxiliary_parameters(model_handle, previous, size);
                 }
             },
-            view,
-            depth,
+            &targets.views(),
+            Some(targets.depth_view()),
             device,
             queue,
         );
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Project/AuxiliaryTargets/CommandEncoder"),
+        });
+        for model in self.model_handle_map.values() {
+            model.store_previous_vertex_buffer(&mut encoder);
+        }
+        queue.submit(Some(encoder.finish()));
+        if let Some(targets) = &mut self.auxiliary_targets {
+            targets.set_previous_transforms(transforms);
+        }
     }
 
-    fn _draw_viewport_from(
+    fn _draw_viewport(
         &self,
-        world: [f32; 16],
-        camera_view: [f32; 16],
-        camera_projection: [f32; 16],
+        viewpoint: Option<&Viewpoint>,
         draw_type: DrawType,
         view: &wgpu::TextureView,
         depth: Option<&wgpu::TextureView>,
@@ -1757,11 +3626,10 @@ impl Project {
         queue: &wgpu::Queue,
     ) {
         log::debug!("Start internal drawing viewport");
-        let mut new_camera = self.camera.clone();
-        new_camera.update_view_projection(
-            f32_array_to_mat4_col_major_order(camera_view),
-            f32_array_to_mat4_col_major_order(camera_projection),
-        );
+        let (camera, model_world) = viewpoint
+            .map_or((&self.camera, Model::INITIAL_WORLD_MATRIX), |viewpoint| {
+                (&viewpoint.camera, viewpoint.world)
+            });
         self.main_render_target.draw(
             draw_type,
             &|model_handle, uniform_data| {
@@ -1770,26 +3638,29 @@ impl Project {
                         DrawType::GroundShadow | DrawType::ShadowMap => {
                             self.light.get_shadow_transform()
                         }
-                        _ => f32_array_to_mat4_col_major_order(world),
+                        _ => model_world,
                     };
-                    uniform_data.set_camera_parameters(&new_camera, &world, model);
+                    uniform_data.set_camera_parameters(camera, &world, model);
                     uniform_data.set_light_parameters(&self.light);
+                    uniform_data.set_environment_parameters(&self.environment);
+                    uniform_data
+                        .set_local_light_parameters(&self.local_lights, &self.shadow_camera);
                     uniform_data.set_all_model_parameters(model, &self.model_handle_map.values());
                     for (idx, material) in model.materials.iter().enumerate() {
                         uniform_data.set_material_parameters(idx, material);
                         if let DrawType::Edge = draw_type {
-                            let edge_size_scale_factor = model.edge_size(&new_camera);
+                            let edge_size_scale_factor = model.edge_size(camera);
                             uniform_data.set_edge_parameters(idx, material, edge_size_scale_factor);
                         }
                     }
                     if let DrawType::GroundShadow = draw_type {
-                        uniform_data.set_ground_shadow_parameters(&self.light, &new_camera, &world);
+                        uniform_data.set_ground_shadow_parameters(&self.light, camera, &world);
                     }
                     if matches!(draw_type, DrawType::Color(_) | DrawType::ShadowMap) {
                         uniform_data.set_shadow_map_parameters(
                             &self.shadow_camera,
                             &world,
-                            &new_camera,
+                            camera,
                             &self.light,
                         );
                     }
@@ -1802,3 +3673,665 @@ impl Project {
         );
     }
 }
+
+impl Project {
+    pub fn load_model_from(
+        &mut self,
+        path: &str,
+        loader: &mut dyn AssetLoader,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<ModelHandle, MdanceioError> {
+        let data = loader.load(path)?;
+        let handle = self.load_model(&data, device, queue)?;
+        self.asset_sources.models.insert(handle, path.to_owned());
+        let texture_paths = self
+            .model_handle_map
+            .get(&handle)
+            .map(|model| {
+                model
+                    .textures()
+                    .iter()
+                    .map(|texture| texture.path.clone())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        for texture_path in texture_paths {
+            let result = loader
+                .load(&resolve_relative_path(path, &texture_path))
+                .and_then(|data| {
+                    self.load_image_texture(&texture_path, &data, false, device, queue)
+                });
+            if let Err(err) = result {
+                // missing textures fall back to white image
+                log::warn!("{}", err);
+            }
+        }
+        self.update_bind_texture(device);
+        Ok(handle)
+    }
+
+    pub fn load_model_motion_from(
+        &mut self,
+        path: &str,
+        loader: &mut dyn AssetLoader,
+    ) -> Result<(), MdanceioError> {
+        let data = loader.load(path)?;
+        self.load_model_motion(&data)?;
+        if let Some(handle) = self.active_model_pair.0 {
+            self.asset_sources
+                .model_motions
+                .insert(handle, path.to_owned());
+        }
+        Ok(())
+    }
+
+    /// Adds the motion at `path` as a layer of the model, returning its index.
+    pub fn load_model_motion_layer_from(
+        &mut self,
+        handle: ModelHandle,
+        name: &str,
+        path: &str,
+        loader: &mut dyn AssetLoader,
+    ) -> Result<usize, MdanceioError> {
+        let data = loader.load(path)?;
+        let index = self.load_model_motion_layer(handle, name, &data)?;
+        self.update_model_motion_layer(handle, index, |layer| layer.source = Some(path.to_owned()));
+        Ok(index)
+    }
+
+    pub fn load_camera_motion_from(
+        &mut self,
+        path: &str,
+        loader: &mut dyn AssetLoader,
+    ) -> Result<(), MdanceioError> {
+        let data = loader.load(path)?;
+        self.load_camera_motion(&data)?;
+        self.asset_sources.camera_motion = Some(path.to_owned());
+        Ok(())
+    }
+
+    pub fn load_light_motion_from(
+        &mut self,
+        path: &str,
+        loader: &mut dyn AssetLoader,
+    ) -> Result<(), MdanceioError> {
+        let data = loader.load(path)?;
+        self.load_light_motion(&data)?;
+        self.asset_sources.light_motion = Some(path.to_owned());
+        Ok(())
+    }
+
+    pub fn load_color_grading_lut_from(
+        &mut self,
+        path: &str,
+        loader: &mut dyn AssetLoader,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<(), MdanceioError> {
+        let data = loader.load(path)?;
+        self.load_color_grading_lut(&data, device, queue)?;
+        self.asset_sources.color_grading_lut = Some(path.to_owned());
+        Ok(())
+    }
+
+    pub fn load_environment_image_from(
+        &mut self,
+        path: &str,
+        loader: &mut dyn AssetLoader,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<(), MdanceioError> {
+        let data = loader.load(path)?;
+        self.load_environment_image(&data, device, queue)?;
+        self.asset_sources.environment_image = Some(path.to_owned());
+        Ok(())
+    }
+
+    /// Records path of the audio played with the project, the decoding is up to the host.
+    pub fn set_audio_source(&mut self, path: Option<&str>) {
+        self.asset_sources.audio = path.map(|path| path.to_owned());
+    }
+
+    pub fn audio_source(&self) -> Option<&str> {
+        self.asset_sources.audio.as_deref()
+    }
+
+    pub fn save(&self) -> ProjectFile {
+        let models = self
+            .transform_model_order_list
+            .iter()
+            .filter_map(|handle| {
+                let model = self.model_handle_map.get(handle)?;
+                let source = match self.asset_sources.models.get(handle) {
+                    Some(source) => source,
+                    None => {
+                        log::warn!("Model {} has no source and is not saved", model.get_name());
+                        return None;
+                    }
+                };
+                let layers = self
+                    .model_motion_layers(*handle)
+                    .iter()
+                    .filter_map(|layer| {
+                        let Some(source) = &layer.source else {
+                            log::warn!(
+                                "Motion layer {} has no source and is not saved",
+                                layer.name
+                            );
+                            return None;
+                        };
+                        Some(MotionLayerEntry {
+                            name: layer.name.clone(),
+                            source: source.clone(),
+                            weight: layer.weight,
+                            additive: layer.blend_mode == LayerBlendMode::Additive,
+                            frame_offset: layer.frame_offset,
+                            enabled: layer.enabled,
+                            bone_mask: layer.bone_mask.as_ref().map(sorted_names),
+                            morph_mask: layer.morph_mask.as_ref().map(sorted_names),
+                        })
+                    })
+                    .collect();
+                let mut material_effects = self
+                    .material_effects
+                    .iter()
+                    .filter(|((model_handle, _), _)| model_handle == handle)
+                    .map(|((_, idx), name)| (*idx, name.clone()))
+                    .collect::<Vec<_>>();
+                material_effects.sort();
+                Some(ModelEntry {
+                    source: source.clone(),
+                    motion: self.asset_sources.model_motions.get(handle).cloned(),
+                    layers,
+                    effect: self.model_effects.get(handle).cloned(),
+                    material_effects,
+                    visible: model.is_visible(),
+                    shadow_map: model.states.enable_shadow_map,
+                    ground_shadow: model.states.enable_ground_shadow,
+                    add_blend: model.is_add_blend_enabled(),
+                    edge_color: model.edge_color().into(),
+                    edge_size_scale_factor: model.edge_size_scale_factor(),
+                    ambient_occlusion_excluded: excluded_materials(
+                        model,
+                        Material::is_ambient_occlusion_excluded,
+                    ),
+                    outline_excluded: excluded_materials(model, Material::is_outline_excluded),
+                })
+            })
+            .collect();
+        let camera_motion_source = |name: &String| {
+            if *name == self.active_camera_name {
+                self.asset_sources.camera_motion.clone()
+            } else {
+                self.inactive_cameras
+                    .get(name)
+                    .and_then(|camera| camera.motion_source.clone())
+            }
+        };
+        let cameras = self
+            .camera_names
+            .iter()
+            .skip(1)
+            .map(|name| CameraEntry {
+                name: name.clone(),
+                motion: camera_motion_source(name),
+            })
+            .collect();
+        let mut effect_names = self.effects.keys().collect::<Vec<_>>();
+        effect_names.sort();
+        let effect_entries = effect_names
+            .into_iter()
+            .filter_map(|name| {
+                let entry = self.asset_sources.effects.get(name);
+                if entry.is_none() {
+                    log::warn!("Effect {} has no source and is not saved", name);
+                }
+                entry.cloned()
+            })
+            .collect();
+        ProjectFile {
+            version: ProjectFile::CURRENT_VERSION,
+            fps: self.preferred_motion_fps.value(),
+            physics_simulation_mode: self.physics_engine.simulation_mode.into(),
+            grid_visible: self.grid.visible(),
+            grid_cell: self.grid.cell().into(),
+            grid_size: self.grid.size().into(),
+            background_color: self.viewport_background_color.into(),
+            audio: self.asset_sources.audio.clone(),
+            camera_name: self.camera_names[0].clone(),
+            camera_motion: camera_motion_source(&self.camera_names[0]),
+            cameras,
+            active_camera: Some(self.active_camera_name.clone())
+                .filter(|name| *name != self.camera_names[0]),
+            light_motion: self.asset_sources.light_motion.clone(),
+            post_process: *self.post_process.settings(),
+            color_grading_lut: self.asset_sources.color_grading_lut.clone(),
+            environment: *self.environment.settings(),
+            environment_image: self.asset_sources.environment_image.clone(),
+            motion_blur: self.motion_blur_settings,
+            sample_level: self.sample_level.1,
+            supersampling: self.supersampling_settings,
+            shadow_map: self.shadow_camera.settings(),
+            flags: self.state_flags,
+            effects: effect_entries,
+            lights: self
+                .local_lights
+                .iter()
+                .map(|light| {
+                    let (inner_angle, outer_angle) = light.cone_angles();
+                    LightEntry {
+                        typ: light.typ(),
+                        color: light.color().into(),
+                        position: light.position().into(),
+                        direction: light.direction().into(),
+                        range: light.range(),
+                        inner_angle: inner_angle.0,
+                        outer_angle: outer_angle.0,
+                        shadow: light.is_shadow_enabled(),
+                    }
+                })
+                .collect(),
+            models,
+        }
+    }
+
+    /// Restores a saved project into this empty project, resolving assets through `loader`.
+    pub fn load(
+        &mut self,
+        file: &ProjectFile,
+        loader: &mut dyn AssetLoader,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<(), MdanceioError> {
+        if file.version > ProjectFile::CURRENT_VERSION {
+            return Err(MdanceioError::unsupported_project_version(file.version));
+        }
+        if !self.model_handle_map.is_empty() {
+            return Err(MdanceioError::project_not_empty());
+        }
+        // cameras of the file replace all but the active camera, which takes the first name
+        self.inactive_cameras.clear();
+        self.camera_names = vec![self.active_camera_name.clone()];
+        self.set_preferred_motion_fps(file.fps);
+        self.set_physics_simulation_mode(file.physics_simulation_mode.into());
+        self.set_grid_visible(file.grid_visible);
+        self.grid
+            .set_cell_and_size(file.grid_cell.into(), file.grid_size.into(), device);
+        self.viewport_background_color = file.background_color.into();
+        for entry in &file.effects {
+            if entry.fx {
+                self.load_fx_effect(&entry.source, loader, device, queue)?;
+            } else {
+                self.load_effect_from(&entry.source, loader, device, queue)?;
+            }
+        }
+        let mut loaded_models = vec![];
+        for entry in &file.models {
+            let handle = self.load_model_from(&entry.source, loader, device, queue)?;
+            if let Some(motion) = &entry.motion {
+                self.load_model_motion_from(motion, loader)?;
+            }
+            for layer_entry in &entry.layers {
+                let index = self.load_model_motion_layer_from(
+                    handle,
+                    &layer_entry.name,
+                    &layer_entry.source,
+                    loader,
+                )?;
+                self.update_model_motion_layer(handle, index, |layer| {
+                    layer.weight = layer_entry.weight;
+                    layer.blend_mode = if layer_entry.additive {
+                        LayerBlendMode::Additive
+                    } else {
+                        LayerBlendMode::Override
+                    };
+                    layer.frame_offset = layer_entry.frame_offset;
+                    layer.enabled = layer_entry.enabled;
+                    layer.bone_mask = layer_entry
+                        .bone_mask
+                        .as_ref()
+                        .map(|names| names.iter().cloned().collect());
+                    layer.morph_mask = layer_entry
+                        .morph_mask
+                        .as_ref()
+                        .map(|names| names.iter().cloned().collect());
+                });
+            }
+            loaded_models.push((handle, entry));
+        }
+        let first_camera_name = self.active_camera_name.clone();
+        self.rename_camera(&first_camera_name, &file.camera_name)?;
+        if let Some(motion) = &file.camera_motion {
+            self.load_camera_motion_from(motion, loader)?;
+        }
+        for entry in &file.cameras {
+            self.add_camera(&entry.name)?;
+            if let Some(motion) = &entry.motion {
+                self.set_active_camera(&entry.name)?;
+                self.load_camera_motion_from(motion, loader)?;
+            }
+        }
+        self.set_active_camera(file.active_camera.as_ref().unwrap_or(&file.camera_name))?;
+        if let Some(motion) = &file.light_motion {
+            self.load_light_motion_from(motion, loader)?;
+        }
+        if let Some(lut) = &file.color_grading_lut {
+            self.load_color_grading_lut_from(lut, loader, device, queue)?;
+        }
+        self.set_post_process_settings(file.post_process);
+        if let Some(image) = &file.environment_image {
+            self.load_environment_image_from(image, loader, device, queue)?;
+        }
+        self.set_environment_settings(file.environment);
+        self.set_motion_blur_settings(file.motion_blur);
+        // the sample level depends on the adapter, so projects still load without it
+        if let Err(err) = self.set_sample_level(file.sample_level) {
+            log::warn!("Failed to restore the sample level: {}", err);
+        }
+        self.set_supersampling_settings(file.supersampling);
+        self.set_shadow_map_settings(file.shadow_map, device);
+        self.local_lights = file
+            .lights
+            .iter()
+            .take(MAX_LOCAL_LIGHTS)
+            .map(|entry| {
+                let mut light = match entry.typ {
+                    LocalLightType::Point => LocalLight::new_point(
+                        entry.position.into(),
+                        entry.color.into(),
+                        entry.range,
+                    ),
+                    LocalLightType::Spot => LocalLight::new_spot(
+                        entry.position.into(),
+                        entry.direction.into(),
+                        entry.color.into(),
+                        entry.range,
+                    ),
+                };
+                light.set_cone_angles(Rad(entry.inner_angle), Rad(entry.outer_angle));
+                light.set_shadow_enabled(entry.shadow);
+                light
+            })
+            .collect();
+        self.set_audio_source(file.audio.as_deref());
+        // flags like motion merge affect loading, so they are applied last
+        copy_persistent_flags(&file.flags, &mut self.state_flags);
+        self.restart_from_current();
+        for (handle, entry) in loaded_models {
+            self.set_model_visible(handle, entry.visible);
+            self.set_model_shadow_map_enabled(handle, entry.shadow_map);
+            if let Some(model) = self.model_handle_map.get_mut(&handle) {
+                model.states.enable_ground_shadow = entry.ground_shadow;
+                model.set_add_blend_enabled(entry.add_blend);
+                model.set_edge_color(entry.edge_color.into());
+                model.set_edge_size_scale_factor(entry.edge_size_scale_factor);
+            }
+            for idx in &entry.ambient_occlusion_excluded {
+                if self
+                    .set_material_ambient_occlusion_excluded(handle, *idx, true)
+                    .is_err()
+                {
+                    log::warn!("Excluded material {} not found in {}", idx, entry.source);
+                }
+            }
+            if let Some(effect) = &entry.effect {
+                if let Err(err) = self.set_model_effect(handle, Some(effect), device) {
+                    log::warn!("Effect {} not applied to {}: {}", effect, entry.source, err);
+                }
+            }
+            for (idx, effect) in &entry.material_effects {
+                if let Err(err) = self.set_material_effect(handle, *idx, Some(effect), device) {
+                    log::warn!("Effect {} not applied to material {}: {}", effect, idx, err);
+                }
+            }
+            for idx in &entry.outline_excluded {
+                if self
+                    .set_material_outline_excluded(handle, *idx, true)
+                    .is_err()
+                {
+                    log::warn!("Excluded material {} not found in {}", idx, entry.source);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn sorted_names(names: &HashSet<String>) -> Vec<String> {
+    let mut names = names.iter().cloned().collect::<Vec<_>>();
+    names.sort();
+    names
+}
+
+fn excluded_materials(model: &Model, excluded: fn(&Material) -> bool) -> Vec<usize> {
+    model
+        .materials
+        .iter()
+        .enumerate()
+        .filter(|(_, material)| excluded(material))
+        .map(|(idx, _)| idx)
+        .collect()
+}
+
+/// Sorts models so that each model comes after models its outside parents belong to, keeping
+/// transform order otherwise. Models left on dependency cycles are returned separately.
+fn sort_by_outside_parents(
+    order: &[ModelHandle],
+    dependencies: &HashMap<ModelHandle, HashSet<ModelHandle>>,
+) -> (Vec<ModelHandle>, Vec<ModelHandle>) {
+    let mut sorted = Vec::with_capacity(order.len());
+    let mut placed = HashSet::new();
+    let mut remaining = order.to_vec();
+    while let Some(idx) = remaining.iter().position(|handle| {
+        !dependencies.get(handle).is_some_and(|dependencies| {
+            dependencies
+                .iter()
+                .any(|dependency| !placed.contains(dependency) && order.contains(dependency))
+        })
+    }) {
+        let handle = remaining.remove(idx);
+        placed.insert(handle);
+        sorted.push(handle);
+    }
+    (sorted, remaining)
+}
+
+#[test]
+fn test_sort_by_outside_parents() {
+    let dependencies = HashMap::from([
+        (1, HashSet::from([3])),
+        (2, HashSet::new()),
+        (3, HashSet::from([2])),
+    ]);
+    let (sorted, cyclic) = sort_by_outside_parents(&[1, 2, 3], &dependencies);
+    assert_eq!(vec![2, 3, 1], sorted);
+    assert!(cyclic.is_empty());
+    let dependencies = HashMap::from([
+        (1, HashSet::from([2])),
+        (2, HashSet::from([1])),
+        (3, HashSet::new()),
+    ]);
+    let (sorted, cyclic) = sort_by_outside_parents(&[1, 2, 3], &dependencies);
+    assert_eq!(vec![3], sorted);
+    assert_eq!(vec![1, 2], cyclic);
+}
+
+/// Device of the default adapter, or `None` where no adapter is available to run GPU tests.
+#[cfg(test)]
+pub(crate) async fn test_device() -> Option<(wgpu::Adapter, wgpu::Device, wgpu::Queue)> {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::PRIMARY,
+        ..Default::default()
+    });
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions::default())
+        .await?;
+    let (device, queue) = adapter
+        .request_device(&wgpu::DeviceDescriptor::default(), None)
+        .await
+        .ok()?;
+    Some((adapter, device, queue))
+}
+
+/// Draws the project into a new `Rgba8Unorm` texture of the viewport size and reads it back,
+/// through `draw_viewport_from` with the project camera if `from` is set.
+#[cfg(test)]
+fn draw_test_viewport(
+    project: &mut Project,
+    from: bool,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) -> Vec<u8> {
+    let (width, height) = (project.viewport_size.0.x, project.viewport_size.0.y);
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: None,
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    if from {
+        let (view_matrix, projection_matrix) = project.camera.get_view_transform();
+        let to_array =
+            |matrix: Matrix4<f32>| bytemuck::cast::<[[f32; 4]; 4], [f32; 16]>(matrix.into());
+        project.draw_viewport_from(
+            to_array(Model::INITIAL_WORLD_MATRIX),
+            to_array(view_matrix),
+            to_array(projection_matrix),
+            &view,
+            device,
+            queue,
+        );
+    } else {
+        project.draw_viewport(&view, device, queue);
+    }
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: (width * height * 4) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(width * 4),
+                rows_per_image: None,
+            },
+        },
+        texture.size(),
+    );
+    queue.submit(Some(encoder.finish()));
+    let slice = buffer.slice(..);
+    slice.map_async(wgpu::MapMode::Read, |_| {});
+    device.poll(wgpu::Maintain::Wait);
+    let pixels = slice.get_mapped_range().to_vec();
+    buffer.unmap();
+    pixels
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_draw_viewport_from_draws_environment() {
+    let Some((adapter, device, queue)) = test_device().await else {
+        return;
+    };
+    let injector = Injector {
+        pixel_format: wgpu::TextureFormat::Rgba8Unorm,
+        // rows of 64 pixels are aligned to `COPY_BYTES_PER_ROW_ALIGNMENT`
+        viewport_size: [64, 32],
+    };
+    let mut project = Project::new(&adapter, &device, &queue, injector);
+    project.set_grid_visible(false);
+    // a horizontal cubemap strip with a different color on each face
+    let strip = image::RgbImage::from_fn(24, 4, |x, _| {
+        image::Rgb([(x / 4 * 50) as u8, 128, 255 - (x / 4 * 50) as u8])
+    });
+    let mut data = vec![];
+    strip
+        .write_to(
+            &mut std::io::Cursor::new(&mut data),
+            image::ImageOutputFormat::Png,
+        )
+        .unwrap();
+    project
+        .load_environment_image(&data, &device, &queue)
+        .unwrap();
+    project.update(&device, &queue);
+    let expected = draw_test_viewport(&mut project, false, &device, &queue);
+    assert!(expected.chunks(4).any(|pixel| pixel != [0xff; 4]));
+    assert_eq!(
+        expected,
+        draw_test_viewport(&mut project, true, &device, &queue)
+    );
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_draw_viewport_from_straight_alpha() {
+    let Some((adapter, device, queue)) = test_device().await else {
+        return;
+    };
+    let injector = Injector {
+        pixel_format: wgpu::TextureFormat::Rgba8Unorm,
+        viewport_size: [64, 32],
+    };
+    let mut project = Project::new(&adapter, &device, &queue, injector);
+    project.set_viewport_alpha_mode(ViewportAlphaMode::Straight);
+    project.update(&device, &queue);
+    let expected = draw_test_viewport(&mut project, false, &device, &queue);
+    // the background is left transparent instead of drawing the grid and the environment
+    assert!(expected.chunks(4).all(|pixel| pixel[3] == 0));
+    assert_eq!(
+        expected,
+        draw_test_viewport(&mut project, true, &device, &queue)
+    );
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_load_replaces_existing_cameras() {
+    struct EmptyLoader;
+    impl AssetLoader for EmptyLoader {
+        fn load(&mut self, path: &str) -> Result<Vec<u8>, MdanceioError> {
+            Err(MdanceioError::asset_not_loaded(path, "not found"))
+        }
+    }
+    let Some((adapter, device, queue)) = test_device().await else {
+        return;
+    };
+    let injector = Injector {
+        pixel_format: wgpu::TextureFormat::Rgba8Unorm,
+        viewport_size: [64, 32],
+    };
+    let mut project = Project::new(&adapter, &device, &queue, injector);
+    project.add_camera("Camera 2").unwrap();
+    project.add_camera("Camera 3").unwrap();
+    project.set_active_camera("Camera 3").unwrap();
+    let file = ProjectFile {
+        camera_name: "Front".to_owned(),
+        cameras: vec![CameraEntry {
+            name: "Camera 2".to_owned(),
+            motion: None,
+        }],
+        active_camera: Some("Camera 2".to_owned()),
+        ..Default::default()
+    };
+    project
+        .load(&file, &mut EmptyLoader, &device, &queue)
+        .unwrap();
+    assert_eq!(vec!["Front", "Camera 2"], project.camera_names());
+    assert_eq!("Camera 2", project.active_camera_name());
+}
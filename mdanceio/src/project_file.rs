@@ -0,0 +1,929 @@
+use std::{collections::HashMap, fmt::Write, path::PathBuf};
+
+use crate::{
+    error::MdanceioError,
+    light::{LocalLight, LocalLightType},
+    project::{
+        DownsampleFilter, EnvironmentSettings, ModelHandle, MotionBlurSettings,
+        PostProcessSettings, Project, ProjectStates, SupersamplingSettings, ToneMappingOperator,
+    },
+    shadow_camera::ShadowMapSettings,
+};
+
+/// Resolves asset paths recorded in a project file to their contents.
+pub trait AssetLoader {
+    fn load(&mut self, path: &str) -> Result<Vec<u8>, MdanceioError>;
+}
+
+/// Loads assets from the file system, resolving relative paths against `base_dir`.
+pub struct FileSystemLoader {
+    base_dir: PathBuf,
+}
+
+impl FileSystemLoader {
+    pub fn new(base_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            base_dir: base_dir.into(),
+        }
+    }
+}
+
+impl AssetLoader for FileSystemLoader {
+    fn load(&mut self, path: &str) -> Result<Vec<u8>, MdanceioError> {
+        std::fs::read(self.base_dir.join(path))
+            .map_err(|err| MdanceioError::asset_not_loaded(path, &err.to_string()))
+    }
+}
+
+/// Paths of assets loaded through an `AssetLoader`, recorded so a project can be saved.
+#[derive(Debug, Clone, Default)]
+pub struct AssetSources {
+    pub models: HashMap<ModelHandle, String>,
+    pub model_motions: HashMap<ModelHandle, String>,
+    pub camera_motion: Option<String>,
+    pub light_motion: Option<String>,
+    pub audio: Option<String>,
+    pub color_grading_lut: Option<String>,
+    pub environment_image: Option<String>,
+    /// Sources of effects keyed by effect name
+    pub effects: HashMap<String, EffectEntry>,
+}
+
+/// Resolves `path` relative to the directory containing `base`, normalizing separators.
+pub fn resolve_relative_path(base: &str, path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let base = base.replace('\\', "/");
+    match base.rsplit_once('/') {
+        Some((dir, _)) if !path.starts_with('/') => format!("{}/{}", dir, path),
+        _ => path,
+    }
+}
+
+/// Effect loaded from `source`, an effect package manifest or an MME `.fx` file if `fx`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EffectEntry {
+    pub source: String,
+    pub fx: bool,
+}
+
+/// Motion layer evaluated on top of the motion of a model, masks are sorted by name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MotionLayerEntry {
+    pub name: String,
+    pub source: String,
+    pub weight: f32,
+    pub additive: bool,
+    pub frame_offset: i32,
+    pub enabled: bool,
+    pub bone_mask: Option<Vec<String>>,
+    pub morph_mask: Option<Vec<String>>,
+}
+
+impl MotionLayerEntry {
+    pub fn new(name: &str, source: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            source: source.to_owned(),
+            weight: 1f32,
+            additive: false,
+            frame_offset: 0,
+            enabled: true,
+            bone_mask: None,
+            morph_mask: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelEntry {
+    pub source: String,
+    pub motion: Option<String>,
+    /// Motion layers in evaluation order
+    pub layers: Vec<MotionLayerEntry>,
+    /// Name of the effect drawing the model, the default effect if `None`
+    pub effect: Option<String>,
+    /// Effects of materials by material index, taking precedence over `effect`
+    pub material_effects: Vec<(usize, String)>,
+    pub visible: bool,
+    pub shadow_map: bool,
+    pub ground_shadow: bool,
+    pub add_blend: bool,
+    pub edge_color: [f32; 4],
+    pub edge_size_scale_factor: f32,
+    /// Indices of materials excluded from ambient occlusion
+    pub ambient_occlusion_excluded: Vec<usize>,
+    /// Indices of materials excluded from the screen space outline
+    pub outline_excluded: Vec<usize>,
+}
+
+impl ModelEntry {
+    pub fn new(source: &str) -> Self {
+        Self {
+            source: source.to_owned(),
+            motion: None,
+            layers: vec![],
+            effect: None,
+            material_effects: vec![],
+            visible: true,
+            shadow_map: true,
+            ground_shadow: true,
+            add_blend: false,
+            edge_color: [0f32, 0f32, 0f32, 1f32],
+            edge_size_scale_factor: 1f32,
+            ambient_occlusion_excluded: vec![],
+            outline_excluded: vec![],
+        }
+    }
+}
+
+/// Point or spot light placed in the stage, cone angles are half angles in radians.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LightEntry {
+    pub typ: LocalLightType,
+    pub color: [f32; 3],
+    pub position: [f32; 3],
+    pub direction: [f32; 3],
+    pub range: f32,
+    pub inner_angle: f32,
+    pub outer_angle: f32,
+    pub shadow: bool,
+}
+
+impl Default for LightEntry {
+    fn default() -> Self {
+        Self {
+            typ: LocalLightType::Point,
+            color: [1f32, 1f32, 1f32],
+            position: [0f32, 0f32, 0f32],
+            direction: [0f32, -1f32, 0f32],
+            range: LocalLight::INITIAL_RANGE,
+            inner_angle: LocalLight::INITIAL_INNER_ANGLE.0,
+            outer_angle: LocalLight::INITIAL_OUTER_ANGLE.0,
+            shadow: false,
+        }
+    }
+}
+
+/// Camera other than the first one with the source of its motion.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CameraEntry {
+    pub name: String,
+    pub motion: Option<String>,
+}
+
+/// Persistent project description. Models are stored in draw order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProjectFile {
+    pub version: u32,
+    pub fps: u32,
+    pub physics_simulation_mode: u32,
+    pub grid_visible: bool,
+    pub grid_cell: [f32; 2],
+    pub grid_size: [f32; 2],
+    pub background_color: [f32; 4],
+    pub audio: Option<String>,
+    /// Name of the first camera, `camera_motion` is the motion of it
+    pub camera_name: String,
+    pub camera_motion: Option<String>,
+    pub cameras: Vec<CameraEntry>,
+    /// Name of the active camera, the first camera if `None`
+    pub active_camera: Option<String>,
+    pub light_motion: Option<String>,
+    pub post_process: PostProcessSettings,
+    pub color_grading_lut: Option<String>,
+    pub environment: EnvironmentSettings,
+    pub environment_image: Option<String>,
+    pub motion_blur: MotionBlurSettings,
+    /// MSAA sample count is `1 << sample_level`
+    pub sample_level: u32,
+    pub supersampling: SupersamplingSettings,
+    pub shadow_map: ShadowMapSettings,
+    pub flags: ProjectStates,
+    pub lights: Vec<LightEntry>,
+    pub effects: Vec<EffectEntry>,
+    pub models: Vec<ModelEntry>,
+}
+
+impl Default for ProjectFile {
+    fn default() -> Self {
+        Self {
+            version: Self::CURRENT_VERSION,
+            fps: 60,
+            physics_simulation_mode: 2,
+            grid_visible: true,
+            grid_cell: [5f32, 5f32],
+            grid_size: [10f32, 10f32],
+            background_color: [0f32, 0f32, 0f32, 1f32],
+            audio: None,
+            camera_name: Project::DEFAULT_CAMERA_NAME.to_owned(),
+            camera_motion: None,
+            cameras: vec![],
+            active_camera: None,
+            light_motion: None,
+            post_process: PostProcessSettings::default(),
+            color_grading_lut: None,
+            environment: EnvironmentSettings::default(),
+            environment_image: None,
+            motion_blur: MotionBlurSettings::default(),
+            sample_level: 0,
+            supersampling: SupersamplingSettings::default(),
+            shadow_map: ShadowMapSettings::default(),
+            flags: ProjectStates::default(),
+            lights: vec![],
+            effects: vec![],
+            models: vec![],
+        }
+    }
+}
+
+macro_rules! persistent_flags {
+    ($($flag:ident),* $(,)?) => {
+        fn write_flags(flags: &ProjectStates, out: &mut String) {
+            $(
+                let _ = writeln!(out, "flag.{} = {}", stringify!($flag), flags.$flag);
+            )*
+        }
+
+        fn read_flag(flags: &mut ProjectStates, key: &str, value: bool) -> bool {
+            match key {
+                $(stringify!($flag) => flags.$flag = value,)*
+                _ => return false,
+            }
+            true
+        }
+
+        /// Copies flags recorded in project files, leaving transient runtime states untouched.
+        pub fn copy_persistent_flags(source: &ProjectStates, dest: &mut ProjectStates) {
+            $(dest.$flag = source.$flag;)*
+        }
+    };
+}
+
+macro_rules! settings_keys {
+    ($settings:ident, $prefix:literal, $write:ident, $read:ident;
+     $($key:ident),* ; $($floats:ident),* ; $($index:ident: $ty:ident),* $(,)?) => {
+        fn $write(settings: &$settings, out: &mut String) {
+            $(
+                let _ = writeln!(out, "{}.{} = {}", $prefix, stringify!($key), settings.$key);
+            )*
+            $(
+                let _ = writeln!(out, "{}.{} = {}", $prefix, stringify!($floats), join_floats(&settings.$floats));
+            )*
+            $(
+                let _ = writeln!(out, "{}.{} = {}", $prefix, stringify!($index), settings.$index.index());
+            )*
+        }
+
+        /// Returns `None` if the value is malformed and `Some(false)` if the key is unknown.
+        fn $read(settings: &mut $settings, key: &str, value: &str) -> Option<bool> {
+            match key {
+                $(stringify!($key) => settings.$key = value.parse().ok()?,)*
+                $(stringify!($floats) => settings.$floats = parse_floats(value)?,)*
+                $(stringify!($index) => settings.$index = $ty::from_index(value.parse().ok()?)?,)*
+                _ => return Some(false),
+            }
+            Some(true)
+        }
+    };
+}
+
+settings_keys!(
+    PostProcessSettings, "post_process", write_post_process, read_post_process;
+    enabled,
+    bloom,
+    bloom_threshold,
+    bloom_intensity,
+    tone_mapping,
+    exposure,
+    hdr,
+    color_grading,
+    color_grading_intensity,
+    vignette,
+    vignette_intensity,
+    vignette_smoothness,
+    depth_of_field,
+    focus_distance,
+    focus_range,
+    max_blur_radius,
+    fxaa,
+    ambient_occlusion,
+    ambient_occlusion_radius,
+    ambient_occlusion_intensity,
+    ambient_occlusion_bias,
+    outline,
+    outline_width,
+    outline_depth_threshold,
+    outline_normal_threshold;
+    outline_color;
+    tone_mapping_operator: ToneMappingOperator,
+);
+
+settings_keys!(
+    EnvironmentSettings, "environment", write_environment, read_environment;
+    skybox,
+    skybox_intensity,
+    lighting,
+    diffuse_intensity,
+    specular_intensity,
+    rotation;
+    ;
+);
+
+settings_keys!(
+    MotionBlurSettings, "motion_blur", write_motion_blur, read_motion_blur;
+    enabled,
+    sample_count,
+    shutter;
+    ;
+);
+
+settings_keys!(
+    SupersamplingSettings, "supersampling", write_supersampling, read_supersampling;
+    factor;
+    ;
+    filter: DownsampleFilter,
+);
+
+settings_keys!(
+    ShadowMapSettings, "shadow_map", write_shadow_map, read_shadow_map;
+    resolution,
+    cascade_count,
+    split_lambda,
+    filter_radius,
+    depth_bias;
+    ;
+);
+
+persistent_flags!(
+    disable_hidden_bone_bounds_rigid_body,
+    display_user_interface,
+    display_transform_handle,
+    enable_loop,
+    enable_shared_camera,
+    enable_ground_shadow,
+    enable_multiple_bone_selection,
+    enable_bezier_curve_adjustment,
+    enable_motion_merge,
+    enable_effect_plugin,
+    enable_viewport_locked,
+    disable_display_sync,
+    primary_cursor_type_left,
+    enable_playing_audio_part,
+    enable_viewport_with_transparent,
+    enable_viewport_premultiplied_alpha,
+    enable_compiled_effect_cache,
+    enable_uniformed_viewport_image_size,
+    enable_fps_counter,
+    enable_performance_monitor,
+    enable_physics_simulation_for_bone_keyframe,
+    enable_image_anisotropy,
+    enable_image_mipmap,
+    enable_power_saving,
+);
+
+impl ProjectFile {
+    pub const CURRENT_VERSION: u32 = 5;
+    pub const SIGNATURE: &'static str = "mdanceio-project";
+    pub const FILE_EXTENSION: &'static str = "mdproj";
+
+    pub fn serialize(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "{} {}", Self::SIGNATURE, Self::CURRENT_VERSION);
+        let _ = writeln!(out, "[project]");
+        let _ = writeln!(out, "fps = {}", self.fps);
+        let _ = writeln!(
+            out,
+            "physics_simulation_mode = {}",
+            self.physics_simulation_mode
+        );
+        let _ = writeln!(out, "grid_visible = {}", self.grid_visible);
+        let _ = writeln!(out, "grid_cell = {}", join_floats(&self.grid_cell));
+        let _ = writeln!(out, "grid_size = {}", join_floats(&self.grid_size));
+        let _ = writeln!(
+            out,
+            "background_color = {}",
+            join_floats(&self.background_color)
+        );
+        if let Some(audio) = &self.audio {
+            let _ = writeln!(out, "audio = {}", audio);
+        }
+        let _ = writeln!(out, "camera_name = {}", self.camera_name);
+        if let Some(motion) = &self.camera_motion {
+            let _ = writeln!(out, "camera_motion = {}", motion);
+        }
+        if let Some(name) = &self.active_camera {
+            let _ = writeln!(out, "active_camera = {}", name);
+        }
+        if let Some(motion) = &self.light_motion {
+            let _ = writeln!(out, "light_motion = {}", motion);
+        }
+        if let Some(lut) = &self.color_grading_lut {
+            let _ = writeln!(out, "color_grading_lut = {}", lut);
+        }
+        write_post_process(&self.post_process, &mut out);
+        if let Some(image) = &self.environment_image {
+            let _ = writeln!(out, "environment_image = {}", image);
+        }
+        write_environment(&self.environment, &mut out);
+        write_motion_blur(&self.motion_blur, &mut out);
+        let _ = writeln!(out, "sample_level = {}", self.sample_level);
+        write_supersampling(&self.supersampling, &mut out);
+        write_shadow_map(&self.shadow_map, &mut out);
+        write_flags(&self.flags, &mut out);
+        for camera in &self.cameras {
+            let _ = writeln!(out, "[camera]");
+            let _ = writeln!(out, "name = {}", camera.name);
+            if let Some(motion) = &camera.motion {
+                let _ = writeln!(out, "motion = {}", motion);
+            }
+        }
+        for light in &self.lights {
+            let _ = writeln!(out, "[light]");
+            let _ = writeln!(out, "type = {}", u32::from(light.typ));
+            let _ = writeln!(out, "color = {}", join_floats(&light.color));
+            let _ = writeln!(out, "position = {}", join_floats(&light.position));
+            let _ = writeln!(out, "direction = {}", join_floats(&light.direction));
+            let _ = writeln!(out, "range = {}", light.range);
+            let _ = writeln!(out, "inner_angle = {}", light.inner_angle);
+            let _ = writeln!(out, "outer_angle = {}", light.outer_angle);
+            let _ = writeln!(out, "shadow = {}", light.shadow);
+        }
+        for effect in &self.effects {
+            let _ = writeln!(out, "[effect]");
+            let _ = writeln!(out, "source = {}", effect.source);
+            let _ = writeln!(out, "fx = {}", effect.fx);
+        }
+        for model in &self.models {
+            let _ = writeln!(out, "[model]");
+            let _ = writeln!(out, "source = {}", model.source);
+            if let Some(motion) = &model.motion {
+                let _ = writeln!(out, "motion = {}", motion);
+            }
+            let _ = writeln!(out, "visible = {}", model.visible);
+            let _ = writeln!(out, "shadow_map = {}", model.shadow_map);
+            let _ = writeln!(out, "ground_shadow = {}", model.ground_shadow);
+            let _ = writeln!(out, "add_blend = {}", model.add_blend);
+            let _ = writeln!(out, "edge_color = {}", join_floats(&model.edge_color));
+            let _ = writeln!(
+                out,
+                "edge_size_scale_factor = {}",
+                model.edge_size_scale_factor
+            );
+            if !model.ambient_occlusion_excluded.is_empty() {
+                let _ = writeln!(
+                    out,
+                    "ambient_occlusion_excluded = {}",
+                    join_indices(&model.ambient_occlusion_excluded)
+                );
+            }
+            if !model.outline_excluded.is_empty() {
+                let _ = writeln!(
+                    out,
+                    "outline_excluded = {}",
+                    join_indices(&model.outline_excluded)
+                );
+            }
+            if let Some(effect) = &model.effect {
+                let _ = writeln!(out, "effect = {}", effect);
+            }
+            for (idx, effect) in &model.material_effects {
+                let _ = writeln!(out, "material_effect = {} {}", idx, effect);
+            }
+            for layer in &model.layers {
+                let _ = writeln!(out, "[layer]");
+                let _ = writeln!(out, "name = {}", layer.name);
+                let _ = writeln!(out, "source = {}", layer.source);
+                let _ = writeln!(out, "weight = {}", layer.weight);
+                let _ = writeln!(out, "additive = {}", layer.additive);
+                let _ = writeln!(out, "frame_offset = {}", layer.frame_offset);
+                let _ = writeln!(out, "enabled = {}", layer.enabled);
+                write_mask("bone", &layer.bone_mask, &mut out);
+                write_mask("morph", &layer.morph_mask, &mut out);
+            }
+        }
+        out
+    }
+
+    pub fn parse(text: &str) -> Result<Self, MdanceioError> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(idx, line)| (idx + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+        let version = match lines.next().and_then(|(_, line)| line.split_once(' ')) {
+            Some((signature, version)) if signature == Self::SIGNATURE => version
+                .trim()
+                .parse::<u32>()
+                .map_err(|_| MdanceioError::invalid_project_file(1, "malformed version"))?,
+            _ => return Err(MdanceioError::invalid_project_file(1, "missing signature")),
+        };
+        if version > Self::CURRENT_VERSION {
+            return Err(MdanceioError::unsupported_project_version(version));
+        }
+        let mut file = ProjectFile {
+            version,
+            ..Default::default()
+        };
+        let mut section = Section::Project;
+        for (line_number, line) in lines {
+            match line {
+                "[project]" => {
+                    section = Section::Project;
+                    continue;
+                }
+                "[model]" => {
+                    section = Section::Model;
+                    file.models.push(ModelEntry::new(""));
+                    continue;
+                }
+                "[layer]" => {
+                    section = Section::Layer;
+                    let model = file.models.last_mut().ok_or_else(|| {
+                        MdanceioError::invalid_project_file(line_number, "layer without model")
+                    })?;
+                    model.layers.push(MotionLayerEntry::new("", ""));
+                    continue;
+                }
+                "[effect]" => {
+                    section = Section::Effect;
+                    file.effects.push(EffectEntry {
+                        source: String::default(),
+                        fx: false,
+                    });
+                    continue;
+                }
+                "[light]" => {
+                    section = Section::Light;
+                    file.lights.push(LightEntry::default());
+                    continue;
+                }
+                "[camera]" => {
+                    section = Section::Camera;
+                    file.cameras.push(CameraEntry {
+                        name: String::default(),
+                        motion: None,
+                    });
+                    continue;
+                }
+                _ => {}
+            }
+            let (key, value) = line
+                .split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| {
+                    MdanceioError::invalid_project_file(line_number, "expected key = value")
+                })?;
+            let invalid = || MdanceioError::invalid_project_file(line_number, key);
+            if let Some(camera) = file
+                .cameras
+                .last_mut()
+                .filter(|_| section == Section::Camera)
+            {
+                match key {
+                    "name" => camera.name = value.to_owned(),
+                    "motion" => camera.motion = Some(value.to_owned()),
+                    _ => log::warn!("Unknown camera key in project file: {}", key),
+                }
+                continue;
+            }
+            if let Some(effect) = file
+                .effects
+                .last_mut()
+                .filter(|_| section == Section::Effect)
+            {
+                match key {
+                    "source" => effect.source = value.to_owned(),
+                    "fx" => effect.fx = value.parse().map_err(|_| invalid())?,
+                    _ => log::warn!("Unknown effect key in project file: {}", key),
+                }
+                continue;
+            }
+            if let Some(light) = file.lights.last_mut().filter(|_| section == Section::Light) {
+                match key {
+                    "type" => {
+                        light.typ = value.parse::<u32>().map_err(|_| invalid())?.into();
+                    }
+                    "color" => light.color = parse_floats(value).ok_or_else(invalid)?,
+                    "position" => light.position = parse_floats(value).ok_or_else(invalid)?,
+                    "direction" => light.direction = parse_floats(value).ok_or_else(invalid)?,
+                    "range" => light.range = value.parse().map_err(|_| invalid())?,
+                    "inner_angle" => light.inner_angle = value.parse().map_err(|_| invalid())?,
+                    "outer_angle" => light.outer_angle = value.parse().map_err(|_| invalid())?,
+                    "shadow" => light.shadow = value.parse().map_err(|_| invalid())?,
+                    _ => log::warn!("Unknown light key in project file: {}", key),
+                }
+                continue;
+            }
+            if let Some(layer) = file
+                .models
+                .last_mut()
+                .and_then(|model| model.layers.last_mut())
+                .filter(|_| section == Section::Layer)
+            {
+                match key {
+                    "name" => layer.name = value.to_owned(),
+                    "source" => layer.source = value.to_owned(),
+                    "weight" => layer.weight = value.parse().map_err(|_| invalid())?,
+                    "additive" => layer.additive = value.parse().map_err(|_| invalid())?,
+                    "frame_offset" => layer.frame_offset = value.parse().map_err(|_| invalid())?,
+                    "enabled" => layer.enabled = value.parse().map_err(|_| invalid())?,
+                    "bone_mask" => layer.bone_mask = Some(vec![]),
+                    "bone" => layer
+                        .bone_mask
+                        .get_or_insert_with(Vec::new)
+                        .push(value.to_owned()),
+                    "morph_mask" => layer.morph_mask = Some(vec![]),
+                    "morph" => layer
+                        .morph_mask
+                        .get_or_insert_with(Vec::new)
+                        .push(value.to_owned()),
+                    _ => log::warn!("Unknown layer key in project file: {}", key),
+                }
+                continue;
+            }
+            if let Some(model) = file.models.last_mut().filter(|_| section == Section::Model) {
+                match key {
+                    "source" => model.source = value.to_owned(),
+                    "motion" => model.motion = Some(value.to_owned()),
+                    "visible" => model.visible = value.parse().map_err(|_| invalid())?,
+                    "shadow_map" => model.shadow_map = value.parse().map_err(|_| invalid())?,
+                    "ground_shadow" => {
+                        model.ground_shadow = value.parse().map_err(|_| invalid())?
+                    }
+                    "add_blend" => model.add_blend = value.parse().map_err(|_| invalid())?,
+                    "edge_color" => model.edge_color = parse_floats(value).ok_or_else(invalid)?,
+                    "edge_size_scale_factor" => {
+                        model.edge_size_scale_factor = value.parse().map_err(|_| invalid())?
+                    }
+                    "ambient_occlusion_excluded" => {
+                        model.ambient_occlusion_excluded =
+                            parse_indices(value).ok_or_else(invalid)?
+                    }
+                    "outline_excluded" => {
+                        model.outline_excluded = parse_indices(value).ok_or_else(invalid)?
+                    }
+                    "effect" => model.effect = Some(value.to_owned()),
+                    "material_effect" => {
+                        let (idx, effect) = value
+                            .split_once(' ')
+                            .and_then(|(idx, effect)| Some((idx.parse().ok()?, effect.trim())))
+                            .ok_or_else(invalid)?;
+                        model.material_effects.push((idx, effect.to_owned()));
+                    }
+                    _ => log::warn!("Unknown model key in project file: {}", key),
+                }
+                continue;
+            }
+            match key {
+                "fps" => file.fps = value.parse().map_err(|_| invalid())?,
+                "physics_simulation_mode" => {
+                    file.physics_simulation_mode = value.parse().map_err(|_| invalid())?
+                }
+                "grid_visible" => file.grid_visible = value.parse().map_err(|_| invalid())?,
+                "grid_cell" => file.grid_cell = parse_floats(value).ok_or_else(invalid)?,
+                "grid_size" => file.grid_size = parse_floats(value).ok_or_else(invalid)?,
+                "background_color" => {
+                    file.background_color = parse_floats(value).ok_or_else(invalid)?
+                }
+                "audio" => file.audio = Some(value.to_owned()),
+                "camera_name" => file.camera_name = value.to_owned(),
+                "camera_motion" => file.camera_motion = Some(value.to_owned()),
+                "active_camera" => file.active_camera = Some(value.to_owned()),
+                "light_motion" => file.light_motion = Some(value.to_owned()),
+                "color_grading_lut" => file.color_grading_lut = Some(value.to_owned()),
+                "environment_image" => file.environment_image = Some(value.to_owned()),
+                "sample_level" => file.sample_level = value.parse().map_err(|_| invalid())?,
+                _ => {
+                    if let Some(flag) = key.strip_prefix("flag.") {
+                        if !read_flag(&mut file.flags, flag, value.parse().map_err(|_| invalid())?)
+                        {
+                            log::warn!("Unknown flag in project file: {}", flag);
+                        }
+                    } else if let Some(name) = key.strip_prefix("post_process.") {
+                        if !read_post_process(&mut file.post_process, name, value)
+                            .ok_or_else(invalid)?
+                        {
+                            log::warn!("Unknown post process key in project file: {}", name);
+                        }
+                    } else if let Some(name) = key.strip_prefix("environment.") {
+                        if !read_environment(&mut file.environment, name, value)
+                            .ok_or_else(invalid)?
+                        {
+                            log::warn!("Unknown environment key in project file: {}", name);
+                        }
+                    } else if let Some(name) = key.strip_prefix("motion_blur.") {
+                        if !read_motion_blur(&mut file.motion_blur, name, value)
+                            .ok_or_else(invalid)?
+                        {
+                            log::warn!("Unknown motion blur key in project file: {}", name);
+                        }
+                    } else if let Some(name) = key.strip_prefix("shadow_map.") {
+                        if !read_shadow_map(&mut file.shadow_map, name, value)
+                            .ok_or_else(invalid)?
+                        {
+                            log::warn!("Unknown shadow map key in project file: {}", name);
+                        }
+                    } else if let Some(name) = key.strip_prefix("supersampling.") {
+                        if !read_supersampling(&mut file.supersampling, name, value)
+                            .ok_or_else(invalid)?
+                        {
+                            log::warn!("Unknown supersampling key in project file: {}", name);
+                        }
+                    } else {
+                        log::warn!("Unknown key in project file: {}", key);
+                    }
+                }
+            }
+        }
+        if let Some(idx) = file.models.iter().position(|model| model.source.is_empty()) {
+            return Err(MdanceioError::invalid_project_file(
+                0,
+                &format!("model {} has no source", idx),
+            ));
+        }
+        if file.effects.iter().any(|effect| effect.source.is_empty()) {
+            return Err(MdanceioError::invalid_project_file(
+                0,
+                "effect has no source",
+            ));
+        }
+        if let Some(idx) = file
+            .models
+            .iter()
+            .position(|model| model.layers.iter().any(|layer| layer.source.is_empty()))
+        {
+            return Err(MdanceioError::invalid_project_file(
+                0,
+                &format!("layer of model {} has no source", idx),
+            ));
+        }
+        Ok(file)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Section {
+    Project,
+    Model,
+    Layer,
+    Camera,
+    Light,
+    Effect,
+}
+
+fn join_floats(values: &[f32]) -> String {
+    values
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn parse_floats<const N: usize>(value: &str) -> Option<[f32; N]> {
+    let values = value
+        .split_whitespace()
+        .map(|v| v.parse::<f32>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    values.try_into().ok()
+}
+
+/// Writes `<prefix>_mask` followed by a `<prefix>` line per masked name, nothing if unmasked.
+fn write_mask(prefix: &str, mask: &Option<Vec<String>>, out: &mut String) {
+    if let Some(names) = mask {
+        let _ = writeln!(out, "{}_mask = true", prefix);
+        for name in names {
+            let _ = writeln!(out, "{} = {}", prefix, name);
+        }
+    }
+}
+
+fn join_indices(values: &[usize]) -> String {
+    values
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn parse_indices(value: &str) -> Option<Vec<usize>> {
+    value
+        .split_whitespace()
+        .map(|v| v.parse::<usize>().ok())
+        .collect()
+}
+
+#[test]
+fn test_project_file_round_trip() {
+    let mut file = ProjectFile {
+        fps: 30,
+        audio: Some("audio/song.wav".to_owned()),
+        camera_motion: Some("camera.vmd".to_owned()),
+        background_color: [0.5f32, 0.25f32, 1f32, 1f32],
+        ..Default::default()
+    };
+    file.flags.enable_loop = true;
+    file.flags.enable_motion_merge = true;
+    file.flags.enable_viewport_with_transparent = true;
+    file.flags.enable_viewport_premultiplied_alpha = true;
+    file.post_process.enabled = true;
+    file.post_process.bloom = true;
+    file.post_process.exposure = 1.25f32;
+    file.post_process.outline = true;
+    file.post_process.outline_color = [0.25f32, 0f32, 0.5f32, 1f32];
+    file.post_process.hdr = true;
+    file.post_process.tone_mapping_operator = ToneMappingOperator::Filmic;
+    file.color_grading_lut = Some("luts/warm.png".to_owned());
+    file.environment.rotation = 90f32;
+    file.environment.skybox = false;
+    file.environment_image = Some("sky/stage.hdr".to_owned());
+    file.motion_blur.enabled = true;
+    file.motion_blur.sample_count = 16;
+    file.motion_blur.shutter = 0.75f32;
+    file.sample_level = 2;
+    file.supersampling.factor = 2;
+    file.supersampling.filter = DownsampleFilter::Gaussian;
+    file.shadow_map = ShadowMapSettings {
+        resolution: 4096,
+        cascade_count: 3,
+        split_lambda: 0.5f32,
+        filter_radius: 2,
+        depth_bias: 0.001f32,
+    };
+    let mut model = ModelEntry::new("models/a b/model.pmx");
+    model.motion = Some("dance = 1.vmd".to_owned());
+    model.add_blend = true;
+    model.edge_color = [1f32, 0f32, 0f32, 0.5f32];
+    model.ambient_occlusion_excluded = vec![0, 3];
+    model.outline_excluded = vec![2];
+    model.effect = Some("rim".to_owned());
+    model.material_effects = vec![(1, "toon shade".to_owned()), (4, "rim".to_owned())];
+    let mut layer = MotionLayerEntry::new("upper body", "layers/wave.vmd");
+    layer.weight = 0.5f32;
+    layer.additive = true;
+    layer.frame_offset = -10;
+    layer.bone_mask = Some(vec!["右腕".to_owned(), "左腕".to_owned()]);
+    model.layers.push(layer);
+    let mut layer = MotionLayerEntry::new("face", "layers/smile.vmd");
+    layer.enabled = false;
+    layer.morph_mask = Some(vec![]);
+    model.layers.push(layer);
+    file.models.push(model);
+    file.models.push(ModelEntry::new("stage.pmx"));
+    file.effects.push(EffectEntry {
+        source: "effects/rim.mdfx".to_owned(),
+        fx: false,
+    });
+    file.effects.push(EffectEntry {
+        source: "effects/toon shade.fx".to_owned(),
+        fx: true,
+    });
+    let parsed = ProjectFile::parse(&file.serialize()).unwrap();
+    assert_eq!(file, parsed);
+    file.cameras.push(CameraEntry {
+        name: "close up".to_owned(),
+        motion: Some("close up.vmd".to_owned()),
+    });
+    file.cameras.push(CameraEntry {
+        name: "wide".to_owned(),
+        motion: None,
+    });
+    file.active_camera = Some("wide".to_owned());
+    let parsed = ProjectFile::parse(&file.serialize()).unwrap();
+    assert_eq!(file, parsed);
+    file.lights.push(LightEntry {
+        color: [1f32, 0.5f32, 0.25f32],
+        position: [0f32, 10f32, -5f32],
+        range: 20f32,
+        ..Default::default()
+    });
+    file.lights.push(LightEntry {
+        typ: LocalLightType::Spot,
+        direction: [0f32, -0.6f32, 0.8f32],
+        inner_angle: 0.2f32,
+        outer_angle: 0.4f32,
+        shadow: true,
+        ..Default::default()
+    });
+    let parsed = ProjectFile::parse(&file.serialize()).unwrap();
+    assert_eq!(file, parsed);
+}
+
+#[test]
+fn test_project_file_rejects_newer_version() {
+    let text = format!(
+        "{} {}\n",
+        ProjectFile::SIGNATURE,
+        ProjectFile::CURRENT_VERSION + 1
+    );
+    assert!(ProjectFile::parse(&text).is_err());
+    assert!(ProjectFile::parse("not a project\n").is_err());
+}
+
+#[test]
+fn test_resolve_relative_path() {
+    assert_eq!(
+        "models/tex/a.png",
+        resolve_relative_path("models/m.pmx", "tex\\a.png")
+    );
+    assert_eq!("a.png", resolve_relative_path("m.pmx", "a.png"));
+}
@@ -1,10 +1,10 @@
 use std::sync::Arc;
 
-use cgmath::{InnerSpace, Matrix, Matrix3, Matrix4, Vector2, Vector3};
+use cgmath::{InnerSpace, Matrix, Matrix3, Matrix4, SquareMatrix, Vector2, Vector3};
 
 use crate::{
     camera::{Camera, PerspectiveCamera},
-    light::{DirectionalLight, Light},
+    light::{DirectionalLight, Light, MAX_LOCAL_LIGHTS},
     utils::lerp_f32, graphics::ClearPass,
 };
 
@@ -35,16 +35,54 @@ impl From<CoverageMode> for u32 {
     }
 }
 
+/// One split of the view frustum rendered into its own shadow map layer.
+#[derive(Debug, Clone, Copy)]
+pub struct ShadowCascade {
+    pub view_projection: Matrix4<f32>,
+    /// Distance from the camera where the next cascade takes over.
+    pub split: f32,
+}
+
+/// Shadow map settings kept with the project, each cascade is `resolution` texels square.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShadowMapSettings {
+    pub resolution: u32,
+    pub cascade_count: usize,
+    pub split_lambda: f32,
+    pub filter_radius: u32,
+    pub depth_bias: f32,
+}
+
+impl Default for ShadowMapSettings {
+    fn default() -> Self {
+        Self {
+            resolution: ShadowCamera::INITIAL_TEXTURE_SIZE,
+            cascade_count: 1,
+            split_lambda: ShadowCamera::CASCADE_SPLIT_LAMBDA,
+            filter_radius: ShadowCamera::INITIAL_FILTER_RADIUS,
+            depth_bias: 0f32,
+        }
+    }
+}
+
 pub struct ShadowCamera {
-    shadow_color_texture: wgpu::TextureView,
+    shadow_color_textures: Vec<wgpu::TextureView>,
+    shadow_color_array: wgpu::TextureView,
     bind_group: Arc<wgpu::BindGroup>,
     // fallback_color_texture: wgpu::Texture,
     shadow_depth_texture: wgpu::TextureView,
     // fallback_depth_texture: wgpu::Texture,
+    local_color_textures: Vec<wgpu::TextureView>,
+    local_color_array: wgpu::TextureView,
+    local_depth_texture: wgpu::TextureView,
     clear_pass: ClearPass,
     texture_size: Vector2<u32>,
     coverage_mode: CoverageMode,
     distance: f32,
+    cascade_count: usize,
+    split_lambda: f32,
+    filter_radius: u32,
+    depth_bias: f32,
     enabled: bool,
     dirty: bool,
 }
@@ -57,18 +95,83 @@ impl ShadowCamera {
     pub const MINIMUM_DISTANCE: f32 = 0f32;
     pub const INITIAL_DISTANCE: f32 = 8875f32;
     pub const INITIAL_TEXTURE_SIZE: u32 = 2048;
+    pub const MINIMUM_TEXTURE_SIZE: u32 = 256;
+    pub const MAXIMUM_TEXTURE_SIZE: u32 = 4096;
+    pub const LOCAL_TEXTURE_SIZE: u32 = 1024;
+    pub const MAXIMUM_CASCADE_COUNT: usize = 4;
+    pub const INITIAL_FILTER_RADIUS: u32 = 1;
+    pub const MAXIMUM_FILTER_RADIUS: u32 = 3;
+    pub const CASCADE_NEAR: f32 = 1f32;
+    /// Blend between uniform (0) and logarithmic (1) cascade splits.
+    pub const CASCADE_SPLIT_LAMBDA: f32 = 0.75f32;
+    /// Extends cascades towards the light so casters outside the view still cast shadows.
+    pub const CASCADE_CASTER_MARGIN: f32 = 500f32;
 
     pub fn new(
         bind_group_layout: &wgpu::BindGroupLayout,
         shadow_sampler: &wgpu::Sampler,
         device: &wgpu::Device,
     ) -> Self {
-        let color_texture = device.create_texture(&wgpu::TextureDescriptor {
-            label: Some("ShadowCamera/Color"),
+        let (shadow_color_textures, shadow_color_array) =
+            Self::create_color_layers("ShadowCamera/Color", Self::INITIAL_TEXTURE_SIZE, 1, device);
+        let shadow_depth_texture =
+            Self::create_depth("ShadowCamera/Depth", Self::INITIAL_TEXTURE_SIZE, device);
+        let (local_color_textures, local_color_array) = Self::create_color_layers(
+            "ShadowCamera/LocalColor",
+            Self::LOCAL_TEXTURE_SIZE,
+            MAX_LOCAL_LIGHTS,
+            device,
+        );
+        let local_depth_texture =
+            Self::create_depth("ShadowCamera/LocalDepth", Self::LOCAL_TEXTURE_SIZE, device);
+        let bind_group = Self::create_bind_group(
+            &shadow_color_array,
+            &local_color_array,
+            bind_group_layout,
+            shadow_sampler,
+            device,
+        );
+        let clear_pass = ClearPass::new(
+            &[Some(wgpu::TextureFormat::R32Float)],
+            Some(wgpu::TextureFormat::Depth16Unorm),
+            1,
+            device,
+        );
+        Self {
+            shadow_color_textures,
+            shadow_color_array,
+            bind_group: Arc::new(bind_group),
+            // fallback_color_texture,
+            shadow_depth_texture,
+            // fallback_depth_texture,
+            local_color_textures,
+            local_color_array,
+            local_depth_texture,
+            clear_pass,
+            texture_size: Vector2::new(Self::INITIAL_TEXTURE_SIZE, Self::INITIAL_TEXTURE_SIZE),
+            coverage_mode: CoverageMode::Type1,
+            distance: Self::INITIAL_DISTANCE,
+            cascade_count: 1,
+            split_lambda: Self::CASCADE_SPLIT_LAMBDA,
+            filter_radius: Self::INITIAL_FILTER_RADIUS,
+            depth_bias: 0f32,
+            enabled: true,
+            dirty: false,
+        }
+    }
+
+    fn create_color_layers(
+        label: &str,
+        texture_size: u32,
+        layers: usize,
+        device: &wgpu::Device,
+    ) -> (Vec<wgpu::TextureView>, wgpu::TextureView) {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
             size: wgpu::Extent3d {
-                width: Self::INITIAL_TEXTURE_SIZE,
-                height: Self::INITIAL_TEXTURE_SIZE,
-                depth_or_array_layers: 1,
+                width: texture_size,
+                height: texture_size,
+                depth_or_array_layers: layers as u32,
             },
             mip_level_count: 1,
             sample_count: 1,
@@ -77,64 +180,123 @@ impl ShadowCamera {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
             view_formats: &[],
         });
-        let color_view = color_texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let depth_texture = device.create_texture(&wgpu::TextureDescriptor {
-            label: Some("ShadowCamera/Depth"),
-            size: wgpu::Extent3d {
-                width: Self::INITIAL_TEXTURE_SIZE,
-                height: Self::INITIAL_TEXTURE_SIZE,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Depth16Unorm,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
-            view_formats: &[],
+        let array_view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
         });
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        let layer_views = (0..layers as u32)
+            .map(|layer| {
+                texture.create_view(&wgpu::TextureViewDescriptor {
+                    dimension: Some(wgpu::TextureViewDimension::D2),
+                    base_array_layer: layer,
+                    array_layer_count: Some(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        (layer_views, array_view)
+    }
+
+    fn create_depth(label: &str, texture_size: u32, device: &wgpu::Device) -> wgpu::TextureView {
+        device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some(label),
+                size: wgpu::Extent3d {
+                    width: texture_size,
+                    height: texture_size,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Depth16Unorm,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default())
+    }
+
+    fn create_bind_group(
+        color_array: &wgpu::TextureView,
+        local_color_array: &wgpu::TextureView,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        shadow_sampler: &wgpu::Sampler,
+        device: &wgpu::Device,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("ShadowCamera/BindGroup/Texture"),
             layout: bind_group_layout,
             entries: &[
                 wgpu::BindGroupEntry {
                     binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&color_view),
+                    resource: wgpu::BindingResource::TextureView(color_array),
                 },
                 wgpu::BindGroupEntry {
                     binding: 1,
                     resource: wgpu::BindingResource::Sampler(shadow_sampler),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(local_color_array),
+                },
             ],
-        });
-        let clear_pass = ClearPass::new(
-            &[Some(wgpu::TextureFormat::R32Float)],
-            Some(wgpu::TextureFormat::Depth16Unorm),
+        })
+    }
+
+    /// Recreates the shadow maps, renderers must rebind the new `bind_group` afterwards.
+    pub fn resize(
+        &mut self,
+        texture_size: u32,
+        cascade_count: usize,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        shadow_sampler: &wgpu::Sampler,
+        device: &wgpu::Device,
+    ) {
+        let texture_size = texture_size
+            .clamp(Self::MINIMUM_TEXTURE_SIZE, Self::MAXIMUM_TEXTURE_SIZE)
+            .next_power_of_two();
+        let cascade_count = cascade_count.clamp(1, Self::MAXIMUM_CASCADE_COUNT);
+        let (shadow_color_textures, shadow_color_array) =
+            Self::create_color_layers("ShadowCamera/Color", texture_size, cascade_count, device);
+        self.shadow_color_textures = shadow_color_textures;
+        self.shadow_color_array = shadow_color_array;
+        self.shadow_depth_texture = Self::create_depth("ShadowCamera/Depth", texture_size, device);
+        self.bind_group = Arc::new(Self::create_bind_group(
+            &self.shadow_color_array,
+            &self.local_color_array,
+            bind_group_layout,
+            shadow_sampler,
             device,
-        );
-        Self {
-            shadow_color_texture: color_texture
-                .create_view(&wgpu::TextureViewDescriptor::default()),
-            bind_group: Arc::new(bind_group),
-            // fallback_color_texture,
-            shadow_depth_texture: depth_texture
-                .create_view(&wgpu::TextureViewDescriptor::default()),
-            // fallback_depth_texture,
-            clear_pass,
-            texture_size: Vector2::new(Self::INITIAL_TEXTURE_SIZE, Self::INITIAL_TEXTURE_SIZE),
-            coverage_mode: CoverageMode::Type1,
-            distance: Self::INITIAL_DISTANCE,
-            enabled: true,
-            dirty: false,
+        ));
+        self.texture_size = Vector2::new(texture_size, texture_size);
+        self.cascade_count = cascade_count;
+        self.dirty = true;
+    }
+
+    /// Clears the shadow map layer of the cascade at `layer`.
+    pub fn clear(&mut self, layer: usize, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if let Some(color_view) = self.shadow_color_textures.get(layer) {
+            self.clear_pass.draw(
+                &[Some(color_view)],
+                Some(&self.shadow_depth_texture),
+                wgpu::Color::WHITE,
+                device,
+                queue,
+            );
         }
     }
 
-    pub fn clear(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
-        self.clear_pass.draw(
-            &[Some(&self.shadow_color_texture)],
-            Some(&self.shadow_depth_texture),
-            device,
-            queue,
-        );
+    /// Clears the shadow map layer of the spot light at `layer`.
+    pub fn clear_local(&mut self, layer: usize, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if let Some(color_view) = self.local_color_textures.get(layer) {
+            self.clear_pass.draw(
+                &[Some(color_view)],
+                Some(&self.local_depth_texture),
+                wgpu::Color::WHITE,
+                device,
+                queue,
+            );
+        }
     }
 
     fn get_view_matrix(
@@ -158,7 +320,7 @@ impl ShadowCamera {
         };
         let light_view_matrix3_t = light_view_matrix3.transpose();
         let light_view_matrix4: Matrix4<f32> = light_view_matrix3_t.into();
-        let light_view_origin = camera.position() - light.direction() * 50f32;
+        let light_view_origin = camera.focus_point() - light.direction() * 50f32;
         light_view_matrix4 * Matrix4::from_translation(-light_view_origin)
     }
 
@@ -173,6 +335,28 @@ impl ShadowCamera {
         let angle = camera_direction.dot(light_direction).abs();
         let half_distance = distance * 0.5f32;
         let distance_x0_15 = distance * 0.15f32;
+        if !camera.is_perspective() {
+            // no perspective warp, fits the whole orthographic view volume around the focus point
+            let scale = (distance * 2f32).min(1f32 / camera.orthographic_extent());
+            return Matrix4::new(
+                scale,
+                0f32,
+                0f32,
+                0f32,
+                0f32,
+                scale,
+                0f32,
+                0f32,
+                0f32,
+                0f32,
+                distance_x0_15,
+                0f32,
+                0f32,
+                0f32,
+                0f32,
+                1f32,
+            );
+        }
         let (c0, c1, c2, mut projection) = match self.coverage_mode() {
             CoverageMode::Type2 => {
                 let distance_x3 = distance * 3f32;
@@ -269,6 +453,54 @@ impl ShadowCamera {
         )
     }
 
+    /// Farthest distance from the camera covered by cascades, follows the self shadow distance.
+    pub fn cascade_far(&self, camera: &PerspectiveCamera) -> f32 {
+        let distance = (10000f32 - self.distance) / 100000f32;
+        (2f32 / distance.max(1e-4f32)).min(camera.zfar())
+    }
+
+    /// Falls back to the single `CoverageMode` fit with one cascade or an orthographic camera.
+    pub fn get_cascades(
+        &self,
+        camera: &PerspectiveCamera,
+        light: &DirectionalLight,
+    ) -> Vec<ShadowCascade> {
+        if self.cascade_count <= 1 || !camera.is_perspective() {
+            let (view, projection) = self.get_view_projection(camera, light);
+            return vec![ShadowCascade {
+                view_projection: projection * view,
+                split: f32::MAX,
+            }];
+        }
+        let (view, projection) = camera.get_view_transform();
+        let inverse_view = view.invert().unwrap_or_else(Matrix4::identity);
+        let tan_half_fov = Vector2::new(1f32 / projection[0][0], 1f32 / projection[1][1]);
+        let splits = cascade_splits(
+            Self::CASCADE_NEAR,
+            self.cascade_far(camera).max(Self::CASCADE_NEAR * 2f32),
+            self.cascade_count,
+            self.split_lambda,
+        );
+        let mut near = Self::CASCADE_NEAR;
+        splits
+            .into_iter()
+            .map(|far| {
+                let (center, radius) = frustum_slice_bounds(near, far, tan_half_fov);
+                let center = (inverse_view * center.extend(1f32)).truncate();
+                near = far;
+                ShadowCascade {
+                    view_projection: fit_cascade(
+                        center,
+                        radius,
+                        light.direction(),
+                        self.texture_size.x,
+                    ),
+                    split: far,
+                }
+            })
+            .collect()
+    }
+
     pub fn get_crop_matrix(&self) -> Matrix4<f32> {
         Matrix4::from_translation(Vector3::new(0.5f32, 0.5f32, 0.5f32))
             * Matrix4::from_nonuniform_scale(0.5f32, -0.5f32, 0.5f32)
@@ -294,17 +526,53 @@ impl ShadowCamera {
         &self.bind_group
     }
 
-    pub fn color_image(&self) -> &wgpu::TextureView {
-        &self.shadow_color_texture
+    pub fn cascade_count(&self) -> usize {
+        self.cascade_count
+    }
+
+    /// Blend of logarithmic over uniform cascade splits, 1 is fully logarithmic.
+    pub fn split_lambda(&self) -> f32 {
+        self.split_lambda
+    }
+
+    pub fn settings(&self) -> ShadowMapSettings {
+        ShadowMapSettings {
+            resolution: self.texture_size.x,
+            cascade_count: self.cascade_count,
+            split_lambda: self.split_lambda,
+            filter_radius: self.filter_radius,
+            depth_bias: self.depth_bias,
+        }
+    }
+
+    /// PCF kernel spans `radius * 2 + 1` texels on each axis, 0 disables filtering.
+    pub fn filter_radius(&self) -> u32 {
+        self.filter_radius
+    }
+
+    pub fn depth_bias(&self) -> f32 {
+        self.depth_bias
+    }
+
+    pub fn color_image(&self, layer: usize) -> Option<&wgpu::TextureView> {
+        self.shadow_color_textures.get(layer)
     }
 
     pub fn depth_image(&self) -> &wgpu::TextureView {
         &self.shadow_depth_texture
     }
 
+    pub fn local_color_image(&self, layer: usize) -> Option<&wgpu::TextureView> {
+        self.local_color_textures.get(layer)
+    }
+
+    pub fn local_depth_image(&self) -> &wgpu::TextureView {
+        &self.local_depth_texture
+    }
+
     pub fn set_distance(&mut self, value: f32) {
         if value != self.distance {
-            self.distance = value.clamp(Self::MAXIMUM_DISTANCE, Self::MAXIMUM_DISTANCE);
+            self.distance = value.clamp(Self::MINIMUM_DISTANCE, Self::MAXIMUM_DISTANCE);
             self.dirty = true;
             // TODO: publish event
         }
@@ -327,6 +595,21 @@ impl ShadowCamera {
         }
     }
 
+    pub fn set_filter_radius(&mut self, value: u32) {
+        self.filter_radius = value.min(Self::MAXIMUM_FILTER_RADIUS);
+        self.dirty = true;
+    }
+
+    pub fn set_split_lambda(&mut self, value: f32) {
+        self.split_lambda = value.clamp(0f32, 1f32);
+        self.dirty = true;
+    }
+
+    pub fn set_depth_bias(&mut self, value: f32) {
+        self.depth_bias = value.max(0f32);
+        self.dirty = true;
+    }
+
     pub fn set_dirty(&mut self, value: bool) {
         self.dirty = value;
     }
@@ -338,3 +621,74 @@ impl ShadowCamera {
         lerp_f32(start, end, t)
     }
 }
+
+/// Practical split scheme, far ends of each cascade between `near` and `far`.
+fn cascade_splits(near: f32, far: f32, count: usize, lambda: f32) -> Vec<f32> {
+    (1..=count)
+        .map(|idx| {
+            let t = idx as f32 / count as f32;
+            let uniform = near + (far - near) * t;
+            let logarithmic = near * (far / near).powf(t);
+            lerp_f32(uniform, logarithmic, lambda)
+        })
+        .collect()
+}
+
+/// Bounding sphere of the view frustum between `near` and `far` in view space.
+fn frustum_slice_bounds(near: f32, far: f32, tan_half_fov: Vector2<f32>) -> (Vector3<f32>, f32) {
+    let center = (near + far) * 0.5f32;
+    let spread = tan_half_fov.magnitude2();
+    let radius_at = |z: f32| ((z - center).powi(2) + z * z * spread).sqrt();
+    (
+        Vector3::new(0f32, 0f32, center),
+        radius_at(near).max(radius_at(far)),
+    )
+}
+
+/// Orthographic light view projection around the sphere, snapped to texels to avoid shimmering.
+fn fit_cascade(
+    center: Vector3<f32>,
+    radius: f32,
+    light_direction: Vector3<f32>,
+    texture_size: u32,
+) -> Matrix4<f32> {
+    let z = light_direction.normalize();
+    let up = if z.y.abs() > 0.99f32 {
+        Vector3::unit_z()
+    } else {
+        Vector3::unit_y()
+    };
+    let x = up.cross(z).normalize();
+    let y = z.cross(x);
+    let rotation: Matrix4<f32> = Matrix3 { x, y, z }.transpose().into();
+    let mut light_center = (rotation * center.extend(1f32)).truncate();
+    let texel = radius * 2f32 / texture_size.max(1) as f32;
+    light_center.x = (light_center.x / texel).floor() * texel;
+    light_center.y = (light_center.y / texel).floor() * texel;
+    let depth_range = radius + ShadowCamera::CASCADE_CASTER_MARGIN;
+    let origin = Vector3::new(light_center.x, light_center.y, light_center.z - depth_range);
+    Matrix4::from_nonuniform_scale(1f32 / radius, 1f32 / radius, 0.5f32 / depth_range)
+        * Matrix4::from_translation(-origin)
+        * rotation
+}
+
+#[test]
+fn test_cascade_splits() {
+    let splits = cascade_splits(1f32, 100f32, 4, ShadowCamera::CASCADE_SPLIT_LAMBDA);
+    assert_eq!(splits.len(), 4);
+    assert!(splits.windows(2).all(|pair| pair[0] < pair[1]));
+    assert!((splits[3] - 100f32).abs() < 1e-3);
+}
+
+#[test]
+fn test_fit_cascade() {
+    let (center, radius) = frustum_slice_bounds(10f32, 30f32, Vector2::new(0.5f32, 0.5f32));
+    assert!(radius >= 10f32);
+    let view_projection = fit_cascade(center, radius, Vector3::new(-0.5, -1.0, 0.5), 2048);
+    let clip = view_projection * center.extend(1f32);
+    assert!(clip.x.abs() < 0.01 && clip.y.abs() < 0.01);
+    assert!(clip.z > 0f32 && clip.z < 1f32);
+    let far_corner = Vector3::new(15f32, 15f32, 30f32);
+    let clip = view_projection * far_corner.extend(1f32);
+    assert!(clip.x.abs() <= 1.01 && clip.y.abs() <= 1.01);
+}
@@ -1,4 +1,4 @@
-use std::{cmp::Ordering, collections::HashMap};
+use std::{cmp::Ordering, collections::HashMap, ops::Range};
 
 use crate::{
     common::{Buffer, MutableBuffer, NanoemError},
@@ -7,6 +7,7 @@ use crate::{
 
 pub trait Keyframe {
     fn frame_index(&self) -> u32;
+    fn set_frame_index(&mut self, value: u32);
 }
 
 #[derive(Debug, Clone)]
@@ -91,6 +92,24 @@ where
         }
         self.keyframes.remove(&frame_index)
     }
+
+    /// Move every keyframe to the frame index returned by `f`, removing it if `None` is returned.
+    ///
+    /// When several keyframes are moved to the same frame index, the latest one in the original order is kept.
+    pub fn retime(&mut self, f: impl Fn(u32) -> Option<u32>) {
+        let mut keyframes = std::mem::take(&mut self.keyframes);
+        for frame_index in std::mem::take(&mut self.ordered_frame_index) {
+            if let (Some(mut keyframe), Some(new_frame_index)) =
+                (keyframes.remove(&frame_index), f(frame_index))
+            {
+                keyframe.set_frame_index(new_frame_index);
+                if self.keyframes.insert(new_frame_index, keyframe).is_none() {
+                    self.ordered_frame_index.push(new_frame_index);
+                }
+            }
+        }
+        self.sort();
+    }
 }
 
 impl<K> MotionTrack<K> {
@@ -237,6 +256,12 @@ where
             .copied()
     }
 
+    pub fn retime(&mut self, f: impl Fn(u32) -> Option<u32>) {
+        for track in self.tracks.values_mut() {
+            track.retime(&f);
+        }
+    }
+
     /// Insert a Keyframe into the track without keeping ordered_frame_index sort.
     ///
     /// # Argument
@@ -755,6 +780,39 @@ impl Motion {
         self.camera_keyframes.remove_keyframe(frame_index)
     }
 
+    /// Names of the model and the bone the camera keyframe is bound to.
+    pub fn camera_keyframe_outside_parent(&self, frame_index: u32) -> Option<(&String, &String)> {
+        let outside_parent = self
+            .find_camera_keyframe_object(frame_index)?
+            .outside_parent?;
+        Some((
+            outside_parent.get_target_object_name(self)?,
+            outside_parent.get_target_bone_name(self)?,
+        ))
+    }
+
+    /// Binds the camera keyframe to the bone of the model, or unbinds it if `value` is `None`.
+    ///
+    /// Returns `false` if no camera keyframe exists at the frame.
+    pub fn set_camera_keyframe_outside_parent(
+        &mut self,
+        frame_index: u32,
+        value: Option<(&str, &str)>,
+    ) -> bool {
+        if !self.camera_keyframes.keyframes.contains_key(&frame_index) {
+            return false;
+        }
+        let outside_parent = value.map(|(object_name, bone_name)| MotionOutsideParent {
+            global_model_track_index: self.resolve_global_track_name(object_name),
+            global_bone_track_index: self.resolve_global_track_name(bone_name),
+            local_bone_track_index: 0,
+        });
+        if let Some(keyframe) = self.camera_keyframes.keyframes.get_mut(&frame_index) {
+            keyframe.outside_parent = outside_parent;
+        }
+        true
+    }
+
     pub fn find_light_keyframe_object(&self, frame_index: u32) -> Option<&MotionLightKeyframe> {
         self.light_keyframes.keyframes.get(&frame_index)
     }
@@ -892,6 +950,222 @@ impl Motion {
     }
 }
 
+/// Track of a motion selected by frame range operations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MotionTrackSelector<'a> {
+    Accessory,
+    Bone(&'a str),
+    Camera,
+    Light,
+    Model,
+    Morph(&'a str),
+    SelfShadow,
+}
+
+impl Motion {
+    fn retime_all(&mut self, f: impl Fn(u32) -> Option<u32>) {
+        self.accessory_keyframes.retime(&f);
+        self.camera_keyframes.retime(&f);
+        self.light_keyframes.retime(&f);
+        self.model_keyframes.retime(&f);
+        self.self_shadow_keyframes.retime(&f);
+        self.local_bone_motion_track_bundle.retime(&f);
+        self.local_morph_motion_track_bundle.retime(&f);
+    }
+
+    /// Insert `length` empty frames at `frame_index`, moving later keyframes of all tracks backward.
+    pub fn insert_frames(&mut self, frame_index: u32, length: u32) {
+        self.retime_all(|value| {
+            if value < frame_index {
+                Some(value)
+            } else {
+                value.checked_add(length)
+            }
+        });
+    }
+
+    /// Delete keyframes in `range` of all tracks and move later keyframes forward to fill the gap.
+    pub fn delete_frames(&mut self, range: Range<u32>) {
+        let length = range.end.saturating_sub(range.start);
+        self.retime_all(|value| {
+            if value < range.start {
+                Some(value)
+            } else if value < range.end {
+                None
+            } else {
+                Some(value - length)
+            }
+        });
+    }
+
+    /// Shift keyframes of selected tracks by `delta`, removing keyframes shifted before frame 0.
+    pub fn shift_tracks(&mut self, tracks: &[MotionTrackSelector], delta: i32) {
+        let f = |value: u32| value.checked_add_signed(delta);
+        for track in tracks {
+            match *track {
+                MotionTrackSelector::Accessory => self.accessory_keyframes.retime(f),
+                MotionTrackSelector::Bone(name) => {
+                    if let Some(track) = self.local_bone_motion_track_bundle.tracks.get_mut(name) {
+                        track.retime(f);
+                    }
+                }
+                MotionTrackSelector::Camera => self.camera_keyframes.retime(f),
+                MotionTrackSelector::Light => self.light_keyframes.retime(f),
+                MotionTrackSelector::Model => self.model_keyframes.retime(f),
+                MotionTrackSelector::Morph(name) => {
+                    if let Some(track) = self.local_morph_motion_track_bundle.tracks.get_mut(name) {
+                        track.retime(f);
+                    }
+                }
+                MotionTrackSelector::SelfShadow => self.self_shadow_keyframes.retime(f),
+            }
+        }
+    }
+
+    /// Re-space keyframes in `range` of all tracks by `scale` and move later keyframes to follow the scaled range.
+    ///
+    /// Interpolation parameters are normalized per keyframe interval, so curves keep their shape.
+    /// Keyframes collapsed onto the same frame are merged into the latest one.
+    pub fn scale_frames(&mut self, range: Range<u32>, scale: f32) {
+        let scale = scale.max(0f32);
+        let length = range.end.saturating_sub(range.start);
+        let scaled_length = (length as f32 * scale).round() as u32;
+        self.retime_all(|value| {
+            if value < range.start {
+                Some(value)
+            } else if value < range.end {
+                Some(range.start + ((value - range.start) as f32 * scale).round() as u32)
+            } else {
+                (value - length).checked_add(scaled_length)
+            }
+        });
+    }
+
+    /// Insert keyframes in `range` of `source` at `frame_index`, moving later keyframes backward.
+    ///
+    /// Tracks are matched by name, and bone, effect parameter and outside parent references
+    /// of model keyframes are resolved again against this motion.
+    pub fn splice(&mut self, source: &Motion, range: Range<u32>, frame_index: u32) {
+        let length = range.end.saturating_sub(range.start);
+        self.insert_frames(frame_index, length);
+        let to_local = |value: u32| {
+            if range.contains(&value) {
+                Some(frame_index + (value - range.start))
+            } else {
+                None
+            }
+        };
+        for keyframe in source.accessory_keyframes.iter() {
+            if let Some(new_frame_index) = to_local(keyframe.frame_index()) {
+                let mut n_keyframe = keyframe.clone();
+                n_keyframe.set_frame_index(new_frame_index);
+                n_keyframe.accessory_id = keyframe.accessory_id;
+                for parameter in &mut n_keyframe.effect_parameters {
+                    parameter.parameter_id =
+                        self.copy_global_track_id(source, parameter.parameter_id);
+                }
+                if let Some(outside_parent) = &mut n_keyframe.outside_parent {
+                    *outside_parent = self.copy_outside_parent(source, outside_parent);
+                }
+                self.accessory_keyframes.insert_keyframe(n_keyframe);
+            }
+        }
+        for (name, track) in &source.local_bone_motion_track_bundle.tracks {
+            for keyframe in track.iter() {
+                if let Some(new_frame_index) = to_local(keyframe.frame_index()) {
+                    let mut n_keyframe = keyframe.clone();
+                    n_keyframe.set_frame_index(new_frame_index);
+                    self.local_bone_motion_track_bundle
+                        .insert_keyframe(n_keyframe, name);
+                }
+            }
+        }
+        for (name, track) in &source.local_morph_motion_track_bundle.tracks {
+            for keyframe in track.iter() {
+                if let Some(new_frame_index) = to_local(keyframe.frame_index()) {
+                    let mut n_keyframe = keyframe.clone();
+                    n_keyframe.set_frame_index(new_frame_index);
+                    self.local_morph_motion_track_bundle
+                        .insert_keyframe(n_keyframe, name);
+                }
+            }
+        }
+        for keyframe in source.camera_keyframes.iter() {
+            if let Some(new_frame_index) = to_local(keyframe.frame_index()) {
+                let mut n_keyframe = keyframe.clone();
+                n_keyframe.set_frame_index(new_frame_index);
+                if let Some(outside_parent) = &mut n_keyframe.outside_parent {
+                    *outside_parent = self.copy_outside_parent(source, outside_parent);
+                }
+                self.camera_keyframes.insert_keyframe(n_keyframe);
+            }
+        }
+        for keyframe in source.light_keyframes.iter() {
+            if let Some(new_frame_index) = to_local(keyframe.frame_index()) {
+                let mut n_keyframe = keyframe.clone();
+                n_keyframe.set_frame_index(new_frame_index);
+                self.light_keyframes.insert_keyframe(n_keyframe);
+            }
+        }
+        for keyframe in source.model_keyframes.iter() {
+            if let Some(new_frame_index) = to_local(keyframe.frame_index()) {
+                let mut n_keyframe = keyframe.clone();
+                n_keyframe.set_frame_index(new_frame_index);
+                for state in &mut n_keyframe.constraint_states {
+                    state.bone_id = self.copy_local_bone_track_id(source, state.bone_id);
+                }
+                for parameter in &mut n_keyframe.effect_parameters {
+                    parameter.parameter_id =
+                        self.copy_global_track_id(source, parameter.parameter_id);
+                }
+                for outside_parent in &mut n_keyframe.outside_parents {
+                    *outside_parent = self.copy_outside_parent(source, outside_parent);
+                }
+                self.model_keyframes.insert_keyframe(n_keyframe);
+            }
+        }
+        for keyframe in source.self_shadow_keyframes.iter() {
+            if let Some(new_frame_index) = to_local(keyframe.frame_index()) {
+                let mut n_keyframe = keyframe.clone();
+                n_keyframe.set_frame_index(new_frame_index);
+                self.self_shadow_keyframes.insert_keyframe(n_keyframe);
+            }
+        }
+    }
+
+    fn copy_global_track_id(&mut self, source: &Motion, id: i32) -> i32 {
+        let name = source
+            .global_motion_track_bundle
+            .resolve_id(id)
+            .map_or("".into(), |name| name.clone());
+        self.resolve_global_track_name(&name)
+    }
+
+    fn copy_local_bone_track_id(&mut self, source: &Motion, id: i32) -> i32 {
+        let name = source
+            .local_bone_motion_track_bundle
+            .resolve_id(id)
+            .map_or("".into(), |name| name.clone());
+        self.local_bone_motion_track_bundle
+            .resolve_name_or_new(&name)
+    }
+
+    fn copy_outside_parent(
+        &mut self,
+        source: &Motion,
+        outside_parent: &MotionOutsideParent,
+    ) -> MotionOutsideParent {
+        MotionOutsideParent {
+            global_model_track_index: self
+                .copy_global_track_id(source, outside_parent.global_model_track_index),
+            global_bone_track_index: self
+                .copy_global_track_id(source, outside_parent.global_bone_track_index),
+            local_bone_track_index: self
+                .copy_local_bone_track_id(source, outside_parent.local_bone_track_index),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum MotionEffectParameterValue {
     BOOL(bool),
@@ -1045,6 +1319,10 @@ impl Keyframe for MotionAccessoryKeyframe {
     fn frame_index(&self) -> u32 {
         self.base.frame_index
     }
+
+    fn set_frame_index(&mut self, value: u32) {
+        self.base.frame_index = value;
+    }
 }
 
 impl MotionAccessoryKeyframe {
@@ -1180,6 +1458,10 @@ impl Keyframe for MotionBoneKeyframe {
     fn frame_index(&self) -> u32 {
         self.base.frame_index
     }
+
+    fn set_frame_index(&mut self, value: u32) {
+        self.base.frame_index = value;
+    }
 }
 
 impl MotionBoneKeyframe {
@@ -1304,6 +1586,10 @@ impl Keyframe for MotionCameraKeyframe {
     fn frame_index(&self) -> u32 {
         self.base.frame_index
     }
+
+    fn set_frame_index(&mut self, value: u32) {
+        self.base.frame_index = value;
+    }
 }
 
 impl MotionCameraKeyframe {
@@ -1349,7 +1635,8 @@ impl MotionCameraKeyframe {
             buffer.write_byte(self.interpolation.distance[i])?;
         }
         buffer.write_i32_little_endian(self.fov)?;
-        buffer.write_byte(if self.is_perspective_view { 1u8 } else { 0u8 })?;
+        // VMD stores whether perspective is disabled
+        buffer.write_byte(if self.is_perspective_view { 0u8 } else { 1u8 })?;
         Ok(())
     }
 }
@@ -1365,6 +1652,10 @@ impl Keyframe for MotionLightKeyframe {
     fn frame_index(&self) -> u32 {
         self.base.frame_index
     }
+
+    fn set_frame_index(&mut self, value: u32) {
+        self.base.frame_index = value;
+    }
 }
 
 impl MotionLightKeyframe {
@@ -1445,6 +1736,10 @@ impl Keyframe for MotionModelKeyframe {
     fn frame_index(&self) -> u32 {
         self.base.frame_index
     }
+
+    fn set_frame_index(&mut self, value: u32) {
+        self.base.frame_index = value;
+    }
 }
 
 impl MotionModelKeyframe {
@@ -1513,6 +1808,10 @@ impl Keyframe for MotionMorphKeyframe {
     fn frame_index(&self) -> u32 {
         self.base.frame_index
     }
+
+    fn set_frame_index(&mut self, value: u32) {
+        self.base.frame_index = value;
+    }
 }
 
 impl MotionMorphKeyframe {
@@ -1576,6 +1875,10 @@ impl Keyframe for MotionSelfShadowKeyframe {
     fn frame_index(&self) -> u32 {
         self.base.frame_index
     }
+
+    fn set_frame_index(&mut self, value: u32) {
+        self.base.frame_index = value;
+    }
 }
 
 impl MotionSelfShadowKeyframe {
@@ -1656,3 +1959,96 @@ fn test_save_into_buffer() -> Result<(), Box<dyn std::error::Error + 'static>> {
     }
     Ok(())
 }
+
+#[test]
+fn test_frame_range_edit() {
+    fn bone_keyframe(frame_index: u32) -> MotionBoneKeyframe {
+        MotionBoneKeyframe {
+            base: MotionKeyframeBase {
+                frame_index,
+                annotations: HashMap::new(),
+            },
+            translation: [0f32; 4],
+            orientation: [0f32, 0f32, 0f32, 1f32],
+            interpolation: MotionBoneKeyframeInterpolation::default(),
+            stage_index: 0,
+            is_physics_simulation_enabled: true,
+        }
+    }
+    fn bone_frames(motion: &Motion, name: &str) -> Vec<u32> {
+        motion.local_bone_motion_track_bundle.tracks[name]
+            .ordered_frame_index
+            .clone()
+    }
+
+    let mut motion = Motion::empty();
+    for frame_index in [0, 10, 20, 30] {
+        motion
+            .local_bone_motion_track_bundle
+            .insert_keyframe(bone_keyframe(frame_index), "center");
+    }
+    motion.insert_frames(10, 5);
+    assert_eq!(vec![0, 15, 25, 35], bone_frames(&motion, "center"));
+    motion.delete_frames(10..20);
+    assert_eq!(vec![0, 15, 25], bone_frames(&motion, "center"));
+    motion.scale_frames(0..15, 2f32);
+    assert_eq!(vec![0, 30, 40], bone_frames(&motion, "center"));
+    motion.scale_frames(0..30, 0f32);
+    assert_eq!(vec![0, 10], bone_frames(&motion, "center"));
+    motion.shift_tracks(&[MotionTrackSelector::Bone("center")], -5);
+    assert_eq!(vec![5], bone_frames(&motion, "center"));
+    assert_eq!(
+        5,
+        motion.local_bone_motion_track_bundle.tracks["center"].keyframes[&5].frame_index()
+    );
+
+    let mut source = Motion::empty();
+    source
+        .local_bone_motion_track_bundle
+        .insert_keyframe(bone_keyframe(0), "arm");
+    source
+        .local_bone_motion_track_bundle
+        .insert_keyframe(bone_keyframe(3), "arm");
+    let mut model_keyframe = MotionModelKeyframe {
+        base: MotionKeyframeBase {
+            frame_index: 3,
+            annotations: HashMap::new(),
+        },
+        visible: true,
+        constraint_states: vec![MotionModelKeyframeConstraintState {
+            bone_id: source
+                .local_bone_motion_track_bundle
+                .resolve_name("arm")
+                .unwrap(),
+            enabled: false,
+        }],
+        effect_parameters: vec![],
+        outside_parents: vec![],
+        has_edge_option: false,
+        edge_scale_factor: 1f32,
+        edge_color: [0f32; 4],
+        is_add_blending_enabled: false,
+        is_physics_simulation_enabled: true,
+    };
+    model_keyframe
+        .effect_parameters
+        .push(MotionEffectParameter {
+            parameter_id: source.assign_global_trace_id("Tr").unwrap(),
+            value: MotionEffectParameterValue::FLOAT(0.5f32),
+        });
+    source.add_model_keyframe(model_keyframe);
+    motion.splice(&source, 1..10, 2);
+    assert_eq!(vec![14], bone_frames(&motion, "center"));
+    assert_eq!(vec![4], bone_frames(&motion, "arm"));
+    let keyframe = motion.find_model_keyframe_object(4).unwrap();
+    assert_eq!(
+        Some(&"arm".to_owned()),
+        motion
+            .local_bone_motion_track_bundle
+            .resolve_id(keyframe.constraint_states[0].bone_id)
+    );
+    assert_eq!(
+        Some(&"Tr".to_owned()),
+        keyframe.effect_parameters[0].get_name(&motion)
+    );
+}